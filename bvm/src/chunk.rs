@@ -1,10 +1,32 @@
+use crate::diagnostic::Source;
+use crate::token::Span;
 use crate::value::Value;
+use std::rc::Rc;
 
 #[derive(Clone)]
 pub struct Chunk {
     pub code: Vec<u8>,
-    pub lines: Vec<isize>,
+    pub spans: Vec<Span>, // source code of each byte
     pub constants: Vec<Value>,
+    pub source: Rc<Source>,
+    // Names for the debugger.
+    pub locals: Vec<LocalName>,
+    pub upvalues: Vec<String>,
+}
+
+/// Local variable together with the code where it is in scope.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalName {
+    pub name: String,
+    pub slot: usize,
+    pub start: usize,
+    pub end: Option<usize>, // `None` if the scope lasts till the end of the function
+}
+
+impl LocalName {
+    pub fn is_in_scope(&self, offset: usize) -> bool {
+        self.start <= offset && self.end.is_none_or(|end| offset < end)
+    }
 }
 
 impl Default for Chunk {
@@ -17,19 +39,69 @@ impl Chunk {
     pub fn new() -> Self {
         Chunk {
             code: Vec::new(),
-            lines: Vec::new(),
+            spans: Vec::new(),
             constants: Vec::with_capacity(u8::MAX.into()),
+            source: Rc::default(),
+            locals: Vec::new(),
+            upvalues: Vec::new(),
         }
     }
 
-    pub fn write_instruction(&mut self, code: OpCode, line: isize) {
+    pub fn write_instruction(&mut self, code: OpCode, span: Span) {
         self.code.push(code as u8);
-        self.lines.push(line);
+        self.spans.push(span);
     }
 
-    pub fn write_raw_instruction(&mut self, code: u8, line: isize) {
+    pub fn write_raw_instruction(&mut self, code: u8, span: Span) {
         self.code.push(code);
-        self.lines.push(line);
+        self.spans.push(span);
+    }
+
+    pub fn line(&self, offset: usize) -> isize {
+        self.spans[offset].line
+    }
+
+    /// Length of the instruction at `offset` together with its operands, in bytes.
+    pub fn instruction_len(&self, offset: usize) -> usize {
+        let Ok(opcode) = OpCode::try_from(self.code[offset]) else {
+            return 1;
+        };
+        match opcode {
+            OpCode::Constant
+            | OpCode::GetLocal
+            | OpCode::SetLocal
+            | OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetUpvalue
+            | OpCode::SetUpvalue
+            | OpCode::GetProperty
+            | OpCode::IsVariant
+            | OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Call
+            | OpCode::Class
+            | OpCode::Method
+            | OpCode::BuildList
+            | OpCode::PopN => 2,
+            OpCode::Jump
+            | OpCode::JumpIfFalse
+            | OpCode::Loop
+            | OpCode::Invoke
+            | OpCode::SuperInvoke
+            | OpCode::AddLocalConstant
+            | OpCode::LessJumpIfFalse => 3,
+            OpCode::JumpIfArg => 4,
+            // The enum and the number of its variants, followed by a jump for each variant.
+            OpCode::JumpTable => 3 + 2 * self.code[offset + 2] as usize,
+            // Followed by a pair of bytes for each upvalue.
+            OpCode::Closure => {
+                let function = &self.constants[self.code[offset + 1] as usize];
+                let upvalue_count = unsafe { function.as_obj().as_fun() }.upvalue_count();
+                2 + 2 * upvalue_count as usize
+            }
+            _ => 1,
+        }
     }
 
     pub fn write_value(&mut self, value: Value) -> usize {
@@ -43,8 +115,10 @@ impl Chunk {
     // actually doesn't free anything).
     pub fn free(&mut self) {
         self.code.clear();
-        self.lines.clear();
+        self.spans.clear();
         self.constants.clear();
+        self.locals.clear();
+        self.upvalues.clear();
     }
 }
 
@@ -88,6 +162,20 @@ pub enum OpCode {
     Class,
     Inherit,
     Method,
+    Yield,
+    JumpIfArg,
+    BuildList,
+    Index,
+    Extend,
+    CallList,
+    IsVariant,
+    JumpTable,
+    IsInstance,
+    // Superinstructions made by the optimizer.
+    AddLocalConstant, // GetLocal, Constant, Add
+    LessJumpIfFalse,  // Less, JumpIfFalse
+    PopN,             // Pop, Pop, ...
+    ReturnNil,        // Nil, Return
 }
 
 /// `num_enum` crate is better solution here.
@@ -133,6 +221,19 @@ impl TryFrom<u8> for OpCode {
             34 => Ok(OpCode::Class),
             35 => Ok(OpCode::Inherit),
             36 => Ok(OpCode::Method),
+            37 => Ok(OpCode::Yield),
+            38 => Ok(OpCode::JumpIfArg),
+            39 => Ok(OpCode::BuildList),
+            40 => Ok(OpCode::Index),
+            41 => Ok(OpCode::Extend),
+            42 => Ok(OpCode::CallList),
+            43 => Ok(OpCode::IsVariant),
+            44 => Ok(OpCode::JumpTable),
+            45 => Ok(OpCode::IsInstance),
+            46 => Ok(OpCode::AddLocalConstant),
+            47 => Ok(OpCode::LessJumpIfFalse),
+            48 => Ok(OpCode::PopN),
+            49 => Ok(OpCode::ReturnNil),
             _ => {
                 eprintln!("Code value: {}.", value);
                 Err("Failed to convert from u8: unknown OpCode.")
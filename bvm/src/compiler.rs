@@ -1,11 +1,19 @@
-use crate::chunk::{Chunk, OpCode};
+use crate::chunk::{Chunk, LocalName, OpCode};
 use crate::debug::disassemble_chunk;
-use crate::object::{Function, Obj};
+use crate::diagnostic::{self, Diagnostic, Source};
+use crate::object::{Enum, Function, List, Obj};
+use crate::optimizer;
 use crate::scanner::Scanner;
 use crate::token::{Token, TokenType};
 use crate::value::Value;
 use crate::Config;
-use std::{cell::RefCell, error::Error, fmt, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    rc::Rc,
+};
 
 #[derive(Debug)]
 pub struct CompileError {
@@ -31,6 +39,86 @@ enum Byte {
     Code(OpCode),
 }
 
+/// Code at `start..end` of the current chunk that only pushes `value`,
+/// emitted by a literal or by folding a constant expression.
+struct ConstantExpr {
+    start: usize,
+    end: usize,
+    value: Value,
+}
+
+/// Jump table of a match whose first case is a variant of an enum known at compile time
+/// or a literal.
+struct JumpTable {
+    keys: TableKeys,
+    // Offset of the jumps in the table, one for each key.
+    offset: usize,
+    // Start of the first case that takes each key.
+    cases: Vec<Option<usize>>,
+    // Cleared by a case that can match values the table doesn't know about,
+    // later cases are reached only by the tests.
+    is_open: bool,
+}
+
+enum TableKeys {
+    // Name of the enum in the code and the enum.
+    Variants(String, Enum),
+    // Literals of the cases, until one that can match other values.
+    Literals(Vec<Value>),
+}
+
+/// Where a jump table takes the values of its key.
+enum TableCase {
+    Body(usize),
+    // The case has tests besides the key, the value has to go through them.
+    Tests(usize),
+    // The pattern can't match any key.
+    Never,
+}
+
+impl JumpTable {
+    /// Where the table takes the values that match `pattern`,
+    /// `None` if the pattern can match values the table doesn't know about.
+    fn case_of(&self, pattern: &Pattern, scanner: &Scanner) -> Option<TableCase> {
+        let lexeme = |token: &Token| scanner.lexeme(token.start, token.length);
+        match (&self.keys, pattern) {
+            (TableKeys::Variants(..), Pattern::Literal(_)) => Some(TableCase::Never),
+            (
+                TableKeys::Variants(enum_name, enumeration),
+                Pattern::Variant(name, variant, fields, has_tests),
+            ) => {
+                // Another name may refer to the same enum.
+                if *enum_name != lexeme(name) {
+                    return None;
+                }
+                // The test fails at runtime, later cases must not skip it.
+                let variant = enumeration.variant(&lexeme(variant))?;
+                let key = enumeration.index_of(&variant)?;
+                // Fields that aren't bound are read by the tests, they fail if a field is missing.
+                let has_fields = fields.iter().all(|field| variant.has_field(&lexeme(field)));
+                match *has_tests || !has_fields {
+                    true => Some(TableCase::Tests(key)),
+                    false => Some(TableCase::Body(key)),
+                }
+            }
+            (TableKeys::Literals(literals), Pattern::Literal(value)) => literals
+                .iter()
+                .position(|literal| literal.equal(value.clone()))
+                .map(TableCase::Body),
+            _ => None,
+        }
+    }
+}
+
+/// What a pattern of a match case is, for the jump table.
+enum Pattern {
+    Literal(Value),
+    // The enum, the variant, its fields and whether they are tested.
+    Variant(Token, Token, Vec<Token>, bool),
+    // A name, '_' or a class pattern.
+    Other,
+}
+
 #[derive(Eq, PartialEq, Ord, PartialOrd)]
 #[repr(u8)]
 enum Precedence {
@@ -64,20 +152,22 @@ struct Local {
     name: Token,
     depth: isize,
     is_captured: bool,
+    is_const: bool,
 }
 
 impl Default for Local {
     fn default() -> Self {
-        Self::new(Token::default(), -10, false)
+        Self::new(Token::default(), -10, false, false)
     }
 }
 
 impl Local {
-    pub fn new(name: Token, depth: isize, is_captured: bool) -> Self {
+    pub fn new(name: Token, depth: isize, is_captured: bool, is_const: bool) -> Self {
         Self {
             name,
             depth,
             is_captured,
+            is_const,
         }
     }
 }
@@ -107,6 +197,10 @@ enum FunType {
 }
 
 const UINT8_COUNT: usize = u8::MAX as usize + 1;
+// Keywords that can be followed by a name or a value.
+const KEYWORDS: [&str; 8] = [
+    "class", "const", "enum", "fun", "var", "print", "return", "yield",
+];
 
 #[derive(Clone)]
 struct Compiler {
@@ -178,6 +272,17 @@ pub struct Parser {
     previous: Token,
     had_error: bool,
     panic_mode: bool,
+    errors: Vec<Diagnostic>,
+    // Number of blocks the parser is in.
+    block_depth: usize,
+    // Names of global constants.
+    constants: HashSet<String>,
+    // Enums declared so far, for jump tables of matches.
+    enums: HashMap<String, Enum>,
+    source: Rc<Source>,
+    // The last constant expression and the start of the expression that an infix rule continues.
+    constant_expr: Option<ConstantExpr>,
+    expression_start: usize,
 }
 
 impl Default for Parser {
@@ -196,17 +301,46 @@ impl Parser {
             previous: Token::default(),
             had_error: false,
             panic_mode: false,
+            errors: Vec::new(),
+            block_depth: 0,
+            constants: HashSet::new(),
+            enums: HashMap::new(),
+            source: Rc::new(Source::new("<repl>", "")),
+            constant_expr: None,
+            expression_start: 0,
         }
     }
 
+    /// File name shown in error messages.
+    pub fn set_source_name(&mut self, name: &str) {
+        self.source = Rc::new(Source::new(name, &self.source.text));
+    }
+
+    /// Global constants declared by previously compiled code (e.g. in REPL).
+    pub fn set_constants(&mut self, constants: HashSet<String>) {
+        self.constants = constants;
+    }
+
+    pub fn constants(&self) -> &HashSet<String> {
+        &self.constants
+    }
+
+    /// Errors reported by the last compilation, in source order.
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
     fn set_scanner(&mut self, source: String) {
         self.scanner = Scanner::new(source)
     }
 
     fn init_compiler(&mut self, fun_kind: FunType) {
+        // Offsets of the last constant expression belong to another chunk.
+        self.constant_expr = None;
         self.compiler.set_fun_kind(fun_kind);
         self.compiler.local_count = 0;
         self.compiler.scope_depth = 0;
+        self.current_chunk().borrow_mut().source = Rc::clone(&self.source);
 
         if fun_kind != FunType::Script {
             let name = self
@@ -219,6 +353,7 @@ impl Parser {
         self.compiler.local_count += 1;
         local.depth = 0;
         local.is_captured = false;
+        local.is_const = false;
         // name == ""
         local.name.kind = TokenType::Identifier;
         local.name.start = 0;
@@ -230,12 +365,20 @@ impl Parser {
     }
 
     pub fn compile(&mut self, source: String) -> Result<Rc<RefCell<Function>>, CompileError> {
+        self.source = Rc::new(Source::new(&self.source.name, &source));
+        self.errors.clear();
         self.set_scanner(source);
         self.init_compiler(FunType::Script);
         self.advance();
 
+        let mut last = None;
         while !self.fit(TokenType::EoF) {
             self.declaration();
+            last = Some(self.previous);
+        }
+        // The implicit return belongs to the last line of code, not to the end of the file.
+        if let Some(last) = last {
+            self.previous = last;
         }
 
         let (function, _) = self.end_compiler();
@@ -247,6 +390,7 @@ impl Parser {
     }
 
     fn parse_precedence(&mut self, precedence: Precedence) {
+        let start = self.code_len();
         self.advance();
         let prefix_rule = self.get_rule(self.previous.kind).prefix;
         let can_assign = precedence <= Precedence::Assignment;
@@ -261,6 +405,7 @@ impl Parser {
         while precedence <= self.get_rule(self.current.kind).precedence {
             self.advance();
             let infix_rule = self.get_rule(self.previous.kind).infix;
+            self.expression_start = start;
             match infix_rule {
                 Some(func) => func(self),
                 None => {
@@ -297,12 +442,37 @@ impl Parser {
                 infix: None,
                 precedence: Precedence::None,
             },
+            TokenType::LeftBracket => ParseRule {
+                prefix: Some(Parser::list),
+                infix: Some(Parser::index),
+                precedence: Precedence::Call,
+            },
+            TokenType::RightBracket => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
+            TokenType::DotDotDot => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
+            TokenType::Colon => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             TokenType::Comma => ParseRule {
                 prefix: None,
                 infix: None,
                 precedence: Precedence::None,
             },
             TokenType::Dot => ParseRule {
+                prefix: None,
+                infix: Some(Parser::dot),
+                precedence: Precedence::Call,
+            },
+            TokenType::Pipe => ParseRule {
                 prefix: None,
                 infix: None,
                 precedence: Precedence::None,
@@ -347,6 +517,11 @@ impl Parser {
                 infix: None,
                 precedence: Precedence::None,
             },
+            TokenType::FatArrow => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             TokenType::EqualEqual => ParseRule {
                 prefix: None,
                 infix: Some(Parser::binary),
@@ -392,16 +567,31 @@ impl Parser {
                 infix: Some(Parser::and_),
                 precedence: Precedence::And,
             },
+            TokenType::Case => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             TokenType::Class => ParseRule {
                 prefix: None,
                 infix: None,
                 precedence: Precedence::None,
             },
+            TokenType::Const => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             TokenType::Else => ParseRule {
                 prefix: None,
                 infix: None,
                 precedence: Precedence::None,
             },
+            TokenType::Enum => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             TokenType::False => ParseRule {
                 prefix: Some(Parser::literal),
                 infix: None,
@@ -422,6 +612,11 @@ impl Parser {
                 infix: None,
                 precedence: Precedence::None,
             },
+            TokenType::Match => ParseRule {
+                prefix: None,
+                infix: None,
+                precedence: Precedence::None,
+            },
             TokenType::Nil => ParseRule {
                 prefix: Some(Parser::literal),
                 infix: None,
@@ -467,6 +662,11 @@ impl Parser {
                 infix: None,
                 precedence: Precedence::None,
             },
+            TokenType::Yield => ParseRule {
+                prefix: Some(Parser::yield_),
+                infix: None,
+                precedence: Precedence::None,
+            },
             TokenType::Error => ParseRule {
                 prefix: None,
                 infix: None,
@@ -481,15 +681,20 @@ impl Parser {
     }
 
     fn emit_instruction(&self, byte: OpCode) {
-        self.current_chunk()
-            .borrow_mut()
-            .write_instruction(byte, self.previous.line);
+        self.emit_at(Byte::Code(byte), self.previous);
     }
 
     fn emit_raw_instruction(&self, byte: u8) {
-        self.current_chunk()
-            .borrow_mut()
-            .write_raw_instruction(byte, self.previous.line);
+        self.emit_at(Byte::Raw(byte), self.previous);
+    }
+
+    // Runtime errors of the instruction point to the token.
+    fn emit_at(&self, byte: Byte, token: Token) {
+        let chunk = self.current_chunk();
+        match byte {
+            Byte::Raw(num) => chunk.borrow_mut().write_raw_instruction(num, token.span()),
+            Byte::Code(code) => chunk.borrow_mut().write_instruction(code, token.span()),
+        }
     }
 
     fn emit_universal(&self, byte: Byte) {
@@ -554,10 +759,21 @@ impl Parser {
     }
 
     fn declaration(&mut self) {
+        if self.block_depth == 0 && self.check(TokenType::RightBrace) {
+            self.error_at_current("Unmatched '}'.".to_string());
+            self.advance();
+            self.panic_mode = false;
+            return;
+        }
+
         if self.fit(TokenType::Fun) {
             self.fun_declaration();
         } else if self.fit(TokenType::Var) {
             self.var_declaration();
+        } else if self.fit(TokenType::Const) {
+            self.const_declaration();
+        } else if self.fit(TokenType::Enum) {
+            self.enum_declaration();
         } else {
             self.statement();
         }
@@ -569,9 +785,15 @@ impl Parser {
 
     fn fun_declaration(&mut self) {
         let global = self.parse_variable("Expect function name.");
+        let name = self.previous;
+        let start = self.current_chunk().borrow().code.len();
         self.mark_initialized();
         self.function(FunType::Function);
         self.define_variable(global);
+
+        // The declaration belongs to the line of its name, not of the closing `}`.
+        let chunk = self.current_chunk();
+        chunk.borrow_mut().spans[start..].fill(name.span());
     }
 
     fn var_declaration(&mut self) {
@@ -582,14 +804,74 @@ impl Parser {
         } else {
             self.emit_instruction(OpCode::Nil);
         }
-        self.consume(
-            TokenType::Semicolon,
-            "Expect ';' after variable declaration.",
-        );
+        self.consume_semicolon("Expect ';' after variable declaration.");
 
         self.define_variable(global);
     }
 
+    fn const_declaration(&mut self) {
+        let global = self.parse_variable("Expect constant name.");
+        let name = self.previous;
+
+        self.consume(TokenType::Equal, "Expect '=' after constant name.");
+        self.expression();
+        self.consume_semicolon("Expect ';' after constant declaration.");
+
+        if self.compiler.scope_depth > 0 {
+            self.compiler.locals[self.compiler.local_count as usize - 1].is_const = true;
+        } else {
+            let name = self.scanner.lexeme(name.start, name.length);
+            self.constants.insert(name);
+        }
+        self.define_variable(global);
+    }
+
+    /// The whole enum is built here and stored as a constant.
+    fn enum_declaration(&mut self) {
+        let global = self.parse_variable("Expect enum name.");
+        let name = self
+            .scanner
+            .lexeme(self.previous.start, self.previous.length);
+        self.consume(TokenType::LeftBrace, "Expect '{' before enum body.");
+
+        let mut variants: Vec<(String, Vec<String>)> = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::EoF) {
+            self.consume(TokenType::Identifier, "Expect variant name.");
+            let variant = self
+                .scanner
+                .lexeme(self.previous.start, self.previous.length);
+            if variants.iter().any(|(other, _)| *other == variant) {
+                self.error("Already a variant with this name in this enum.".to_string());
+            }
+
+            let mut fields = Vec::new();
+            if self.fit(TokenType::LeftParen) {
+                loop {
+                    self.consume(TokenType::Identifier, "Expect payload field name.");
+                    fields.push(
+                        self.scanner
+                            .lexeme(self.previous.start, self.previous.length),
+                    );
+                    if !self.fit(TokenType::Comma) {
+                        break;
+                    }
+                }
+                self.consume(TokenType::RightParen, "Expect ')' after payload fields.");
+            }
+            variants.push((variant, fields));
+
+            if !self.fit(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after enum body.");
+
+        let enumeration = Enum::new(name.clone(), variants);
+        self.enums.insert(name, enumeration.clone());
+        self.emit_constant(Value::Obj(Obj::Enum(enumeration)));
+        self.define_variable(global);
+    }
+
     fn statement(&mut self) {
         if self.fit(TokenType::Print) {
             self.print_stmt();
@@ -597,6 +879,8 @@ impl Parser {
             self.for_stmt();
         } else if self.fit(TokenType::If) {
             self.if_stmt();
+        } else if self.fit(TokenType::Match) {
+            self.match_stmt();
         } else if self.fit(TokenType::Return) {
             self.return_stmt();
         } else if self.fit(TokenType::While) {
@@ -612,21 +896,44 @@ impl Parser {
 
     fn print_stmt(&mut self) {
         self.expression();
-        self.consume(TokenType::Semicolon, "Expect ';' after value.");
+        self.consume_semicolon("Expect ';' after value.");
         self.emit_instruction(OpCode::Print);
     }
 
     fn expression_stmt(&mut self) {
+        let first = self.current;
         self.expression();
-        self.consume(TokenType::Semicolon, "Expect ';' after expression.");
+        if self.is_misspelled_keyword(first) {
+            return;
+        }
+        self.consume_semicolon("Expect ';' after expression.");
         self.emit_instruction(OpCode::Pop);
     }
 
     fn if_stmt(&mut self) {
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
+        let condition = self.code_len();
         self.expression();
         self.consume(TokenType::RightParen, "Expect ')' after condition.");
 
+        // Only the branch that is taken is kept, the other one is still compiled for errors.
+        if let Some(value) = self.constant_from(condition) {
+            self.discard(condition);
+            let is_true = !value.is_falsey();
+            self.statement();
+            if !is_true {
+                self.discard(condition);
+            }
+            if self.fit(TokenType::Else) {
+                let start = self.code_len();
+                self.statement();
+                if is_true {
+                    self.discard(start);
+                }
+            }
+            return;
+        }
+
         let then_jump = self.emit_jump(OpCode::JumpIfFalse);
         self.emit_instruction(OpCode::Pop);
         self.statement();
@@ -642,6 +949,416 @@ impl Parser {
         self.patch_jump(else_jump);
     }
 
+    /// Every case tests the value and jumps to the next case on failure,
+    /// a matched case jumps to the common exit after its body.
+    ///
+    /// If the first case is a variant of an enum declared before or a literal, a jump table goes
+    /// before the tests. Variants of that enum, or values of the literals of the cases, jump
+    /// straight to the first case that names them, other values fall through to the tests.
+    fn match_stmt(&mut self) {
+        self.begin_scope();
+        let keyword = self.previous;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'match'.");
+        self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after match value.");
+
+        // The value is kept in a hidden local that can't be named in code.
+        self.add_local(keyword);
+        self.mark_initialized();
+        let slot = (self.compiler.local_count - 1) as u8;
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before match cases.");
+        let mut table = None;
+        let mut end_jumps = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::EoF) {
+            self.consume(TokenType::Case, "Expect 'case' before pattern.");
+            if end_jumps.is_empty() {
+                table = self.jump_table(slot);
+            }
+
+            self.begin_scope();
+            let next_case = self.pattern(slot, &mut table);
+            self.consume(TokenType::FatArrow, "Expect '=>' after pattern.");
+            self.statement();
+            self.end_scope();
+            end_jumps.push(self.emit_jump(OpCode::Jump));
+
+            if !next_case.is_empty() {
+                for jump in next_case {
+                    self.patch_jump(jump);
+                }
+                self.emit_instruction(OpCode::Pop);
+            }
+
+            // Cases may be separated with commas.
+            self.fit(TokenType::Comma);
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after match cases.");
+
+        for end_jump in end_jumps {
+            self.patch_jump(end_jump);
+        }
+        if let Some(table) = table {
+            self.patch_jump_table(table);
+        }
+        self.end_scope();
+    }
+
+    /// Emits an empty jump table if the pattern starts with the name of a known enum or
+    /// with a literal.
+    fn jump_table(&mut self, slot: u8) -> Option<JumpTable> {
+        let (keys, len) = if self.check(TokenType::Identifier) {
+            let name = self.current;
+            let enum_name = self.scanner.lexeme(name.start, name.length);
+            let enumeration = self.enums.get(&enum_name)?.clone();
+            let len = enumeration.len();
+            (TableKeys::Variants(enum_name, enumeration), len)
+        } else {
+            let literals = self.literal_keys();
+            let len = literals.len();
+            (TableKeys::Literals(literals), len)
+        };
+        if len == 0 || len > u8::MAX as usize {
+            return None;
+        }
+
+        self.emit_instructions(Byte::Code(OpCode::GetLocal), Byte::Raw(slot));
+        let constant = match &keys {
+            TableKeys::Variants(_, enumeration) => {
+                // The name is checked at runtime, it may refer to another value by then.
+                self.named_variable(self.current, false);
+                self.make_constant(Value::Obj(Obj::Enum(enumeration.clone())))
+            }
+            // The literals are their own key, the table always applies.
+            TableKeys::Literals(literals) => {
+                let list = Value::Obj(Obj::List(List::new(literals.clone())));
+                let constant = self.make_constant(list);
+                self.emit_instructions(Byte::Code(OpCode::Constant), Byte::Raw(constant));
+                constant
+            }
+        };
+        self.emit_instructions(Byte::Code(OpCode::JumpTable), Byte::Raw(constant));
+        self.emit_raw_instruction(len as u8);
+        let offset = self.code_len();
+        for _ in 0..len {
+            self.emit_raw_instruction(0);
+            self.emit_raw_instruction(0);
+        }
+        Some(JumpTable {
+            keys,
+            offset,
+            cases: vec![None; len],
+            is_open: true,
+        })
+    }
+
+    /// Values of the literal alternatives of the cases, scanned ahead up to the first case that
+    /// can match other values too. Duplicates are left out and the scanner comes back to where
+    /// it was, its errors are reported when the cases are compiled.
+    fn literal_keys(&mut self) -> Vec<Value> {
+        let checkpoint = self.scanner.checkpoint();
+        let mut next = || loop {
+            if let Ok(token) = self.scanner.scan_token() {
+                break token;
+            }
+        };
+        let mut tokens = Vec::new();
+        let mut token = self.current;
+        'cases: loop {
+            // Alternatives that are a literal, possibly negated, come before '|' or '=>'.
+            let mut is_closed = false;
+            loop {
+                let is_negative = token.kind == TokenType::Minus;
+                if is_negative {
+                    token = next();
+                }
+                tokens.push((token, is_negative));
+                let mut depth = 0;
+                loop {
+                    token = next();
+                    match token.kind {
+                        TokenType::Pipe | TokenType::FatArrow if depth == 0 => break,
+                        TokenType::LeftParen => depth += 1,
+                        TokenType::RightParen if depth > 0 => depth -= 1,
+                        TokenType::RightBrace | TokenType::EoF => break 'cases,
+                        _ => (),
+                    }
+                    is_closed = true;
+                }
+                if token.kind == TokenType::FatArrow {
+                    break;
+                }
+                token = next();
+            }
+            if is_closed {
+                break;
+            }
+
+            // The body goes until the next case or the end of the match.
+            let mut depth = 0;
+            loop {
+                token = next();
+                match token.kind {
+                    TokenType::Case if depth == 0 => break,
+                    TokenType::LeftParen | TokenType::LeftBrace => depth += 1,
+                    TokenType::RightParen | TokenType::RightBrace if depth > 0 => depth -= 1,
+                    TokenType::RightBrace | TokenType::EoF => break 'cases,
+                    _ => (),
+                }
+            }
+            token = next();
+        }
+        self.scanner.restore(checkpoint);
+
+        let mut keys: Vec<Value> = Vec::new();
+        for (token, is_negative) in tokens {
+            match self.literal_value(token, is_negative) {
+                Some(value) if !keys.iter().any(|key| key.equal(value.clone())) => keys.push(value),
+                Some(_) => (),
+                None => break,
+            }
+        }
+        keys
+    }
+
+    /// Points the table at the cases, a jump of 0 falls through to the tests.
+    fn patch_jump_table(&mut self, table: JumpTable) {
+        let end = self.code_len();
+        let table_end = table.offset + 2 * table.cases.len();
+        for (i, case) in table.cases.iter().enumerate() {
+            // No case can match a key that isn't named before a catch-all.
+            let target = match case {
+                Some(case) => *case,
+                None if table.is_open => end,
+                None => continue,
+            };
+            let jump = target - table_end;
+            if jump > u16::MAX as usize {
+                self.error("Too much code to jump over.".to_string());
+            }
+            let chunk = self.current_chunk();
+            let code = &mut chunk.borrow_mut().code;
+            code[table.offset + 2 * i] = ((jump >> 8) & 0xff) as u8;
+            code[table.offset + 2 * i + 1] = (jump & 0xff) as u8;
+        }
+    }
+
+    /// Returns the jumps that have to be patched to the next case, they leave `false` on the
+    /// stack. There are none if the pattern always matches.
+    fn pattern(&mut self, slot: u8, table: &mut Option<JumpTable>) -> Vec<isize> {
+        let mut next_case = Vec::new();
+        let mut bindings = Vec::new();
+        let alternatives = self.alternatives(slot, &[], &mut next_case, &mut bindings);
+
+        // The table jumps here, so names are bound after the tests.
+        let body = self.code_len();
+        if let Some(table) = table.as_mut().filter(|table| table.is_open) {
+            let tests = table.offset + 2 * table.cases.len();
+            let mut is_catch_all = false;
+            for alternative in alternatives {
+                match table.case_of(&alternative, &self.scanner) {
+                    Some(TableCase::Body(key)) => {
+                        table.cases[key].get_or_insert(body);
+                    }
+                    Some(TableCase::Tests(key)) => {
+                        table.cases[key].get_or_insert(tests);
+                    }
+                    Some(TableCase::Never) => (),
+                    None => is_catch_all = true,
+                }
+            }
+            table.is_open = !is_catch_all;
+        }
+        for (path, name) in bindings {
+            self.emit_path(slot, &path);
+            self.add_local(name);
+            self.mark_initialized();
+        }
+        next_case
+    }
+
+    /// Emits the tests of patterns joined with '|' on the part of the value at `path`.
+    /// Failed tests jump away with `false` on the stack, their jumps are added to `fails`.
+    fn alternatives(
+        &mut self,
+        slot: u8,
+        path: &[Token],
+        fails: &mut Vec<isize>,
+        bindings: &mut Vec<(Vec<Token>, Token)>,
+    ) -> Vec<Pattern> {
+        let mut patterns = Vec::new();
+        let mut matched_jumps = Vec::new();
+        loop {
+            let start = bindings.len();
+            let mut tests = Vec::new();
+            patterns.push(self.single_pattern(slot, path, &mut tests, bindings));
+            let more = self.check(TokenType::Pipe);
+            if more || patterns.len() > 1 {
+                if let Some((_, name)) = bindings.get(start) {
+                    self.error_at(
+                        *name,
+                        "Can't bind names in alternative patterns.".to_string(),
+                    );
+                }
+            }
+            if !more {
+                fails.extend(tests);
+                break;
+            }
+
+            matched_jumps.push(self.emit_jump(OpCode::Jump));
+            if !tests.is_empty() {
+                for test in tests {
+                    self.patch_jump(test);
+                }
+                self.emit_instruction(OpCode::Pop);
+            }
+            self.advance();
+        }
+
+        for jump in matched_jumps {
+            self.patch_jump(jump);
+        }
+        patterns
+    }
+
+    fn single_pattern(
+        &mut self,
+        slot: u8,
+        path: &[Token],
+        tests: &mut Vec<isize>,
+        bindings: &mut Vec<(Vec<Token>, Token)>,
+    ) -> Pattern {
+        if !self.fit(TokenType::Identifier) {
+            self.emit_path(slot, path);
+            let value = self.literal_pattern();
+            self.emit_instruction(OpCode::Equal);
+            self.emit_test(tests);
+            return value.map_or(Pattern::Other, Pattern::Literal);
+        }
+
+        let name = self.previous;
+        if self.fit(TokenType::Dot) {
+            self.consume(TokenType::Identifier, "Expect variant name after '.'.");
+            let variant = self.previous;
+            self.emit_path(slot, path);
+            self.named_variable(name, false);
+            let constant = self.identifier_constant(variant);
+            self.emit_instructions(Byte::Code(OpCode::IsVariant), Byte::Raw(constant));
+            self.emit_test(tests);
+
+            let start = tests.len();
+            let mut fields = Vec::new();
+            if self.fit(TokenType::LeftParen) {
+                fields = self.field_patterns(slot, path, tests, bindings);
+            }
+            let has_tests = tests.len() > start;
+            return Pattern::Variant(name, variant, fields, has_tests);
+        }
+
+        if self.check(TokenType::LeftParen) {
+            self.emit_path(slot, path);
+            self.named_variable(name, false);
+            self.emit_instruction(OpCode::IsInstance);
+            self.emit_test(tests);
+            self.advance();
+            self.field_patterns(slot, path, tests, bindings);
+        } else if self.scanner.lexeme(name.start, name.length) == "_" {
+            // A field is read even if it isn't bound, so a missing one is an error.
+            if !path.is_empty() {
+                self.emit_path(slot, path);
+                self.emit_instruction(OpCode::Pop);
+            }
+        } else {
+            bindings.push((path.to_vec(), name));
+        }
+        Pattern::Other
+    }
+
+    /// Patterns of fields after '(', like `x, y: 0)`, returns the names of the fields.
+    fn field_patterns(
+        &mut self,
+        slot: u8,
+        path: &[Token],
+        tests: &mut Vec<isize>,
+        bindings: &mut Vec<(Vec<Token>, Token)>,
+    ) -> Vec<Token> {
+        let mut fields = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.consume(TokenType::Identifier, "Expect field name.");
+                let field = self.previous;
+                let mut field_path = path.to_vec();
+                field_path.push(field);
+                // `Circle(radius)` is a short form of `Circle(radius: radius)`.
+                if self.fit(TokenType::Colon) {
+                    self.alternatives(slot, &field_path, tests, bindings);
+                } else if self.scanner.lexeme(field.start, field.length) != "_" {
+                    bindings.push((field_path, field));
+                }
+                fields.push(field);
+
+                if !self.fit(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after field patterns.");
+        fields
+    }
+
+    /// Pushes the part of the matched value at `path`, a field of a field of ... the value.
+    fn emit_path(&mut self, slot: u8, path: &[Token]) {
+        self.emit_instructions(Byte::Code(OpCode::GetLocal), Byte::Raw(slot));
+        for &field in path {
+            let constant = self.identifier_constant(field);
+            self.emit_at(Byte::Code(OpCode::GetProperty), field);
+            self.emit_at(Byte::Raw(constant), field);
+        }
+    }
+
+    // Jumps away with `false` on the stack if the test failed, goes on without it otherwise.
+    fn emit_test(&mut self, tests: &mut Vec<isize>) {
+        tests.push(self.emit_jump(OpCode::JumpIfFalse));
+        self.emit_instruction(OpCode::Pop);
+    }
+
+    fn literal_pattern(&mut self) -> Option<Value> {
+        let is_negative = self.fit(TokenType::Minus);
+        let value = self.literal_value(self.current, is_negative);
+        match &value {
+            Some(value) => {
+                self.advance();
+                self.emit_value(value.clone());
+            }
+            None if is_negative => {
+                self.error_at_current("Expect number after '-' in pattern.".to_string())
+            }
+            None => self.error_at_current("Expect pattern.".to_string()),
+        }
+        value
+    }
+
+    /// Value of the literal `token` of a pattern, negated if a '-' goes before it.
+    fn literal_value(&self, token: Token, is_negative: bool) -> Option<Value> {
+        let value = match token.kind {
+            TokenType::Number => {
+                let lexeme = self.scanner.lexeme(token.start, token.length);
+                let number = lexeme.trim().parse::<f64>().ok()?;
+                return Some(Value::Num(if is_negative { -number } else { number }));
+            }
+            TokenType::String => {
+                let string = self.scanner.lexeme(token.start + 1, token.length - 2);
+                Value::Obj(Obj::Str(string))
+            }
+            TokenType::True => Value::Bool(true),
+            TokenType::False => Value::Bool(false),
+            TokenType::Nil => Value::Nil,
+            _ => return None,
+        };
+        (!is_negative).then_some(value)
+    }
+
     fn return_stmt(&mut self) {
         if self.compiler.kind == FunType::Script {
             self.error("Can't return from top-level code.".to_string());
@@ -651,7 +1368,7 @@ impl Parser {
             self.emit_return();
         } else {
             self.expression();
-            self.consume(TokenType::Semicolon, "Expect ';' after return value.");
+            self.consume_semicolon("Expect ';' after return value.");
             self.emit_instruction(OpCode::Return);
         }
     }
@@ -662,6 +1379,17 @@ impl Parser {
         self.expression();
         self.consume(TokenType::RightParen, "Expect ')' after condition.");
 
+        // A loop that never runs is dropped, one that never ends doesn't test the condition.
+        if let Some(value) = self.constant_from(loop_start) {
+            self.discard(loop_start);
+            self.statement();
+            match value.is_falsey() {
+                true => self.discard(loop_start),
+                false => self.emit_loop(loop_start),
+            }
+            return;
+        }
+
         let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
         self.emit_instruction(OpCode::Pop);
         self.statement();
@@ -683,14 +1411,22 @@ impl Parser {
         }
 
         let mut loop_start = self.current_chunk().borrow().code.len();
+        let condition = loop_start;
         let mut exit_jump: isize = -1;
+        let mut is_dead = false;
         if !self.fit(TokenType::Semicolon) {
             self.expression();
             self.consume(TokenType::Semicolon, "Expect ';' after loop condition.");
 
-            // Jump out of the loop if the condition is false.
-            exit_jump = self.emit_jump(OpCode::JumpIfFalse);
-            self.emit_instruction(OpCode::Pop); // Condition.
+            if let Some(value) = self.constant_from(condition) {
+                // The loop either never runs or never ends.
+                self.discard(condition);
+                is_dead = value.is_falsey();
+            } else {
+                // Jump out of the loop if the condition is false.
+                exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_instruction(OpCode::Pop); // Condition.
+            }
         }
 
         if !self.fit(TokenType::RightParen) {
@@ -707,6 +1443,9 @@ impl Parser {
 
         self.statement();
         self.emit_loop(loop_start);
+        if is_dead {
+            self.discard(condition);
+        }
 
         if exit_jump != -1 {
             self.patch_jump(exit_jump);
@@ -717,10 +1456,21 @@ impl Parser {
     }
 
     fn block(&mut self) {
+        let open = self.previous;
+        self.block_depth += 1;
         while !self.check(TokenType::RightBrace) && !self.check(TokenType::EoF) {
             self.declaration();
         }
+        self.block_depth -= 1;
 
+        if self.check(TokenType::EoF) {
+            let note = format!(
+                "the block opened at {}:{} is never closed",
+                open.line,
+                open.column + 1
+            );
+            self.error_with_help(self.current, "Expect '}' after block.", note);
+        }
         self.consume(TokenType::RightBrace, "Expect '}' after block.");
     }
 
@@ -735,6 +1485,22 @@ impl Parser {
         if !self.check(TokenType::RightParen) {
             loop {
                 let func = self.compiler.current_fun();
+                if func.borrow().is_variadic() {
+                    self.error_at_current("Rest parameter must be the last one.".to_string());
+                }
+
+                // The rest parameter gets the slot right after the regular ones.
+                if self.fit(TokenType::DotDotDot) {
+                    func.borrow_mut().set_variadic(true);
+                    let constant = self.parse_variable("Expect parameter name after '...'.");
+                    self.define_variable(constant);
+                    if self.fit(TokenType::Comma) {
+                        continue;
+                    } else {
+                        break;
+                    }
+                }
+
                 let arity = func.borrow().arity();
                 func.borrow_mut().change_arity(arity + 1);
                 if arity + 1 > 255 {
@@ -743,6 +1509,16 @@ impl Parser {
                 let constant = self.parse_variable("Expect parameter name.");
                 self.define_variable(constant);
 
+                if self.fit(TokenType::Equal) {
+                    self.default_parameter(arity as u8);
+                } else if func.borrow().min_arity() != arity {
+                    self.error(
+                        "Parameter without default value can't follow one with it.".to_string(),
+                    );
+                } else {
+                    func.borrow_mut().change_min_arity(arity + 1);
+                }
+
                 if self.fit(TokenType::Comma) {
                     continue;
                 } else {
@@ -769,6 +1545,21 @@ impl Parser {
         }
     }
 
+    /// Initializer of a parameter runs only if the caller didn't pass the argument.
+    fn default_parameter(&mut self, param: u8) {
+        self.emit_instructions(Byte::Code(OpCode::JumpIfArg), Byte::Raw(param));
+        self.emit_raw_instruction(255);
+        self.emit_raw_instruction(255);
+        let jump = self.current_chunk().borrow().code.len() as isize - 2;
+
+        self.expression();
+        // Slot 0 holds the function itself.
+        self.emit_instructions(Byte::Code(OpCode::SetLocal), Byte::Raw(param + 1));
+        self.emit_instruction(OpCode::Pop);
+
+        self.patch_jump(jump);
+    }
+
     fn expression(&mut self) {
         self.parse_precedence(Precedence::Assignment);
     }
@@ -779,96 +1570,264 @@ impl Parser {
     }
 
     fn unary(&mut self, _: bool) {
-        let op_type = self.previous.kind;
+        let operator = self.previous;
+        let start = self.code_len();
 
         // Compile the operand.
         self.parse_precedence(Precedence::Unary);
 
+        if let Some(operand) = self.constant_from(start) {
+            let value = match operator.kind {
+                TokenType::Bang => Some(Value::Bool(operand.is_falsey())),
+                TokenType::Minus if operand.is_num() => {
+                    Some(Value::Num(-unsafe { operand.as_num() }))
+                }
+                _ => None,
+            };
+            if let Some(value) = value {
+                self.fold(start, value);
+                return;
+            }
+        }
+
         // Emit the operator instruction.
-        match op_type {
-            TokenType::Bang => self.emit_instruction(OpCode::Not),
-            TokenType::Minus => self.emit_instruction(OpCode::Negate),
+        match operator.kind {
+            TokenType::Bang => self.emit_at(Byte::Code(OpCode::Not), operator),
+            TokenType::Minus => self.emit_at(Byte::Code(OpCode::Negate), operator),
             _ => unreachable!("Unary can be only one of: '-', '!'."),
         }
     }
 
     fn binary(&mut self) {
-        let op_type = self.previous.kind;
-        let rule = self.get_rule(op_type);
+        let operator = self.previous;
+        let start = self.expression_start;
+        let left = self.constant_from(start);
+        let right_start = self.code_len();
+        let rule = self.get_rule(operator.kind);
         let precedence = Precedence::inc(rule.precedence);
         self.parse_precedence(precedence);
 
-        match op_type {
-            TokenType::BangEqual => {
-                self.emit_instructions(Byte::Code(OpCode::Equal), Byte::Code(OpCode::Not))
-            }
-            TokenType::EqualEqual => self.emit_instruction(OpCode::Equal),
-            TokenType::Greater => self.emit_instruction(OpCode::Greater),
-            TokenType::GreaterEqual => {
-                self.emit_instructions(Byte::Code(OpCode::Less), Byte::Code(OpCode::Not))
-            }
-            TokenType::Less => self.emit_instruction(OpCode::Less),
-            TokenType::LessEqual => {
-                self.emit_instructions(Byte::Code(OpCode::Greater), Byte::Code(OpCode::Not))
-            }
-            TokenType::Plus => self.emit_instruction(OpCode::Add),
-            TokenType::Minus => self.emit_instruction(OpCode::Subtract),
-            TokenType::Star => self.emit_instruction(OpCode::Multiply),
-            TokenType::Slash => self.emit_instruction(OpCode::Divide),
+        let codes: &[OpCode] = match operator.kind {
+            TokenType::BangEqual => &[OpCode::Equal, OpCode::Not],
+            TokenType::EqualEqual => &[OpCode::Equal],
+            TokenType::Greater => &[OpCode::Greater],
+            TokenType::GreaterEqual => &[OpCode::Less, OpCode::Not],
+            TokenType::Less => &[OpCode::Less],
+            TokenType::LessEqual => &[OpCode::Greater, OpCode::Not],
+            TokenType::Plus => &[OpCode::Add],
+            TokenType::Minus => &[OpCode::Subtract],
+            TokenType::Star => &[OpCode::Multiply],
+            TokenType::Slash => &[OpCode::Divide],
             _ => unreachable!(
                 "Binary can be one of: '+', '-', '*', '/', '!=', '==', '>', '>=', '<', '<='."
             ),
+        };
+
+        let right = self.constant_from(right_start);
+        if let Some(value) = left.zip(right).and_then(|(a, b)| fold_binary(codes, a, b)) {
+            self.fold(start, value);
+            return;
+        }
+        for &code in codes {
+            self.emit_at(Byte::Code(code), operator);
         }
     }
 
     fn call(&mut self) {
-        let arg_count = self.argument_list();
-        self.emit_instructions(Byte::Code(OpCode::Call), Byte::Raw(arg_count));
+        match self.argument_list() {
+            Some(arg_count) => {
+                self.emit_instructions(Byte::Code(OpCode::Call), Byte::Raw(arg_count))
+            }
+            None => self.emit_instruction(OpCode::CallList),
+        }
+    }
+
+    fn dot(&mut self) {
+        self.consume(TokenType::Identifier, "Expect property name after '.'.");
+        let name = self.identifier_constant(self.previous);
+        self.emit_instructions(Byte::Code(OpCode::GetProperty), Byte::Raw(name));
+    }
+
+    fn list(&mut self, _: bool) {
+        let mut count: u8 = 0;
+        if !self.check(TokenType::RightBracket) {
+            loop {
+                self.expression();
+                if count == 255 {
+                    self.error("Can't have more than 255 elements in a list literal.".to_string());
+                }
+                count = count.wrapping_add(1);
+
+                if !self.fit(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightBracket, "Expect ']' after list elements.");
+        self.emit_instructions(Byte::Code(OpCode::BuildList), Byte::Raw(count));
+    }
+
+    fn index(&mut self) {
+        self.expression();
+        self.consume(TokenType::RightBracket, "Expect ']' after index.");
+        self.emit_instruction(OpCode::Index);
+    }
+
+    fn yield_(&mut self, _: bool) {
+        if self.compiler.kind == FunType::Script {
+            self.error("Can't yield from top-level code.".to_string());
+        }
+        // Any function that yields becomes a generator function:
+        // calling it creates a suspended generator instead of running the body.
+        self.compiler.current_fun().borrow_mut().set_generator(true);
+
+        if self.check(TokenType::Semicolon) || self.check(TokenType::RightParen) {
+            self.emit_instruction(OpCode::Nil);
+        } else {
+            self.parse_precedence(Precedence::Assignment);
+        }
+        self.emit_instruction(OpCode::Yield);
     }
 
     fn literal(&mut self, _: bool) {
-        match self.previous.kind {
-            TokenType::False => self.emit_instruction(OpCode::False),
-            TokenType::Nil => self.emit_instruction(OpCode::Nil),
-            TokenType::True => self.emit_instruction(OpCode::True),
+        let start = self.code_len();
+        let value = match self.previous.kind {
+            TokenType::False => Value::Bool(false),
+            TokenType::Nil => Value::Nil,
+            TokenType::True => Value::Bool(true),
             _ => unreachable!("Expected one of: 'false', 'true', 'nil'."),
-        }
+        };
+        self.emit_value(value.clone());
+        self.set_constant_expr(start, value);
     }
 
     fn number(&mut self, _: bool) {
+        let start = self.code_len();
         let lexeme = self
             .scanner
             .lexeme(self.previous.start, self.previous.length);
         match lexeme.trim().parse::<f64>() {
-            Ok(num) => self.emit_constant(Value::Num(num)),
+            Ok(num) => {
+                self.emit_constant(Value::Num(num));
+                self.set_constant_expr(start, Value::Num(num));
+            }
             Err(_) => self.error("Failed parsing float number.".to_string()),
         }
     }
 
     fn string(&mut self, _: bool) {
+        let start = self.code_len();
         let str = self
             .scanner
             .lexeme(self.previous.start + 1, self.previous.length - 2);
-        self.emit_constant(Value::Obj(Obj::Str(str)));
+        let value = Value::Obj(Obj::Str(str));
+        self.emit_constant(value.clone());
+        self.set_constant_expr(start, value);
+    }
+
+    // Emits the instruction that pushes a constant value.
+    fn emit_value(&mut self, value: Value) {
+        if value.is_nil() {
+            self.emit_instruction(OpCode::Nil);
+        } else if value.is_bool() {
+            match unsafe { value.as_bool() } {
+                true => self.emit_instruction(OpCode::True),
+                false => self.emit_instruction(OpCode::False),
+            }
+        } else {
+            self.emit_constant(value);
+        }
+    }
+
+    fn set_constant_expr(&mut self, start: usize, value: Value) {
+        let end = self.code_len();
+        self.constant_expr = Some(ConstantExpr { start, end, value });
+    }
+
+    /// Value of the code from `start` to the end of the chunk
+    /// if it is a constant expression.
+    fn constant_from(&self, start: usize) -> Option<Value> {
+        let constant = self.constant_expr.as_ref()?;
+        (constant.start == start && constant.end == self.code_len()).then(|| constant.value.clone())
+    }
+
+    /// Replaces the code of a constant expression from `start` with its value,
+    /// which keeps the span of the start.
+    fn fold(&mut self, start: usize, value: Value) {
+        {
+            // Constants of the operands are the last ones in the chunk.
+            let chunk = self.current_chunk();
+            let mut chunk = chunk.borrow_mut();
+            let mut indices = Vec::new();
+            let mut offset = start;
+            while offset < chunk.code.len() {
+                if chunk.code[offset] == OpCode::Constant as u8 {
+                    indices.push(chunk.code[offset + 1] as usize);
+                }
+                offset += chunk.instruction_len(offset);
+            }
+            for index in indices.into_iter().rev() {
+                if index + 1 == chunk.constants.len() {
+                    chunk.constants.pop();
+                }
+            }
+        }
+        let span = self.current_chunk().borrow().spans[start];
+        self.discard(start);
+        let previous = self.previous;
+        self.previous.start = span.start;
+        self.previous.length = span.length;
+        self.previous.line = span.line;
+        self.previous.column = span.column;
+        self.emit_value(value.clone());
+        self.previous = previous;
+        self.set_constant_expr(start, value);
+    }
+
+    /// Removes the code from `start`, e.g. of a branch that is never taken.
+    /// Its constants are left in the chunk.
+    fn discard(&mut self, start: usize) {
+        let chunk = self.current_chunk();
+        let mut chunk = chunk.borrow_mut();
+        chunk.code.truncate(start);
+        chunk.spans.truncate(start);
+        // Locals of the removed code are out of scope by now, the enclosing ones are not.
+        chunk
+            .locals
+            .retain(|l| l.start <= start && l.end.is_none_or(|end| end <= start));
+        self.constant_expr = None;
+    }
+
+    fn code_len(&self) -> usize {
+        self.current_chunk().borrow().code.len()
     }
 
     fn named_variable(&mut self, name: Token, can_assign: bool) {
         let mut arg = self.resolve_local_current(&name);
-        let (get_op, set_op) = if arg != -1 {
-            (OpCode::GetLocal, OpCode::SetLocal)
+        let (get_op, set_op, is_const) = if arg != -1 {
+            let is_const = self.compiler.locals[arg as usize].is_const;
+            (OpCode::GetLocal, OpCode::SetLocal, is_const)
         } else {
             let enclosing = self.compiler.enclosing.as_ref().map(Rc::clone);
             arg = self.resolve_upvalue_current(enclosing, &name);
             if arg != -1 {
-                (OpCode::GetUpvalue, OpCode::SetUpvalue)
+                let is_const = self.is_const_upvalue(&name);
+                (OpCode::GetUpvalue, OpCode::SetUpvalue, is_const)
             } else {
                 arg = self.identifier_constant(name) as isize;
-                (OpCode::GetGlobal, OpCode::SetGlobal)
+                let is_const = self
+                    .constants
+                    .contains(&self.scanner.lexeme(name.start, name.length));
+                (OpCode::GetGlobal, OpCode::SetGlobal, is_const)
             }
         };
 
         let arg = arg as u8;
         if can_assign && self.fit(TokenType::Equal) {
+            if is_const {
+                let lexeme = self.scanner.lexeme(name.start, name.length);
+                self.error_at(name, format!("Can't assign to constant '{}'.", lexeme));
+            }
             self.expression();
             self.emit_instructions(Byte::Code(set_op), Byte::Raw(arg));
         } else {
@@ -898,6 +1857,12 @@ impl Parser {
 
     fn declare_variable(&mut self) {
         if self.compiler.scope_depth == 0 {
+            let name = self
+                .scanner
+                .lexeme(self.previous.start, self.previous.length);
+            if self.constants.contains(&name) {
+                self.error(format!("Already a constant with this name '{}'.", name));
+            }
             return;
         }
 
@@ -941,15 +1906,31 @@ impl Parser {
         self.emit_instructions(Byte::Code(OpCode::DefineGlobal), Byte::Raw(var));
     }
 
-    fn argument_list(&mut self) -> u8 {
+    /// Returns `None` if some argument is spread. Then all of them
+    /// are collected into a list instead of being left on the stack.
+    fn argument_list(&mut self) -> Option<u8> {
         let mut arg_count = 0;
+        let mut spread = false;
         if !self.check(TokenType::RightParen) {
             loop {
-                self.expression();
-                if arg_count == 255 {
-                    self.error("Can't have more than 255 arguments.".to_string());
+                if self.fit(TokenType::DotDotDot) {
+                    if !spread {
+                        spread = true;
+                        self.emit_instructions(Byte::Code(OpCode::BuildList), Byte::Raw(arg_count));
+                    }
+                    self.expression();
+                    self.emit_instruction(OpCode::Extend);
+                } else if spread {
+                    self.expression();
+                    self.emit_instructions(Byte::Code(OpCode::BuildList), Byte::Raw(1));
+                    self.emit_instruction(OpCode::Extend);
+                } else {
+                    self.expression();
+                    if arg_count == 255 {
+                        self.error("Can't have more than 255 arguments.".to_string());
+                    }
+                    arg_count += 1;
                 }
-                arg_count += 1;
 
                 if self.fit(TokenType::Comma) {
                     continue;
@@ -959,10 +1940,21 @@ impl Parser {
             }
         }
         self.consume(TokenType::RightParen, "Expect ')' after arguments.");
-        arg_count
+        if spread {
+            None
+        } else {
+            Some(arg_count)
+        }
     }
 
     fn and_(&mut self) {
+        let start = self.expression_start;
+        if let Some(left) = self.constant_from(start) {
+            let is_right = !left.is_falsey();
+            self.fold_logical(start, left, is_right, Precedence::And);
+            return;
+        }
+
         let end_jump = self.emit_jump(OpCode::JumpIfFalse);
 
         self.emit_instruction(OpCode::Pop);
@@ -972,6 +1964,13 @@ impl Parser {
     }
 
     fn or_(&mut self) {
+        let start = self.expression_start;
+        if let Some(left) = self.constant_from(start) {
+            let is_right = left.is_falsey();
+            self.fold_logical(start, left, is_right, Precedence::Or);
+            return;
+        }
+
         let else_jump = self.emit_jump(OpCode::JumpIfFalse);
         let end_jump = self.emit_jump(OpCode::Jump);
 
@@ -982,6 +1981,20 @@ impl Parser {
         self.patch_jump(end_jump);
     }
 
+    /// `and` or `or` with a constant left operand at `start` is the right operand if `is_right`,
+    /// else the left one. The right operand is still compiled for errors.
+    fn fold_logical(&mut self, start: usize, left: Value, is_right: bool, precedence: Precedence) {
+        if is_right {
+            self.discard(start);
+            self.parse_precedence(precedence);
+            return;
+        }
+        let right_start = self.code_len();
+        self.parse_precedence(precedence);
+        self.discard(right_start);
+        self.set_constant_expr(start, left);
+    }
+
     fn add_local(&mut self, name: Token) {
         if self.compiler.local_count >= UINT8_COUNT as isize {
             self.error(format!(
@@ -995,6 +2008,7 @@ impl Parser {
         local.name = name;
         local.depth = -1;
         local.is_captured = false;
+        local.is_const = false;
     }
 
     fn advance(&mut self) {
@@ -1006,11 +2020,7 @@ impl Parser {
                     self.current = token;
                     break;
                 }
-                Err(err) => {
-                    // eprintln!("(During compilation, in advance()). {err}");
-                    // let message = self.scanner.lexeme(self.current.start, self.current.length);
-                    self.error_at_current(err.message());
-                }
+                Err(err) => self.error_at(err.token(), err.message()),
             }
         }
     }
@@ -1065,6 +2075,22 @@ impl Parser {
         -1
     }
 
+    /// Checks the local that an upvalue with this name refers to.
+    fn is_const_upvalue(&self, name: &Token) -> bool {
+        let mut compiler = self.compiler.enclosing.as_ref().map(Rc::clone);
+        while let Some(current) = compiler {
+            let current = current.borrow();
+            let local_count = current.local_count as usize;
+            for local in current.locals.iter().take(local_count).rev() {
+                if self.identifiers_equal(name, &local.name) {
+                    return local.is_const;
+                }
+            }
+            compiler = current.enclosing.as_ref().map(Rc::clone);
+        }
+        false
+    }
+
     fn resolve_local(&mut self, compiler: &Option<Rc<RefCell<Compiler>>>, name: &Token) -> isize {
         let current = compiler
             .as_ref()
@@ -1089,7 +2115,8 @@ impl Parser {
         -1
     }
 
-    fn add_upvalue_current(&mut self, index: u8, is_local: bool) -> isize {
+    fn add_upvalue_current(&mut self, index: u8, is_local: bool, name: &Token) -> isize {
+        let name = self.scanner.lexeme(name.start, name.length);
         let compiler = &mut self.compiler;
         let upvalue_count = compiler.function.borrow().upvalue_count() as usize;
 
@@ -1107,14 +2134,20 @@ impl Parser {
         compiler.upvalues[upvalue_count].is_local = is_local;
         compiler.upvalues[upvalue_count].index = index;
         let upvalue_count = upvalue_count as isize;
-        compiler
-            .function
-            .borrow_mut()
-            .change_upvalue_count(upvalue_count + 1);
+        let mut function = compiler.function.borrow_mut();
+        function.change_upvalue_count(upvalue_count + 1);
+        function.chunk().borrow_mut().upvalues.push(name);
         upvalue_count
     }
 
-    fn add_upvalue(&mut self, compiler: &mut Compiler, index: u8, is_local: bool) -> isize {
+    fn add_upvalue(
+        &mut self,
+        compiler: &mut Compiler,
+        index: u8,
+        is_local: bool,
+        name: &Token,
+    ) -> isize {
+        let name = self.scanner.lexeme(name.start, name.length);
         let upvalue_count = compiler.function.borrow().upvalue_count() as usize;
 
         for (i, upvalue) in compiler.upvalues.iter().take(upvalue_count).enumerate() {
@@ -1131,10 +2164,9 @@ impl Parser {
         compiler.upvalues[upvalue_count].is_local = is_local;
         compiler.upvalues[upvalue_count].index = index;
         let upvalue_count = upvalue_count as isize;
-        compiler
-            .function
-            .borrow_mut()
-            .change_upvalue_count(upvalue_count + 1);
+        let mut function = compiler.function.borrow_mut();
+        function.change_upvalue_count(upvalue_count + 1);
+        function.chunk().borrow_mut().upvalues.push(name);
         upvalue_count
     }
 
@@ -1150,7 +2182,7 @@ impl Parser {
         let local = self.resolve_local(&enclosing, name);
         if local != -1 {
             enclosing.unwrap().borrow_mut().set_captured(local, true);
-            return self.add_upvalue_current(local as u8, true);
+            return self.add_upvalue_current(local as u8, true, name);
         }
 
         let enclosing = enclosing.unwrap();
@@ -1158,7 +2190,7 @@ impl Parser {
         let enclosing_enclosing = enclosing.enclosing.as_ref().map(Rc::clone);
         let upvalue = self.resolve_upvalue(enclosing, enclosing_enclosing, name);
         if upvalue != -1 {
-            return self.add_upvalue_current(upvalue as u8, false);
+            return self.add_upvalue_current(upvalue as u8, false, name);
         }
 
         -1
@@ -1177,7 +2209,7 @@ impl Parser {
         let local = self.resolve_local(&enclosing, name);
         if local != -1 {
             enclosing.unwrap().borrow_mut().set_captured(local, true);
-            return self.add_upvalue(compiler, local as u8, true);
+            return self.add_upvalue(compiler, local as u8, true, name);
         }
 
         let enclosing = enclosing.unwrap();
@@ -1185,7 +2217,7 @@ impl Parser {
         let enclosing_enclosing = enclosing.enclosing.as_ref().map(Rc::clone);
         let upvalue = self.resolve_upvalue(enclosing, enclosing_enclosing, name);
         if upvalue != -1 {
-            return self.add_upvalue(compiler, upvalue as u8, false);
+            return self.add_upvalue(compiler, upvalue as u8, false, name);
         }
 
         -1
@@ -1195,8 +2227,22 @@ impl Parser {
         if self.compiler.scope_depth == 0 {
             return;
         }
-        self.compiler.locals[self.compiler.local_count as usize - 1].depth =
-            self.compiler.scope_depth;
+        let slot = self.compiler.local_count as usize - 1;
+        self.compiler.locals[slot].depth = self.compiler.scope_depth;
+
+        // Hidden locals (e.g. the value of `match`) are not shown in the debugger.
+        let name = self.compiler.locals[slot].name;
+        if name.kind == TokenType::Identifier {
+            let chunk = self.current_chunk();
+            let mut chunk = chunk.borrow_mut();
+            let local = LocalName {
+                name: self.scanner.lexeme(name.start, name.length),
+                slot,
+                start: chunk.code.len(),
+                end: None,
+            };
+            chunk.locals.push(local);
+        }
     }
 
     fn error_at_current(&mut self, message: String) {
@@ -1208,49 +2254,117 @@ impl Parser {
     }
 
     fn error_at(&mut self, token: Token, message: String) {
+        self.report(Diagnostic::new(&message, token.span()));
+    }
+
+    fn error_with_help(&mut self, token: Token, message: &str, help: String) {
+        self.report(Diagnostic::new(message, token.span()).with_note("help", help));
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
         if self.panic_mode {
             return;
         }
         self.panic_mode = true;
-        eprint!("[line {}] CompileError", token.line);
 
-        match token.kind {
-            TokenType::EoF => eprint!(" at end"),
-            TokenType::Error => (),
-            _ => {
-                let lexeme = self.scanner.lexeme(self.current.start, self.current.length);
-                eprint!(" at '{}'", lexeme);
-            }
+        if !self.config.quiet {
+            eprint!(
+                "{}",
+                diagnostic.render(&self.source, diagnostic::is_colored())
+            );
         }
-
-        eprintln!(": {}", message);
+        self.errors.push(diagnostic);
         self.had_error = true;
     }
 
+    // A missing `;` at the end of a line is reported and parsing goes on as if it was there.
+    fn consume_semicolon(&mut self, message: &str) {
+        if self.fit(TokenType::Semicolon) {
+            return;
+        }
+
+        let is_line_end = self.current.line > self.previous.line
+            || matches!(self.current.kind, TokenType::RightBrace | TokenType::EoF);
+        if !is_line_end {
+            self.error_at_current(message.to_string());
+            return;
+        }
+
+        let lexeme = self
+            .scanner
+            .lexeme(self.previous.start, self.previous.length);
+        self.error_with_help(
+            self.previous,
+            message,
+            format!("insert ';' after '{lexeme}'"),
+        );
+        self.panic_mode = false;
+    }
+
+    // Two names in a row are never valid, so an expression statement made of a single
+    // name followed by another one is likely a misspelled keyword, e.g. `retrun x;`.
+    fn is_misspelled_keyword(&mut self, first: Token) -> bool {
+        let is_single_name =
+            first.kind == TokenType::Identifier && self.previous.start == first.start;
+        let is_followed_by_value = matches!(
+            self.current.kind,
+            TokenType::Identifier | TokenType::Number | TokenType::String
+        );
+        if !is_single_name || !is_followed_by_value || self.current.line != first.line {
+            return false;
+        }
+
+        let name = self.scanner.lexeme(first.start, first.length);
+        let Some(keyword) = diagnostic::suggest(&name, KEYWORDS) else {
+            return false;
+        };
+        let message = format!("Unknown keyword '{name}'.");
+        self.error_with_help(first, &message, format!("did you mean '{keyword}'?"));
+        true
+    }
+
+    // Skips tokens until the start of the next statement. Blocks inside the broken
+    // statement are skipped as a whole and the `}` of the enclosing block is kept.
     fn synchronize(&mut self) {
         self.panic_mode = false;
 
+        let mut depth: usize = 0; // of blocks inside the broken statement
         while self.current.kind != TokenType::EoF {
-            if self.previous.kind == TokenType::Semicolon {
+            if depth == 0 && self.previous.kind == TokenType::Semicolon {
                 return;
             }
             match self.current.kind {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => return,
+                TokenType::RightBrace => depth -= 1,
                 TokenType::Class
+                | TokenType::Const
+                | TokenType::Enum
                 | TokenType::Fun
                 | TokenType::Var
                 | TokenType::For
                 | TokenType::If
+                | TokenType::Match
                 | TokenType::While
                 | TokenType::Print
-                | TokenType::Return => return,
-                _ => self.advance(),
+                | TokenType::Return
+                    if depth == 0 =>
+                {
+                    return
+                }
+                _ => (),
             }
+            self.advance();
         }
     }
 
     fn end_compiler(&mut self) -> (Rc<RefCell<Function>>, Compiler) {
         self.emit_return();
         let function = self.compiler.current_fun();
+        // Code with errors is never run, and its jumps may be broken.
+        if !self.had_error {
+            optimizer::optimize(&mut self.current_chunk().borrow_mut());
+        }
 
         if self.config.debug && self.had_error {
             let name = if function.borrow().name().is_empty() {
@@ -1263,13 +2377,11 @@ impl Parser {
         }
 
         let saved_compiler = self.compiler.clone();
-        match &self.compiler.enclosing {
-            Some(compiler) => {
-                let compiler = compiler.borrow().clone();
-                self.compiler = compiler;
-            }
-            None => (),
+        if let Some(compiler) = &self.compiler.enclosing {
+            let compiler = compiler.borrow().clone();
+            self.compiler = compiler;
         }
+        self.constant_expr = None;
         (function, saved_compiler)
     }
 
@@ -1284,7 +2396,9 @@ impl Parser {
             && self.compiler.locals[self.compiler.local_count as usize - 1].depth
                 > self.compiler.scope_depth
         {
-            if self.compiler.locals[self.compiler.local_count as usize - 1].is_captured {
+            let slot = self.compiler.local_count as usize - 1;
+            self.end_local_name(slot);
+            if self.compiler.locals[slot].is_captured {
                 self.emit_instruction(OpCode::CloseUpvalue);
             } else {
                 self.emit_instruction(OpCode::Pop);
@@ -1293,12 +2407,51 @@ impl Parser {
         }
     }
 
+    fn end_local_name(&self, slot: usize) {
+        let chunk = self.current_chunk();
+        let mut chunk = chunk.borrow_mut();
+        let end = chunk.code.len();
+        let local = chunk.locals.iter_mut().rev().find(|l| l.slot == slot);
+        if let Some(local) = local.filter(|l| l.end.is_none()) {
+            local.end = Some(end);
+        }
+    }
+
     fn emit_return(&self) {
         self.emit_instruction(OpCode::Nil);
         self.emit_instruction(OpCode::Return);
     }
 }
 
+/// Result of the instructions for a binary operator with constant operands, the same as
+/// the VM computes it. `None` if the operation fails at runtime, so that the error is still
+/// reported there, and for division by zero, which is left to the VM as well.
+fn fold_binary(codes: &[OpCode], a: Value, b: Value) -> Option<Value> {
+    let value = match (codes[0], a.obj(), b.obj()) {
+        (OpCode::Equal, _, _) => Value::Bool(a.equal(b)),
+        (OpCode::Add, Some(Obj::Str(a)), Some(Obj::Str(b))) => {
+            Value::Obj(Obj::Str(format!("{a}{b}")))
+        }
+        (code, None, None) if a.is_num() && b.is_num() => {
+            let (a, b) = unsafe { (a.as_num(), b.as_num()) };
+            match code {
+                OpCode::Greater => Value::Bool(a > b),
+                OpCode::Less => Value::Bool(a < b),
+                OpCode::Add => Value::Num(a + b),
+                OpCode::Subtract => Value::Num(a - b),
+                OpCode::Multiply => Value::Num(a * b),
+                OpCode::Divide if b != 0.0 => Value::Num(a / b),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    match codes.get(1) {
+        Some(OpCode::Not) => Some(Value::Bool(value.is_falsey())),
+        _ => Some(value),
+    }
+}
+
 /// `num_enum` crate is better solution here.
 impl TryFrom<u8> for Precedence {
     type Error = &'static str;
@@ -1323,3 +2476,236 @@ impl TryFrom<u8> for Precedence {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use crate::chunk::OpCode;
+    use crate::object::Obj;
+    use crate::value::Value;
+
+    fn compile(source: &str) -> Option<Value> {
+        let mut parser = Parser::default();
+        let function = parser.compile(source.to_string()).ok()?;
+        let chunk = function.borrow().chunk();
+        let constant = chunk.borrow().constants.last().cloned();
+        constant
+    }
+
+    #[test]
+    fn test_yield_makes_generator() {
+        match compile("fun gen() { yield 1; }")
+            .as_ref()
+            .and_then(Value::obj)
+        {
+            Some(Obj::Fun(fun)) => assert!(fun.is_generator()),
+            _ => panic!("Expected compiled function."),
+        }
+        match compile("fun plain() { return 1; }")
+            .as_ref()
+            .and_then(Value::obj)
+        {
+            Some(Obj::Fun(fun)) => assert!(!fun.is_generator()),
+            _ => panic!("Expected compiled function."),
+        }
+    }
+
+    #[test]
+    fn test_yield_outside_function() {
+        assert!(compile("yield 1;").is_none());
+    }
+
+    #[test]
+    fn test_constants() {
+        assert!(compile("const a = 1; { const b = a + 1; fun f() { return b; } }").is_some());
+        assert!(compile("const a = 1; a = 2;").is_none());
+        assert!(compile("{ const b = 1; b = 2; }").is_none());
+        assert!(compile("const a;").is_none());
+    }
+
+    #[test]
+    fn test_match() {
+        let source = "match (1) { case 1 | 2 => print 1; case -3 => print 2; case x => print x; }";
+        assert!(compile(source).is_some());
+        assert!(compile("match (1) { case _ => print 1; case \"a\" => print 2; }").is_some());
+        assert!(compile("match (1) { case 1 | x => print x; }").is_none());
+        assert!(compile("match (1) { case P(x, y: 0 | _) => print x; }").is_some());
+        assert!(compile("match (1) { case P(x: Q(y) | 1) => print y; }").is_none());
+        assert!(compile("match (1) { case 1 print 1; }").is_none());
+    }
+
+    #[test]
+    fn test_match_jump_table() {
+        // Keys of the table of the first match, an enum or a list of literals.
+        let keys = |source: &str| {
+            let mut parser = Parser::default();
+            let function = parser.compile(source.to_string()).unwrap();
+            let chunk = function.borrow().chunk();
+            let chunk = chunk.borrow();
+            let mut offset = 0;
+            while offset < chunk.code.len() {
+                if chunk.code[offset] == OpCode::JumpTable as u8 {
+                    let constant = chunk.code[offset + 1] as usize;
+                    return Some(chunk.constants[constant].to_string());
+                }
+                offset += chunk.instruction_len(offset);
+            }
+            None
+        };
+        let colors = "enum C { A, B } match (C.A) { case C.B => print 1; case _ => print 2; }";
+        assert_eq!(keys(colors).as_deref(), Some("C"));
+        // Only the first case decides, an unknown name is left to the tests.
+        assert_eq!(
+            keys("enum C { A, B } match (C.A) { case 1 => print 1; case C.A => print 2; }")
+                .as_deref(),
+            Some("[1]")
+        );
+        assert_eq!(keys("match (1) { case C.A => print 1; }"), None);
+        assert_eq!(keys("match (1) { case x => print x; }"), None);
+
+        let literals = "match (1) {
+            case 1 | -2 => print 1;
+            case \"a\" | 1 => { match (2) { case 3 => print 3; } }
+            case nil => print 3;
+        }";
+        assert_eq!(keys(literals).as_deref(), Some("[1, -2, a, nil]"));
+        // Literals are collected up to the first case that can match other values.
+        let catch_all =
+            "match (1) { case 1 => print 1; case 2 | _ => print 2; case 3 => print 3; }";
+        assert_eq!(keys(catch_all).as_deref(), Some("[1, 2]"));
+    }
+
+    #[test]
+    fn test_enums() {
+        assert!(compile("enum A { B, C(x, y) } print A.C(1, 2).y;").is_some());
+        assert!(compile("enum A { B, B }").is_none());
+        assert!(compile("enum A { B(x) } match (A.B(1)) { case A.B(x: 1) => print 1; }").is_some());
+        assert!(
+            compile("enum A { B(x) } match (A.B(1)) { case A.B(x: A.B(y)) => print y; }").is_some()
+        );
+    }
+
+    #[test]
+    fn test_operator_span() {
+        let mut parser = Parser::default();
+        let function = parser.compile("print a +\n  2;".to_string()).unwrap();
+        let chunk = function.borrow().chunk();
+        let chunk = chunk.borrow();
+        let add = chunk.code.iter().position(|&c| c == OpCode::Add as u8);
+        let span = chunk.spans[add.unwrap()];
+        assert_eq!(
+            (span.start, span.length, span.line, span.column),
+            (8, 1, 1, 8)
+        );
+        assert_eq!(chunk.source.text, "print a +\n  2;");
+    }
+
+    fn code(source: &str) -> Vec<u8> {
+        let mut parser = Parser::default();
+        let function = parser.compile(source.to_string()).unwrap();
+        let chunk = function.borrow().chunk();
+        let code = chunk.borrow().code.clone();
+        code
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let folded = |source: &str| match compile(source) {
+            Some(value) if code(source).len() == 4 => value.to_string(),
+            _ => panic!("Expected one constant in '{source}'."),
+        };
+        assert_eq!(folded("print 1 + 2 * 3;"), "7");
+        assert_eq!(folded("print -(2 - 5) / 2;"), "1.5");
+        assert_eq!(folded("print \"a\" + \"b\" + \"c\";"), "abc");
+        let print_false = [OpCode::False, OpCode::Print, OpCode::ReturnNil];
+        assert_eq!(code("print !true;"), print_false.map(|c| c as u8));
+        assert_eq!(code("print 1 >= 2 == !nil;"), print_false.map(|c| c as u8));
+
+        // Operations that fail or divide by zero are left to the VM.
+        for source in [
+            "print -\"a\";",
+            "print 1 + \"a\";",
+            "print 1 < nil;",
+            "print 1 / 0;",
+        ] {
+            assert!(code(source).len() > 4, "'{source}' is folded.");
+        }
+        // A constant left operand of `and` or `or` decides which operand is left.
+        assert_eq!(code("print true and false;"), print_false.map(|c| c as u8));
+        assert_eq!(
+            code("print nil or 1 and false;"),
+            print_false.map(|c| c as u8)
+        );
+        assert_eq!(folded("print nil or 1 + 2;"), "3");
+        assert_eq!(folded("print (\"a\" or f()) + \"b\";"), "ab");
+        let jump = OpCode::JumpIfFalse as u8;
+        for source in ["var a; print false and a;", "var a; print true and a;"] {
+            assert!(!code(source).contains(&jump), "'{source}' has a jump.");
+        }
+        assert!(code("var a; print a and true;").contains(&jump));
+        assert!(compile("print true or 1 +;").is_none());
+
+        // Only the right operand is constant.
+        let code = code("var a = 1; print a + 2 * 3;");
+        assert_eq!(code.iter().filter(|&&c| c == OpCode::Add as u8).count(), 1);
+        assert_eq!(
+            code.iter()
+                .filter(|&&c| c == OpCode::Multiply as u8)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_dead_branches() {
+        let print = OpCode::Print as u8;
+        let count = |source: &str| code(source).iter().filter(|&&c| c == print).count();
+        assert_eq!(count("if (false) print 1; else print 2;"), 1);
+        assert_eq!(count("if (1 < 2) print 1; else { var a = 2; print a; }"), 1);
+        assert_eq!(count("while (nil) print 1;"), 0);
+        assert_eq!(count("for (var i = 0; false; i = i + 1) print i;"), 0);
+        assert!(!code("while (true) print 1;").contains(&(OpCode::JumpIfFalse as u8)));
+
+        // Errors in dead code are still reported.
+        assert!(compile("if (false) print 1 +;").is_none());
+        assert!(compile("while (false) { return; }").is_none());
+    }
+
+    #[test]
+    fn test_recovery() {
+        let mut parser = Parser::default();
+        let source = "var a = 1\nretrun a;\nfun f() { print +; }\n}\n{ print a;";
+        assert!(parser.compile(source.to_string()).is_err());
+        let errors: Vec<_> = parser
+            .errors()
+            .iter()
+            .map(|e| (e.message(), e.span().line))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("Expect ';' after variable declaration.", 1),
+                ("Unknown keyword 'retrun'.", 2),
+                ("Expect expression.", 3),
+                ("Unmatched '}'.", 4),
+                ("Expect '}' after block.", 5),
+            ]
+        );
+
+        // Assigning a constant doesn't stop the parser, the error points to the name.
+        let source = "const limit = 10;\n{ const b = 1;\n  b = 2; }\nlimit = 20;";
+        assert!(parser.compile(source.to_string()).is_err());
+        let errors: Vec<_> = parser
+            .errors()
+            .iter()
+            .map(|e| (e.message(), e.span().line, e.span().column))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("Can't assign to constant 'b'.", 3, 2),
+                ("Can't assign to constant 'limit'.", 4, 0),
+            ]
+        );
+    }
+}
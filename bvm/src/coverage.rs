@@ -0,0 +1,295 @@
+//! Line and branch coverage of a script, written in the lcov tracefile format and as a
+//! short summary:
+//!
+//! ```text
+//! Coverage of lib.lox:
+//!   lines     10/12 (83.3%), missed: 4, 7-8
+//!   branches  5/8 (62.5%), missed: 3 true, 9 false, 9 true
+//! ```
+//!
+//! A line is hit each time the VM comes to the first instruction of its code. Every `JumpIfFalse`
+//! (of `if`, `while`, `for`, `and`, `or` and patterns of `match`), also when it is fused into
+//! `LessJumpIfFalse`, is a branch with two ways: the condition was true and it was false.
+
+use crate::chunk::{Chunk, OpCode};
+use crate::vm::CallFrame;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::rc::Rc;
+
+struct Branch {
+    line: isize,
+    column: usize,
+    counts: [u64; 2], // times the condition was true and false
+}
+
+/// Offsets of line starts and of branches in a chunk.
+struct Sites {
+    starts: Vec<bool>,
+    branches: HashMap<usize, usize>, // offset -> branch
+}
+
+#[derive(Default)]
+pub struct Coverage {
+    file: String,
+    chunks: HashMap<*const RefCell<Chunk>, Sites>,
+    lines: BTreeMap<isize, u64>,
+    branches: Vec<Branch>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called before every instruction with the frame that runs it.
+    pub fn record(&mut self, frame: &CallFrame) {
+        let chunk = frame.closure.borrow().chunk();
+        let key = Rc::as_ptr(&chunk);
+        if !self.chunks.contains_key(&key) {
+            self.add_chunk(&chunk);
+        }
+        if self.chunks[&key].starts[frame.ip] {
+            *self.lines.entry(chunk.borrow().line(frame.ip)).or_default() += 1;
+        }
+    }
+
+    /// Called by `JumpIfFalse` or `LessJumpIfFalse` at `offset` with the value of its condition.
+    pub fn branch(&mut self, frame: &CallFrame, offset: usize, is_true: bool) {
+        let key = Rc::as_ptr(&frame.closure.borrow().chunk());
+        if let Some(&id) = self
+            .chunks
+            .get(&key)
+            .and_then(|sites| sites.branches.get(&offset))
+        {
+            self.branches[id].counts[usize::from(!is_true)] += 1;
+        }
+    }
+
+    /// Finds lines and branches of the chunk and of all functions declared in it, so the
+    /// ones never run are reported too.
+    fn add_chunk(&mut self, chunk: &Rc<RefCell<Chunk>>) {
+        let key = Rc::as_ptr(chunk);
+        if self.chunks.contains_key(&key) {
+            return;
+        }
+        let chunk = chunk.borrow();
+        if self.file.is_empty() {
+            self.file = chunk.source.name.clone();
+        }
+
+        let mut sites = Sites {
+            starts: vec![false; chunk.code.len()],
+            branches: HashMap::new(),
+        };
+        let mut previous = None;
+        let mut offset = 0;
+        while offset < chunk.code.len() {
+            let span = chunk.spans[offset];
+            // Scopes end and functions return at a `}` or at the end of the file, these
+            // aren't lines of code.
+            let text = chunk.source.text.get(span.start..span.start + span.length);
+            if span.line > 0 && !matches!(text, None | Some("" | "}")) {
+                self.lines.entry(span.line).or_default();
+                sites.starts[offset] = previous != Some(span.line);
+                previous = Some(span.line);
+            }
+
+            let jump = match OpCode::try_from(chunk.code[offset]) {
+                Ok(OpCode::JumpIfFalse) => Some(span),
+                // The operands keep the span of the jump.
+                Ok(OpCode::LessJumpIfFalse) => Some(chunk.spans[offset + 1]),
+                _ => None,
+            };
+            if let Some(span) = jump {
+                sites.branches.insert(offset, self.branches.len());
+                self.branches.push(Branch {
+                    line: span.line,
+                    column: span.column,
+                    counts: [0, 0],
+                });
+            }
+            offset += chunk.instruction_len(offset);
+        }
+        self.chunks.insert(key, sites);
+
+        for constant in &chunk.constants {
+            if constant.is_obj_type("Function") {
+                let function = unsafe { constant.as_obj().as_fun() };
+                self.add_chunk(&function.chunk());
+            }
+        }
+    }
+
+    /// Branches in the source order.
+    fn sorted_branches(&self) -> Vec<&Branch> {
+        let mut branches: Vec<&Branch> = self.branches.iter().collect();
+        branches.sort_by_key(|branch| (branch.line, branch.column));
+        branches
+    }
+
+    /// Report in the lcov tracefile format, e.g. for `genhtml`. Branches of the same line
+    /// are numbered as blocks in the source order.
+    pub fn lcov(&self) -> String {
+        let mut out = format!("TN:\nSF:{}\n", self.file);
+
+        let mut blocks: HashMap<isize, usize> = HashMap::new(); // line -> next block
+        for branch in self.sorted_branches() {
+            let block = blocks.entry(branch.line).or_default();
+            *block += 1;
+            let block = *block - 1;
+            for (way, count) in branch.counts.iter().enumerate() {
+                let taken = match branch.counts {
+                    [0, 0] => "-".to_string(),
+                    _ => count.to_string(),
+                };
+                let _ = writeln!(out, "BRDA:{},{block},{way},{taken}", branch.line);
+            }
+        }
+        let (branches_hit, branches_found) = self.branches_hit();
+        let _ = writeln!(out, "BRF:{branches_found}\nBRH:{branches_hit}");
+
+        for (line, count) in &self.lines {
+            let _ = writeln!(out, "DA:{line},{count}");
+        }
+        let lines_hit = self.lines.values().filter(|&&count| count > 0).count();
+        let _ = writeln!(out, "LF:{}\nLH:{lines_hit}", self.lines.len());
+
+        out.push_str("end_of_record\n");
+        out
+    }
+
+    pub fn summary(&self) -> String {
+        let missed: Vec<isize> = self
+            .lines
+            .iter()
+            .filter(|(_, &count)| count == 0)
+            .map(|(&line, _)| line)
+            .collect();
+        let lines_hit = self.lines.len() - missed.len();
+        let mut out = format!("Coverage of {}:\n", self.file);
+        let _ = write!(
+            out,
+            "  lines     {lines_hit}/{} ({})",
+            self.lines.len(),
+            percent(lines_hit, self.lines.len())
+        );
+        if !missed.is_empty() {
+            let _ = write!(out, ", missed: {}", ranges(&missed));
+        }
+
+        let (branches_hit, branches_found) = self.branches_hit();
+        let _ = write!(
+            out,
+            "\n  branches  {branches_hit}/{branches_found} ({})",
+            percent(branches_hit, branches_found)
+        );
+        let mut missed = Vec::new();
+        for branch in self.sorted_branches() {
+            for (way, name) in ["true", "false"].iter().enumerate() {
+                if branch.counts[way] == 0 {
+                    missed.push(format!("{} {name}", branch.line));
+                }
+            }
+        }
+        if !missed.is_empty() {
+            let _ = write!(out, ", missed: {}", missed.join(", "));
+        }
+        out.push('\n');
+        out
+    }
+
+    /// Ways of the branches that were taken, and all of them.
+    fn branches_hit(&self) -> (usize, usize) {
+        let hit = self
+            .branches
+            .iter()
+            .flat_map(|branch| branch.counts)
+            .filter(|&count| count > 0)
+            .count();
+        (hit, self.branches.len() * 2)
+    }
+}
+
+fn percent(hit: usize, found: usize) -> String {
+    match found {
+        0 => "100.0%".to_string(),
+        _ => format!("{:.1}%", hit as f64 * 100.0 / found as f64),
+    }
+}
+
+/// Sorted line numbers, e.g. `4, 7-9`.
+fn ranges(lines: &[isize]) -> String {
+    let mut ranges: Vec<(isize, isize)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{start}-{end}"),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Coverage;
+    use crate::vm::VM;
+
+    #[test]
+    fn test_lcov() {
+        let source = "fun sign(n) {
+  if (n > 0 and n < 100) return 1;
+  if (n < 0) {
+    return -1;
+  }
+  return 0;
+}
+print sign(5);
+print sign(0);";
+        let mut vm = VM::default();
+        vm.init();
+        vm.set_source_name("test.lox");
+        vm.set_coverage(Coverage::new());
+        vm.interpret(source.to_string());
+        let coverage = vm.take_coverage().unwrap();
+
+        let lcov = coverage.lcov();
+        let records: Vec<&str> = lcov.lines().collect();
+        assert_eq!(
+            records,
+            [
+                "TN:",
+                "SF:test.lox",
+                "BRDA:2,0,0,1",
+                "BRDA:2,0,1,1",
+                "BRDA:2,1,0,1",
+                "BRDA:2,1,1,1",
+                "BRDA:3,0,0,0",
+                "BRDA:3,0,1,1",
+                "BRF:6",
+                "BRH:5",
+                "DA:1,1",
+                "DA:2,2",
+                "DA:3,1",
+                "DA:4,0",
+                "DA:6,1",
+                "DA:8,1",
+                "DA:9,1",
+                "LF:7",
+                "LH:6",
+                "end_of_record",
+            ]
+        );
+        assert!(coverage.summary().ends_with(
+            "lines     6/7 (85.7%), missed: 4\n  branches  5/6 (83.3%), missed: 3 true\n"
+        ));
+    }
+}
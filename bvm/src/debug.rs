@@ -16,10 +16,10 @@ pub fn disassemble_instruction(chunk: &Chunk, mut offset: usize) -> usize {
         .expect("Instruction index out of bounds (in chunk.code).");
 
     print!("{:04} ", offset);
-    if offset > 0 && chunk.lines[offset] == chunk.lines[offset - 1] {
+    if offset > 0 && chunk.line(offset) == chunk.line(offset - 1) {
         print!("   | ");
     } else {
-        print!("{:4} ", chunk.lines[offset]);
+        print!("{:4} ", chunk.line(offset));
     }
 
     match OpCode::try_from(*instruction).unwrap() {
@@ -35,6 +35,7 @@ pub fn disassemble_instruction(chunk: &Chunk, mut offset: usize) -> usize {
         OpCode::SetGlobal => constant_instruction("OP_SET_GLOBAL", chunk, offset),
         OpCode::GetUpvalue => byte_instruction("OP_GET_UPVALUE", chunk, offset),
         OpCode::SetUpvalue => byte_instruction("OP_SET_UPVALUE", chunk, offset),
+        OpCode::GetProperty => constant_instruction("OP_GET_PROPERTY", chunk, offset),
         OpCode::Equal => simple_instruction("OP_EQUAL", offset),
         OpCode::Greater => simple_instruction("OP_GREATER", offset),
         OpCode::Less => simple_instruction("OP_LESS", offset),
@@ -86,6 +87,46 @@ pub fn disassemble_instruction(chunk: &Chunk, mut offset: usize) -> usize {
         }
         OpCode::CloseUpvalue => simple_instruction("OP_CLOSE_UPVALUE", offset),
         OpCode::Return => simple_instruction("OP_RETURN", offset),
+        OpCode::Yield => simple_instruction("OP_YIELD", offset),
+        OpCode::JumpIfArg => jump_if_arg_instruction("OP_JUMP_IF_ARG", chunk, offset),
+        OpCode::BuildList => byte_instruction("OP_BUILD_LIST", chunk, offset),
+        OpCode::Index => simple_instruction("OP_INDEX", offset),
+        OpCode::Extend => simple_instruction("OP_EXTEND", offset),
+        OpCode::CallList => simple_instruction("OP_CALL_LIST", offset),
+        OpCode::IsVariant => constant_instruction("OP_IS_VARIANT", chunk, offset),
+        OpCode::JumpTable => {
+            let constant = chunk.code[offset + 1];
+            let count = chunk.code[offset + 2] as usize;
+            println!(
+                "{:16} {:4} '{}'",
+                "OP_JUMP_TABLE", constant, chunk.constants[constant as usize]
+            );
+            let end = offset + 3 + 2 * count;
+            for i in 0..count {
+                let at = offset + 3 + 2 * i;
+                let jump = (chunk.code[at] as usize) << 8 | chunk.code[at + 1] as usize;
+                println!(
+                    "{:04}      |                     {} -> {}",
+                    at,
+                    i,
+                    end + jump
+                );
+            }
+            end
+        }
+        OpCode::IsInstance => simple_instruction("OP_IS_INSTANCE", offset),
+        OpCode::AddLocalConstant => {
+            let slot = chunk.code[offset + 1];
+            let constant = chunk.code[offset + 2];
+            println!(
+                "{:16} {:4} {:4} '{}'",
+                "OP_ADD_LOCAL_CONSTANT", slot, constant, chunk.constants[constant as usize]
+            );
+            offset + 3
+        }
+        OpCode::LessJumpIfFalse => jump_instruction("OP_LESS_JUMP_IF_FALSE", 1, chunk, offset),
+        OpCode::PopN => byte_instruction("OP_POP_N", chunk, offset),
+        OpCode::ReturnNil => simple_instruction("OP_RETURN_NIL", offset),
 
         _ => {
             eprintln!("Unknown opcode {:?}", instruction);
@@ -135,3 +176,14 @@ fn jump_instruction(name: &str, sign: isize, chunk: &Chunk, offset: usize) -> us
     println!("{:16} {:4} -> {}", name, offset, dest);
     offset + 3
 }
+
+fn jump_if_arg_instruction(name: &str, chunk: &Chunk, offset: usize) -> usize {
+    let param = chunk
+        .code
+        .get(offset + 1)
+        .expect("Failed to get parameter index of jump.");
+    let jump = ((chunk.code[offset + 2] as u16) << 8) | chunk.code[offset + 3] as u16;
+    let dest = offset + 4 + jump as usize;
+    println!("{:16} {:4} {:4} -> {}", name, param, offset, dest);
+    offset + 4
+}
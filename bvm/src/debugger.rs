@@ -0,0 +1,425 @@
+//! Source-level debugger driven by commands, one per line:
+//!
+//! ```text
+//! (lox) break fib.lox:3
+//! (lox) continue
+//! fib.lox:3 in fib()
+//!    3 |   if (n < 2) return n;
+//! (lox) print n
+//! n = 10
+//! ```
+//!
+//! The VM gives it control before every instruction, but it stops only
+//! at the first instruction of a line.
+
+use crate::chunk::Chunk;
+use crate::object::{Closure, Obj};
+use crate::value::Value;
+use crate::vm::{CallFrame, VM};
+use std::io::{self, BufRead, BufReader, Write};
+
+const HELP: &str = "Commands:
+  break, b [FILE:]LINE    Stop at the line
+  delete, d [FILE:]LINE   Remove the breakpoint
+  continue, c             Run until the next breakpoint
+  step, s                 Go to the next line, entering calls
+  next, n                 Go to the next line of this function
+  finish, f               Run until this function returns
+  print, p NAME           Print a variable, e.g. `p list[0]` or `p shape.radius`
+  locals, globals, upvalues
+                          Print all variables of the kind
+  backtrace, bt           Print the call stack
+  watch, w NAME           Print the variable at every stop
+  unwatch NAME            Stop watching the variable
+  quit, q                 Stop the program
+End of input runs the program to the end.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Continue,
+    Step,
+    Next(usize, isize), // frame count and line where the command was given
+    Finish(usize),
+}
+
+struct Breakpoint {
+    file: String,
+    line: isize,
+}
+
+impl Breakpoint {
+    fn is_at(&self, file: &str, line: isize) -> bool {
+        self.line == line && (file == self.file || file.ends_with(&format!("/{}", self.file)))
+    }
+}
+
+pub struct Debugger {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    mode: Mode,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<String>,
+    // Frame count and line of the previous instruction.
+    last: Option<(usize, isize)>,
+}
+
+impl Debugger {
+    /// Stops at the first line of the program.
+    pub fn new(input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Debugger {
+            input,
+            output,
+            mode: Mode::Step,
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+            last: None,
+        }
+    }
+
+    pub fn stdio() -> Self {
+        Self::new(
+            Box::new(BufReader::new(io::stdin())),
+            Box::new(io::stdout()),
+        )
+    }
+
+    /// Called before every instruction, returns `false` if the program should stop.
+    pub fn hook(&mut self, vm: &VM) -> bool {
+        let depth = vm.frame_count as usize;
+        let frame = &vm.frames[depth - 1];
+        let chunk = frame.closure.borrow().chunk();
+        let chunk = chunk.borrow();
+        let line = chunk.line(frame.ip);
+
+        if self.last.replace((depth, line)) == Some((depth, line)) {
+            return true;
+        }
+        let is_breakpoint = self
+            .breakpoints
+            .iter()
+            .any(|b| b.is_at(&chunk.source.name, line));
+        let is_stop = match self.mode {
+            Mode::Continue => false,
+            Mode::Step => true,
+            Mode::Next(d, l) => depth < d || (depth == d && line != l),
+            Mode::Finish(d) => depth < d,
+        };
+        if !is_breakpoint && !is_stop {
+            return true;
+        }
+
+        // Errors of writing to the output are not worth stopping the program.
+        let _ = self.show_stop(vm, frame, &chunk, line);
+        self.prompt(vm, depth, line).unwrap_or(true)
+    }
+
+    fn show_stop(
+        &mut self,
+        vm: &VM,
+        frame: &CallFrame,
+        chunk: &Chunk,
+        line: isize,
+    ) -> io::Result<()> {
+        let name = frame_name(&frame.closure.borrow());
+        writeln!(self.output, "{}:{line} in {name}", chunk.source.name)?;
+        let index = usize::try_from(line - 1).ok();
+        if let Some(text) = index.and_then(|i| chunk.source.text.lines().nth(i)) {
+            writeln!(self.output, "{line:>4} | {text}")?;
+        }
+        for watch in &self.watches {
+            match evaluate(vm, watch) {
+                Ok(value) => writeln!(self.output, "{watch} = {value}")?,
+                Err(message) => writeln!(self.output, "{watch}: {message}")?,
+            }
+        }
+        Ok(())
+    }
+
+    // Reads commands until one of them resumes the program.
+    fn prompt(&mut self, vm: &VM, depth: usize, line: isize) -> io::Result<bool> {
+        loop {
+            write!(self.output, "(lox) ")?;
+            self.output.flush()?;
+
+            let mut input = String::new();
+            if self.input.read_line(&mut input)? == 0 {
+                writeln!(self.output)?;
+                self.mode = Mode::Continue;
+                self.breakpoints.clear();
+                return Ok(true);
+            }
+            let (command, argument) = match input.trim().split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (input.trim(), ""),
+            };
+
+            match command {
+                "" => (),
+                "break" | "b" => match self.breakpoint(vm, argument) {
+                    Some(b) => {
+                        writeln!(self.output, "Breakpoint at {}:{}", b.file, b.line)?;
+                        self.breakpoints.push(b);
+                    }
+                    None => writeln!(self.output, "Expect [FILE:]LINE.")?,
+                },
+                "delete" | "d" => match self.breakpoint(vm, argument) {
+                    Some(b) => {
+                        let count = self.breakpoints.len();
+                        self.breakpoints
+                            .retain(|other| other.file != b.file || other.line != b.line);
+                        if count == self.breakpoints.len() {
+                            writeln!(self.output, "No breakpoint at {}:{}", b.file, b.line)?;
+                        }
+                    }
+                    None => writeln!(self.output, "Expect [FILE:]LINE.")?,
+                },
+                "continue" | "c" => return self.resume(Mode::Continue),
+                "step" | "s" => return self.resume(Mode::Step),
+                "next" | "n" => return self.resume(Mode::Next(depth, line)),
+                "finish" | "f" => return self.resume(Mode::Finish(depth)),
+                "print" | "p" => match evaluate(vm, argument) {
+                    Ok(value) => writeln!(self.output, "{argument} = {value}")?,
+                    Err(message) => writeln!(self.output, "{message}")?,
+                },
+                "locals" => {
+                    for (name, value) in locals(vm, vm.frame_count as usize - 1) {
+                        writeln!(self.output, "{name} = {value}")?;
+                    }
+                }
+                "upvalues" => {
+                    for (name, value) in upvalues(vm) {
+                        writeln!(self.output, "{name} = {value}")?;
+                    }
+                }
+                "globals" => {
+                    let mut globals: Vec<_> = vm
+                        .globals
+                        .iter()
+                        .filter(|(_, value)| !value.is_obj_type("BuiltIn"))
+                        .collect();
+                    globals.sort_by(|a, b| a.0.cmp(b.0));
+                    for (name, value) in globals {
+                        writeln!(self.output, "{name} = {value}")?;
+                    }
+                }
+                "backtrace" | "bt" => self.backtrace(vm)?,
+                "watch" | "w" if !argument.is_empty() => self.watches.push(argument.to_string()),
+                "unwatch" => self.watches.retain(|w| w != argument),
+                "help" | "h" => writeln!(self.output, "{HELP}")?,
+                "quit" | "q" => return Ok(false),
+                _ => writeln!(
+                    self.output,
+                    "Unknown command '{command}'. Type 'help' for a list of commands."
+                )?,
+            }
+        }
+    }
+
+    fn resume(&mut self, mode: Mode) -> io::Result<bool> {
+        self.mode = mode;
+        Ok(true)
+    }
+
+    // `FILE:LINE`, or `LINE` in the file of the current function.
+    fn breakpoint(&self, vm: &VM, argument: &str) -> Option<Breakpoint> {
+        let (file, line) = match argument.rsplit_once(':') {
+            Some((file, line)) => (file.to_string(), line),
+            None => {
+                let frame = &vm.frames[vm.frame_count as usize - 1];
+                let chunk = frame.closure.borrow().chunk();
+                let file = chunk.borrow().source.name.clone();
+                (file, argument)
+            }
+        };
+        let line = line.parse().ok()?;
+        Some(Breakpoint { file, line })
+    }
+
+    fn backtrace(&mut self, vm: &VM) -> io::Result<()> {
+        let frames = vm.frames.iter().take(vm.frame_count as usize).rev();
+        for (i, frame) in frames.enumerate() {
+            let closure = frame.closure.borrow();
+            let chunk = closure.chunk();
+            let chunk = chunk.borrow();
+            // Callers have already moved past the call instruction.
+            let offset = if i == 0 { frame.ip } else { frame.ip - 1 };
+            let (file, line) = (&chunk.source.name, chunk.line(offset));
+            writeln!(
+                self.output,
+                "#{i} {} at {file}:{line}",
+                frame_name(&closure)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn frame_name(closure: &Closure) -> String {
+    let function = closure.function();
+    let name = function.borrow().name();
+    match name.is_empty() {
+        true => "script".to_string(),
+        false => format!("{name}()"),
+    }
+}
+
+// Locals of a frame that are in scope, in the order of declaration.
+fn locals(vm: &VM, index: usize) -> Vec<(String, Value)> {
+    let frame = &vm.frames[index];
+    let chunk = frame.closure.borrow().chunk();
+    let chunk = chunk.borrow();
+    chunk
+        .locals
+        .iter()
+        .filter(|local| local.is_in_scope(frame.ip))
+        .map(|local| {
+            let value = vm.stack[frame.slots + local.slot].clone();
+            (local.name.clone(), value)
+        })
+        .collect()
+}
+
+fn upvalues(vm: &VM) -> Vec<(String, Value)> {
+    let frame = &vm.frames[vm.frame_count as usize - 1];
+    let closure = frame.closure.borrow();
+    let chunk = closure.chunk();
+    let chunk = chunk.borrow();
+    chunk
+        .upvalues
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let upvalue = closure.upvalue(i);
+            let upvalue = upvalue.borrow();
+            let value = match upvalue.is_closed() {
+                true => upvalue.closed_value(),
+                false => vm.stack[upvalue.location()].clone(),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+// A variable of the current function followed by `.name` and `[index]` accesses.
+fn evaluate(vm: &VM, expression: &str) -> Result<Value, String> {
+    let end = expression.find(['.', '[']).unwrap_or(expression.len());
+    let (name, mut rest) = expression.split_at(end);
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Expect variable name.".to_string());
+    }
+
+    let local = locals(vm, vm.frame_count as usize - 1)
+        .into_iter()
+        .rev()
+        .find(|(local, _)| local == name);
+    let mut value = local
+        .or_else(|| {
+            upvalues(vm)
+                .into_iter()
+                .find(|(upvalue, _)| upvalue == name)
+        })
+        .map(|(_, value)| value)
+        .or_else(|| vm.globals.get(name).cloned())
+        .ok_or_else(|| format!("Undefined variable '{name}'."))?;
+
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            let field = tail[..end].trim();
+            value = match value.obj() {
+                Some(Obj::Variant(variant)) => variant.field(field),
+                Some(Obj::Enum(enumeration)) => enumeration
+                    .variant(field)
+                    .map(|v| Value::Obj(Obj::Variant(v))),
+                _ => None,
+            }
+            .ok_or_else(|| format!("Undefined property '{field}'."))?;
+            rest = &tail[end..];
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']').ok_or("Expect ']' after index.")?;
+            let index: usize = tail[..end]
+                .trim()
+                .parse()
+                .map_err(|_| "Index must be a non-negative integer.".to_string())?;
+            value = match value.obj() {
+                Some(Obj::List(list)) => list.get(index),
+                _ => return Err("Only lists can be indexed.".to_string()),
+            }
+            .ok_or_else(|| format!("Index {index} is out of bounds."))?;
+            rest = &tail[end + 1..];
+        } else {
+            return Err(format!("Unexpected '{rest}'."));
+        }
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Debugger;
+    use crate::vm::VM;
+    use std::cell::RefCell;
+    use std::io::{self, Cursor, Write};
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn debug(source: &str, commands: &str) -> String {
+        let output = Output::default();
+        let input = Cursor::new(commands.to_string());
+        let mut vm = VM::default();
+        vm.init();
+        vm.set_source_name("test.lox");
+        vm.set_debugger(Debugger::new(Box::new(input), Box::new(output.clone())));
+        vm.interpret(source.to_string());
+        let text = String::from_utf8(output.0.take()).unwrap();
+        text.replace("(lox) ", "")
+    }
+
+    #[test]
+    fn test_session() {
+        let source = "fun add(a, b) {
+  var sum = a + b;
+  return sum;
+}
+var list = [1, 2];
+print add(list[0], 2);
+print 4;";
+        let commands = "b 2\nc\nlocals\nw sum\nbt\nn\nf\np list[1]\nn\nq\n";
+        assert_eq!(
+            debug(source, commands),
+            "test.lox:1 in script
+   1 | fun add(a, b) {
+Breakpoint at test.lox:2
+test.lox:2 in add()
+   2 |   var sum = a + b;
+a = 1
+b = 2
+#0 add() at test.lox:2
+#1 script at test.lox:6
+test.lox:3 in add()
+   3 |   return sum;
+sum = 3
+test.lox:6 in script
+   6 | print add(list[0], 2);
+sum: Undefined variable 'sum'.
+list[1] = 2
+test.lox:7 in script
+   7 | print 4;
+sum: Undefined variable 'sum'.
+"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Errors rendered the way rustc does it:
+//!
+//! ```text
+//! error: Operands must be numbers.
+//!  --> example.lox:3:9
+//!   |
+//! 3 | print a + "b";
+//!   |         ^
+//! ```
+
+use crate::token::Span;
+use std::env;
+use std::io::{self, IsTerminal};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+const RED: &str = "\x1b[1;31m";
+
+/// Colours are used only on a terminal and never with `NO_COLOR` set.
+pub fn is_colored() -> bool {
+    io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+/// Compiled code together with the file it comes from.
+#[derive(Debug, Default)]
+pub struct Source {
+    pub name: String,
+    pub text: String,
+}
+
+impl Source {
+    pub fn new(name: &str, text: &str) -> Self {
+        Source {
+            name: name.to_string(),
+            text: text.to_string(),
+        }
+    }
+}
+
+pub struct Diagnostic {
+    message: String,
+    span: Span,
+    notes: Vec<(&'static str, String)>, // (kind, text), e.g. ("help", "did you mean 'x'?")
+}
+
+impl Diagnostic {
+    pub fn new(message: &str, span: Span) -> Self {
+        Diagnostic {
+            message: message.to_string(),
+            span,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, kind: &'static str, text: String) -> Self {
+        self.notes.push((kind, text));
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn render(&self, source: &Source, is_colored: bool) -> String {
+        let paint = |color: &str, s: &str| match is_colored {
+            true => format!("{color}{s}{RESET}"),
+            false => s.to_string(),
+        };
+
+        let Span {
+            start,
+            length,
+            line,
+            column,
+        } = self.span;
+        let gutter = " ".repeat(line.to_string().len());
+        let mut out = format!(
+            "{}{}\n{gutter}{} {}:{line}:{}\n",
+            paint(RED, "error"),
+            paint(BOLD, &format!(": {}", self.message)),
+            paint(BLUE, "-->"),
+            source.name,
+            column + 1,
+        );
+
+        let index = usize::try_from(line - 1).ok();
+        if let Some(snippet) = index.and_then(|i| source.text.lines().nth(i)) {
+            // Only the first line of a multiline token is underlined.
+            let rest = snippet.chars().count().saturating_sub(column);
+            let lexeme = source.text.chars().skip(start).take(length);
+            let length = lexeme.take_while(|&c| c != '\n').count().min(rest).max(1);
+            // Tabs are kept so that the underline is aligned with the code.
+            let padding: String = snippet
+                .chars()
+                .take(column)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            out.push_str(&paint(BLUE, &format!("{gutter} |\n{line} |")));
+            out.push_str(&format!(" {snippet}\n"));
+            out.push_str(&paint(BLUE, &format!("{gutter} |")));
+            out.push_str(&format!(" {padding}{}\n", paint(RED, &"^".repeat(length))));
+        }
+
+        for (kind, text) in &self.notes {
+            out.push_str(&format!("{gutter} {} {kind}: {text}\n", paint(BLUE, "=")));
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// The most similar name if it is close enough to be a typo.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance > 0 && distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, a swap of two neighbours counts as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
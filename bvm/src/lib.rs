@@ -1,23 +1,38 @@
 #![feature(linked_list_remove)]
 
+use coverage::Coverage;
+use debugger::Debugger;
+use profiler::Profiler;
 use std::{fs, io::Write, process};
 use vm::{InterpretResult, VM};
 
 pub mod chunk;
 pub mod compiler;
+pub mod coverage;
 pub mod debug;
+pub mod debugger;
+pub mod diagnostic;
 pub mod memory;
+#[cfg(feature = "nan-boxing")]
+mod nan_boxing;
 pub mod native;
 pub mod object;
+pub mod optimizer;
+pub mod profiler;
 pub mod scanner;
 pub mod token;
 pub mod value;
+pub mod verifier;
 pub mod vm;
 
 #[derive(Clone, Copy)]
 pub struct Config {
     pub bytecode: bool,
+    pub coverage: bool,
     pub debug: bool,
+    pub debugger: bool,
+    pub profile: bool,
+    pub quiet: bool, // errors are returned without printing them
     pub scanner: bool,
     pub trace: bool,
 }
@@ -32,7 +47,11 @@ impl Config {
     pub fn new() -> Self {
         Config {
             bytecode: false,
+            coverage: false,
             debug: false,
+            debugger: false,
+            profile: false,
+            quiet: false,
             scanner: false,
             trace: false,
         }
@@ -46,6 +65,9 @@ Written in Rust by sevlabr";
 
 pub fn repl(config: Config, mut vm: VM) {
     vm.set_config(config);
+    if config.debugger {
+        vm.set_debugger(Debugger::stdio());
+    }
     println!("{}", WELCOME_REPL);
     loop {
         print!("> ");
@@ -72,14 +94,53 @@ pub fn repl(config: Config, mut vm: VM) {
     }
 }
 
+/// Prints the report to stderr and saves stacks for flame graphs next to the script.
+fn write_profile(mut profiler: Profiler, path: &str) {
+    eprint!("{}", profiler.report());
+    let folded_path = format!("{path}.folded");
+    match fs::write(&folded_path, profiler.folded()) {
+        Ok(()) => eprintln!("\nFolded stacks are written to {folded_path}"),
+        Err(e) => eprintln!("\nError during writing {folded_path}: {e}"),
+    }
+}
+
+/// Prints the summary to stderr and saves the lcov report next to the script.
+fn write_coverage(coverage: Coverage, path: &str) {
+    eprint!("{}", coverage.summary());
+    let lcov_path = format!("{path}.lcov");
+    match fs::write(&lcov_path, coverage.lcov()) {
+        Ok(()) => eprintln!("Coverage report is written to {lcov_path}"),
+        Err(e) => eprintln!("Error during writing {lcov_path}: {e}"),
+    }
+}
+
 pub fn run_file(config: Config, mut vm: VM, path: String) {
     vm.set_config(config);
+    vm.set_source_name(&path);
+    if config.debugger {
+        vm.set_debugger(Debugger::stdio());
+    }
+    if config.profile {
+        vm.set_profiler(Profiler::new());
+    }
+    if config.coverage {
+        vm.set_coverage(Coverage::new());
+    }
     match fs::read_to_string(path.clone()) {
-        Ok(contents) => match vm.interpret(contents) {
-            InterpretResult::CompileError => process::exit(65),
-            InterpretResult::RuntimeError => process::exit(70),
-            _ => (),
-        },
+        Ok(contents) => {
+            let result = vm.interpret(contents);
+            if let Some(profiler) = vm.take_profiler() {
+                write_profile(profiler, &path);
+            }
+            if let Some(coverage) = vm.take_coverage() {
+                write_coverage(coverage, &path);
+            }
+            match result {
+                InterpretResult::CompileError => process::exit(65),
+                InterpretResult::RuntimeError => process::exit(70),
+                _ => (),
+            }
+        }
         Err(e) => {
             eprintln!("Error during reading file: {e}.\nGiven [PATH]: {}", path);
             process::exit(74);
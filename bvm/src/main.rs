@@ -12,7 +12,7 @@ fn main() {
     match args.len() {
         1 => repl(config, vm),
         2 => match args[1].as_str() {
-            "-b" | "-d" | "-s" | "-t" => {
+            "-b" | "-d" | "-g" | "-s" | "-t" => {
                 set_config(&mut config, args[1].as_str());
                 repl(config, vm);
             }
@@ -20,7 +20,7 @@ fn main() {
             _ => run_file(config, vm, args.swap_remove(1)),
         },
         3 => match args[1].as_str() {
-            "-b" | "-d" | "-s" | "-t" => {
+            "-b" | "-d" | "-g" | "-s" | "-t" | "--coverage" | "--profile" => {
                 set_config(&mut config, args[1].as_str());
                 run_file(config, vm, args.swap_remove(2));
             }
@@ -41,9 +41,12 @@ fn set_config(config: &mut Config, option: &str) {
     match option {
         "-b" => config.bytecode = true,
         "-d" => config.debug = true,
+        "-g" => config.debugger = true,
         "-s" => config.scanner = true,
         "-t" => config.trace = true,
-        _ => unreachable!("Expected one of: -b -d -s -t"),
+        "--coverage" => config.coverage = true,
+        "--profile" => config.profile = true,
+        _ => unreachable!("Expected one of: -b -d -g -s -t --coverage --profile"),
     }
 }
 
@@ -56,14 +59,24 @@ const HELP_MESSAGE: &str = "Usage: lox [OPTIONS] [PATH]
 Options:
   -b  Print generated top-level bytecode without program execution
   -d  Debug mode: execute normally, in case of error print bytecode
+  -g  Debugger: stop at the first line and read commands (breakpoints,
+      stepping, printing variables) from the prompt, type 'help' for a list
   -s  Print tokens generated by lexer (scanner) without program execution
   -t  Tracing mode (online debugging): execute program and additionally
       print each bytecode instruction and virtual machine stack state
   -h  Print help information
+  --coverage
+      Record which lines and branches (of if, while, for, and, or, match)
+      are run. The summary is printed to stderr, the lcov report is written
+      to [PATH].lcov
+  --profile
+      Count executed instructions per opcode, function and source line
+      and measure time spent in functions. The report is printed to stderr,
+      call stacks for flame graphs are written to [PATH].folded
 
 There are 2 modes of execution available: interactive prompt and source file program.
 The first one is activated when [PATH] to a source file isn't provided.
-All options except [-h] can be used in both modes. Simultaneous usage of more than
+All options except [-h], [--coverage] and [--profile] can be used in both modes. Simultaneous usage of more than
 one option at a time is not supported yet.
 
 Examples:
@@ -74,4 +87,9 @@ Examples:
   lox -b               Print generated top-level bytecode for each interactively written line of code
                        (it won't be executed by virtual machine)
   lox -t path/to/file  Source file execution in tracing mode
+  lox -g path/to/file  Source file execution in the debugger
+  lox --coverage path/to/file
+                       Source file execution with a coverage report
+  lox --profile path/to/file
+                       Source file execution with a profile report
   lox -h path/to/file  Error, can't print help information and then execute code in normal mode";
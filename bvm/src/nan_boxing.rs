@@ -0,0 +1,208 @@
+//! Values packed into 64 bits, as in the "Optimization" chapter of the book.
+//!
+//! A number is stored as its `f64` bits. Everything else is a quiet NaN, which the FPU never
+//! produces with these bits set:
+//!
+//! ```text
+//! nil, false, true   0111 1111 1111 11.. ....  tag in the lowest 2 bits: 01, 10, 11
+//! object             1111 1111 1111 11.. ....  pointer to `Rc<Obj>` in the lowest 48 bits
+//! ```
+//!
+//! Objects live on the heap behind a reference counted pointer, so copying a value onto the
+//! stack is a copy of 8 bytes and a count increment instead of a deep clone of the object.
+//! The API is the same as the one of the enum in `value.rs`, `Value::Nil`, `Value::Bool`,
+//! `Value::Num` and `Value::Obj` are constructors here.
+
+use crate::object::Obj;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+const SIGN_BIT: u64 = 0x8000_0000_0000_0000;
+const QNAN: u64 = 0x7ffc_0000_0000_0000;
+
+const TAG_NIL: u64 = 1;
+const TAG_FALSE: u64 = 2;
+const TAG_TRUE: u64 = 3;
+
+pub struct Value {
+    bits: u64,
+    // Owns a count of the object, which can't be shared between threads.
+    marker: PhantomData<Rc<Obj>>,
+}
+
+#[allow(non_snake_case, non_upper_case_globals)]
+impl Value {
+    pub const Nil: Value = Value::from_bits(QNAN | TAG_NIL);
+
+    pub fn Bool(val: bool) -> Self {
+        match val {
+            true => Value::from_bits(QNAN | TAG_TRUE),
+            false => Value::from_bits(QNAN | TAG_FALSE),
+        }
+    }
+
+    pub fn Num(val: f64) -> Self {
+        Value::from_bits(val.to_bits())
+    }
+
+    pub fn Obj(obj: Obj) -> Self {
+        let pointer = Rc::into_raw(Rc::new(obj)) as u64;
+        debug_assert_eq!(
+            pointer & (SIGN_BIT | QNAN),
+            0,
+            "Pointer takes more than 48 bits."
+        );
+        Value::from_bits(SIGN_BIT | QNAN | pointer)
+    }
+}
+
+impl Value {
+    const fn from_bits(bits: u64) -> Self {
+        Value {
+            bits,
+            marker: PhantomData,
+        }
+    }
+
+    fn pointer(&self) -> *const Obj {
+        (self.bits & !(SIGN_BIT | QNAN)) as *const Obj
+    }
+
+    pub fn is_bool(&self) -> bool {
+        self.bits | 1 == QNAN | TAG_TRUE
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.bits == QNAN | TAG_NIL
+    }
+
+    pub fn is_num(&self) -> bool {
+        self.bits & QNAN != QNAN
+    }
+
+    pub fn is_obj(&self) -> bool {
+        self.bits & (SIGN_BIT | QNAN) == SIGN_BIT | QNAN
+    }
+
+    pub fn is_falsey(&self) -> bool {
+        self.bits == QNAN | TAG_NIL || self.bits == QNAN | TAG_FALSE
+    }
+
+    pub fn is_obj_type(&self, kind: &'static str) -> bool {
+        self.obj().is_some_and(|obj| obj.is_obj_type(kind))
+    }
+
+    pub fn equal(&self, other: Self) -> bool {
+        if self.is_num() && other.is_num() {
+            return f64::from_bits(self.bits) == f64::from_bits(other.bits);
+        }
+        match (self.obj(), other.obj()) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.bits == other.bits,
+        }
+    }
+
+    /// The object if the value is one.
+    pub fn obj(&self) -> Option<&Obj> {
+        // The value holds a count, so the object lives at least as long as the reference.
+        self.is_obj().then(|| unsafe { &*self.pointer() })
+    }
+
+    /// Extract inner `bool` value.
+    ///
+    /// # Safety
+    ///
+    /// Fails if `Value::is_bool()` returns `false`.
+    /// Use `Value::is_bool()` before applying this function.
+    pub unsafe fn as_bool(&self) -> bool {
+        debug_assert!(self.is_bool(), "Expected bool value.");
+        self.bits == QNAN | TAG_TRUE
+    }
+
+    /// Extract inner `f64` value.
+    ///
+    /// # Safety
+    ///
+    /// Fails if `Value::is_num()` returns `false`.
+    /// Use `Value::is_num()` before applying this function.
+    pub unsafe fn as_num(&self) -> f64 {
+        debug_assert!(self.is_num(), "Expected f64 value.");
+        f64::from_bits(self.bits)
+    }
+
+    /// Borrow inner `Obj`. Use the `Obj::as_*` functions to clone
+    /// the part that is needed.
+    ///
+    /// # Safety
+    ///
+    /// Fails if `Value::is_obj()` returns `false`.
+    /// Use `Value::is_obj()` before applying this function.
+    pub unsafe fn as_obj(&self) -> &Obj {
+        match self.obj() {
+            Some(obj) => obj,
+            None => panic!("Expected Obj value."),
+        }
+    }
+}
+
+impl Clone for Value {
+    fn clone(&self) -> Self {
+        if self.is_obj() {
+            unsafe { Rc::increment_strong_count(self.pointer()) };
+        }
+        Value::from_bits(self.bits)
+    }
+}
+
+impl Drop for Value {
+    fn drop(&mut self) {
+        if self.is_obj() {
+            unsafe { Rc::decrement_strong_count(self.pointer()) };
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(obj) = self.obj() {
+            write!(f, "{}", obj)
+        } else if self.is_num() {
+            write!(f, "{}", f64::from_bits(self.bits))
+        } else if self.is_nil() {
+            write!(f, "nil")
+        } else {
+            write!(f, "{}", self.bits == QNAN | TAG_TRUE)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+    use crate::object::Obj;
+
+    #[test]
+    fn test_nan_boxing() {
+        assert_eq!(std::mem::size_of::<Value>(), 8);
+        for num in [0.0, -0.0, 1.5, f64::INFINITY, f64::NAN, -f64::NAN] {
+            let value = Value::Num(num);
+            assert!(value.is_num() && !value.is_obj() && !value.is_nil());
+            assert_eq!(unsafe { value.as_num() }.to_bits(), num.to_bits());
+        }
+        assert!(!Value::Num(f64::NAN).equal(Value::Num(f64::NAN)));
+        assert!(Value::Num(0.0).equal(Value::Num(-0.0)));
+
+        assert!(Value::Nil.is_nil() && Value::Nil.is_falsey() && !Value::Nil.is_bool());
+        assert!(Value::Bool(false).is_falsey() && !Value::Bool(true).is_falsey());
+        assert!(unsafe { Value::Bool(true).as_bool() });
+        assert!(!Value::Bool(true).equal(Value::Num(1.0)));
+
+        let a = Value::Obj(Obj::Str("a".to_string()));
+        let b = a.clone();
+        assert!(a.is_obj_type("String") && !a.is_num());
+        assert!(a.equal(Value::Obj(Obj::Str("a".to_string()))));
+        drop(a);
+        assert_eq!(b.to_string(), "a");
+    }
+}
@@ -1,3 +1,5 @@
+use crate::object::Obj;
+use crate::value::Value;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn clock() -> f64 {
@@ -9,3 +11,25 @@ pub fn clock() -> f64 {
     let in_secs: f64 = (in_millis as f64) / 1e+3;
     in_secs
 }
+
+pub fn status(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("Expected 1 arguments but got {}.", args.len()));
+    }
+    match args[0].obj() {
+        Some(Obj::Gen(generator)) => Ok(Value::Obj(Obj::Str(generator.status().to_string()))),
+        _ => Err("Argument must be a generator.".to_string()),
+    }
+}
+
+pub fn len(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("Expected 1 arguments but got {}.", args.len()));
+    }
+    match args[0].obj() {
+        Some(Obj::List(list)) => Ok(Value::Num(list.len() as f64)),
+        Some(Obj::Str(s)) => Ok(Value::Num(s.chars().count() as f64)),
+        Some(Obj::Enum(e)) => Ok(Value::Num(e.len() as f64)),
+        _ => Err("Can only get length of lists, strings and enums.".to_string()),
+    }
+}
@@ -1,14 +1,19 @@
 use crate::chunk::Chunk;
 use crate::value::Value;
+use crate::vm::CallFrame;
 use std::{cell::RefCell, fmt, rc::Rc};
 
 #[derive(Clone, PartialEq, Eq)]
 pub enum Obj {
     BuiltIn(Native),
     Closure(Closure),
+    Enum(Enum),
     Fun(Function),
+    Gen(Generator),
+    List(List),
     Str(String),
     Upval(Upvalue),
+    Variant(Variant),
 }
 
 impl fmt::Display for Obj {
@@ -20,13 +25,17 @@ impl fmt::Display for Obj {
                 // write!(f, "[ Closure: {} ]", closure.function.borrow())
                 write!(f, "{}", closure.function.borrow())
             }
+            Obj::Enum(enumeration) => write!(f, "{}", enumeration),
             Obj::Fun(fun) => write!(f, "{}", fun),
+            Obj::Gen(generator) => write!(f, "{}", generator),
+            Obj::List(list) => write!(f, "{}", list),
             Obj::Str(s) => write!(f, "{}", s),
             Obj::Upval(_value) => {
                 // debug version (probably outdated):
                 // write!(f, "{}", value.location.borrow())
                 write!(f, "upvalue")
             }
+            Obj::Variant(variant) => write!(f, "{}", variant),
         }
     }
 }
@@ -40,20 +49,40 @@ impl Obj {
         matches!(self, Obj::Closure(_))
     }
 
+    pub fn is_enum(&self) -> bool {
+        matches!(self, Obj::Enum(_))
+    }
+
     pub fn is_fun(&self) -> bool {
         matches!(self, Obj::Fun(_))
     }
 
+    pub fn is_generator(&self) -> bool {
+        matches!(self, Obj::Gen(_))
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, Obj::List(_))
+    }
+
     pub fn is_string(&self) -> bool {
         matches!(self, Obj::Str(_))
     }
 
+    pub fn is_variant(&self) -> bool {
+        matches!(self, Obj::Variant(_))
+    }
+
     pub fn is_obj_type(&self, kind: &'static str) -> bool {
         match kind {
             "BuiltIn" => self.is_builtin(),
             "Closure" => self.is_closure(),
+            "Enum" => self.is_enum(),
             "Function" => self.is_fun(),
+            "Generator" => self.is_generator(),
+            "List" => self.is_list(),
             "String" => self.is_string(),
+            "Variant" => self.is_variant(),
             _ => panic!("Invalid Obj type specified: {}.", kind),
         }
     }
@@ -100,6 +129,62 @@ impl Obj {
         }
     }
 
+    /// Extract inner `Generator`. The returned handle shares its state
+    /// with the original one.
+    ///
+    /// # Safety
+    ///
+    /// Fails if `Obj::is_generator()` returns `false`.
+    /// Use `Obj::is_generator()` before applying this function.
+    pub unsafe fn as_generator(&self) -> Generator {
+        match self {
+            Obj::Gen(generator) => generator.clone(),
+            _ => panic!("Expected Generator object."),
+        }
+    }
+
+    /// Extract inner `List`. The returned handle shares its elements
+    /// with the original one.
+    ///
+    /// # Safety
+    ///
+    /// Fails if `Obj::is_list()` returns `false`.
+    /// Use `Obj::is_list()` before applying this function.
+    pub unsafe fn as_list(&self) -> List {
+        match self {
+            Obj::List(list) => list.clone(),
+            _ => panic!("Expected List object."),
+        }
+    }
+
+    /// Extract inner `Enum`. This function returns cloned object,
+    /// not the original one.
+    ///
+    /// # Safety
+    ///
+    /// Fails if `Obj::is_enum()` returns `false`.
+    /// Use `Obj::is_enum()` before applying this function.
+    pub unsafe fn as_enum(&self) -> Enum {
+        match self {
+            Obj::Enum(enumeration) => enumeration.clone(),
+            _ => panic!("Expected Enum object."),
+        }
+    }
+
+    /// Extract inner `Variant`. This function returns cloned object,
+    /// not the original one.
+    ///
+    /// # Safety
+    ///
+    /// Fails if `Obj::is_variant()` returns `false`.
+    /// Use `Obj::is_variant()` before applying this function.
+    pub unsafe fn as_variant(&self) -> Variant {
+        match self {
+            Obj::Variant(variant) => variant.clone(),
+            _ => panic!("Expected Variant object."),
+        }
+    }
+
     /// Extract inner `String`. This function returns cloned value,
     /// not the original one.
     ///
@@ -113,14 +198,30 @@ impl Obj {
             _ => panic!("Expected Str value."),
         }
     }
+
+    /// Borrow inner `String`, for names that are only looked up.
+    ///
+    /// # Safety
+    ///
+    /// Fails if `Obj::is_string()` returns `false`.
+    /// Use `Obj::is_string()` before applying this function.
+    pub unsafe fn as_str(&self) -> &str {
+        match self {
+            Obj::Str(s) => s,
+            _ => panic!("Expected Str value."),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct Function {
     arity: isize,
+    min_arity: isize,
+    is_variadic: bool,
     upvalue_count: isize,
     chunk: Rc<RefCell<Chunk>>,
     name: String,
+    is_generator: bool,
 }
 
 impl Default for Function {
@@ -133,9 +234,12 @@ impl Function {
     pub fn new() -> Self {
         Self {
             arity: 0,
+            min_arity: 0,
+            is_variadic: false,
             upvalue_count: 0,
             chunk: Rc::new(RefCell::new(Chunk::default())),
             name: String::new(),
+            is_generator: false,
         }
     }
 
@@ -147,6 +251,24 @@ impl Function {
         self.arity = arity;
     }
 
+    /// Number of parameters without default values.
+    pub fn min_arity(&self) -> isize {
+        self.min_arity
+    }
+
+    pub fn change_min_arity(&mut self, min_arity: isize) {
+        self.min_arity = min_arity;
+    }
+
+    /// Has a rest parameter, which is not counted in `arity`.
+    pub fn is_variadic(&self) -> bool {
+        self.is_variadic
+    }
+
+    pub fn set_variadic(&mut self, is_variadic: bool) {
+        self.is_variadic = is_variadic;
+    }
+
     pub fn upvalue_count(&self) -> isize {
         self.upvalue_count
     }
@@ -167,6 +289,14 @@ impl Function {
         self.name = name;
     }
 
+    pub fn is_generator(&self) -> bool {
+        self.is_generator
+    }
+
+    pub fn set_generator(&mut self, is_generator: bool) {
+        self.is_generator = is_generator;
+    }
+
     // Example function for deallocation. May change later.
     // Supposed to be used for GC.
     // (Also check Zeroize crate if needed since this implementation
@@ -182,7 +312,7 @@ impl fmt::Display for Function {
         if self.name.is_empty() {
             return write!(f, "<script>");
         }
-        write!(f, "<fun {}>", &self.name)
+        write!(f, "<fun {}>", self.name)
     }
 }
 
@@ -278,9 +408,11 @@ impl Native {
         self.name.clone()
     }
 
-    pub fn call(&self, _arg_count: usize, _args: usize) -> Value {
+    pub fn call(&self, args: &[Value]) -> Result<Value, String> {
         match self.name.as_str() {
-            "clock" => Value::Num(crate::native::clock()),
+            "clock" => Ok(Value::Num(crate::native::clock())),
+            "status" => crate::native::status(args),
+            "len" => crate::native::len(args),
             _ => panic!(
                 "Call of unknown Native function with name: '{}'.",
                 self.name
@@ -291,10 +423,304 @@ impl Native {
 
 impl fmt::Display for Native {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<native fun {}>", &self.name)
+        write!(f, "<native fun {}>", self.name)
     }
 }
 
+#[derive(Clone)]
+pub struct List {
+    items: Rc<RefCell<Vec<Value>>>,
+}
+
+impl List {
+    pub fn new(items: Vec<Value>) -> Self {
+        Self {
+            items: Rc::new(RefCell::new(items)),
+        }
+    }
+
+    pub fn items(&self) -> Rc<RefCell<Vec<Value>>> {
+        Rc::clone(&self.items)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.borrow().is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Value> {
+        self.items.borrow().get(index).cloned()
+    }
+}
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, item) in self.items.borrow().iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        let items = self.items.borrow();
+        let other_items = other.items.borrow();
+        items.len() == other_items.len()
+            && items
+                .iter()
+                .zip(other_items.iter())
+                .all(|(l, r)| l.equal(r.clone()))
+    }
+}
+
+impl Eq for List {}
+
+/// Enums are created by the compiler and stored as constants.
+#[derive(Clone)]
+pub struct Enum {
+    name: String,
+    variants: Rc<Vec<Variant>>,
+}
+
+impl Enum {
+    /// Each variant is given with the names of its payload fields.
+    pub fn new(name: String, variants: Vec<(String, Vec<String>)>) -> Self {
+        let variants = variants
+            .into_iter()
+            .enumerate()
+            .map(|(index, (variant, fields))| Variant::new(name.clone(), index, variant, fields))
+            .collect();
+        Self {
+            name,
+            variants: Rc::new(variants),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn variant(&self, name: &str) -> Option<Variant> {
+        self.variants.iter().find(|v| &*v.name == name).cloned()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Variant> {
+        self.variants.get(index).cloned()
+    }
+
+    /// Index of the variant in this enum, `None` if it belongs to another enum.
+    pub fn index_of(&self, variant: &Variant) -> Option<usize> {
+        let own = self.variants.get(variant.index)?;
+        own.is_same_variant(variant).then_some(variant.index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+}
+
+impl fmt::Display for Enum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// An enum is equal only to itself.
+impl PartialEq for Enum {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.variants, &other.variants)
+    }
+}
+
+impl Eq for Enum {}
+
+/// A variant with payload fields that are not filled yet works as a constructor.
+#[derive(Clone)]
+pub struct Variant {
+    enum_name: Rc<str>,
+    index: usize, // in the enum
+    name: Rc<str>,
+    fields: Rc<Vec<String>>,
+    values: Rc<Vec<Value>>,
+}
+
+impl Variant {
+    fn new(enum_name: String, index: usize, name: String, fields: Vec<String>) -> Self {
+        Self {
+            enum_name: enum_name.into(),
+            index,
+            name: name.into(),
+            fields: Rc::new(fields),
+            values: Rc::new(Vec::new()),
+        }
+    }
+
+    pub fn is_constructor(&self) -> bool {
+        !self.fields.is_empty() && self.values.is_empty()
+    }
+
+    /// Whether both are values of one variant, whatever their payloads are.
+    pub fn is_same_variant(&self, other: &Variant) -> bool {
+        Rc::ptr_eq(&self.name, &other.name)
+    }
+
+    pub fn arity(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn construct(&self, values: Vec<Value>) -> Self {
+        Self {
+            enum_name: Rc::clone(&self.enum_name),
+            index: self.index,
+            name: Rc::clone(&self.name),
+            fields: Rc::clone(&self.fields),
+            values: Rc::new(values),
+        }
+    }
+
+    pub fn has_field(&self, name: &str) -> bool {
+        self.fields.iter().any(|f| f == name)
+    }
+
+    pub fn field(&self, name: &str) -> Option<Value> {
+        let index = self.fields.iter().position(|f| f == name)?;
+        self.values.get(index).cloned()
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.enum_name, self.name)?;
+        if !self.values.is_empty() {
+            write!(f, "(")?;
+            for (i, value) in self.values.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", value)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Values of the same variant are equal if their payloads are. The name is shared by all
+/// values of a variant, so it tells variants of different enums with the same names apart.
+impl PartialEq for Variant {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_same_variant(other)
+            && self.values.len() == other.values.len()
+            && self
+                .values
+                .iter()
+                .zip(other.values.iter())
+                .all(|(a, b)| a.equal(b.clone()))
+    }
+}
+
+impl Eq for Variant {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeneratorStatus {
+    Suspended,
+    Running,
+    Done,
+    Error,
+}
+
+impl fmt::Display for GeneratorStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorStatus::Suspended => write!(f, "suspended"),
+            GeneratorStatus::Running => write!(f, "running"),
+            GeneratorStatus::Done => write!(f, "done"),
+            GeneratorStatus::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Execution context of a coroutine: its own value stack and call frames.
+/// While the generator is running, the VM swaps these fields with its own,
+/// so during that time they hold the context of the resumer instead.
+pub struct GeneratorState {
+    pub status: GeneratorStatus,
+    pub started: bool,
+    pub frames: Vec<CallFrame>,
+    pub frame_count: isize,
+    pub stack: Vec<Value>,
+    pub stack_top: usize,
+    pub open_upvalues: Option<Rc<RefCell<Upvalue>>>,
+}
+
+impl GeneratorState {
+    pub fn new(frames: Vec<CallFrame>, stack: Vec<Value>) -> Self {
+        Self {
+            status: GeneratorStatus::Suspended,
+            started: false,
+            frame_count: frames.len() as isize,
+            frames,
+            stack_top: stack.len(),
+            stack,
+            open_upvalues: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Generator {
+    name: String,
+    state: Rc<RefCell<GeneratorState>>,
+}
+
+impl Generator {
+    pub fn new(name: String, state: GeneratorState) -> Self {
+        Self {
+            name,
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
+    pub fn state(&self) -> Rc<RefCell<GeneratorState>> {
+        Rc::clone(&self.state)
+    }
+
+    pub fn status(&self) -> GeneratorStatus {
+        self.state.borrow().status
+    }
+
+    pub fn set_status(&mut self, status: GeneratorStatus) {
+        self.state.borrow_mut().status = status;
+    }
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<generator {}>", self.name)
+    }
+}
+
+impl PartialEq for Generator {
+    fn eq(&self, other: &Self) -> bool {
+        // Every call of a generator function creates a distinct generator.
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for Generator {}
+
 #[derive(Clone)]
 pub struct Upvalue {
     location: usize,
@@ -342,8 +768,8 @@ impl Upvalue {
     }
 
     pub fn next(&self) -> Option<Rc<RefCell<Upvalue>>> {
-        if self.next.is_some() {
-            Some(Rc::clone(self.next.as_ref().unwrap()))
+        if let Some(next) = &self.next {
+            Some(Rc::clone(next))
         } else {
             // panic!("Next Upvalue expected to be non-null.");
             None
@@ -358,6 +784,10 @@ impl Upvalue {
         self.is_closed = true;
     }
 
+    pub fn set_open(&mut self) {
+        self.is_closed = false;
+    }
+
     pub fn set_closed_value(&mut self, closed: Box<Value>) {
         self.closed = closed;
     }
@@ -372,21 +802,7 @@ impl PartialEq for Upvalue {
         if !self.is_closed && !other.is_closed {
             self.location == other.location
         } else if self.is_closed && other.is_closed {
-            match *self.closed {
-                Value::Bool(l) => match *other.closed {
-                    Value::Bool(r) => l == r,
-                    _ => false,
-                },
-                Value::Nil => matches!(*other.closed, Value::Nil),
-                Value::Obj(ref l) => match *other.closed {
-                    Value::Obj(ref r) => *l == *r,
-                    _ => false,
-                },
-                Value::Num(l) => match *other.closed {
-                    Value::Num(r) => l == r,
-                    _ => false,
-                },
-            }
+            self.closed.equal((*other.closed).clone())
         } else {
             false
         }
@@ -423,3 +839,34 @@ impl Eq for Upvalue {}
 // }
 
 // impl Eq for Upvalue {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Enum, Obj};
+    use crate::value::Value;
+
+    #[test]
+    fn test_variant_equality() {
+        let color = Enum::new("Color".to_string(), vec![("Red".to_string(), vec![])]);
+        let paint = Enum::new("Paint".to_string(), vec![("Red".to_string(), vec![])]);
+        let option = Enum::new(
+            "Option".to_string(),
+            vec![("Some".to_string(), vec!["value".to_string()])],
+        );
+        let red = color.variant("Red").unwrap();
+        let some = option.variant("Some").unwrap();
+        let some_of = |n| some.construct(vec![Value::Num(n)]);
+
+        assert!(red == color.variant("Red").unwrap());
+        assert!(red != paint.variant("Red").unwrap());
+        assert!(some_of(1.0) == some_of(1.0));
+        assert!(some_of(1.0) != some_of(2.0));
+        assert!(some != some_of(1.0));
+        assert_eq!(color.index_of(&red), Some(0));
+        assert_eq!(paint.index_of(&red), None);
+        assert_eq!(
+            Value::Obj(Obj::Variant(some_of(2.0))).to_string(),
+            "Option.Some(2)"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Peephole optimization of compiled chunks.
+//!
+//! Common sequences of instructions are replaced by superinstructions, which do the same work
+//! with one dispatch in the VM:
+//!
+//! - `GetLocal`, `Constant`, `Add` become `AddLocalConstant`,
+//! - `Less`, `JumpIfFalse` become `LessJumpIfFalse`,
+//! - runs of `Pop` become `PopN`,
+//! - `Nil`, `Return` become `ReturnNil`.
+//!
+//! A sequence is fused only if no jump lands inside it. Jumps, spans and scopes of local names
+//! are moved to the new offsets.
+
+use crate::chunk::{Chunk, OpCode};
+use crate::token::Span;
+use std::collections::HashSet;
+
+/// Rewrites the code of the chunk. Functions in its constants are optimized on their own,
+/// when the compiler finishes them.
+pub fn optimize(chunk: &mut Chunk) {
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        starts.push(offset);
+        offset += chunk.instruction_len(offset);
+    }
+    let targets: HashSet<usize> = starts
+        .iter()
+        .flat_map(|&offset| jump_targets(chunk, offset))
+        .collect();
+
+    let mut code = Vec::with_capacity(chunk.code.len());
+    let mut spans = Vec::with_capacity(chunk.code.len());
+    // New offset of every old byte and of the end of the code.
+    let mut offsets = vec![0; chunk.code.len() + 1];
+    // New offsets of jumps together with their old targets.
+    let mut jumps = Vec::new();
+
+    let mut i = 0;
+    while i < starts.len() {
+        let new = code.len();
+        let (bytes, count) = fuse(chunk, &starts[i..], &targets).unwrap_or_else(|| {
+            let start = starts[i];
+            let end = start + chunk.instruction_len(start);
+            let bytes = (start..end).map(|j| (chunk.code[j], chunk.spans[j]));
+            (bytes.collect(), 1)
+        });
+        let end = starts.get(i + count).copied().unwrap_or(chunk.code.len());
+        offsets[starts[i]..end].fill(new);
+        // Only the last instruction of a sequence can be a jump.
+        let last = starts[i + count - 1];
+        for (k, target) in jump_targets(chunk, last).into_iter().enumerate() {
+            jumps.push((new, k, target));
+        }
+        for (byte, span) in bytes {
+            code.push(byte);
+            spans.push(span);
+        }
+        i += count;
+    }
+    offsets[chunk.code.len()] = code.len();
+
+    // Fused code is shorter, so the distances still fit into two bytes.
+    for (offset, k, target) in jumps {
+        let target = offsets[target];
+        let (operand, distance) = match OpCode::try_from(code[offset]) {
+            Ok(OpCode::Loop) => (offset + 1, offset + 3 - target),
+            Ok(OpCode::JumpIfArg) => (offset + 2, target - (offset + 4)),
+            Ok(OpCode::JumpTable) => {
+                let end = offset + 3 + 2 * code[offset + 2] as usize;
+                (offset + 3 + 2 * k, target - end)
+            }
+            _ => (offset + 1, target - (offset + 3)),
+        };
+        code[operand] = (distance >> 8) as u8;
+        code[operand + 1] = (distance & 0xff) as u8;
+    }
+
+    for local in &mut chunk.locals {
+        local.start = offsets[local.start];
+        local.end = local.end.map(|end| offsets[end]);
+    }
+    chunk.code = code;
+    chunk.spans = spans;
+}
+
+// Offsets the instruction at `offset` jumps to, in the order of its operands.
+fn jump_targets(chunk: &Chunk, offset: usize) -> Vec<usize> {
+    let code = &chunk.code;
+    let short = |at: usize| (code[at] as usize) << 8 | code[at + 1] as usize;
+    match OpCode::try_from(code[offset]) {
+        Ok(OpCode::Jump | OpCode::JumpIfFalse | OpCode::LessJumpIfFalse) => {
+            vec![offset + 3 + short(offset + 1)]
+        }
+        Ok(OpCode::Loop) => vec![offset + 3 - short(offset + 1)],
+        Ok(OpCode::JumpIfArg) => vec![offset + 4 + short(offset + 2)],
+        Ok(OpCode::JumpTable) => {
+            let end = offset + chunk.instruction_len(offset);
+            (offset + 3..end)
+                .step_by(2)
+                .map(|at| end + short(at))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Superinstruction for the instructions that start at `starts` together with the number of
+// instructions it replaces. Jump operands are patched later.
+fn fuse(
+    chunk: &Chunk,
+    starts: &[usize],
+    targets: &HashSet<usize>,
+) -> Option<(Vec<(u8, Span)>, usize)> {
+    let code = &chunk.code;
+    let spans = &chunk.spans;
+    let is = |i: usize, opcode: OpCode| starts.get(i).is_some_and(|&at| code[at] == opcode as u8);
+    // Jumps may land only on the first instruction of a sequence.
+    let is_free = |count: usize| starts[1..count].iter().all(|at| !targets.contains(at));
+
+    if is(0, OpCode::GetLocal) && is(1, OpCode::Constant) && is(2, OpCode::Add) && is_free(3) {
+        let (get, constant, add) = (starts[0], starts[1], starts[2]);
+        // Runtime errors of the addition point to the last operand.
+        let bytes = vec![
+            (OpCode::AddLocalConstant as u8, spans[get]),
+            (code[get + 1], spans[get + 1]),
+            (code[constant + 1], spans[add]),
+        ];
+        return Some((bytes, 3));
+    }
+    if is(0, OpCode::Less) && is(1, OpCode::JumpIfFalse) && is_free(2) {
+        let (less, jump) = (starts[0], starts[1]);
+        let bytes = vec![
+            (OpCode::LessJumpIfFalse as u8, spans[less]),
+            (code[jump + 1], spans[jump + 1]),
+            (code[jump + 2], spans[jump + 2]),
+        ];
+        return Some((bytes, 2));
+    }
+    if is(0, OpCode::Nil) && is(1, OpCode::Return) && is_free(2) {
+        return Some((vec![(OpCode::ReturnNil as u8, spans[starts[0]])], 2));
+    }
+    if is(0, OpCode::Pop) {
+        let mut count = 1;
+        while count < u8::MAX as usize && is(count, OpCode::Pop) && is_free(count + 1) {
+            count += 1;
+        }
+        if count > 1 {
+            let bytes = vec![
+                (OpCode::PopN as u8, spans[starts[0]]),
+                (count as u8, spans[starts[count - 1]]),
+            ];
+            return Some((bytes, count));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimize;
+    use crate::chunk::{Chunk, LocalName, OpCode};
+    use crate::token::Span;
+    use crate::value::Value;
+
+    // Chunk of instructions, every one on its own line.
+    fn chunk(instructions: &[&[u8]]) -> Chunk {
+        let mut chunk = Chunk::new();
+        for (line, instruction) in instructions.iter().enumerate() {
+            let span = Span {
+                line: line as isize + 1,
+                ..Span::default()
+            };
+            for &byte in instruction.iter() {
+                chunk.write_raw_instruction(byte, span);
+            }
+        }
+        chunk.constants.push(Value::Num(1.0));
+        chunk
+    }
+
+    #[test]
+    fn test_superinstructions() {
+        use OpCode::*;
+        let mut chunk = chunk(&[
+            &[GetLocal as u8, 1],
+            &[Constant as u8, 0],
+            &[Add as u8],
+            &[Less as u8],
+            &[JumpIfFalse as u8, 0, 7], // to the second `Pop` of the last run
+            &[Pop as u8],
+            &[Pop as u8],
+            &[Pop as u8],
+            &[Jump as u8, 0, 2], // to `Nil`
+            &[Pop as u8],
+            &[Pop as u8],
+            &[Nil as u8],
+            &[Return as u8],
+        ]);
+        chunk.locals.push(LocalName {
+            name: "a".to_string(),
+            slot: 1,
+            start: 5,
+            end: Some(10),
+        });
+        optimize(&mut chunk);
+
+        let code: Vec<u8> = [
+            &[AddLocalConstant as u8, 1, 0][..],
+            &[LessJumpIfFalse as u8, 0, 6],
+            &[PopN as u8, 3],
+            &[Jump as u8, 0, 2],
+            &[Pop as u8],
+            &[Pop as u8],
+            &[ReturnNil as u8],
+        ]
+        .concat();
+        assert_eq!(chunk.code, code);
+        // The line of `AddLocalConstant` is the line of `GetLocal`, its errors point to `Add`.
+        let lines: Vec<isize> = chunk.spans.iter().map(|span| span.line).collect();
+        assert_eq!(lines, [1, 1, 3, 4, 5, 5, 6, 8, 9, 9, 9, 10, 11, 12]);
+        assert_eq!((chunk.locals[0].start, chunk.locals[0].end), (3, Some(6)));
+    }
+}
@@ -0,0 +1,355 @@
+//! Counts executed instructions per `OpCode`, per function and per source line,
+//! and measures time spent in functions:
+//!
+//! - *self* is the time (or instructions) of the function's own code,
+//! - *inclusive* also has the functions it called. Recursive calls are counted once.
+//!
+//! Call stacks with their instruction counts are also kept in the folded format
+//! of flame-graph tools, e.g. `script;fib;fib 120`.
+
+use crate::chunk::{Chunk, OpCode};
+use crate::object::Closure;
+use crate::vm::CallFrame;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const HOT_LINES: usize = 10;
+
+#[derive(Clone, Copy, Default)]
+struct Cost {
+    self_count: u64,
+    total_count: u64,
+    self_time: Duration,
+    total_time: Duration,
+}
+
+struct Function {
+    name: String,
+    file: String,
+}
+
+// Call stack with the instructions and time spent on its top. Stacks make a tree,
+// so a call or a return only moves to a child or to the parent.
+struct Stack {
+    parent: Option<usize>,
+    function: usize,
+    children: HashMap<usize, usize>, // function -> stack
+    count: u64,
+    time: Duration,
+}
+
+pub struct Profiler {
+    started: Instant,
+    last: Instant, // when the previous instruction started
+    functions: Vec<Function>,
+    function_ids: HashMap<*const RefCell<Chunk>, usize>,
+    stacks: Vec<Stack>,
+    roots: HashMap<usize, usize>, // function -> stack
+    // Chunks of the frames of the current stack, it is looked up again only when they change.
+    current: Option<(Vec<*const RefCell<Chunk>>, usize)>,
+    opcodes: [u64; 256],
+    lines: HashMap<(usize, isize), u64>, // (function, line)
+    total: u64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Profiler {
+            started: now,
+            last: now,
+            functions: Vec::new(),
+            function_ids: HashMap::new(),
+            stacks: Vec::new(),
+            roots: HashMap::new(),
+            current: None,
+            opcodes: [0; 256],
+            lines: HashMap::new(),
+            total: 0,
+        }
+    }
+
+    /// Called before every instruction with the frames of the running functions.
+    pub fn record(&mut self, frames: &[CallFrame]) {
+        let Some(top) = frames.last() else {
+            return;
+        };
+        self.charge_time();
+
+        let chunk = top.closure.borrow().chunk();
+        let key = Rc::as_ptr(&chunk);
+        let stack = match &self.current {
+            Some((keys, stack)) if keys.len() == frames.len() && keys.last() == Some(&key) => {
+                *stack
+            }
+            _ => self.enter(frames),
+        };
+
+        let chunk = chunk.borrow();
+        let function = self.stacks[stack].function;
+        self.stacks[stack].count += 1;
+        self.opcodes[chunk.code[top.ip] as usize] += 1;
+        *self
+            .lines
+            .entry((function, chunk.line(top.ip)))
+            .or_default() += 1;
+        self.total += 1;
+    }
+
+    /// Time since the previous instruction goes to the stack that ran it.
+    fn charge_time(&mut self) {
+        let now = Instant::now();
+        if let Some((_, stack)) = self.current {
+            self.stacks[stack].time += now - self.last;
+        }
+        self.last = now;
+    }
+
+    fn enter(&mut self, frames: &[CallFrame]) -> usize {
+        let (mut keys, mut stack) = match self.current.take() {
+            Some((keys, stack)) => (keys, Some(stack)),
+            None => (Vec::new(), None),
+        };
+
+        // After a call or a return the frames below the top one are the same.
+        let keep = match keys.len().abs_diff(frames.len()) {
+            0 | 1 => keys.len().min(frames.len() - 1),
+            _ => 0,
+        };
+        while keys.len() > keep {
+            keys.pop();
+            stack = stack.and_then(|stack| self.stacks[stack].parent);
+        }
+
+        for frame in &frames[keep..] {
+            let closure = frame.closure.borrow();
+            keys.push(Rc::as_ptr(&closure.chunk()));
+            let function = self.function_id(&closure);
+            stack = Some(self.child(stack, function));
+        }
+        let stack = stack.expect("There is at least one frame.");
+        self.current = Some((keys, stack));
+        stack
+    }
+
+    fn child(&mut self, parent: Option<usize>, function: usize) -> usize {
+        let children = match parent {
+            Some(parent) => &self.stacks[parent].children,
+            None => &self.roots,
+        };
+        if let Some(&stack) = children.get(&function) {
+            return stack;
+        }
+
+        let stack = self.stacks.len();
+        self.stacks.push(Stack {
+            parent,
+            function,
+            children: HashMap::new(),
+            count: 0,
+            time: Duration::ZERO,
+        });
+        match parent {
+            Some(parent) => self.stacks[parent].children.insert(function, stack),
+            None => self.roots.insert(function, stack),
+        };
+        stack
+    }
+
+    // Functions of the stack, outermost first.
+    fn functions(&self, mut stack: usize) -> Vec<usize> {
+        let mut functions = vec![self.stacks[stack].function];
+        while let Some(parent) = self.stacks[stack].parent {
+            functions.push(self.stacks[parent].function);
+            stack = parent;
+        }
+        functions.reverse();
+        functions
+    }
+
+    // Functions are told apart by their chunks, which are shared by all their closures.
+    fn function_id(&mut self, closure: &Closure) -> usize {
+        let chunk = closure.chunk();
+        let key = Rc::as_ptr(&chunk);
+        if let Some(&id) = self.function_ids.get(&key) {
+            return id;
+        }
+
+        let id = self.functions.len();
+        let name = closure.function().borrow().name();
+        let name = match name.is_empty() {
+            true => "script".to_string(),
+            false => name,
+        };
+        self.function_ids.insert(key, id);
+        self.functions.push(Function {
+            name,
+            file: chunk.borrow().source.name.clone(),
+        });
+        id
+    }
+
+    fn costs(&self) -> Vec<Cost> {
+        let mut costs = vec![Cost::default(); self.functions.len()];
+        for (i, stack) in self.stacks.iter().enumerate() {
+            costs[stack.function].self_count += stack.count;
+            costs[stack.function].self_time += stack.time;
+            let functions = self.functions(i);
+            for (i, &id) in functions.iter().enumerate() {
+                if !functions[..i].contains(&id) {
+                    costs[id].total_count += stack.count;
+                    costs[id].total_time += stack.time;
+                }
+            }
+        }
+        costs
+    }
+
+    /// Text report sorted by cost, finishes the measurement.
+    pub fn report(&mut self) -> String {
+        self.charge_time();
+        self.current = None;
+        let elapsed = self.started.elapsed();
+
+        let mut out = format!(
+            "== Profile: {} instructions in {} ==\n",
+            self.total,
+            format_time(elapsed)
+        );
+
+        let _ = writeln!(out, "\nFunctions (by inclusive time):");
+        let _ = writeln!(
+            out,
+            "{:>12} {:>12} {:>11} {:>11}  function",
+            "self instr", "incl instr", "self time", "incl time"
+        );
+        let mut functions: Vec<(&Function, Cost)> =
+            self.functions.iter().zip(self.costs()).collect();
+        functions.sort_by_key(|(_, cost)| Reverse(cost.total_time));
+        for (function, cost) in functions {
+            let Cost {
+                self_count,
+                total_count,
+                self_time,
+                total_time,
+            } = cost;
+            let _ = writeln!(
+                out,
+                "{self_count:>12} {total_count:>12} {:>11} {:>11}  {}",
+                format_time(self_time),
+                format_time(total_time),
+                function.name
+            );
+        }
+
+        let _ = writeln!(out, "\nInstructions (by count):");
+        let mut opcodes: Vec<(u8, u64)> = (0..=u8::MAX)
+            .map(|code| (code, self.opcodes[code as usize]))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for (code, count) in opcodes {
+            let name = match OpCode::try_from(code) {
+                Ok(opcode) => format!("{opcode:?}"),
+                Err(_) => format!("<unknown {code}>"),
+            };
+            let _ = writeln!(out, "{count:>12} {:>6}  {name}", self.share(count));
+        }
+
+        let _ = writeln!(out, "\nHot lines (top {HOT_LINES}):");
+        let mut lines: Vec<(&(usize, isize), &u64)> = self.lines.iter().collect();
+        lines.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (&(id, line), &count) in lines.into_iter().take(HOT_LINES) {
+            let function = &self.functions[id];
+            let _ = writeln!(
+                out,
+                "{count:>12} {:>6}  {}:{line} in {}",
+                self.share(count),
+                function.file,
+                function.name
+            );
+        }
+        out
+    }
+
+    /// Stacks in the folded format, one per line, sorted.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<String> = self
+            .stacks
+            .iter()
+            .enumerate()
+            .filter(|(_, stack)| stack.count > 0)
+            .map(|(i, stack)| {
+                let names: Vec<&str> = self
+                    .functions(i)
+                    .iter()
+                    .map(|&id| self.functions[id].name.as_str())
+                    .collect();
+                format!("{} {}\n", names.join(";"), stack.count)
+            })
+            .collect();
+        stacks.sort();
+        stacks.concat()
+    }
+
+    fn share(&self, count: u64) -> String {
+        format!("{:.1}%", count as f64 * 100.0 / self.total.max(1) as f64)
+    }
+}
+
+fn format_time(time: Duration) -> String {
+    format!("{:.3}ms", time.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Profiler;
+    use crate::vm::VM;
+
+    #[test]
+    fn test_profile() {
+        let source = "fun f(n) { if (n > 0) f(n - 1); }
+fun g() { f(2); }
+g();
+";
+        let mut vm = VM::default();
+        vm.init();
+        vm.set_source_name("test.lox");
+        vm.set_profiler(Profiler::new());
+        vm.interpret(source.to_string());
+        let mut profiler = vm.take_profiler().unwrap();
+
+        let folded = profiler.folded();
+        let stacks: Vec<&str> = folded
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            [
+                "script",
+                "script;g",
+                "script;g;f",
+                "script;g;f;f",
+                "script;g;f;f;f"
+            ]
+        );
+
+        let report = profiler.report();
+        assert!(report.contains("Call"));
+        assert!(report.contains("test.lox:1 in f"));
+        // The implicit return of the script is on its last line, not after it.
+        assert!(report.contains("test.lox:3 in script"));
+        assert!(!report.contains("test.lox:4"));
+    }
+}
@@ -29,11 +29,22 @@ impl ScanError {
     }
 }
 
+/// Position of a scanner, to scan ahead and come back to it.
+#[derive(Clone, Copy)]
+pub struct Checkpoint {
+    current: usize,
+    line: isize,
+    line_start: usize,
+}
+
 pub struct Scanner {
     source: String,
     start: usize,
     current: usize,
     line: isize,
+    line_start: usize,   // index of the first character of the current line
+    start_line: isize,   // line of the current lexeme, multiline strings start on it
+    start_column: usize, // column of the current lexeme
 }
 
 pub fn print_tokens(source: String) {
@@ -74,12 +85,17 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 0,
         }
     }
 
     pub fn scan_token(&mut self) -> Result<Token, ScanError> {
         self.skip_whitespace();
         self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.start - self.line_start;
 
         if self.is_end() {
             return Ok(self.make_token(TokenType::EoF));
@@ -91,10 +107,21 @@ impl Scanner {
             ')' => Ok(self.make_token(TokenType::RightParen)),
             '{' => Ok(self.make_token(TokenType::LeftBrace)),
             '}' => Ok(self.make_token(TokenType::RightBrace)),
+            '[' => Ok(self.make_token(TokenType::LeftBracket)),
+            ']' => Ok(self.make_token(TokenType::RightBracket)),
             ';' => Ok(self.make_token(TokenType::Semicolon)),
+            ':' => Ok(self.make_token(TokenType::Colon)),
             ',' => Ok(self.make_token(TokenType::Comma)),
-            '.' => Ok(self.make_token(TokenType::Dot)),
+            '.' => {
+                if self.peek() == '.' && self.peek_next() == '.' {
+                    self.current += 2;
+                    Ok(self.make_token(TokenType::DotDotDot))
+                } else {
+                    Ok(self.make_token(TokenType::Dot))
+                }
+            }
             '-' => Ok(self.make_token(TokenType::Minus)),
+            '|' => Ok(self.make_token(TokenType::Pipe)),
             '+' => Ok(self.make_token(TokenType::Plus)),
             '/' => Ok(self.make_token(TokenType::Slash)),
             '*' => Ok(self.make_token(TokenType::Star)),
@@ -110,6 +137,8 @@ impl Scanner {
             '=' => {
                 if self.complete('=') {
                     Ok(self.make_token(TokenType::EqualEqual))
+                } else if self.complete('>') {
+                    Ok(self.make_token(TokenType::FatArrow))
                 } else {
                     Ok(self.make_token(TokenType::Equal))
                 }
@@ -141,12 +170,32 @@ impl Scanner {
         }
     }
 
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            current: self.current,
+            line: self.line,
+            line_start: self.line_start,
+        }
+    }
+
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.current = checkpoint.current;
+        self.line = checkpoint.line;
+        self.line_start = checkpoint.line_start;
+    }
+
     pub fn lexeme(&self, begin: usize, length: usize) -> String {
         self.source.chars().skip(begin).take(length).collect()
     }
 
     fn make_token(&self, kind: TokenType) -> Token {
-        Token::new(kind, self.start, self.current - self.start, self.line)
+        Token::new(
+            kind,
+            self.start,
+            self.current - self.start,
+            self.start_line,
+            self.start_column,
+        )
     }
 
     fn error_token(&self, message: &'static str) -> ScanError {
@@ -162,6 +211,11 @@ impl Scanner {
         self.nth(self.current - 1)
     }
 
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
     fn complete(&mut self, expected: char) -> bool {
         if self.is_end() {
             return false;
@@ -175,10 +229,9 @@ impl Scanner {
 
     fn string(&mut self) -> Result<Token, ScanError> {
         while self.peek() != '"' && !self.is_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_end() {
@@ -227,8 +280,29 @@ impl Scanner {
     fn identifier_type(&self) -> TokenType {
         match self.nth(self.start) {
             'a' => self.check_keyword(1, 2, "nd", TokenType::And),
-            'c' => self.check_keyword(1, 4, "lass", TokenType::Class),
-            'e' => self.check_keyword(1, 3, "lse", TokenType::Else),
+            'c' => {
+                if self.current - self.start > 1 {
+                    match self.nth(self.start + 1) {
+                        'a' => self.check_keyword(2, 2, "se", TokenType::Case),
+                        'l' => self.check_keyword(2, 3, "ass", TokenType::Class),
+                        'o' => self.check_keyword(2, 3, "nst", TokenType::Const),
+                        _ => TokenType::Identifier,
+                    }
+                } else {
+                    TokenType::Identifier
+                }
+            }
+            'e' => {
+                if self.current - self.start > 1 {
+                    match self.nth(self.start + 1) {
+                        'l' => self.check_keyword(2, 2, "se", TokenType::Else),
+                        'n' => self.check_keyword(2, 2, "um", TokenType::Enum),
+                        _ => TokenType::Identifier,
+                    }
+                } else {
+                    TokenType::Identifier
+                }
+            }
             'f' => {
                 if self.current - self.start > 1 {
                     match self.nth(self.start + 1) {
@@ -242,6 +316,7 @@ impl Scanner {
                 }
             }
             'i' => self.check_keyword(1, 1, "f", TokenType::If),
+            'm' => self.check_keyword(1, 4, "atch", TokenType::Match),
             'n' => self.check_keyword(1, 2, "il", TokenType::Nil),
             'o' => self.check_keyword(1, 1, "r", TokenType::Or),
             'p' => self.check_keyword(1, 4, "rint", TokenType::Print),
@@ -260,6 +335,7 @@ impl Scanner {
             }
             'v' => self.check_keyword(1, 2, "ar", TokenType::Var),
             'w' => self.check_keyword(1, 4, "hile", TokenType::While),
+            'y' => self.check_keyword(1, 4, "ield", TokenType::Yield),
             _ => TokenType::Identifier,
         }
     }
@@ -306,8 +382,8 @@ impl Scanner {
                     self.advance();
                 }
                 '\n' => {
-                    self.line += 1;
                     self.advance();
+                    self.new_line();
                 }
                 '/' => {
                     if self.peek_next() == '/' {
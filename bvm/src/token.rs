@@ -6,25 +6,46 @@ pub struct Token {
     pub start: usize,
     pub length: usize,
     pub line: isize,
+    pub column: usize, // of the first character, starts from 0
 }
 
 impl Token {
-    pub fn new(kind: TokenType, start: usize, length: usize, line: isize) -> Self {
+    pub fn new(kind: TokenType, start: usize, length: usize, line: isize, column: usize) -> Self {
         Token {
             kind,
             start,
             length,
             line,
+            column,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            length: self.length,
+            line: self.line,
+            column: self.column,
         }
     }
 }
 
 impl Default for Token {
     fn default() -> Self {
-        Self::new(TokenType::Error, 0, 0, -1)
+        Self::new(TokenType::Error, 0, 0, -1, 0)
     }
 }
 
+/// Place in the source code: characters from `start` (an index in the whole source)
+/// and the same place as a line and a column.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub length: usize,
+    pub line: isize,
+    pub column: usize,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -32,23 +53,29 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
+    Pipe,
     Plus,
     Semicolon,
     Slash,
     Star,
 
-    // One or two character tokens.
+    // One, two or three character tokens.
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    DotDotDot,
 
     // Literals.
     Identifier,
@@ -57,12 +84,16 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Case,
     Class,
+    Const,
     Else,
+    Enum,
     False,
     Fun,
     For,
     If,
+    Match,
     Nil,
     Or,
     Print,
@@ -72,6 +103,7 @@ pub enum TokenType {
     True,
     Var,
     While,
+    Yield,
 
     Error,
     EoF,
@@ -1,6 +1,15 @@
+//! Values of the VM. With the `nan-boxing` feature they are packed into 64 bits instead,
+//! see `nan_boxing.rs`. The rest of the VM uses only the API that both of them have.
+
+#[cfg(feature = "nan-boxing")]
+pub use crate::nan_boxing::Value;
+
+#[cfg(not(feature = "nan-boxing"))]
 use crate::object::Obj;
+#[cfg(not(feature = "nan-boxing"))]
 use std::fmt;
 
+#[cfg(not(feature = "nan-boxing"))]
 #[derive(Clone)]
 pub enum Value {
     Bool(bool),
@@ -9,6 +18,7 @@ pub enum Value {
     Obj(Obj),
 }
 
+#[cfg(not(feature = "nan-boxing"))]
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -20,6 +30,7 @@ impl fmt::Display for Value {
     }
 }
 
+#[cfg(not(feature = "nan-boxing"))]
 impl Value {
     pub fn is_bool(&self) -> bool {
         matches!(self, Value::Bool(_))
@@ -53,7 +64,7 @@ impl Value {
             Value::Bool(a) => *a == unsafe { other.as_bool() },
             Value::Nil => true,
             Value::Num(a) => *a == unsafe { other.as_num() },
-            Value::Obj(obj) => *obj == unsafe { other.as_obj() },
+            Value::Obj(obj) => obj == unsafe { other.as_obj() },
         }
     }
 
@@ -66,6 +77,14 @@ impl Value {
         }
     }
 
+    /// The object if the value is one.
+    pub fn obj(&self) -> Option<&Obj> {
+        match self {
+            Value::Obj(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
     /// Extract inner `bool` value.
     ///
     /// # Safety
@@ -92,16 +111,16 @@ impl Value {
         }
     }
 
-    /// Extract inner `Obj`. This function returns cloned value,
-    /// not the original one.
+    /// Borrow inner `Obj`. Use the `Obj::as_*` functions to clone
+    /// the part that is needed.
     ///
     /// # Safety
     ///
     /// Fails if `Value::is_obj()` returns `false`.
     /// Use `Value::is_obj()` before applying this function.
-    pub unsafe fn as_obj(&self) -> Obj {
+    pub unsafe fn as_obj(&self) -> &Obj {
         match self {
-            Value::Obj(obj) => obj.clone(),
+            Value::Obj(obj) => obj,
             _ => panic!("Expected Obj value."),
         }
     }
@@ -0,0 +1,585 @@
+//! Checks of bytecode before it is run.
+//!
+//! `VM::run` trusts the code completely, so bad bytecode makes it panic or silently read
+//! the wrong values. The verifier checks a function and every function in its constants:
+//!
+//! - every byte is either a supported opcode or one of its operands,
+//! - constant indexes, local slots, upvalue and parameter indexes are in bounds and constants
+//!   have the kind that the instruction expects,
+//! - jumps land on the start of an instruction,
+//! - the stack has the same depth at an instruction on every path to it, no instruction pops
+//!   values below its frame and no path runs past the end of the code.
+
+use crate::chunk::{Chunk, OpCode};
+use crate::object::Function;
+use crate::value::Value;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct VerifyError {
+    pub function: String,
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid bytecode in {} at {:04}: {}",
+            self.function, self.offset, self.message
+        )
+    }
+}
+
+/// Checks the function and the functions nested in it, returns the first error.
+pub fn verify(function: &Function) -> Result<(), VerifyError> {
+    let chunk = function.chunk();
+    let chunk = chunk.borrow();
+    let verifier = Verifier {
+        function,
+        chunk: &chunk,
+    };
+    verifier.verify()?;
+
+    for constant in &chunk.constants {
+        if constant.is_obj_type("Function") {
+            verify(&unsafe { constant.as_obj().as_fun() })?;
+        }
+    }
+    Ok(())
+}
+
+// Instruction that starts at an offset.
+#[derive(Clone, Copy)]
+struct Instruction {
+    opcode: OpCode,
+    len: usize, // together with operands
+}
+
+struct Verifier<'a> {
+    function: &'a Function,
+    chunk: &'a Chunk,
+}
+
+impl Verifier<'_> {
+    fn error(&self, offset: usize, message: String) -> VerifyError {
+        VerifyError {
+            function: self.function.to_string(),
+            offset,
+            message,
+        }
+    }
+
+    fn verify(&self) -> Result<(), VerifyError> {
+        let code = &self.chunk.code;
+        if self.chunk.spans.len() != code.len() {
+            let message = format!(
+                "Chunk has {} spans for {} bytes of code.",
+                self.chunk.spans.len(),
+                code.len()
+            );
+            return Err(self.error(0, message));
+        }
+
+        let instructions = self.decode()?;
+        self.check_stack(&instructions)
+    }
+
+    // Instructions at their offsets, `None` for operands.
+    fn decode(&self) -> Result<Vec<Option<Instruction>>, VerifyError> {
+        let code = &self.chunk.code;
+        let mut instructions = vec![None; code.len()];
+        let mut offset = 0;
+        while offset < code.len() {
+            // Checked first, `try_from` prints unknown opcodes.
+            if code[offset] > OpCode::ReturnNil as u8 {
+                return Err(self.error(offset, format!("Unknown opcode {}.", code[offset])));
+            }
+            let opcode = OpCode::try_from(code[offset]).expect("Opcode is in range.");
+            let len = self.operands(offset, opcode)? + 1;
+            instructions[offset] = Some(Instruction { opcode, len });
+            offset += len;
+        }
+        Ok(instructions)
+    }
+
+    fn byte(&self, offset: usize) -> u8 {
+        self.chunk.code[offset]
+    }
+
+    fn short(&self, offset: usize) -> usize {
+        (self.byte(offset) as usize) << 8 | self.byte(offset + 1) as usize
+    }
+
+    // Checks operands that don't depend on the stack, returns their number.
+    fn operands(&self, offset: usize, opcode: OpCode) -> Result<usize, VerifyError> {
+        let count = match opcode {
+            OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Invoke
+            | OpCode::SuperInvoke
+            | OpCode::Class
+            | OpCode::Inherit
+            | OpCode::Method => {
+                let message = format!("{opcode:?} is not supported by the VM.");
+                return Err(self.error(offset, message));
+            }
+            OpCode::Constant
+            | OpCode::GetLocal
+            | OpCode::SetLocal
+            | OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetUpvalue
+            | OpCode::SetUpvalue
+            | OpCode::GetProperty
+            | OpCode::IsVariant
+            | OpCode::Call
+            | OpCode::BuildList
+            | OpCode::Closure
+            | OpCode::PopN => 1,
+            OpCode::Jump
+            | OpCode::JumpIfFalse
+            | OpCode::Loop
+            | OpCode::AddLocalConstant
+            | OpCode::LessJumpIfFalse => 2,
+            OpCode::JumpIfArg => 3,
+            OpCode::JumpTable => 2,
+            _ => 0,
+        };
+        self.check_len(offset, opcode, count)?;
+
+        match opcode {
+            OpCode::Constant => {
+                self.constant(offset, self.byte(offset + 1))?;
+            }
+            OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetProperty
+            | OpCode::IsVariant => {
+                if !self
+                    .constant(offset, self.byte(offset + 1))?
+                    .is_obj_type("String")
+                {
+                    let message =
+                        format!("{opcode:?} needs a name, but the constant isn't a string.");
+                    return Err(self.error(offset, message));
+                }
+            }
+            OpCode::GetUpvalue | OpCode::SetUpvalue => {
+                self.upvalue(offset, self.byte(offset + 1))?;
+            }
+            OpCode::AddLocalConstant => {
+                self.constant(offset, self.byte(offset + 2))?;
+            }
+            OpCode::JumpIfArg => {
+                let param = self.byte(offset + 1) as isize;
+                if param >= self.function.arity() {
+                    let message = format!(
+                        "Parameter {param} is out of range, the function has {}.",
+                        self.function.arity()
+                    );
+                    return Err(self.error(offset, message));
+                }
+            }
+            OpCode::JumpTable => {
+                let keys = self.constant(offset, self.byte(offset + 1))?;
+                if !keys.is_obj_type("Enum") && !keys.is_obj_type("List") {
+                    let message =
+                        "JumpTable of a constant that isn't an enum or a list.".to_string();
+                    return Err(self.error(offset, message));
+                }
+                // A jump for each key.
+                let jumps = 2 * self.byte(offset + 2) as usize;
+                self.check_len(offset, opcode, count + jumps)?;
+                return Ok(count + jumps);
+            }
+            OpCode::Yield if !self.function.is_generator() => {
+                let message = "Yield outside of a generator function.".to_string();
+                return Err(self.error(offset, message));
+            }
+            OpCode::Closure => {
+                let constant = self.constant(offset, self.byte(offset + 1))?;
+                if !constant.is_obj_type("Function") {
+                    let message = "Closure of a constant that isn't a function.".to_string();
+                    return Err(self.error(offset, message));
+                }
+                // A pair of bytes for each upvalue: whether it is a local and its index.
+                let upvalues =
+                    unsafe { constant.as_obj().as_fun() }.upvalue_count().max(0) as usize;
+                self.check_len(offset, opcode, count + 2 * upvalues)?;
+                for i in 0..upvalues {
+                    let is_local = self.byte(offset + 2 + 2 * i);
+                    let index = self.byte(offset + 3 + 2 * i);
+                    match is_local {
+                        0 => self.upvalue(offset, index)?,
+                        1 => (),
+                        _ => {
+                            let message = format!("Upvalue {i} is neither local nor enclosing.");
+                            return Err(self.error(offset, message));
+                        }
+                    }
+                }
+                return Ok(count + 2 * upvalues);
+            }
+            _ => (),
+        }
+        Ok(count)
+    }
+
+    fn check_len(&self, offset: usize, opcode: OpCode, count: usize) -> Result<(), VerifyError> {
+        if offset + count >= self.chunk.code.len() {
+            let message = format!("{opcode:?} needs {count} bytes of operands after it.");
+            return Err(self.error(offset, message));
+        }
+        Ok(())
+    }
+
+    fn constant(&self, offset: usize, index: u8) -> Result<&Value, VerifyError> {
+        let index = index as usize;
+        self.chunk.constants.get(index).ok_or_else(|| {
+            let message = format!(
+                "Constant {index} is out of range, the chunk has {}.",
+                self.chunk.constants.len()
+            );
+            self.error(offset, message)
+        })
+    }
+
+    fn upvalue(&self, offset: usize, index: u8) -> Result<(), VerifyError> {
+        if index as isize >= self.function.upvalue_count() {
+            let message = format!(
+                "Upvalue {index} is out of range, the function has {}.",
+                self.function.upvalue_count()
+            );
+            return Err(self.error(offset, message));
+        }
+        Ok(())
+    }
+
+    // Follows every path from the start with the depth of the stack, which counts the callee
+    // and the arguments in the frame.
+    fn check_stack(&self, instructions: &[Option<Instruction>]) -> Result<(), VerifyError> {
+        let function = self.function;
+        let mut entry = 1 + function.arity().max(0) as usize;
+        if function.is_variadic() {
+            entry += 1;
+        }
+
+        if instructions.is_empty() {
+            let message = "Execution runs past the end of the code.".to_string();
+            return Err(self.error(0, message));
+        }
+        let mut depths: Vec<Option<usize>> = vec![None; instructions.len()];
+        let mut paths = vec![(0, entry)];
+        while let Some((offset, depth)) = paths.pop() {
+            match depths[offset] {
+                Some(other) if other == depth => continue,
+                Some(other) => {
+                    let message =
+                        format!("Stack depth is {other} on one path and {depth} on another.");
+                    return Err(self.error(offset, message));
+                }
+                None => depths[offset] = Some(depth),
+            }
+
+            let instruction = instructions[offset].expect("Paths start at instructions.");
+            let (pops, pushes) = self.stack_effect(offset, instruction.opcode, depth)?;
+            // The callee in slot 0 always stays.
+            if depth < pops + 1 {
+                let message = format!(
+                    "{:?} pops {pops} values, but the frame has {} above the callee.",
+                    instruction.opcode,
+                    depth - 1
+                );
+                return Err(self.error(offset, message));
+            }
+            let depth = depth - pops + pushes;
+
+            let next = offset + instruction.len;
+            let jumps = match instruction.opcode {
+                OpCode::Jump | OpCode::JumpIfFalse | OpCode::LessJumpIfFalse => {
+                    vec![next.checked_add(self.short(offset + 1))]
+                }
+                OpCode::Loop => vec![next.checked_sub(self.short(offset + 1))],
+                OpCode::JumpIfArg => vec![next.checked_add(self.short(offset + 2))],
+                OpCode::JumpTable => (offset + 3..next)
+                    .step_by(2)
+                    .map(|at| next.checked_add(self.short(at)))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let falls_through = !matches!(
+                instruction.opcode,
+                OpCode::Jump | OpCode::Loop | OpCode::Return | OpCode::ReturnNil
+            );
+
+            for target in jumps {
+                match target {
+                    Some(target) if instructions.get(target).is_some_and(Option::is_some) => {
+                        paths.push((target, depth));
+                    }
+                    _ => {
+                        let message = "Jump target isn't the start of an instruction.".to_string();
+                        return Err(self.error(offset, message));
+                    }
+                }
+            }
+            if falls_through {
+                if next == instructions.len() {
+                    let message = "Execution runs past the end of the code.".to_string();
+                    return Err(self.error(offset, message));
+                }
+                paths.push((next, depth));
+            }
+        }
+        Ok(())
+    }
+
+    // Values that the instruction pops and pushes, checks operands that point into the stack.
+    fn stack_effect(
+        &self,
+        offset: usize,
+        opcode: OpCode,
+        depth: usize,
+    ) -> Result<(usize, usize), VerifyError> {
+        let operand = || self.byte(offset + 1) as usize;
+        let effect = match opcode {
+            OpCode::GetLocal | OpCode::SetLocal | OpCode::AddLocalConstant => {
+                let slot = operand();
+                if slot >= depth {
+                    let message =
+                        format!("Local slot {slot} is out of range, the frame has {depth}.");
+                    return Err(self.error(offset, message));
+                }
+                match opcode {
+                    OpCode::SetLocal => (1, 1),
+                    _ => (0, 1),
+                }
+            }
+            OpCode::Closure => {
+                let constant = &self.chunk.constants[operand()];
+                let upvalues =
+                    unsafe { constant.as_obj().as_fun() }.upvalue_count().max(0) as usize;
+                for i in 0..upvalues {
+                    let is_local = self.byte(offset + 2 + 2 * i) == 1;
+                    let slot = self.byte(offset + 3 + 2 * i) as usize;
+                    // The closure itself is pushed before its upvalues are captured, so
+                    // the frame has one more slot and a local function can refer to itself.
+                    let slots = depth + 1;
+                    if is_local && slot >= slots {
+                        let message = format!(
+                            "Captured local slot {slot} is out of range, the frame has {slots}."
+                        );
+                        return Err(self.error(offset, message));
+                    }
+                }
+                (0, 1)
+            }
+            OpCode::Constant
+            | OpCode::Nil
+            | OpCode::True
+            | OpCode::False
+            | OpCode::GetGlobal
+            | OpCode::GetUpvalue => (0, 1),
+            OpCode::Pop | OpCode::DefineGlobal | OpCode::Print | OpCode::CloseUpvalue => (1, 0),
+            // Values that are only peeked at are counted as popped and pushed back.
+            OpCode::SetGlobal
+            | OpCode::SetUpvalue
+            | OpCode::GetProperty
+            | OpCode::Not
+            | OpCode::Negate
+            | OpCode::JumpIfFalse
+            | OpCode::Yield => (1, 1),
+            OpCode::Equal
+            | OpCode::Greater
+            | OpCode::Less
+            | OpCode::Add
+            | OpCode::Subtract
+            | OpCode::Multiply
+            | OpCode::Divide
+            | OpCode::Index
+            | OpCode::Extend
+            | OpCode::CallList
+            | OpCode::IsVariant
+            | OpCode::IsInstance
+            | OpCode::LessJumpIfFalse => (2, 1),
+            // The value and the enum.
+            OpCode::JumpTable => (2, 0),
+            OpCode::Jump | OpCode::Loop | OpCode::JumpIfArg => (0, 0),
+            // The callee and the arguments are replaced by the result.
+            OpCode::Call => (operand() + 1, 1),
+            OpCode::BuildList => (operand(), 1),
+            OpCode::PopN => (operand(), 0),
+            OpCode::Return => (1, 0),
+            OpCode::ReturnNil => (0, 0),
+            OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Invoke
+            | OpCode::SuperInvoke
+            | OpCode::Class
+            | OpCode::Inherit
+            | OpCode::Method => unreachable!("Unsupported opcodes are rejected while decoding."),
+        };
+        Ok(effect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify;
+    use crate::chunk::OpCode;
+    use crate::compiler::Parser;
+    use crate::object::{Function, Obj};
+    use crate::token::Span;
+    use crate::value::Value;
+    use crate::vm::{InterpretResult, VM};
+
+    fn function(code: &[u8], constants: Vec<Value>) -> Function {
+        let function = Function::new();
+        let chunk = function.chunk();
+        let mut chunk = chunk.borrow_mut();
+        for &byte in code {
+            chunk.write_raw_instruction(byte, Span::default());
+        }
+        chunk.constants = constants;
+        drop(chunk);
+        function
+    }
+
+    fn error(code: &[u8], constants: Vec<Value>) -> String {
+        match verify(&function(code, constants)) {
+            Ok(()) => panic!("{code:?} is valid"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compiled() {
+        let source = "fun counter(start, step = 1, ...rest) {
+  var count = start;
+  fun next() { count = count + step; return count; }
+  return next;
+}
+fun gen() { var x = yield 1; yield x; }
+enum Shape { Circle(r), Dot }
+var c = counter(0, ...[2]);
+for (var i = 0; i < 3; i = i + 1) { if (i > 1 and c() < 10) print i; }
+match (Shape.Circle(2)) {
+  case 1 | 2 => print 1;
+  case s => print s.r;
+}
+match (Shape.Dot) {
+  case Shape.Circle(r) => print r;
+  case Shape.Dot | 3 => print 0;
+}
+{ fun local() { return local; } print [1, 2][0]; }
+while (false) print gen()();";
+        let mut parser = Parser::default();
+        let function = parser.compile(source.to_string()).unwrap();
+        assert_eq!(verify(&function.borrow()), Ok(()));
+    }
+
+    #[test]
+    fn test_hand_built() {
+        let (nil, ret) = (OpCode::Nil as u8, OpCode::Return as u8);
+        let (constant, pop) = (OpCode::Constant as u8, OpCode::Pop as u8);
+        let jump_if_false = OpCode::JumpIfFalse as u8;
+
+        // print 1 + 2;
+        let code = [
+            constant,
+            0,
+            constant,
+            1,
+            OpCode::Add as u8,
+            OpCode::Print as u8,
+            nil,
+            ret,
+        ];
+        let mut vm = VM::default();
+        vm.init();
+        let result = vm.interpret_function(function(&code, vec![Value::Num(1.0), Value::Num(2.0)]));
+        assert!(matches!(result, InterpretResult::Ok));
+
+        assert_eq!(
+            error(&[constant, 1, pop, nil, ret], vec![Value::Nil]),
+            "Invalid bytecode in <script> at 0000: Constant 1 is out of range, the chunk has 1."
+        );
+        assert_eq!(
+            error(&[200, nil, ret], vec![]),
+            "Invalid bytecode in <script> at 0000: Unknown opcode 200."
+        );
+        assert_eq!(
+            error(&[OpCode::GetGlobal as u8, 0, nil, ret], vec![Value::Num(1.0)]),
+            "Invalid bytecode in <script> at 0000: GetGlobal needs a name, but the constant isn't a string."
+        );
+        assert_eq!(
+            error(
+                &[OpCode::Jump as u8, 0, 1, constant, 0, nil, ret],
+                vec![Value::Nil]
+            ),
+            "Invalid bytecode in <script> at 0000: Jump target isn't the start of an instruction."
+        );
+        assert_eq!(
+            error(
+                &[nil, nil, OpCode::JumpTable as u8, 0, 1, 0, 0, nil, ret],
+                vec![Value::Nil]
+            ),
+            "Invalid bytecode in <script> at 0002: JumpTable of a constant that isn't an enum or a list."
+        );
+        assert_eq!(
+            error(&[OpCode::Loop as u8, 0, 4, nil, ret], vec![]),
+            "Invalid bytecode in <script> at 0000: Jump target isn't the start of an instruction."
+        );
+        assert_eq!(
+            error(&[pop, nil, ret], vec![]),
+            "Invalid bytecode in <script> at 0000: Pop pops 1 values, but the frame has 0 above the callee."
+        );
+        assert_eq!(
+            error(&[OpCode::GetLocal as u8, 1, ret], vec![]),
+            "Invalid bytecode in <script> at 0000: Local slot 1 is out of range, the frame has 1."
+        );
+        assert_eq!(
+            error(&[nil, OpCode::Print as u8], vec![]),
+            "Invalid bytecode in <script> at 0001: Execution runs past the end of the code."
+        );
+        assert_eq!(
+            error(&[OpCode::Jump as u8], vec![]),
+            "Invalid bytecode in <script> at 0000: Jump needs 2 bytes of operands after it."
+        );
+        // `if (true) nil;` that forgets to pop the condition on one path.
+        let code = [OpCode::True as u8, jump_if_false, 0, 1, pop, nil, ret];
+        assert_eq!(
+            error(&code, vec![]),
+            "Invalid bytecode in <script> at 0005: Stack depth is 1 on one path and 2 on another."
+        );
+        assert_eq!(
+            error(&[nil, OpCode::Yield as u8, ret], vec![]),
+            "Invalid bytecode in <script> at 0001: Yield outside of a generator function."
+        );
+
+        // Nested functions are checked too.
+        let inner = function(&[pop, nil, ret], vec![]);
+        let code = [OpCode::Closure as u8, 0, pop, nil, ret];
+        assert_eq!(
+            error(&code, vec![Value::Obj(Obj::Fun(inner))]),
+            "Invalid bytecode in <script> at 0000: Pop pops 1 values, but the frame has 0 above the callee."
+        );
+
+        // A closure can capture the slots below it and its own slot, but nothing above.
+        let mut inner = function(&[nil, ret], vec![]);
+        inner.change_upvalue_count(1);
+        let inner = Value::Obj(Obj::Fun(inner));
+        let closure = OpCode::Closure as u8;
+        let code = [nil, closure, 0, 1, 2, pop, pop, nil, ret];
+        assert_eq!(verify(&function(&code, vec![inner.clone()])), Ok(()));
+        let code = [nil, closure, 0, 1, 3, pop, pop, nil, ret];
+        assert_eq!(
+            error(&code, vec![inner]),
+            "Invalid bytecode in <script> at 0001: Captured local slot 3 is out of range, the frame has 3."
+        );
+    }
+}
@@ -1,31 +1,46 @@
 use crate::chunk::OpCode;
 use crate::compiler::Parser;
+use crate::coverage::Coverage;
 use crate::debug::{disassemble_chunk, disassemble_instruction};
-use crate::object::{Closure, Native, Obj, Upvalue};
+use crate::debugger::Debugger;
+use crate::diagnostic::{self, Diagnostic};
+use crate::object::{
+    Closure, Function, Generator, GeneratorState, GeneratorStatus, List, Native, Obj, Upvalue,
+};
+use crate::profiler::Profiler;
 use crate::scanner::print_tokens;
 use crate::value::Value;
+use crate::verifier;
 use crate::Config;
-use std::collections::{HashMap, LinkedList};
-use std::{cell::RefCell, rc::Rc};
+use std::collections::{HashMap, HashSet, LinkedList};
+use std::io::{self, Write};
+use std::{cell::RefCell, mem, rc::Rc};
 
 const FRAMES_MAX: usize = 64;
 const STACK_MAX: usize = FRAMES_MAX * 256;
 
 pub struct CallFrame {
-    closure: Rc<RefCell<Closure>>,
-    ip: usize,
-    slots: usize,
+    pub(crate) closure: Rc<RefCell<Closure>>,
+    pub(crate) ip: usize,
+    pub(crate) slots: usize,
+    // Number of arguments the caller passed, used to skip default values.
+    arg_count: usize,
 }
 
 impl Default for CallFrame {
     fn default() -> Self {
-        Self::new(Rc::new(RefCell::new(Closure::default())), 0, 0)
+        Self::new(Rc::new(RefCell::new(Closure::default())), 0, 0, 0)
     }
 }
 
 impl CallFrame {
-    fn new(closure: Rc<RefCell<Closure>>, ip: usize, slots: usize) -> Self {
-        Self { closure, ip, slots }
+    fn new(closure: Rc<RefCell<Closure>>, ip: usize, slots: usize, arg_count: usize) -> Self {
+        Self {
+            closure,
+            ip,
+            slots,
+            arg_count,
+        }
     }
 }
 
@@ -36,12 +51,12 @@ pub enum InterpretResult {
 }
 
 pub struct VM {
-    frames: [CallFrame; FRAMES_MAX],
-    frame_count: isize,
+    pub(crate) frames: Vec<CallFrame>,
+    pub(crate) frame_count: isize,
 
     config: Config,
 
-    stack: Vec<Value>,
+    pub(crate) stack: Vec<Value>,
     stack_top: usize,
 
     open_upvalues: Option<Rc<RefCell<Upvalue>>>,
@@ -49,13 +64,28 @@ pub struct VM {
     // maybe use Rc::try_unwrap
     objects: LinkedList<*mut Obj>,
 
-    globals: HashMap<String, Value>,
+    pub(crate) globals: HashMap<String, Value>,
+    // Names of global constants, kept between REPL lines.
+    constants: HashSet<String>,
+
+    // Chain of generators that are currently running, innermost last.
+    generators: Vec<Generator>,
+
+    // File name shown in error messages.
+    source_name: String,
+
+    debugger: Option<Debugger>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+
+    // Where `print` writes to.
+    output: Box<dyn Write>,
 }
 
 impl Default for VM {
     fn default() -> Self {
         VM {
-            frames: [(); FRAMES_MAX].map(|_| CallFrame::default()),
+            frames: (0..FRAMES_MAX).map(|_| CallFrame::default()).collect(),
             frame_count: 0,
             config: Config::default(),
             stack: vec![Value::Nil; STACK_MAX],
@@ -63,6 +93,13 @@ impl Default for VM {
             open_upvalues: None,
             objects: LinkedList::new(),
             globals: HashMap::new(),
+            constants: HashSet::new(),
+            generators: Vec::new(),
+            source_name: "<repl>".to_string(),
+            debugger: None,
+            profiler: None,
+            coverage: None,
+            output: Box::new(io::stdout()),
         }
     }
 }
@@ -70,7 +107,7 @@ impl Default for VM {
 impl VM {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        frames: [CallFrame; FRAMES_MAX],
+        frames: Vec<CallFrame>,
         frame_count: isize,
         config: Config,
         stack: Vec<Value>,
@@ -78,6 +115,13 @@ impl VM {
         open_upvalues: Option<Rc<RefCell<Upvalue>>>,
         objects: LinkedList<*mut Obj>,
         globals: HashMap<String, Value>,
+        constants: HashSet<String>,
+        generators: Vec<Generator>,
+        source_name: String,
+        debugger: Option<Debugger>,
+        profiler: Option<Profiler>,
+        coverage: Option<Coverage>,
+        output: Box<dyn Write>,
     ) -> Self {
         VM {
             frames,
@@ -88,6 +132,13 @@ impl VM {
             open_upvalues,
             objects,
             globals,
+            constants,
+            generators,
+            source_name,
+            debugger,
+            profiler,
+            coverage,
+            output,
         }
     }
 
@@ -95,10 +146,40 @@ impl VM {
         self.config = config;
     }
 
+    pub fn set_source_name(&mut self, name: &str) {
+        self.source_name = name.to_string();
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub fn set_debugger(&mut self, debugger: Debugger) {
+        self.debugger = Some(debugger);
+    }
+
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
+    }
+
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    pub fn set_coverage(&mut self, coverage: Coverage) {
+        self.coverage = Some(coverage);
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
     pub fn init(&mut self) {
         self.reset_stack();
 
         self.define_native("clock");
+        self.define_native("status");
+        self.define_native("len");
     }
 
     fn reset_stack(&mut self) {
@@ -110,7 +191,12 @@ impl VM {
     // Maybe add check of a value type here to add it to `self.objects`,
     // so that GC can delete it later.
     fn push(&mut self, value: Value) {
-        self.stack[self.stack_top] = value;
+        // Generator stacks start small and grow on demand.
+        if self.stack_top == self.stack.len() {
+            self.stack.push(value);
+        } else {
+            self.stack[self.stack_top] = value;
+        }
         self.stack_top += 1;
     }
 
@@ -125,35 +211,19 @@ impl VM {
             InterpretResult::Ok
         } else {
             let mut parser = Parser::new(self.config);
+            parser.set_source_name(&self.source_name);
+            parser.set_constants(self.constants.clone());
             match parser.compile(source) {
                 Ok(function) => {
-                    self.push(Value::Obj(Obj::Fun((*function).borrow().clone())));
-                    let closure = Closure::new(&function);
-                    let closure_ref = Rc::new(RefCell::new(closure.clone()));
-                    self.pop();
-                    self.push(Value::Obj(Obj::Closure(closure)));
-
-                    // self.call(function.clone() // .borrow().clone(), 0);
-                    let frame = self
-                        .frames
-                        .get_mut(self.frame_count as usize)
-                        .expect("Instruction pointer is out of vm.frames bounds.");
-                    self.frame_count += 1;
-                    frame.closure = closure_ref;
-                    frame.ip = 0;
-                    frame.slots = 0;
-
-                    if self.config.bytecode {
-                        let chunk = (*frame.closure).borrow().chunk();
-                        let chunk = (*chunk).borrow();
-                        disassemble_chunk(&chunk, "code");
-                        InterpretResult::Ok
-                    } else {
-                        match self.run() {
-                            Ok(result) => result,
-                            Err(result) => result,
+                    self.constants = parser.constants().clone();
+                    // A failure here is a bug in the compiler.
+                    if let Err(err) = verifier::verify(&function.borrow()) {
+                        if !self.config.quiet {
+                            eprintln!("error: {err}");
                         }
+                        return InterpretResult::CompileError;
                     }
+                    self.run_function(function)
                 }
                 Err(_err) => {
                     // eprintln!("{_err}");
@@ -163,8 +233,73 @@ impl VM {
         }
     }
 
+    /// Runs a function that doesn't come from the compiler, e.g. a chunk built by hand,
+    /// after the verifier has checked it.
+    pub fn interpret_function(&mut self, function: Function) -> InterpretResult {
+        if let Err(err) = verifier::verify(&function) {
+            if !self.config.quiet {
+                eprintln!("error: {err}");
+            }
+            return InterpretResult::CompileError;
+        }
+        self.run_function(Rc::new(RefCell::new(function)))
+    }
+
+    fn run_function(&mut self, function: Rc<RefCell<Function>>) -> InterpretResult {
+        self.push(Value::Obj(Obj::Fun((*function).borrow().clone())));
+        let closure = Closure::new(&function);
+        let closure_ref = Rc::new(RefCell::new(closure.clone()));
+        self.pop();
+        self.push(Value::Obj(Obj::Closure(closure)));
+
+        // self.call(function.clone() // .borrow().clone(), 0);
+        let frame = self
+            .frames
+            .get_mut(self.frame_count as usize)
+            .expect("Instruction pointer is out of vm.frames bounds.");
+        self.frame_count += 1;
+        frame.closure = closure_ref;
+        frame.ip = 0;
+        frame.slots = 0;
+
+        if self.config.bytecode {
+            let chunk = (*frame.closure).borrow().chunk();
+            let chunk = (*chunk).borrow();
+            disassemble_chunk(&chunk, "code");
+            InterpretResult::Ok
+        } else {
+            match self.run() {
+                Ok(result) => result,
+                Err(result) => result,
+            }
+        }
+    }
+
     fn run(&mut self) -> Result<InterpretResult, InterpretResult> {
         loop {
+            let result = self.run_frames();
+            // A runtime error inside a generator kills only that generator:
+            // control goes back to the resumer, which receives `nil`.
+            if result.is_err() && !self.generators.is_empty() {
+                self.suspend(GeneratorStatus::Error, Value::Nil);
+                continue;
+            }
+            return result;
+        }
+    }
+
+    fn run_frames(&mut self) -> Result<InterpretResult, InterpretResult> {
+        loop {
+            if self.debugger.is_some() && !self.debug() {
+                return Ok(InterpretResult::Ok);
+            }
+            if let Some(profiler) = &mut self.profiler {
+                profiler.record(&self.frames[..self.frame_count as usize]);
+            }
+            if let Some(coverage) = &mut self.coverage {
+                coverage.record(&self.frames[self.frame_count as usize - 1]);
+            }
+
             if self.config.trace {
                 print!("          ");
                 for (i, val) in self.stack.iter().enumerate() {
@@ -199,6 +334,10 @@ impl VM {
                 OpCode::Pop => {
                     self.pop();
                 }
+                OpCode::PopN => {
+                    let count = self.read_byte() as usize;
+                    self.stack_top -= count;
+                }
                 OpCode::GetLocal => {
                     let slot = self.read_byte();
                     let frame = self
@@ -221,12 +360,13 @@ impl VM {
                     // Safe to not check if it is a string,
                     // because compiler never emits an instruction
                     // that refers to a non-string constant.
-                    let name = unsafe { self.read_constant().as_obj().as_string() };
-                    let value = self.globals.get(&name);
+                    let name = self.read_constant();
+                    let name = unsafe { name.as_obj().as_str() };
+                    let value = self.globals.get(name);
                     match value {
                         Some(val) => self.push(val.clone()),
                         None => {
-                            self.runtime_error(format!("Undefined variable '{}'.", name));
+                            self.runtime_error(self.undefined(name));
                             return Err(InterpretResult::RuntimeError);
                         }
                     }
@@ -239,11 +379,15 @@ impl VM {
                 }
                 OpCode::SetGlobal => {
                     // See comment for GetGlobal.
-                    let name = unsafe { self.read_constant().as_obj().as_string() };
-                    if self.globals.insert(name.clone(), self.peek(0)).is_none() {
-                        self.globals.remove(&name);
-                        self.runtime_error(format!("Undefined variable '{}'.", name));
-                        return Err(InterpretResult::RuntimeError);
+                    let name = self.read_constant();
+                    let name = unsafe { name.as_obj().as_str() };
+                    let value = self.peek(0);
+                    match self.globals.get_mut(name) {
+                        Some(global) => *global = value,
+                        None => {
+                            self.runtime_error(self.undefined(name));
+                            return Err(InterpretResult::RuntimeError);
+                        }
                     }
                 }
                 OpCode::GetUpvalue => {
@@ -278,6 +422,40 @@ impl VM {
                         self.stack[location] = value;
                     }
                 }
+                OpCode::GetProperty => {
+                    // See comment for GetGlobal.
+                    let name = self.read_constant();
+                    let name = unsafe { name.as_obj().as_str() };
+                    let object = self.peek(0);
+                    let property = match object.obj() {
+                        Some(Obj::Enum(enumeration)) => match enumeration.variant(name) {
+                            Some(variant) => Value::Obj(Obj::Variant(variant)),
+                            None => {
+                                self.runtime_error(format!(
+                                    "Undefined variant '{}' in enum '{}'.",
+                                    name,
+                                    enumeration.name()
+                                ));
+                                return Err(InterpretResult::RuntimeError);
+                            }
+                        },
+                        Some(Obj::Variant(variant)) => match variant.field(name) {
+                            Some(value) => value,
+                            None => {
+                                self.runtime_error(format!("Undefined payload field '{}'.", name));
+                                return Err(InterpretResult::RuntimeError);
+                            }
+                        },
+                        _ => {
+                            self.runtime_error(
+                                "Only enums and variants have properties.".to_string(),
+                            );
+                            return Err(InterpretResult::RuntimeError);
+                        }
+                    };
+                    self.pop();
+                    self.push(property);
+                }
                 OpCode::Equal => {
                     let b = self.pop();
                     let a = self.pop();
@@ -286,6 +464,20 @@ impl VM {
                 OpCode::Greater => self.binary_op(">")?,
                 OpCode::Less => self.binary_op("<")?,
                 OpCode::Add => self.binary_op("+")?,
+                OpCode::AddLocalConstant => {
+                    let slot = self.read_byte() as usize;
+                    let frame = &self.frames[self.frame_count as usize - 1];
+                    let local = self.stack[frame.slots + slot].clone();
+                    let constant = self.read_constant();
+                    if local.is_num() && constant.is_num() {
+                        let sum = unsafe { local.as_num() + constant.as_num() };
+                        self.push(Value::Num(sum));
+                    } else {
+                        self.push(local);
+                        self.push(constant);
+                        self.binary_plus()?;
+                    }
+                }
                 OpCode::Subtract => self.binary_op("-")?,
                 OpCode::Multiply => self.binary_op("*")?,
                 OpCode::Divide => self.binary_op("/")?,
@@ -303,7 +495,8 @@ impl VM {
                     self.push(Value::Num(-val_f64));
                 }
                 OpCode::Print => {
-                    println!("{}", self.pop());
+                    let value = self.pop();
+                    writeln!(self.output, "{value}").expect("Couldn't write the output.");
                 }
                 OpCode::Jump => {
                     let offset: u16 = self.read_short();
@@ -315,7 +508,35 @@ impl VM {
                 }
                 OpCode::JumpIfFalse => {
                     let offset: u16 = self.read_short();
-                    if self.peek(0).is_falsey() {
+                    let is_false = self.peek(0).is_falsey();
+                    if let Some(coverage) = &mut self.coverage {
+                        let frame = &self.frames[self.frame_count as usize - 1];
+                        coverage.branch(frame, frame.ip - 3, !is_false);
+                    }
+                    if is_false {
+                        let frame = self
+                            .frames
+                            .get_mut(self.frame_count as usize - 1)
+                            .expect("Instruction pointer is out of vm.frames bounds.");
+                        frame.ip += offset as usize;
+                    }
+                }
+                OpCode::LessJumpIfFalse => {
+                    // Checked before the operands are read, so that an error points to `<`.
+                    if !self.peek(0).is_num() || !self.peek(1).is_num() {
+                        self.runtime_error("Operands must be numbers.".to_string());
+                        return Err(InterpretResult::RuntimeError);
+                    }
+                    let b = unsafe { self.pop().as_num() };
+                    let a = unsafe { self.pop().as_num() };
+                    let is_less = a < b;
+                    self.push(Value::Bool(is_less));
+                    let offset: u16 = self.read_short();
+                    if let Some(coverage) = &mut self.coverage {
+                        let frame = &self.frames[self.frame_count as usize - 1];
+                        coverage.branch(frame, frame.ip - 3, is_less);
+                    }
+                    if !is_less {
                         let frame = self
                             .frames
                             .get_mut(self.frame_count as usize - 1)
@@ -337,6 +558,154 @@ impl VM {
                         return Err(InterpretResult::RuntimeError);
                     }
                 }
+                OpCode::CallList => {
+                    // Arguments were collected into a list because some of them are spread.
+                    let args = unsafe { self.pop().as_obj().as_list() };
+                    let arg_count = args.len();
+                    for arg in args.items().borrow().iter() {
+                        self.push(arg.clone());
+                    }
+                    if !self.call_value(self.peek(arg_count), arg_count) {
+                        return Err(InterpretResult::RuntimeError);
+                    }
+                }
+                OpCode::IsVariant => {
+                    let name = self.read_constant();
+                    let name = unsafe { name.as_obj().as_str() };
+                    let enumeration = self.pop();
+                    let value = self.pop();
+                    let Some(Obj::Enum(enumeration)) = enumeration.obj() else {
+                        self.runtime_error("Can only match variants of enums.".to_string());
+                        return Err(InterpretResult::RuntimeError);
+                    };
+                    let Some(expected) = enumeration.variant(name) else {
+                        self.runtime_error(format!(
+                            "Undefined variant '{}' in enum '{}'.",
+                            name,
+                            enumeration.name()
+                        ));
+                        return Err(InterpretResult::RuntimeError);
+                    };
+                    let is_variant = matches!(
+                        value.obj(),
+                        Some(Obj::Variant(variant)) if variant.is_same_variant(&expected)
+                    );
+                    self.push(Value::Bool(is_variant));
+                }
+                OpCode::JumpTable => {
+                    let keys = self.read_constant();
+                    let count = self.read_byte() as usize;
+                    let enumeration = self.pop();
+                    let value = self.pop();
+                    // Only variants of the enum that the table was compiled for take a jump,
+                    // a table of literals pushes itself in place of the enum.
+                    let index = match (keys.obj(), enumeration.obj(), value.obj()) {
+                        (
+                            Some(Obj::Enum(table_enum)),
+                            Some(Obj::Enum(enumeration)),
+                            Some(Obj::Variant(variant)),
+                        ) if table_enum == enumeration => enumeration.index_of(variant),
+                        (Some(Obj::List(literals)), _, _) => literals
+                            .items()
+                            .borrow()
+                            .iter()
+                            .position(|literal| literal.equal(value.clone())),
+                        _ => None,
+                    };
+                    let frame = self
+                        .frames
+                        .get_mut(self.frame_count as usize - 1)
+                        .expect("Instruction pointer is out of vm.frames bounds.");
+                    let table = frame.ip;
+                    frame.ip += 2 * count;
+                    if let Some(index) = index.filter(|&index| index < count) {
+                        let chunk = (*frame.closure).borrow().chunk();
+                        let code = &(*chunk).borrow().code;
+                        let at = table + 2 * index;
+                        frame.ip += (code[at] as usize) << 8 | code[at + 1] as usize;
+                    }
+                }
+                OpCode::IsInstance => {
+                    self.pop();
+                    self.pop();
+                    // There are no classes in bvm yet, so nothing can be matched.
+                    self.runtime_error("Can only match instances of classes.".to_string());
+                    return Err(InterpretResult::RuntimeError);
+                }
+                OpCode::JumpIfArg => {
+                    let param = self.read_byte() as usize;
+                    let offset: u16 = self.read_short();
+                    let frame = self
+                        .frames
+                        .get_mut(self.frame_count as usize - 1)
+                        .expect("Instruction pointer is out of vm.frames bounds.");
+                    if frame.arg_count > param {
+                        frame.ip += offset as usize;
+                    }
+                }
+                OpCode::BuildList => {
+                    let count = self.read_byte() as usize;
+                    let items = self.stack[self.stack_top - count..self.stack_top].to_vec();
+                    self.stack_top -= count;
+                    self.push(Value::Obj(Obj::List(List::new(items))));
+                }
+                OpCode::Index => {
+                    let index = self.pop();
+                    let list = self.pop();
+                    if list.is_obj_type("Enum") {
+                        // Enums are indexed by the order of their variants.
+                        let enumeration = unsafe { list.as_obj().as_enum() };
+                        let variant = if index.is_num() && unsafe { index.as_num() } >= 0.0 {
+                            let index = unsafe { index.as_num() };
+                            enumeration
+                                .get(index as usize)
+                                .filter(|_| index.fract() == 0.0)
+                        } else {
+                            None
+                        };
+                        match variant {
+                            Some(variant) => self.push(Value::Obj(Obj::Variant(variant))),
+                            None => {
+                                self.runtime_error("Enum index out of range.".to_string());
+                                return Err(InterpretResult::RuntimeError);
+                            }
+                        }
+                        continue;
+                    }
+                    if !list.is_obj_type("List") {
+                        self.runtime_error("Can only index lists and enums.".to_string());
+                        return Err(InterpretResult::RuntimeError);
+                    }
+                    if !index.is_num() {
+                        self.runtime_error("List index must be a number.".to_string());
+                        return Err(InterpretResult::RuntimeError);
+                    }
+                    let list = unsafe { list.as_obj().as_list() };
+                    let index = unsafe { index.as_num() };
+                    let item = if index.fract() == 0.0 && index >= 0.0 {
+                        list.get(index as usize)
+                    } else {
+                        None
+                    };
+                    match item {
+                        Some(item) => self.push(item),
+                        None => {
+                            self.runtime_error("List index out of range.".to_string());
+                            return Err(InterpretResult::RuntimeError);
+                        }
+                    }
+                }
+                OpCode::Extend => {
+                    let spread = self.pop();
+                    if !spread.is_obj_type("List") {
+                        self.runtime_error("Can only spread lists.".to_string());
+                        return Err(InterpretResult::RuntimeError);
+                    }
+                    let spread = unsafe { spread.as_obj().as_list() };
+                    let args = unsafe { self.peek(0).as_obj().as_list() };
+                    let spread_items = spread.items().borrow().clone();
+                    args.items().borrow_mut().extend(spread_items);
+                }
                 OpCode::Closure => {
                     let function = self.read_constant();
                     if function.is_obj_type("Function") {
@@ -377,23 +746,18 @@ impl VM {
                 }
                 OpCode::Return => {
                     let result = self.pop();
-                    let frame = self
-                        .frames
-                        .get(self.frame_count as usize - 1)
-                        .expect("Instruction pointer is out of vm.frames bounds.");
-                    self.close_upvalues(frame.slots);
-                    self.frame_count -= 1;
-                    if self.frame_count == 0 {
-                        self.pop();
+                    if self.return_value(result) {
                         return Ok(InterpretResult::Ok);
                     }
-
-                    let frame = self
-                        .frames
-                        .get(self.frame_count as usize)
-                        .expect("Instruction pointer is out of vm.frames bounds.");
-                    self.stack_top = frame.slots;
-                    self.push(result);
+                }
+                OpCode::ReturnNil => {
+                    if self.return_value(Value::Nil) {
+                        return Ok(InterpretResult::Ok);
+                    }
+                }
+                OpCode::Yield => {
+                    let value = self.pop();
+                    self.suspend(GeneratorStatus::Suspended, value);
                 }
 
                 _ => {
@@ -404,6 +768,32 @@ impl VM {
         }
     }
 
+    // Leaves the current frame with the result, returns whether the script has ended.
+    fn return_value(&mut self, result: Value) -> bool {
+        let frame = self
+            .frames
+            .get(self.frame_count as usize - 1)
+            .expect("Instruction pointer is out of vm.frames bounds.");
+        self.close_upvalues(frame.slots);
+        self.frame_count -= 1;
+        if self.frame_count == 0 {
+            if !self.generators.is_empty() {
+                self.suspend(GeneratorStatus::Done, result);
+                return false;
+            }
+            self.pop();
+            return true;
+        }
+
+        let frame = self
+            .frames
+            .get(self.frame_count as usize)
+            .expect("Instruction pointer is out of vm.frames bounds.");
+        self.stack_top = frame.slots;
+        self.push(result);
+        false
+    }
+
     fn read_byte(&mut self) -> u8 {
         let frame = self
             .frames
@@ -496,20 +886,53 @@ impl VM {
     fn call(&mut self, closure: Closure, arg_count: usize) -> bool {
         let function = closure.function();
         let function = function.borrow();
-        if arg_count != function.arity() as usize {
+        let arity = function.arity() as usize;
+        let min_arity = function.min_arity() as usize;
+        if arg_count < min_arity || (!function.is_variadic() && arg_count > arity) {
+            let expected = if function.is_variadic() {
+                format!("at least {}", min_arity)
+            } else if min_arity == arity {
+                arity.to_string()
+            } else {
+                format!("{} to {}", min_arity, arity)
+            };
             self.runtime_error(format!(
                 "Expected {} arguments but got {}.",
-                function.arity(),
-                arg_count
+                expected, arg_count
             ));
             return false;
         }
 
+        // Missing arguments are `nil` until their default values are computed,
+        // extra ones go to the rest parameter.
+        for _ in arg_count..arity {
+            self.push(Value::Nil);
+        }
+        let mut slot_count = arity;
+        if function.is_variadic() {
+            let rest_count = arg_count.saturating_sub(arity);
+            let rest = self.stack[self.stack_top - rest_count..self.stack_top].to_vec();
+            self.stack_top -= rest_count;
+            self.push(Value::Obj(Obj::List(List::new(rest))));
+            slot_count += 1;
+        }
+        let arg_count = arg_count.min(arity);
+
+        if function.is_generator() {
+            let name = function.name();
+            drop(function);
+            self.create_generator(closure, name, slot_count, arg_count);
+            return true;
+        }
+
         if self.frame_count as usize == FRAMES_MAX {
             self.runtime_error("Stack overflow.".to_string());
             return false;
         }
 
+        if self.frame_count as usize == self.frames.len() {
+            self.frames.push(CallFrame::default());
+        }
         let frame = self
             .frames
             .get_mut(self.frame_count as usize)
@@ -517,20 +940,46 @@ impl VM {
         self.frame_count += 1;
         frame.closure = Rc::new(RefCell::new(closure));
         frame.ip = 0;
-        frame.slots = self.stack_top - arg_count - 1;
+        frame.slots = self.stack_top - slot_count - 1;
+        frame.arg_count = arg_count;
         true
     }
 
     fn call_value(&mut self, callee: Value, arg_count: usize) -> bool {
         if callee.is_obj() {
-            match callee {
-                Value::Obj(Obj::Closure(callee)) => {
-                    return self.call(callee, arg_count);
+            match callee.obj() {
+                Some(Obj::Closure(callee)) => {
+                    return self.call(callee.clone(), arg_count);
+                }
+                Some(Obj::BuiltIn(native)) => {
+                    let args = self.stack[self.stack_top - arg_count..self.stack_top].to_vec();
+                    match native.call(&args) {
+                        Ok(result) => {
+                            self.stack_top -= arg_count + 1;
+                            self.push(result);
+                            return true;
+                        }
+                        Err(message) => {
+                            self.runtime_error(message);
+                            return false;
+                        }
+                    }
                 }
-                Value::Obj(Obj::BuiltIn(native)) => {
-                    let result = native.call(arg_count, self.stack_top - arg_count);
+                Some(Obj::Gen(generator)) => {
+                    return self.resume(generator.clone(), arg_count);
+                }
+                Some(Obj::Variant(variant)) if variant.is_constructor() => {
+                    if arg_count != variant.arity() {
+                        self.runtime_error(format!(
+                            "Expected {} arguments but got {}.",
+                            variant.arity(),
+                            arg_count
+                        ));
+                        return false;
+                    }
+                    let values = self.stack[self.stack_top - arg_count..self.stack_top].to_vec();
                     self.stack_top -= arg_count + 1;
-                    self.push(result);
+                    self.push(Value::Obj(Obj::Variant(variant.construct(values))));
                     return true;
                 }
                 // Non-callable object type.
@@ -541,6 +990,122 @@ impl VM {
         false
     }
 
+    /// Move the callee and its arguments from the stack into a fresh
+    /// generator and leave the generator in their place.
+    fn create_generator(
+        &mut self,
+        closure: Closure,
+        name: String,
+        slot_count: usize,
+        arg_count: usize,
+    ) {
+        let base = self.stack_top - slot_count - 1;
+        let stack = self.stack[base..self.stack_top].to_vec();
+        let frames = vec![CallFrame::new(
+            Rc::new(RefCell::new(closure)),
+            0,
+            0,
+            arg_count,
+        )];
+        let generator = Generator::new(name, GeneratorState::new(frames, stack));
+        self.stack_top = base;
+        self.push(Value::Obj(Obj::Gen(generator)));
+    }
+
+    /// Calling a generator resumes it. The optional argument becomes
+    /// the value of the `yield` expression the generator is suspended at.
+    fn resume(&mut self, mut generator: Generator, arg_count: usize) -> bool {
+        if arg_count > 1 {
+            self.runtime_error(format!("Expected 0 or 1 arguments but got {}.", arg_count));
+            return false;
+        }
+        match generator.status() {
+            GeneratorStatus::Suspended => (),
+            GeneratorStatus::Running => {
+                self.runtime_error("Generator is already running.".to_string());
+                return false;
+            }
+            GeneratorStatus::Done => {
+                self.runtime_error("Can't resume a finished generator.".to_string());
+                return false;
+            }
+            GeneratorStatus::Error => {
+                self.runtime_error("Can't resume a failed generator.".to_string());
+                return false;
+            }
+        }
+
+        let value = if arg_count == 1 {
+            self.pop()
+        } else {
+            Value::Nil
+        };
+        // The generator itself.
+        self.pop();
+
+        let started = mem::replace(&mut generator.state().borrow_mut().started, true);
+        generator.set_status(GeneratorStatus::Running);
+        self.switch_context(&generator);
+        if started {
+            // Result of the `yield` that suspended the generator.
+            self.push(value);
+        }
+        self.generators.push(generator);
+        true
+    }
+
+    /// Leave the innermost running generator with the given status
+    /// and hand `result` over to its resumer.
+    fn suspend(&mut self, status: GeneratorStatus, result: Value) {
+        let mut generator = self
+            .generators
+            .pop()
+            .expect("Only a running generator can be suspended.");
+        self.switch_context(&generator);
+        generator.set_status(status);
+        self.push(result);
+    }
+
+    // Locations of open upvalues point into the stack that is swapped in. Closures can be
+    // called from another context, so the upvalues of the context that is swapped out hold
+    // their values until it is swapped back in.
+    fn switch_context(&mut self, generator: &Generator) {
+        self.hoist_upvalues();
+        let state = generator.state();
+        let mut state = state.borrow_mut();
+        mem::swap(&mut self.frames, &mut state.frames);
+        mem::swap(&mut self.frame_count, &mut state.frame_count);
+        mem::swap(&mut self.stack, &mut state.stack);
+        mem::swap(&mut self.stack_top, &mut state.stack_top);
+        mem::swap(&mut self.open_upvalues, &mut state.open_upvalues);
+        drop(state);
+        self.restore_upvalues();
+    }
+
+    // Closes the open upvalues but keeps them in the list.
+    fn hoist_upvalues(&mut self) {
+        let mut upvalue = self.open_upvalues.as_ref().map(Rc::clone);
+        while let Some(upval) = upvalue {
+            let mut upval = upval.borrow_mut();
+            let value = self.stack[upval.location()].clone();
+            upval.set_closed_value(Box::new(value));
+            upval.set_closed();
+            upvalue = upval.next();
+        }
+    }
+
+    // Moves the values of upvalues hoisted by `hoist_upvalues` back into the stack.
+    fn restore_upvalues(&mut self) {
+        let mut upvalue = self.open_upvalues.as_ref().map(Rc::clone);
+        while let Some(upval) = upvalue {
+            let mut upval = upval.borrow_mut();
+            self.stack[upval.location()] = upval.closed_value();
+            upval.set_closed_value(Box::new(Value::Nil));
+            upval.set_open();
+            upvalue = upval.next();
+        }
+    }
+
     fn capture_upvalue(&mut self, local: usize) -> Rc<RefCell<Upvalue>> {
         let mut prev_upvalue = None;
         let mut upvalue = self.open_upvalues.as_ref().map(Rc::clone);
@@ -559,14 +1124,11 @@ impl VM {
         created_upvalue.set_next(upvalue);
         let created_upvalue = Rc::new(RefCell::new(created_upvalue));
 
-        if prev_upvalue.is_none() {
-            self.open_upvalues = Some(Rc::clone(&created_upvalue));
-        } else {
-            prev_upvalue
-                .as_ref()
-                .unwrap()
-                .borrow_mut()
+        if let Some(prev) = &prev_upvalue {
+            prev.borrow_mut()
                 .set_next(Some(Rc::clone(&created_upvalue)));
+        } else {
+            self.open_upvalues = Some(Rc::clone(&created_upvalue));
         }
 
         created_upvalue
@@ -596,8 +1158,28 @@ impl VM {
         self.pop();
     }
 
+    // Gives control to the debugger, `false` if the user stopped the program.
+    fn debug(&mut self) -> bool {
+        let Some(mut debugger) = self.debugger.take() else {
+            return true;
+        };
+        let is_running = debugger.hook(self);
+        self.debugger = Some(debugger);
+        is_running
+    }
+
+    fn undefined(&self, name: &str) -> String {
+        let names = self.globals.keys().map(String::as_str);
+        match diagnostic::suggest(name, names) {
+            Some(similar) => format!("Undefined variable '{name}'. Did you mean '{similar}'?"),
+            None => format!("Undefined variable '{name}'."),
+        }
+    }
+
     fn runtime_error(&mut self, message: String) {
-        eprintln!("\nRuntimeError: {message}");
+        // The error points to the current instruction, notes show the call stack.
+        let mut error = None;
+        let mut notes = Vec::new();
         for frame in self.frames.iter().take(self.frame_count as usize).rev() {
             let closure = frame.closure.borrow();
             let function = closure.function();
@@ -605,13 +1187,22 @@ impl VM {
             let instruction = frame.ip - 1;
             let chunk = function.chunk();
             let chunk = chunk.borrow();
-            let line = chunk.lines[instruction];
-            eprint!("[line {}] in ", line);
-            if function.name().is_empty() {
-                eprintln!("script");
-            } else {
-                eprintln!("{}()", function.name());
-            }
+            let span = chunk.spans[instruction];
+            let name = match function.name().is_empty() {
+                true => "script".to_string(),
+                false => format!("{}()", function.name()),
+            };
+            notes.push(format!("in {name} at line {}", span.line));
+            error.get_or_insert((span, Rc::clone(&chunk.source)));
+        }
+
+        if let Some((span, source)) = error.filter(|_| !self.config.quiet) {
+            let diagnostic = notes
+                .into_iter()
+                .fold(Diagnostic::new(&message, span), |d, note| {
+                    d.with_note("note", note)
+                });
+            eprint!("{}", diagnostic.render(&source, diagnostic::is_colored()));
         }
 
         if self.config.debug {
@@ -670,9 +1261,12 @@ impl VM {
                         // free Vec with upvalues.
                         ()
                     }
+                    Obj::Enum(_) => (),
                     Obj::Fun(ref mut fun) => {
                         fun.free();
                     }
+                    Obj::Gen(_) => (),
+                    Obj::List(_) => (),
                     Obj::Str(ref mut s) => {
                         s.clear() // This does not do the job.
                                   // s.zeroize();
@@ -686,6 +1280,7 @@ impl VM {
                         //     _ => (),
                         // }
                     }
+                    Obj::Variant(_) => (),
                 }
             }
         } else {
@@ -693,3 +1288,155 @@ impl VM {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{InterpretResult, VM};
+
+    fn globals(source: &str, names: &[&str]) -> Vec<String> {
+        let mut vm = VM::default();
+        assert!(matches!(
+            vm.interpret(source.to_string()),
+            InterpretResult::Ok
+        ));
+        names
+            .iter()
+            .map(|name| vm.globals[*name].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_match_variants() {
+        let shapes = "
+            enum Shape { Circle(radius), Rect(width, height), Empty }
+            fun size(shape) {
+                match (shape) {
+                    case Shape.Rect(width: w, height: h) => return w * h;
+                    case Shape.Circle(radius) => return 3 * radius * radius;
+                    case Shape.Empty => return 0;
+                }
+                return nil;
+            }
+            var circle = size(Shape.Circle(2));
+            var rect = size(Shape.Rect(3, 4));
+            var empty = size(Shape.Empty);
+            var other = size(1);";
+        assert_eq!(
+            globals(shapes, &["circle", "rect", "empty", "other"]),
+            ["12", "12", "0", "nil"]
+        );
+
+        // The jump table keeps the order of the cases, a catch-all takes the rest.
+        let colors = "
+            enum Color { Red, Green, Blue }
+            var names = \"\";
+            for (var i = 0; i < 3; i = i + 1) {
+                match (Color[i]) {
+                    case Color.Green | Color.Blue => names = names + \"cold \";
+                    case Color.Blue => names = names + \"blue \";
+                    case _ => names = names + \"warm \";
+                }
+            }";
+        assert_eq!(globals(colors, &["names"]), ["warm cold cold "]);
+
+        // The table is compiled for the global enum, the tests follow the local name.
+        let shadowed = "
+            enum Color { Red, Green }
+            enum Paint { Red }
+            fun check(Color, value) {
+                match (value) { case Color.Red => return \"red\"; case _ => return \"other\"; }
+            }
+            var paint = check(Paint, Paint.Red);
+            var mixed = check(Color, Paint.Red);
+            var color = check(Color, Color.Red);";
+        assert_eq!(
+            globals(shadowed, &["paint", "mixed", "color"]),
+            ["red", "other", "red"]
+        );
+    }
+
+    #[test]
+    fn test_match_patterns() {
+        let shapes = "
+            enum Size { Small, Big(n) }
+            enum Shape { Circle(radius), Rect(w, h) }
+            fun describe(shape) {
+                match (shape) {
+                    case Shape.Rect(w: 0, h: _) => return \"flat\";
+                    case Shape.Rect(w, h: 1 | 2) => return w;
+                    case Shape.Circle(radius: Size.Big(n)) => return n;
+                    case Shape.Circle(radius: Size.Small | nil) => return \"small\";
+                    case _ => return \"other\";
+                }
+            }
+            var flat = describe(Shape.Rect(0, 5));
+            var thin = describe(Shape.Rect(3, 2));
+            var rect = describe(Shape.Rect(3, 4));
+            var big = describe(Shape.Circle(Size.Big(7)));
+            var small = describe(Shape.Circle(nil));";
+        assert_eq!(
+            globals(shapes, &["flat", "thin", "rect", "big", "small"]),
+            ["flat", "3", "other", "7", "small"]
+        );
+
+        // The table of literals takes the first case of each one, other values go to the tests.
+        let codes = "
+            fun code(c) {
+                match (c) {
+                    case 200 => return \"ok\";
+                    case 301 | -302 => return \"redirect\";
+                    case \"x\" | nil => return \"odd\";
+                    case 200 => return \"never\";
+                    case other => return other;
+                }
+            }
+            var ok = code(200);
+            var redirect = code(-302);
+            var odd = code(nil);
+            var other = code(404);";
+        assert_eq!(
+            globals(codes, &["ok", "redirect", "odd", "other"]),
+            ["ok", "redirect", "odd", "404"]
+        );
+    }
+
+    #[test]
+    fn test_match_errors() {
+        for source in [
+            "var A = 1; match (2) { case A.B => print 1; }",
+            "enum A { B } match (A.B) { case A.C => print 1; }",
+            "enum A { B(x) } match (A.B(1)) { case A.B(y) => print y; }",
+            "enum A { B(x) } match (A.B(1)) { case A.B(y: _) => print 1; }",
+            // No value is an instance of a class in bvm.
+            "var P = 1; match (2) { case P(x) => print x; }",
+            // The table doesn't skip a case that fails at runtime.
+            "var A = 1; match (2) { case 1 => print 1; case A.B => print 2; case 2 => print 3; }",
+        ] {
+            let mut vm = VM::default();
+            let result = vm.interpret(source.to_string());
+            assert!(matches!(result, InterpretResult::RuntimeError), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_upvalues_across_generators() {
+        // The closure is called while the generator that owns `a` is suspended.
+        let source = "
+            fun gen() { var a = \"inside\"; fun get() { return a; } yield get; a = \"changed\"; yield a; }
+            var g = gen(); var get = g();
+            var first = get(); g(); var second = get();";
+        assert_eq!(globals(source, &["first", "second"]), ["inside", "changed"]);
+
+        // The closure is called inside a generator while its owner is suspended.
+        let source = "
+            fun outer() {
+                var b = \"outer\";
+                fun get() { return b; }
+                fun gen(f) { yield f(); b = \"set\"; yield f(); }
+                var g = gen(get);
+                return g() + \" \" + g() + \" \" + b;
+            }
+            var result = outer();";
+        assert_eq!(globals(source, &["result"]), ["outer set set"]);
+    }
+}
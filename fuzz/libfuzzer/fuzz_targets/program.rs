@@ -0,0 +1,14 @@
+// The input drives the choices of the generator, the program is run by both interpreters.
+#![no_main]
+
+use fuzz::generator::Generator;
+use fuzz::harness::{self, Mode};
+use fuzz::rng::Rng;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let program = Generator::new(Rng::from_bytes(data)).program();
+    if let Err(crash) = harness::check(&program, Mode::Run) {
+        panic!("{crash}\n{program}");
+    }
+});
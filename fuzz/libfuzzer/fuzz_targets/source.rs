@@ -0,0 +1,12 @@
+// The input is the code itself, it is only checked, because it may not terminate.
+#![no_main]
+
+use fuzz::harness::{self, Mode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data);
+    if let Err(crash) = harness::check(&source, Mode::Check) {
+        panic!("{crash}");
+    }
+});
@@ -0,0 +1,1259 @@
+//! Generator of random Lox programs.
+//!
+//! Every program is written in a dialect: the part of the language that both interpreters
+//! support, or that part with the classes, mixins and overloaded operators of twi, or with the
+//! generators of bvm. Programs are valid by construction: every variable keeps the type of its
+//! initializer, so expressions are built for a type and only `print` statements sometimes get
+//! a value of a wrong type to reach runtime errors. Every run terminates:
+//!
+//! - loops count up to a small bound and their counters are never assigned in the body,
+//! - a function can only call functions that were declared before it, so there is no
+//!   recursion, and names are never reused, so removing a line can't make a call recursive,
+//! - methods follow the same rule: a class can only use classes declared before it, and an
+//!   overriding method only calls the method of the superclass,
+//! - a budget limits the number of statements that a run may execute, loops, calls and
+//!   resumed generators spend it for every time their body runs.
+
+use crate::rng::Rng;
+
+// Nesting of blocks and functions.
+const MAX_DEPTH: usize = 3;
+const MAX_EXPR_DEPTH: usize = 3;
+const MAX_STATEMENTS: usize = 12;
+const MAX_PARAMS: usize = 3;
+const MAX_LOOP: usize = 4;
+const MAX_MEMBERS: usize = 4;
+// Statements that a run may execute.
+const BUDGET: usize = 2_000;
+// Chance of a value of a wrong type in a `print` statement.
+const MISTAKE_PERCENT: usize = 3;
+
+const VARIANTS: [&str; 4] = ["Red", "Green", "Blue", "Alpha"];
+const WORDS: [&str; 8] = [
+    "",
+    "a",
+    "lox",
+    "fun",
+    "Hello, world!",
+    "\\n",
+    "naïve",
+    "λ → 😀",
+];
+// Special methods of twi with the type that they return, binary operators take one argument.
+const OPERATORS: [(&str, Type); 4] = [
+    ("__add__", Type::Number),
+    ("__lt__", Type::Bool),
+    ("__neg__", Type::Number),
+    ("toString", Type::String),
+];
+
+/// Part of the language that a program uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Common, // what both interpreters support
+    Twi,    // with classes, mixins, getters, statics and overloaded operators
+    Bvm,    // with generators
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Nil,
+    Bool,
+    Number,
+    String,
+    List,            // of numbers, never empty
+    Variant(usize),  // of the enum with the index
+    Fun(usize),      // with the signature of the index
+    Instance(usize), // of exactly the class with the index
+    Gen(usize),      // made by the generator function with the signature of the index
+}
+
+// Types of parameters, variables and return values.
+const VALUE_TYPES: [Type; 5] = [
+    Type::Nil,
+    Type::Bool,
+    Type::Number,
+    Type::String,
+    Type::List,
+];
+
+struct Variable {
+    name: String,
+    ty: Type,
+    is_assignable: bool,
+}
+
+struct Enum {
+    name: String,
+    variants: Vec<Vec<String>>, // payload fields of every variant, they hold numbers
+}
+
+#[derive(Clone)]
+struct Signature {
+    params: Vec<Type>,
+    defaults: usize, // trailing parameters that have a default value
+    has_rest: bool,  // numbers after the parameters are collected into a list
+    ret: Type,
+    cost: usize, // statements that a call executes
+    is_generator: bool,
+}
+
+impl Signature {
+    fn new(ret: Type) -> Self {
+        Signature {
+            params: Vec::new(),
+            defaults: 0,
+            has_rest: false,
+            ret,
+            cost: 0,
+            is_generator: false,
+        }
+    }
+}
+
+// Class or mixin with the members that can be used on it, inherited ones included.
+#[derive(Clone, Default)]
+struct Class {
+    name: String,
+    init: Option<usize>,           // signature of `init`
+    methods: Vec<(String, usize)>, // (name, signature)
+    getters: Vec<(String, usize)>,
+    statics: Vec<(String, usize)>,
+    fields: Vec<(String, Type)>, // class fields
+    operators: Vec<(&'static str, usize)>,
+}
+
+// Function whose body is being generated.
+struct Function {
+    ret: Type,
+    is_generator: bool,
+    cost: usize, // cost of the caller's code before the function
+    repeats: usize,
+}
+
+pub struct Generator {
+    rng: Rng,
+    dialect: Dialect,
+    code: String,
+    indent: usize,
+
+    scopes: Vec<Vec<Variable>>,
+    signatures: Vec<Signature>,
+    enums: Vec<Enum>,
+    classes: Vec<Class>,
+    mixins: Vec<Class>,
+    names: usize, // names that were given out, for unique names
+
+    functions: Vec<Function>,
+    cost: usize,       // statements that the current function or script executes
+    repeats: usize,    // times that the current statement runs in a call of the function
+    is_mistaken: bool, // whether a value of a wrong type may be used
+}
+
+impl Generator {
+    pub fn new(mut rng: Rng) -> Self {
+        let dialect = match rng.below(4) {
+            1 => Dialect::Twi,
+            2 => Dialect::Bvm,
+            _ => Dialect::Common,
+        };
+        Generator {
+            rng,
+            dialect,
+            code: String::new(),
+            indent: 0,
+            scopes: vec![Vec::new()],
+            signatures: Vec::new(),
+            enums: Vec::new(),
+            classes: Vec::new(),
+            mixins: Vec::new(),
+            names: 0,
+            functions: Vec::new(),
+            cost: 0,
+            repeats: 1,
+            is_mistaken: false,
+        }
+    }
+
+    /// Dialect of the program, programs of twi or bvm are errors for the other interpreter.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Random program, the same one for the same choices of `rng`.
+    pub fn program(mut self) -> String {
+        let count = 1 + self.rng.below(MAX_STATEMENTS);
+        for _ in 0..count {
+            if self.rng.chance(10) {
+                self.enum_declaration();
+            } else if self.dialect == Dialect::Twi && self.rng.chance(25) {
+                self.class_declaration();
+            } else {
+                self.declaration(0);
+            }
+        }
+        self.code
+    }
+
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.indent {
+            self.code.push_str("    ");
+        }
+        self.code.push_str(line);
+        self.code.push('\n');
+    }
+
+    fn name(&mut self, prefix: &str) -> String {
+        self.names += 1;
+        format!("{prefix}{}", self.names - 1)
+    }
+
+    fn define(&mut self, name: &str, ty: Type, is_assignable: bool) {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("There is always a global scope.");
+        scope.push(Variable {
+            name: name.to_string(),
+            ty,
+            is_assignable,
+        });
+    }
+
+    // Whether a statement that runs `repeats` times and executes `cost` statements each time
+    // fits into the budget, spends the budget if it does.
+    fn spend(&mut self, cost: usize) -> bool {
+        let cost = cost.saturating_mul(self.repeats);
+        if self.cost.saturating_add(cost) > BUDGET {
+            return false;
+        }
+        self.cost += cost;
+        true
+    }
+
+    fn value_type(&mut self) -> Type {
+        *self.rng.choose(&VALUE_TYPES)
+    }
+
+    // Type of a variable or a printed value, instances are only used in twi.
+    fn variable_type(&mut self) -> Type {
+        if self.dialect == Dialect::Twi && !self.classes.is_empty() && self.rng.chance(20) {
+            return Type::Instance(self.rng.below(self.classes.len()));
+        }
+        self.value_type()
+    }
+
+    fn variables(&self, is_match: impl Fn(&Variable) -> bool) -> Vec<(String, Type)> {
+        self.scopes
+            .iter()
+            .flatten()
+            .filter(|variable| is_match(variable))
+            .map(|variable| (variable.name.clone(), variable.ty))
+            .collect()
+    }
+
+    fn block(&mut self, header: &str, depth: usize) {
+        match header.is_empty() {
+            true => self.open("{", depth),
+            false => self.open(&format!("{header} {{"), depth),
+        }
+    }
+
+    // Block that starts with the line, which must open it.
+    fn open(&mut self, line: &str, depth: usize) {
+        self.line(line);
+        self.indent += 1;
+        self.scopes.push(Vec::new());
+        let count = self.rng.below(4);
+        for _ in 0..count {
+            self.declaration(depth + 1);
+        }
+        self.scopes.pop();
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn declaration(&mut self, depth: usize) {
+        if !self.spend(1) {
+            return;
+        }
+        match self.rng.below(10) {
+            0 | 1 if depth < MAX_DEPTH => self.fun_declaration(depth),
+            2 | 3 => self.var_declaration(),
+            _ => self.statement(depth),
+        }
+    }
+
+    fn enum_declaration(&mut self) {
+        let name = self.name("E");
+        let count = 1 + self.rng.below(VARIANTS.len());
+        let mut variants = Vec::new();
+        let mut declarations = Vec::new();
+        for variant in &VARIANTS[..count] {
+            let fields: Vec<String> = (0..self.rng.below(3)).map(|_| self.name("q")).collect();
+            match fields.is_empty() {
+                true => declarations.push(variant.to_string()),
+                false => declarations.push(format!("{variant}({})", fields.join(", "))),
+            }
+            variants.push(fields);
+        }
+        self.line(&format!("enum {name} {{ {} }}", declarations.join(", ")));
+        self.enums.push(Enum { name, variants });
+    }
+
+    fn var_declaration(&mut self) {
+        // Closures are stored to be called later, like in `var counter = makeCounter();`.
+        if self.rng.chance(20) {
+            if let Some((closure, ty)) = self.closure() {
+                let name = self.name("v");
+                self.line(&format!("var {name} = {closure};"));
+                return self.define(&name, ty, false);
+            }
+        }
+        if self.dialect == Dialect::Bvm && self.rng.chance(20) {
+            if let Some((generator, ty)) = self.generator() {
+                let name = self.name("v");
+                self.line(&format!("var {name} = {generator};"));
+                return self.define(&name, ty, false);
+            }
+        }
+
+        let ty = self.variable_type();
+        let value = self.expr(ty, 0);
+        let (keyword, prefix, is_assignable) = match self.rng.chance(20) {
+            true => ("const", "c", false),
+            false => ("var", "v", true),
+        };
+        let name = self.name(prefix);
+        self.line(&format!("{keyword} {name} = {value};"));
+        self.define(&name, ty, is_assignable);
+    }
+
+    // Parameter list of a new function with its signature, the call cost is filled in later.
+    // The names are defined in the scope of the function by `begin_function`.
+    fn parameters(&mut self) -> (String, Vec<String>, Signature) {
+        let params: Vec<Type> = (0..self.rng.below(MAX_PARAMS + 1))
+            .map(|_| self.value_type())
+            .collect();
+        let signature = Signature {
+            defaults: self.rng.below(params.len() + 1),
+            has_rest: self.rng.chance(15),
+            params,
+            ..Signature::new(Type::Nil)
+        };
+        let names = self.parameter_names(&signature);
+        let list = self.parameter_list(&names, &signature);
+        (list, names, signature)
+    }
+
+    fn parameter_names(&mut self, signature: &Signature) -> Vec<String> {
+        let mut names: Vec<String> = signature.params.iter().map(|_| self.name("p")).collect();
+        if signature.has_rest {
+            names.push(self.name("r"));
+        }
+        names
+    }
+
+    // Parameters get a literal as the default value, the rest parameter is the last name.
+    fn parameter_list(&mut self, names: &[String], signature: &Signature) -> String {
+        let required = signature.params.len() - signature.defaults;
+        let mut list = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            match signature.params.get(i) {
+                Some(ty) if i >= required => list.push(format!("{name} = {}", self.literal(*ty))),
+                Some(_) => list.push(name.clone()),
+                None => list.push(format!("...{name}")),
+            }
+        }
+        list.join(", ")
+    }
+
+    // Opens the body of a function after the line that starts it.
+    fn begin_function(&mut self, line: &str, names: &[String], signature: &Signature) {
+        self.functions.push(Function {
+            ret: signature.ret,
+            is_generator: signature.is_generator,
+            cost: self.cost,
+            repeats: self.repeats,
+        });
+        self.cost = 0;
+        self.repeats = 1;
+
+        self.line(line);
+        self.indent += 1;
+        self.scopes.push(Vec::new());
+        // The rest parameter always gets some numbers, so it is a list like the others.
+        let types = signature.params.iter().copied().chain([Type::List]);
+        for (name, ty) in names.iter().zip(types) {
+            self.define(name, ty, true);
+        }
+    }
+
+    // Closes the body of a function, returns the cost of a call.
+    fn end_function(&mut self) -> usize {
+        self.scopes.pop();
+        self.indent -= 1;
+        self.line("}");
+
+        let function = self.functions.pop().expect("Function was pushed.");
+        let cost = self.cost + 1;
+        self.cost = function.cost;
+        self.repeats = function.repeats;
+        cost
+    }
+
+    // Statements of a function body that end with a `return` of the signature's type.
+    fn body(&mut self, signature: &Signature, depth: usize) {
+        let count = self.rng.below(5);
+        for _ in 0..count {
+            self.declaration(depth + 1);
+        }
+        if signature.is_generator {
+            let value = self.expr(signature.ret, 0);
+            self.line(&format!("yield {value};"));
+        }
+        if signature.ret != Type::Nil || self.rng.chance(50) {
+            let value = self.expr(signature.ret, 0);
+            self.line(&format!("return {value};"));
+        }
+    }
+
+    fn fun_declaration(&mut self, depth: usize) {
+        let name = self.name("f");
+        let (params, names, mut signature) = self.parameters();
+        let returns_closure = depth + 1 < MAX_DEPTH && self.rng.chance(25);
+        signature.ret = match returns_closure {
+            true => Type::Nil, // the type of the closure isn't known yet
+            false => self.value_type(),
+        };
+        signature.is_generator =
+            !returns_closure && self.dialect == Dialect::Bvm && self.rng.chance(30);
+
+        let line = format!("fun {name}({params}) {{");
+        self.begin_function(&line, &names, &signature);
+        if returns_closure {
+            self.fun_declaration(depth + 1);
+            let scope = self.scopes.last().expect("Function has a scope.");
+            let closure = scope.last().expect("Closure was just declared.");
+            let line = format!("return {};", closure.name);
+            signature.ret = closure.ty;
+            self.line(&line);
+        } else {
+            self.body(&signature, depth);
+        }
+        signature.cost = self.end_function();
+
+        self.signatures.push(signature);
+        self.define(&name, Type::Fun(self.signatures.len() - 1), false);
+    }
+
+    // Method, static method (`class name`) or getter (without parameters) with the body of
+    // a function, returns its signature.
+    fn method(&mut self, header: &str, has_params: bool) -> usize {
+        let (line, names, mut signature) = match has_params {
+            true => {
+                let (list, names, signature) = self.parameters();
+                (format!("{header}({list}) {{"), names, signature)
+            }
+            false => (
+                format!("{header} {{"),
+                Vec::new(),
+                Signature::new(Type::Nil),
+            ),
+        };
+        signature.ret = self.value_type();
+
+        self.begin_function(&line, &names, &signature);
+        self.body(&signature, 0);
+        signature.cost = self.end_function();
+        self.signatures.push(signature);
+        self.signatures.len() - 1
+    }
+
+    fn mixin_declaration(&mut self) {
+        let name = self.name("M");
+        self.line(&format!("mixin {name} {{"));
+        self.indent += 1;
+        let mut mixin = Class {
+            name,
+            ..Class::default()
+        };
+        for _ in 0..1 + self.rng.below(MAX_MEMBERS) {
+            if self.rng.chance(70) {
+                let method = self.name("k");
+                let signature = self.method(&method, true);
+                mixin.methods.push((method, signature));
+            } else {
+                let getter = self.name("g");
+                let signature = self.method(&getter, false);
+                mixin.getters.push((getter, signature));
+            }
+        }
+        self.indent -= 1;
+        self.line("}");
+        self.mixins.push(mixin);
+    }
+
+    // Class of twi with members of every kind. Its own instances can't be used in its body,
+    // so that `init` and the methods can't reach themselves.
+    fn class_declaration(&mut self) {
+        if !self.spend(1) {
+            return;
+        }
+        if self.rng.chance(30) {
+            return self.mixin_declaration();
+        }
+
+        let name = self.name("C");
+        let mut class = Class {
+            name: name.clone(),
+            ..Class::default()
+        };
+        let mut header = format!("class {name}");
+        if !self.classes.is_empty() && self.rng.chance(40) {
+            let superclass = self.rng.below(self.classes.len());
+            let superclass = &self.classes[superclass];
+            header.push_str(&format!(" < {}", superclass.name));
+            class.init = superclass.init;
+            class.methods = superclass.methods.clone();
+            class.getters = superclass.getters.clone();
+            class.operators = superclass.operators.clone();
+        }
+        let inherited = class.methods.len();
+        if !self.mixins.is_empty() && self.rng.chance(40) {
+            let mut mixins: Vec<usize> = (0..1 + self.rng.below(2))
+                .map(|_| self.rng.below(self.mixins.len()))
+                .collect();
+            mixins.sort();
+            mixins.dedup();
+            let names: Vec<&str> = mixins.iter().map(|&i| &*self.mixins[i].name).collect();
+            header.push_str(&format!(" with {}", names.join(", ")));
+            for i in mixins {
+                let mixin = &self.mixins[i];
+                class.methods.extend(mixin.methods.iter().cloned());
+                class.getters.extend(mixin.getters.iter().cloned());
+            }
+        }
+        self.line(&format!("{header} {{"));
+        self.indent += 1;
+
+        if self.rng.chance(40) {
+            let (list, names, mut signature) = self.parameters();
+            self.begin_function(&format!("init({list}) {{"), &names, &signature);
+            let count = self.rng.below(4);
+            for _ in 0..count {
+                self.declaration(1);
+            }
+            signature.cost = self.end_function();
+            self.signatures.push(signature);
+            class.init = Some(self.signatures.len() - 1);
+        }
+
+        for _ in 0..self.rng.below(MAX_MEMBERS + 1) {
+            match self.rng.below(4) {
+                0 => {
+                    let method = self.name("k");
+                    let signature = self.method(&method, true);
+                    class.methods.push((method, signature));
+                }
+                1 => {
+                    let getter = self.name("g");
+                    let signature = self.method(&getter, false);
+                    class.getters.push((getter, signature));
+                }
+                2 => {
+                    let method = self.name("s");
+                    let signature = self.method(&format!("class {method}"), true);
+                    class.statics.push((method, signature));
+                }
+                _ => {
+                    let field = self.name("a");
+                    let ty = self.value_type();
+                    let value = self.literal(ty);
+                    self.line(&format!("class {field} = {value};"));
+                    class.fields.push((field, ty));
+                }
+            }
+        }
+
+        if inherited > 0 && self.rng.chance(30) {
+            self.override_method(&mut class, inherited);
+        }
+
+        for (operator, ret) in OPERATORS {
+            if !self.rng.chance(25) {
+                continue;
+            }
+            // The other operand isn't used, since it is an instance of this class.
+            let param = match operator {
+                "__add__" | "__lt__" => self.name("p"),
+                _ => String::new(),
+            };
+            let mut signature = Signature::new(ret);
+            let line = format!("{operator}({param}) {{");
+            self.begin_function(&line, &[], &signature);
+            self.body(&signature, 0);
+            signature.cost = self.end_function();
+            self.signatures.push(signature);
+            let index = self.signatures.len() - 1;
+            class.operators.retain(|(other, _)| *other != operator);
+            class.operators.push((operator, index));
+        }
+
+        self.indent -= 1;
+        self.line("}");
+        self.classes.push(class);
+    }
+
+    // Method that calls one of the first `inherited` methods of the class, which come from the
+    // superclass, through `super` with the same arguments.
+    fn override_method(&mut self, class: &mut Class, inherited: usize) {
+        let i = self.rng.below(inherited);
+        let (name, index) = class.methods[i].clone();
+        let mut signature = self.signatures[index].clone();
+
+        let names = self.parameter_names(&signature);
+        let list = self.parameter_list(&names, &signature);
+        let mut arguments = names.clone();
+        if signature.has_rest {
+            let rest = arguments.pop().expect("Rest parameter is the last one.");
+            arguments.push(format!("...{rest}"));
+        }
+
+        self.line(&format!("{name}({list}) {{"));
+        self.line(&format!(
+            "    return super.{name}({});",
+            arguments.join(", ")
+        ));
+        self.line("}");
+        signature.cost += 1;
+        self.signatures.push(signature);
+        class.methods[i].1 = self.signatures.len() - 1;
+    }
+
+    // Call of a function that returns a closure and the type of the closure.
+    fn closure(&mut self) -> Option<(String, Type)> {
+        let functions = self.variables(|variable| match variable.ty {
+            Type::Fun(index) => matches!(self.signatures[index].ret, Type::Fun(_)),
+            _ => false,
+        });
+        if functions.is_empty() {
+            return None;
+        }
+        let (_, Type::Fun(index)) = *self.rng.choose(&functions) else {
+            unreachable!("Only functions are chosen.");
+        };
+        let ret = self.signatures[index].ret;
+        let call = self.call(Some(ret), 0)?;
+        Some((call, ret))
+    }
+
+    // Call of a generator function, which doesn't run its body yet, and the type of the
+    // generator.
+    fn generator(&mut self) -> Option<(String, Type)> {
+        let functions = self.variables(|variable| match variable.ty {
+            Type::Fun(index) => self.signatures[index].is_generator,
+            _ => false,
+        });
+        if functions.is_empty() {
+            return None;
+        }
+        let (name, ty) = self.rng.choose(&functions).clone();
+        let Type::Fun(index) = ty else {
+            unreachable!("Only functions are chosen.");
+        };
+        let arguments = self.arguments(index, 0);
+        Some((format!("{name}({arguments})"), Type::Gen(index)))
+    }
+
+    fn statement(&mut self, depth: usize) {
+        let is_nested = depth < MAX_DEPTH;
+        let function = self.functions.last();
+        let is_generator = function.is_some_and(|function| function.is_generator);
+        match self.rng.below(13) {
+            0 | 1 => {
+                self.is_mistaken = true;
+                let mut ty = self.variable_type();
+                if let Type::Instance(class) = ty {
+                    // Printing an instance calls its `toString`.
+                    if !self.spend_operator(class, "toString") {
+                        ty = Type::Nil;
+                    }
+                }
+                let value = self.expr(ty, 0);
+                self.is_mistaken = false;
+                self.line(&format!("print {value};"));
+            }
+            2 | 3 => self.assignment(),
+            4 => match self.call(None, 0) {
+                Some(call) => self.line(&format!("{call};")),
+                None => self.line("nil;"),
+            },
+            5 if is_nested => self.if_statement(depth),
+            6 if is_nested => self.for_statement(depth),
+            7 if is_nested => self.while_statement(depth),
+            8 if is_nested => self.match_statement(depth),
+            9 if is_nested => self.block("", depth),
+            10 if !self.functions.is_empty() => {
+                let ret = self.functions.last().expect("Inside a function.").ret;
+                let condition = self.expr(Type::Bool, 0);
+                let value = self.expr(ret, 0);
+                self.line(&format!("if ({condition}) return {value};"));
+            }
+            // The value sent by the resumer isn't used, since its type isn't known.
+            11 if is_generator => {
+                let ret = self.functions.last().expect("Inside a function.").ret;
+                let value = self.expr(ret, 0);
+                match self.rng.chance(30) {
+                    true => {
+                        let name = self.name("y");
+                        self.line(&format!("var {name} = yield {value};"));
+                    }
+                    false => self.line(&format!("yield {value};")),
+                }
+            }
+            _ => {
+                let value = self.expr(Type::Number, 0);
+                self.line(&format!("{value};"));
+            }
+        }
+    }
+
+    fn assignment(&mut self) {
+        let variables = self.variables(|variable| variable.is_assignable);
+        if variables.is_empty() {
+            return self.var_declaration();
+        }
+        let (name, ty) = self.rng.choose(&variables).clone();
+        let value = self.expr(ty, 0);
+        self.line(&format!("{name} = {value};"));
+    }
+
+    fn if_statement(&mut self, depth: usize) {
+        let condition = self.expr(Type::Bool, 0);
+        self.block(&format!("if ({condition})"), depth);
+        if self.rng.chance(50) {
+            self.block("else", depth);
+        }
+    }
+
+    fn for_statement(&mut self, depth: usize) {
+        let counter = self.name("i");
+        let bound = self.rng.below(MAX_LOOP + 1);
+        let repeats = self.repeats;
+        self.repeats = repeats.saturating_mul(bound.max(1));
+        self.scopes.push(Vec::new());
+        self.define(&counter, Type::Number, false);
+        self.block(
+            &format!("for (var {counter} = 0; {counter} < {bound}; {counter} = {counter} + 1)"),
+            depth,
+        );
+        self.scopes.pop();
+        self.repeats = repeats;
+    }
+
+    // The counter is incremented on the line of the loop, so that removing a line of the
+    // body while minimizing can't make the loop infinite.
+    fn while_statement(&mut self, depth: usize) {
+        let counter = self.name("w");
+        let bound = self.rng.below(MAX_LOOP + 1);
+        self.line(&format!("var {counter} = 0;"));
+        self.define(&counter, Type::Number, false);
+        let repeats = self.repeats;
+        self.repeats = repeats.saturating_mul(bound.max(1));
+        self.open(
+            &format!("while ({counter} < {bound}) {{ {counter} = {counter} + 1;"),
+            depth,
+        );
+        self.repeats = repeats;
+    }
+
+    fn match_statement(&mut self, depth: usize) {
+        let mut types = vec![Type::Bool, Type::Number, Type::String];
+        if !self.enums.is_empty() {
+            types.push(Type::Variant(self.rng.below(self.enums.len())));
+        }
+        let ty = *self.rng.choose(&types);
+        let value = self.expr(ty, 0);
+        self.line(&format!("match ({value}) {{"));
+        self.indent += 1;
+        for _ in 0..self.rng.below(3) {
+            self.scopes.push(Vec::new());
+            let mut pattern = self.pattern(ty, true);
+            if self.rng.chance(30) {
+                // Alternatives can't bind names.
+                self.scopes.last_mut().expect("Case has a scope.").clear();
+                pattern = format!("{} | {}", self.pattern(ty, false), self.pattern(ty, false));
+            }
+            self.case(&pattern, ty, depth);
+            self.scopes.pop();
+        }
+        match self.rng.below(3) {
+            0 => self.case("_", ty, depth),
+            1 => {
+                let name = self.name("m");
+                self.scopes.push(Vec::new());
+                self.define(&name, ty, false);
+                self.case(&name, ty, depth);
+                self.scopes.pop();
+            }
+            _ => (),
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    // Pattern of a value of the type, names that a variant pattern binds are defined in the
+    // current scope.
+    fn pattern(&mut self, ty: Type, is_binding: bool) -> String {
+        let Type::Variant(index) = ty else {
+            return self.literal(ty);
+        };
+        let variant = self.rng.below(self.enums[index].variants.len());
+        let name = format!("{}.{}", self.enums[index].name, VARIANTS[variant]);
+        let fields = self.enums[index].variants[variant].clone();
+        if fields.is_empty() || self.rng.chance(30) {
+            return name;
+        }
+
+        // Some of the fields, each one binds its own name, another name or nothing.
+        let mut patterns = Vec::new();
+        for field in &fields {
+            if !self.rng.chance(70) {
+                continue;
+            }
+            match self.rng.below(3) {
+                0 if is_binding => {
+                    patterns.push(field.clone());
+                    self.define(field, Type::Number, false);
+                }
+                1 if is_binding => {
+                    let binding = self.name("m");
+                    patterns.push(format!("{field}: {binding}"));
+                    self.define(&binding, Type::Number, false);
+                }
+                _ => patterns.push(format!("{field}: _")),
+            }
+        }
+        if patterns.is_empty() {
+            return name;
+        }
+        format!("{name}({})", patterns.join(", "))
+    }
+
+    fn case(&mut self, pattern: &str, ty: Type, depth: usize) {
+        if self.rng.chance(50) {
+            let value = self.expr(ty, 0);
+            self.line(&format!("case {pattern} => print {value};"));
+        } else {
+            self.block(&format!("case {pattern} =>"), depth);
+        }
+    }
+
+    fn literal(&mut self, ty: Type) -> String {
+        match ty {
+            Type::Nil => "nil".to_string(),
+            Type::Bool => self.rng.choose(&["true", "false"]).to_string(),
+            Type::Number => {
+                let number = self.rng.below(20) as isize - 5;
+                match self.rng.below(4) {
+                    0 => format!("{number}.5"),
+                    _ => number.to_string(),
+                }
+            }
+            Type::String => format!("\"{}\"", self.rng.choose(&WORDS)),
+            Type::List => {
+                let count = 1 + self.rng.below(4);
+                let numbers: Vec<String> = (0..count).map(|_| self.literal(Type::Number)).collect();
+                format!("[{}]", numbers.join(", "))
+            }
+            Type::Variant(index) => {
+                let variant = self.rng.below(self.enums[index].variants.len());
+                let count = self.enums[index].variants[variant].len();
+                let payload: Vec<String> = (0..count).map(|_| self.literal(Type::Number)).collect();
+                self.variant(index, variant, &payload)
+            }
+            Type::Fun(_) | Type::Instance(_) | Type::Gen(_) => {
+                unreachable!("Functions, instances and generators have no literals.")
+            }
+        }
+    }
+
+    // Variant of an enum, constructed with the payload if it has fields.
+    fn variant(&self, index: usize, variant: usize, payload: &[String]) -> String {
+        let name = format!("{}.{}", self.enums[index].name, VARIANTS[variant]);
+        match payload.is_empty() {
+            true => name,
+            false => format!("{name}({})", payload.join(", ")),
+        }
+    }
+
+    // Arguments of a call of the function with the signature. Parameters with a default
+    // value may be left out, and the rest parameter gets at least one number otherwise.
+    fn arguments(&mut self, index: usize, depth: usize) -> String {
+        // Arguments are stored, so they must have the right types.
+        let is_mistaken = std::mem::replace(&mut self.is_mistaken, false);
+        let signature = &self.signatures[index];
+        let (mut params, defaults) = (signature.params.clone(), signature.defaults);
+        let has_rest = signature.has_rest && !self.rng.chance(20);
+        if !has_rest {
+            let omitted = self.rng.below(defaults + 1);
+            params.truncate(params.len() - omitted);
+        }
+        if is_mistaken && self.rng.chance(MISTAKE_PERCENT) {
+            params.pop();
+        }
+
+        let mut arguments: Vec<String> =
+            params.iter().map(|ty| self.expr(*ty, depth + 1)).collect();
+        if has_rest && params.len() == self.signatures[index].params.len() {
+            match self.rng.chance(50) {
+                true => arguments.push(format!("...{}", self.expr(Type::List, depth + 1))),
+                false => {
+                    for _ in 0..1 + self.rng.below(2) {
+                        arguments.push(self.expr(Type::Number, depth + 1));
+                    }
+                }
+            }
+        }
+        self.is_mistaken = is_mistaken;
+        arguments.join(", ")
+    }
+
+    // Call of a function that returns `ty` (any if `None`), if there is one that fits into
+    // the budget.
+    fn call(&mut self, ty: Option<Type>, depth: usize) -> Option<String> {
+        let functions = self.variables(|variable| match variable.ty {
+            Type::Fun(index) => {
+                let signature = &self.signatures[index];
+                !signature.is_generator && ty.is_none_or(|ty| signature.ret == ty)
+            }
+            _ => false,
+        });
+        if functions.is_empty() {
+            return None;
+        }
+        let (name, ty) = self.rng.choose(&functions).clone();
+        let Type::Fun(index) = ty else {
+            unreachable!("Only functions are chosen.");
+        };
+        if !self.spend(self.signatures[index].cost) {
+            return None;
+        }
+        let arguments = self.arguments(index, depth);
+        Some(format!("{name}({arguments})"))
+    }
+
+    // Resumption of a generator that yields `ty`, or its status for a string.
+    fn resume(&mut self, ty: Type, depth: usize) -> Option<String> {
+        let generators = self.variables(|variable| match variable.ty {
+            Type::Gen(index) => ty == Type::String || self.signatures[index].ret == ty,
+            _ => false,
+        });
+        if generators.is_empty() {
+            return None;
+        }
+        let (name, generator) = self.rng.choose(&generators).clone();
+        let Type::Gen(index) = generator else {
+            unreachable!("Only generators are chosen.");
+        };
+        if self.signatures[index].ret != ty || self.rng.chance(30) {
+            return Some(format!("status({name})"));
+        }
+        if !self.spend(self.signatures[index].cost) {
+            return None;
+        }
+        match self.rng.chance(50) {
+            true => Some(format!("{name}()")),
+            false => Some(format!("{name}({})", self.expr(ty, depth))),
+        }
+    }
+
+    // Instance of the class, `nil` if `init` doesn't fit into the budget.
+    fn construct(&mut self, class: usize, depth: usize) -> String {
+        let name = self.classes[class].name.clone();
+        let Some(init) = self.classes[class].init else {
+            return format!("{name}()");
+        };
+        if !self.spend(self.signatures[init].cost) {
+            return "nil".to_string();
+        }
+        format!("{name}({})", self.arguments(init, depth))
+    }
+
+    fn spend_operator(&mut self, class: usize, operator: &str) -> bool {
+        let operators = &self.classes[class].operators;
+        match operators.iter().find(|(other, _)| *other == operator) {
+            Some(&(_, index)) => self.spend(self.signatures[index].cost),
+            None => true,
+        }
+    }
+
+    // Use of a member of a class or an overloaded operator that gives `ty`, if there is one
+    // that fits into the budget.
+    fn member(&mut self, ty: Type, depth: usize) -> Option<String> {
+        // (class, kind, name, signature)
+        let mut members: Vec<(usize, &str, String, Option<usize>)> = Vec::new();
+        for (i, class) in self.classes.iter().enumerate() {
+            let ret = |(_, index): &&(String, usize)| self.signatures[*index].ret == ty;
+            let signatures = [
+                ("method", &class.methods),
+                ("getter", &class.getters),
+                ("static", &class.statics),
+            ];
+            for (kind, list) in signatures {
+                for (name, index) in list.iter().filter(ret) {
+                    members.push((i, kind, name.clone(), Some(*index)));
+                }
+            }
+            for (name, _) in class.fields.iter().filter(|(_, other)| *other == ty) {
+                members.push((i, "field", name.clone(), None));
+            }
+            for &(operator, index) in &class.operators {
+                if self.signatures[index].ret == ty {
+                    members.push((i, "operator", operator.to_string(), Some(index)));
+                }
+            }
+        }
+        if members.is_empty() {
+            return None;
+        }
+
+        let (class, kind, name, index) = self.rng.choose(&members).clone();
+        if let Some(index) = index {
+            if !self.spend(self.signatures[index].cost) {
+                return None;
+            }
+        }
+        let class_name = self.classes[class].name.clone();
+        let instance = Type::Instance(class);
+        let member = match (kind, name.as_str()) {
+            ("method", _) => {
+                let object = self.expr(instance, depth);
+                let arguments = self.arguments(index?, depth);
+                format!("{object}.{name}({arguments})")
+            }
+            ("getter", _) => format!("{}.{name}", self.expr(instance, depth)),
+            ("static", _) => format!("{class_name}.{name}({})", self.arguments(index?, depth)),
+            ("field", _) => format!("{class_name}.{name}"),
+            (_, "__add__") => {
+                let left = self.expr(instance, depth);
+                let right = self.expr(instance, depth);
+                format!("({left} + {right})")
+            }
+            (_, "__lt__") => {
+                let left = self.expr(instance, depth);
+                let right = self.expr(instance, depth);
+                format!("({left} < {right})")
+            }
+            (_, "__neg__") => format!("-{}", self.expr(instance, depth)),
+            _ => {
+                let left = self.literal(Type::String);
+                let right = self.expr(instance, depth);
+                format!("{left} + {right}")
+            }
+        };
+        Some(member)
+    }
+
+    fn expr(&mut self, mut ty: Type, depth: usize) -> String {
+        if self.is_mistaken && self.rng.chance(MISTAKE_PERCENT) {
+            ty = self.value_type();
+        }
+        let is_leaf = depth >= MAX_EXPR_DEPTH || self.rng.chance(30);
+        let depth = depth + 1;
+
+        // Variables, calls, members and generators can give any type.
+        match self.rng.below(4) {
+            0 => {
+                let variables = self.variables(|variable| variable.ty == ty);
+                if !variables.is_empty() {
+                    return self.rng.choose(&variables).0.clone();
+                }
+            }
+            1 if !is_leaf => {
+                if let Some(call) = self.call(Some(ty), depth) {
+                    return call;
+                }
+            }
+            2 if !is_leaf && self.dialect == Dialect::Twi => {
+                if let Some(member) = self.member(ty, depth) {
+                    return member;
+                }
+            }
+            3 if !is_leaf && self.dialect == Dialect::Bvm => {
+                if let Some(resume) = self.resume(ty, depth) {
+                    return resume;
+                }
+            }
+            _ => (),
+        }
+        if is_leaf {
+            return match ty {
+                Type::Fun(_) | Type::Gen(_) => "nil".to_string(),
+                Type::Instance(class) => self.construct(class, depth),
+                _ => self.literal(ty),
+            };
+        }
+
+        match ty {
+            Type::Bool => match self.rng.below(5) {
+                0 => format!("!{}", self.expr(Type::Bool, depth)),
+                1 => {
+                    let operator = self.rng.choose(&["<", "<=", ">", ">="]);
+                    let left = self.expr(Type::Number, depth);
+                    let right = self.expr(Type::Number, depth);
+                    format!("({left} {operator} {right})")
+                }
+                2 => {
+                    let operator = self.rng.choose(&["==", "!="]);
+                    let operand = self.value_type();
+                    let left = self.expr(operand, depth);
+                    let right = self.expr(operand, depth);
+                    format!("({left} {operator} {right})")
+                }
+                3 => {
+                    let operator = self.rng.choose(&["and", "or"]);
+                    let left = self.expr(Type::Bool, depth);
+                    let right = self.expr(Type::Bool, depth);
+                    format!("({left} {operator} {right})")
+                }
+                _ => {
+                    let enums = self.enums.len();
+                    if enums == 0 {
+                        return self.literal(Type::Bool);
+                    }
+                    let operand = Type::Variant(self.rng.below(enums));
+                    let left = self.literal(operand);
+                    let right = self.literal(operand);
+                    format!("({left} == {right})")
+                }
+            },
+            Type::Number => match self.rng.below(6) {
+                0 => format!("-{}", self.expr(Type::Number, depth)),
+                1 => format!("len({})", self.expr(Type::List, depth)),
+                2 => format!("{}[0]", self.expr(Type::List, depth)),
+                3 => self.payload(depth),
+                _ => {
+                    let operator = self.rng.choose(&["+", "-", "*", "/"]);
+                    let left = self.expr(Type::Number, depth);
+                    let right = self.expr(Type::Number, depth);
+                    format!("({left} {operator} {right})")
+                }
+            },
+            // A literal is added, never another string, so strings grow by at most a literal
+            // per executed statement.
+            Type::String => {
+                let left = self.expr(Type::String, depth);
+                let right = self.literal(Type::String);
+                format!("{left} + {right}")
+            }
+            Type::List => {
+                let count = 1 + self.rng.below(3);
+                let numbers: Vec<String> =
+                    (0..count).map(|_| self.expr(Type::Number, depth)).collect();
+                format!("[{}]", numbers.join(", "))
+            }
+            Type::Variant(index) => {
+                let variant = self.rng.below(self.enums[index].variants.len());
+                let count = self.enums[index].variants[variant].len();
+                let payload: Vec<String> =
+                    (0..count).map(|_| self.expr(Type::Number, depth)).collect();
+                self.variant(index, variant, &payload)
+            }
+            Type::Instance(class) => self.construct(class, depth),
+            Type::Nil => self.literal(ty),
+            Type::Fun(_) | Type::Gen(_) => "nil".to_string(),
+        }
+    }
+
+    // Payload field of a constructed variant, a number literal if no enum has fields.
+    fn payload(&mut self, depth: usize) -> String {
+        let mut variants = Vec::new();
+        for (index, enumeration) in self.enums.iter().enumerate() {
+            for (variant, fields) in enumeration.variants.iter().enumerate() {
+                variants.extend(fields.iter().map(|field| (index, variant, field.clone())));
+            }
+        }
+        if variants.is_empty() {
+            return self.literal(Type::Number);
+        }
+        let (index, variant, field) = self.rng.choose(&variants).clone();
+        let count = self.enums[index].variants[variant].len();
+        let payload: Vec<String> = (0..count).map(|_| self.expr(Type::Number, depth)).collect();
+        format!("{}.{field}", self.variant(index, variant, &payload))
+    }
+}
+
+#[cfg(test)]
+mod test_generator {
+    use super::{Dialect, Generator};
+    use crate::rng::Rng;
+    use bvm::compiler::Parser;
+    use twi::resolver::lint::lint;
+
+    #[test]
+    fn test_programs_compile() {
+        // bvm has no type checker, so values of wrong types don't stop the compilation, and
+        // twi only parses and resolves the programs for the same reason.
+        for seed in 0..100 {
+            let generator = Generator::new(Rng::seeded(seed));
+            let dialect = generator.dialect();
+            let program = generator.program();
+            if dialect != Dialect::Twi {
+                let mut parser = Parser::new(bvm::Config::new());
+                assert!(parser.compile(program.clone()).is_ok(), "{program}");
+            }
+            if dialect != Dialect::Bvm {
+                assert!(lint(&program).is_ok(), "{program}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_features() {
+        let programs: String = (0..300)
+            .map(|seed| Generator::new(Rng::seeded(seed)).program())
+            .collect();
+        let features = [
+            "enum E",
+            "Green(q",
+            ".q",
+            "case E",
+            ": _",
+            "...r",
+            "(...",
+            "class C",
+            " < C",
+            " with M",
+            "mixin M",
+            "class s",
+            "class a",
+            "return super.",
+            "__add__",
+            "__lt__",
+            "__neg__",
+            "toString",
+            "yield ",
+            "status(",
+        ];
+        for feature in features {
+            assert!(programs.contains(feature), "{feature}");
+        }
+        // Default values are only written in parameter lists.
+        let is_default = |line: &str| line.trim_start().starts_with("fun ") && line.contains(" = ");
+        assert!(programs.lines().any(is_default));
+    }
+
+    #[test]
+    fn test_same_choices() {
+        let program = |rng| Generator::new(rng).program();
+        assert_eq!(program(Rng::seeded(7)), program(Rng::seeded(7)));
+        assert_eq!(
+            program(Rng::from_bytes(&[1, 2, 3])),
+            program(Rng::from_bytes(&[1, 2, 3]))
+        );
+        // The simplest choices when the bytes run out.
+        assert_eq!(program(Rng::from_bytes(&[])), "enum E0 { Red }\n");
+    }
+}
@@ -0,0 +1,184 @@
+//! Runs code through both interpreters and catches panics.
+//!
+//! Errors in the code are expected and ignored, only a panic is a failure, as well as
+//! compiled bytecode that the verifier rejects. Output of the interpreters goes to a sink
+//! and their errors are not printed, so that thousands of runs don't flood the terminal, and
+//! panic messages are recorded instead of being printed.
+
+use bvm::compiler::Parser;
+use bvm::verifier;
+use bvm::vm::VM;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::Once;
+use twi::evaluator::{environment::Environment, Evaluator};
+use twi::Lox;
+
+// Name of the script in diagnostics.
+const PATH: &str = "fuzz.lox";
+// Location of crashes that are bytecode rejected by the verifier.
+const VERIFIER: &str = "bvm/src/verifier.rs";
+
+thread_local! {
+    static IS_CHECKING: Cell<bool> = const { Cell::new(false) };
+    static PANIC: RefCell<Option<(String, String)>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Check, // scan, parse, resolve and compile, the code may not terminate
+    Run,   // also evaluate, the code must terminate
+}
+
+impl Mode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Check => "check",
+            Mode::Run => "run",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "check" => Some(Mode::Check),
+            "run" => Some(Mode::Run),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crash {
+    pub interpreter: &'static str,
+    pub location: String, // file:line:column of the panic or `VERIFIER`
+    pub message: String,
+}
+
+impl Crash {
+    /// Crashes at the same place with the same message, apart from numbers in it, are
+    /// considered to be the same bug.
+    pub fn is_same(&self, other: &Crash) -> bool {
+        let shape = |message: &str| message.replace(|c: char| c.is_ascii_digit(), "");
+        self.interpreter == other.interpreter
+            && self.location == other.location
+            && shape(&self.message) == shape(&other.message)
+    }
+}
+
+impl fmt::Display for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.location == VERIFIER {
+            return write!(f, "{} compiled {}", self.interpreter, self.message);
+        }
+        write!(
+            f,
+            "{} panicked at {}: {}",
+            self.interpreter, self.location, self.message
+        )
+    }
+}
+
+/// Feeds the code to twi and then to bvm, returns the first panic.
+pub fn check(source: &str, mode: Mode) -> Result<(), Crash> {
+    catch("twi", || {
+        let mut evaluator = Evaluator::new(Environment::new(None));
+        evaluator.set_output(Rc::new(RefCell::new(io::sink())));
+        let mut lox = Lox::new(evaluator);
+        lox.set_quiet(true);
+        match mode {
+            Mode::Check => lox.check_source(PATH, source.to_string()),
+            Mode::Run => lox.run_source(PATH, source.to_string()),
+        };
+    })?;
+
+    let config = bvm::Config {
+        quiet: true,
+        ..bvm::Config::new()
+    };
+    let verified = catch("bvm", || {
+        let mut parser = Parser::new(config);
+        parser.set_source_name(PATH);
+        let function = parser.compile(source.to_string()).ok()?;
+        let verified = verifier::verify(&function.borrow());
+        Some(verified)
+    })?;
+    // Bytecode that the verifier rejects is a bug in the compiler.
+    if let Some(Err(err)) = verified {
+        return Err(Crash {
+            interpreter: "bvm",
+            location: VERIFIER.to_string(),
+            message: err.to_string(),
+        });
+    }
+
+    if mode == Mode::Run {
+        catch("bvm", || {
+            let mut vm = VM::default();
+            vm.set_config(config);
+            vm.set_output(Box::new(io::sink()));
+            vm.init();
+            vm.set_source_name(PATH);
+            vm.interpret(source.to_string());
+        })?;
+    }
+    Ok(())
+}
+
+fn catch<T>(interpreter: &'static str, f: impl FnOnce() -> T) -> Result<T, Crash> {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let default = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !IS_CHECKING.get() {
+                return default(info);
+            }
+            let location = match info.location() {
+                Some(location) => location.to_string(),
+                None => "<unknown>".to_string(),
+            };
+            let payload = info.payload();
+            let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref()) {
+                (Some(message), _) => message.to_string(),
+                (None, Some(message)) => String::clone(message),
+                (None, None) => "<unknown>".to_string(),
+            };
+            PANIC.set(Some((location, message)));
+        }));
+    });
+
+    IS_CHECKING.set(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    IS_CHECKING.set(false);
+
+    match result {
+        Ok(value) => Ok(value),
+        Err(_) => {
+            let (location, message) = PANIC.take().unwrap_or_default();
+            Err(Crash {
+                interpreter,
+                location,
+                message,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_harness {
+    use super::{catch, check, Mode};
+
+    #[test]
+    fn test_catch() {
+        let crash = catch("twi", || panic!("index {} is out of bounds", 3)).unwrap_err();
+        assert_eq!(crash.interpreter, "twi");
+        assert_eq!(crash.message, "index 3 is out of bounds");
+        assert!(crash.location.starts_with("fuzz/src/harness.rs:"));
+
+        // Errors in the code are not crashes.
+        assert_eq!(check("print 1 +;", Mode::Check), Ok(()));
+        assert_eq!(check("print -\"a\";", Mode::Run), Ok(()));
+    }
+}
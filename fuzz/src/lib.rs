@@ -0,0 +1,6 @@
+pub mod generator;
+pub mod harness;
+pub mod minimizer;
+pub mod mutator;
+pub mod regression;
+pub mod rng;
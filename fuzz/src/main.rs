@@ -0,0 +1,138 @@
+use fuzz::generator::Generator;
+use fuzz::harness::{self, Crash, Mode};
+use fuzz::minimizer;
+use fuzz::mutator;
+use fuzz::regression;
+use fuzz::rng::Rng;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Edited copies of every program that are checked.
+const MUTANTS: usize = 4;
+
+const USAGE: &str = "Usage: fuzz [--seed N] [--runs N] [--out DIR]
+       fuzz --show SEED
+       fuzz [--bytes] [--out DIR] files...";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut seed = 0;
+    let mut runs = 1000;
+    let mut dir = PathBuf::from(regression::DIR);
+    let mut paths = Vec::new();
+    let mut is_bytes = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" | "--runs" | "--show" => {
+                let Some(n) = args.next().and_then(|n| n.parse().ok()) else {
+                    usage();
+                };
+                match arg.as_str() {
+                    "--seed" => seed = n,
+                    "--runs" => runs = n,
+                    _ => {
+                        print!("{}", Generator::new(Rng::seeded(n)).program());
+                        return;
+                    }
+                }
+            }
+            "--out" => match args.next() {
+                Some(path) => dir = PathBuf::from(path),
+                None => usage(),
+            },
+            "--bytes" => is_bytes = true,
+            "-h" | "--help" => usage(),
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    let is_crashed = match paths.is_empty() {
+        true => fuzz(seed, runs, &dir),
+        false => replay(&paths, is_bytes, &dir),
+    };
+    if is_crashed {
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    process::exit(64);
+}
+
+// Runs programs of seeds `seed..seed + runs` and checks their mutants, saves every new crash
+// minimized into `dir`. Returns whether there were crashes.
+fn fuzz(seed: u64, runs: u64, dir: &Path) -> bool {
+    let mut crashes: Vec<Crash> = Vec::new();
+    for seed in seed..seed + runs {
+        let program = Generator::new(Rng::seeded(seed)).program();
+        let mut inputs = vec![(program.clone(), Mode::Run)];
+        let mut rng = Rng::seeded(seed);
+        for _ in 0..MUTANTS {
+            inputs.push((mutator::mutate(&program, &mut rng), Mode::Check));
+        }
+
+        for (source, mode) in inputs {
+            let Err(crash) = harness::check(&source, mode) else {
+                continue;
+            };
+            if crashes.iter().any(|other| other.is_same(&crash)) {
+                continue;
+            }
+            println!("CRASH seed {seed} ({}) {crash}", mode.name());
+            save(&source, mode, &crash, dir);
+            crashes.push(crash);
+        }
+    }
+
+    println!(
+        "\n{runs} programs, {} mutants, {} crashes",
+        runs * MUTANTS as u64,
+        crashes.len()
+    );
+    !crashes.is_empty()
+}
+
+// Minimizes the crashing code and saves it into `dir`.
+fn save(source: &str, mode: Mode, crash: &Crash, dir: &Path) {
+    let minimized = minimizer::minimize(source, mode, crash);
+    match regression::save(dir, &minimized, mode, crash) {
+        Ok(path) => println!("  minimized to {}", path.display()),
+        Err(err) => println!("  couldn't save the minimized code: {err}"),
+    }
+}
+
+// Feeds the files to the interpreters, saves crashes like `fuzz` does. Files are scripts or,
+// with `is_bytes`, inputs of the libFuzzer `program` target, which are turned into programs.
+// Returns whether any of them crashed.
+fn replay(paths: &[PathBuf], is_bytes: bool, dir: &Path) -> bool {
+    let mut is_crashed = false;
+    for path in paths {
+        let script = match is_bytes {
+            true => fs::read(path)
+                .map(|bytes| (Generator::new(Rng::from_bytes(&bytes)).program(), Mode::Run)),
+            false => regression::load(path),
+        };
+        let (source, mode) = match script {
+            Ok(script) => script,
+            Err(err) => {
+                println!("FAIL {}\n  {err}", path.display());
+                is_crashed = true;
+                continue;
+            }
+        };
+        match harness::check(&source, mode) {
+            Ok(()) => println!("PASS {}", path.display()),
+            Err(crash) => {
+                println!("FAIL {} ({}) {crash}", path.display(), mode.name());
+                save(&source, mode, &crash, dir);
+                is_crashed = true;
+            }
+        }
+    }
+    is_crashed
+}
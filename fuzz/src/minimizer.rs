@@ -0,0 +1,60 @@
+//! Minimization of crashing code by delta debugging.
+//!
+//! Chunks of lines are removed while the code still crashes at the same place, then the
+//! same is done with words of the remaining lines if the code is only checked. Generated
+//! programs keep loop counters on the line of the loop, so removing lines of a program that
+//! is run can't make it hang.
+
+use crate::harness::{self, Crash, Mode};
+
+/// Smallest code found that crashes like `crash`.
+pub fn minimize(source: &str, mode: Mode, crash: &Crash) -> String {
+    let is_crashing = |pieces: &[&str]| match harness::check(&pieces.concat(), mode) {
+        Ok(()) => false,
+        Err(other) => other.is_same(crash),
+    };
+
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let code = reduce(lines, &is_crashing).concat();
+    // Removing words could remove the increment of a loop.
+    if mode == Mode::Run {
+        return code;
+    }
+    let words: Vec<&str> = code.split_inclusive(' ').collect();
+    reduce(words, &is_crashing).concat()
+}
+
+// Removes chunks of pieces, halving the chunks until single pieces can't be removed.
+fn reduce<'a>(mut pieces: Vec<&'a str>, is_crashing: &impl Fn(&[&str]) -> bool) -> Vec<&'a str> {
+    let mut size = pieces.len().div_ceil(2).max(1);
+    loop {
+        let mut is_reduced = false;
+        let mut start = 0;
+        while start < pieces.len() {
+            let end = (start + size).min(pieces.len());
+            let candidate: Vec<&str> = [&pieces[..start], &pieces[end..]].concat();
+            if is_crashing(&candidate) {
+                pieces = candidate;
+                is_reduced = true;
+            } else {
+                start = end;
+            }
+        }
+        if size == 1 && !is_reduced {
+            return pieces;
+        }
+        size = (size / 2).max(1);
+    }
+}
+
+#[cfg(test)]
+mod test_minimizer {
+    use super::reduce;
+
+    #[test]
+    fn test_reduce() {
+        let pieces = vec!["a", "b", "c", "d", "e", "f", "g"];
+        let is_crashing = |pieces: &[&str]| pieces.contains(&"b") && pieces.contains(&"f");
+        assert_eq!(reduce(pieces, &is_crashing), ["b", "f"]);
+    }
+}
@@ -0,0 +1,76 @@
+//! Small random edits of valid programs.
+//!
+//! Generated programs never reach error recovery of the parsers, edited ones mostly do.
+//! Edited code may not terminate, so it is only checked, never run.
+
+use crate::rng::Rng;
+
+// Pieces of code that are inserted, the parsers should handle them anywhere. The last ones are
+// characters of two to four bytes, a combining accent and what invalid UTF-8 is decoded to.
+const TOKENS: [&str; 42] = [
+    "(", ")", "{", "}", "[", "]", ",", ".", ";", "-", "+", "/", "*", "!", "=", "==", "<", "|",
+    "=>", "...", "\"", "//", "0.", "1e", "and", "class", "const", "enum", "fun", "match", "case",
+    "return", "super", "this", "var", "yield", "é", "λ", "€", "😀", "\u{301}", "\u{fffd}",
+];
+
+/// Code with a few random edits.
+pub fn mutate(source: &str, rng: &mut Rng) -> String {
+    let mut code = source.to_string();
+    for _ in 0..1 + rng.below(3) {
+        code = edit(&code, rng);
+    }
+    code
+}
+
+fn edit(code: &str, rng: &mut Rng) -> String {
+    let mut lines: Vec<&str> = code.lines().collect();
+    if lines.is_empty() {
+        return rng.choose(&TOKENS).to_string();
+    }
+    if rng.chance(10) {
+        return truncate(code, rng);
+    }
+    let line = rng.below(lines.len());
+    match rng.below(5) {
+        0 => {
+            lines.remove(line);
+        }
+        1 => lines.insert(line, lines[line]),
+        2 => {
+            let other = rng.below(lines.len());
+            lines.swap(line, other);
+        }
+        _ => {
+            // Inserted or removed at a character boundary of the line.
+            let text = lines[line];
+            let boundaries: Vec<usize> = text
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([text.len()])
+                .collect();
+            let start = *rng.choose(&boundaries);
+            let end = *rng.choose(&boundaries);
+            let (start, end) = (start.min(end), start.max(end));
+            let edited = match rng.chance(50) {
+                true => format!("{}{}", &text[..start], &text[end..]),
+                false => format!(
+                    "{}{}{}",
+                    &text[..start],
+                    rng.choose(&TOKENS),
+                    &text[start..]
+                ),
+            };
+            let mut code: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+            code[line] = edited;
+            return code.join("\n");
+        }
+    }
+    lines.join("\n")
+}
+
+// Cut at any byte, like a partly written file. A character cut in the middle is decoded to
+// U+FFFD, as inputs that aren't UTF-8 are.
+fn truncate(code: &str, rng: &mut Rng) -> String {
+    let end = rng.below(code.len() + 1);
+    String::from_utf8_lossy(&code.as_bytes()[..end]).into_owned()
+}
@@ -0,0 +1,86 @@
+//! Minimized crashes saved as scripts, which are replayed by the tests.
+//!
+//! The first line says how the script is fed to the interpreters, the second one how it
+//! crashed when it was found:
+//!
+//! // fuzz: run
+//! // bvm panicked at bvm/src/vm.rs:631:42: called `Option::unwrap()` on a `None` value
+
+use crate::harness::{Crash, Mode};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/regressions");
+
+/// Writes the script into `dir`, named after the place of the crash, so that the same bug is
+/// saved once. Returns the path of the script.
+pub fn save(dir: &Path, source: &str, mode: Mode, crash: &Crash) -> io::Result<PathBuf> {
+    // bvm/src/vm.rs:631:42 -> bvm-vm-631, bvm/src/verifier.rs -> bvm-verifier
+    let parts: Vec<&str> = crash.location.split(':').collect();
+    let stem = |file| {
+        Path::new(file)
+            .file_stem()
+            .map_or("unknown".into(), |stem| stem.to_string_lossy())
+    };
+    let name = match parts[..] {
+        [file, line, _] => format!("{}-{}-{line}", crash.interpreter, stem(file)),
+        _ => format!("{}-{}", crash.interpreter, stem(&crash.location)),
+    };
+    let path = dir.join(format!("{name}.lox"));
+
+    // Messages may span lines.
+    let crash = crash.to_string().lines().collect::<Vec<_>>().join(" ");
+    fs::create_dir_all(dir)?;
+    fs::write(
+        &path,
+        format!("// fuzz: {}\n// {crash}\n{source}", mode.name()),
+    )?;
+    Ok(path)
+}
+
+/// Script and the mode it should be fed in.
+pub fn load(path: &Path) -> io::Result<(String, Mode)> {
+    // Inputs found by libFuzzer may not be UTF-8.
+    let source = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let mode = source
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("// fuzz: "))
+        .and_then(Mode::from_name)
+        .unwrap_or(Mode::Check);
+    Ok((source, mode))
+}
+
+/// Scripts in `dir`, sorted by name.
+pub fn scripts(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut scripts = Vec::new();
+    if !dir.exists() {
+        return Ok(scripts);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "lox") {
+            scripts.push(path);
+        }
+    }
+    scripts.sort();
+    Ok(scripts)
+}
+
+#[cfg(test)]
+mod test_regression {
+    use super::{load, scripts, DIR};
+    use crate::harness;
+    use std::path::Path;
+
+    #[test]
+    fn test_regressions() {
+        for path in scripts(Path::new(DIR)).unwrap() {
+            let (source, mode) = load(&path).unwrap();
+            if let Err(crash) = harness::check(&source, mode) {
+                panic!("{}: {crash}", path.display());
+            }
+        }
+    }
+}
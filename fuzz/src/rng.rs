@@ -0,0 +1,57 @@
+//! Source of the random choices of the generator.
+//!
+//! A seeded xorshift generator is used by the in-repo fuzzer, so every program can be
+//! reproduced from its seed. Under libFuzzer the choices are read from the input bytes
+//! instead, so mutations of the input become small edits of the program.
+
+pub enum Rng {
+    Seeded(u64),
+    Bytes(Vec<u8>, usize), // (bytes, position of the next choice)
+}
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        // Xorshift never leaves zero, and close seeds would start with close numbers.
+        let mut rng = Rng::Seeded(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1);
+        rng.next();
+        rng
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Rng::Bytes(bytes.to_vec(), 0)
+    }
+
+    fn next(&mut self) -> u64 {
+        match self {
+            Rng::Seeded(state) => {
+                *state ^= *state << 13;
+                *state ^= *state >> 7;
+                *state ^= *state << 17;
+                *state
+            }
+            // Zero when the bytes run out, which is always the simplest choice.
+            Rng::Bytes(bytes, position) => {
+                let mut value = 0;
+                for _ in 0..2 {
+                    value = value << 8 | u64::from(bytes.get(*position).copied().unwrap_or(0));
+                    *position += 1;
+                }
+                value
+            }
+        }
+    }
+
+    /// Number in `0..n`, `n` must not be zero.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// True with the probability of `percent`%.
+    pub fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
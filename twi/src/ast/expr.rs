@@ -7,11 +7,41 @@ pub enum Expr {
     Call(Box<Expr>, Token, Vec<Expr>),
     Get(Box<Expr>, Token),
     Grouping(Box<Expr>),
+    Index(Box<Expr>, Token, Box<Expr>), // (list, bracket, index)
+    List(Token, Vec<Expr>),             // (bracket, elements)
     LiteralExpr(Literal),
     Logical(Box<Expr>, Token, Box<Expr>),
     Unary(Token, Box<Expr>),
     Set(Box<Expr>, Token, Box<Expr>),
+    Spread(Token, Box<Expr>),
     Super(Token, Token),
     This(Token),
     Variable(Token),
 }
+
+impl Expr {
+    // First token, literals have none.
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            Expr::Assign(name, _) => Some(name),
+            Expr::Binary(left, op, _) | Expr::Logical(left, op, _) => left.token().or(Some(op)),
+            Expr::Call(expr, tok, _)
+            | Expr::Get(expr, tok)
+            | Expr::Index(expr, tok, _)
+            | Expr::Set(expr, tok, _) => expr.token().or(Some(tok)),
+            Expr::Grouping(expr) => expr.token(),
+            Expr::List(tok, _)
+            | Expr::Unary(tok, _)
+            | Expr::Spread(tok, _)
+            | Expr::Super(tok, _)
+            | Expr::This(tok)
+            | Expr::Variable(tok) => Some(tok),
+            Expr::LiteralExpr(_) => None,
+        }
+    }
+
+    // Line of the first token, literals have none.
+    pub fn line(&self) -> Option<usize> {
+        self.token().map(Token::get_line)
+    }
+}
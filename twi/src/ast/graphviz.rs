@@ -1,5 +1,5 @@
 use super::expr::Expr;
-use super::stmt::Stmt;
+use super::stmt::{Param, Stmt};
 use crate::lexer::token::{Literal, Token};
 use crate::Visitor;
 
@@ -65,6 +65,14 @@ fn auxiliary_node(node_num: usize, color: &str, content: &str) -> String {
     format!("{name}{color_def}{label}{end}")
 }
 
+// "name" or "name: Type".
+fn typed_name(name: &Token, annotation: &Option<Token>) -> String {
+    match annotation {
+        Some(ty) => format!("{}: {}", name.get_lexeme(), ty.get_lexeme()),
+        None => name.get_lexeme().to_string(),
+    }
+}
+
 fn str_type(token: &Token) -> String {
     let str_token_type = format!("{:#?}", token.get_type());
     str_token_type
@@ -177,6 +185,22 @@ impl AstVis {
         auxiliary_node(self.current_node, "#ffcde0", "Methods")
     }
 
+    fn class_members_node(&mut self) -> String {
+        self.increment_node_count();
+        auxiliary_node(self.current_node, "#ffe4b5", "Class members")
+    }
+
+    fn case_node(&mut self, pattern: &str) -> String {
+        self.increment_node_count();
+        let content = format!("case {}", pattern.replace('"', "\\\""));
+        auxiliary_node(self.current_node, "#b6d7a8", &content)
+    }
+
+    fn mixins_node(&mut self) -> String {
+        self.increment_node_count();
+        auxiliary_node(self.current_node, "#d8bfd8", "Mixins")
+    }
+
     fn start_stmt(&mut self, count: usize) {
         let s = format!("subgraph stmt{count} {{\n");
         self.graph.push_str(&s);
@@ -266,11 +290,40 @@ impl Visitor<(String, usize), (String, usize)> for AstVis {
                     obj_num
                 )
             }
+            Expr::Spread(_, exp) => {
+                let root = self.expr_node("Spread", Some(vec!["..."]));
+                let (inner, inner_num) = self.visit_expr(exp);
+                format!("{}{}\tN{} -> N{}\n", root, inner, next_node, inner_num)
+            }
             Expr::Grouping(group) => {
                 let root = self.expr_node("Grouping", Some(vec!["()"]));
                 let (inner, inner_num) = self.visit_expr(group);
                 format!("{}{}\tN{} -> N{}\n", root, inner, next_node, inner_num)
             }
+            Expr::Index(list, _, index) => {
+                let root = self.expr_node("Index", Some(vec!["[]"]));
+                let (list_str, list_num) = self.visit_expr(list);
+                let (index_str, index_num) = self.visit_expr(index);
+                format!(
+                    "{}{}{}\tN{} -> {{N{}, N{}}}\n",
+                    root, list_str, index_str, next_node, list_num, index_num
+                )
+            }
+            Expr::List(_, elements) => {
+                let root = self.expr_node("List", Some(vec!["[]"]));
+                let mut elements_str = String::new();
+                let mut elements_nums = Vec::new();
+                for element in elements {
+                    let (element_str, num) = self.visit_expr(element);
+                    elements_str.push_str(&element_str);
+                    elements_nums.push(num);
+                }
+                for i in elements_nums {
+                    let link = format!("\tN{} -> N{}\n", next_node, i);
+                    elements_str.push_str(&link);
+                }
+                format!("{root}{elements_str}")
+            }
             Expr::LiteralExpr(literal) => match literal {
                 Literal::Bool(b) => self.literal_node("Bool", Some(vec![&b.to_string()])),
                 Literal::Number(n) => self.literal_node("Number", Some(vec![&n.to_string()])),
@@ -357,7 +410,7 @@ impl Visitor<(String, usize), (String, usize)> for AstVis {
 
                 format!("{root}{statements}")
             }
-            Stmt::Class(name, superclass, methods) => {
+            Stmt::Class(name, superclass, mixins, methods, members) => {
                 let root = if superclass.is_none() {
                     self.stmt_node("Class", Some(vec!["class"]))
                 } else {
@@ -402,15 +455,131 @@ impl Visitor<(String, usize), (String, usize)> for AstVis {
                     methods_stmts.push_str(&link);
                 }
 
+                let mut members_stmts = String::new();
+                if !members.is_empty() {
+                    members_stmts.push_str(&self.class_members_node());
+                    let members_num = self.current_node;
+                    members_stmts.push_str(&format!("\tN{} -> N{}\n", next_node, members_num));
+
+                    let mut members_nums = Vec::new();
+                    for member in members {
+                        let (member_str, num) = self.visit_stmt(member);
+                        members_stmts.push_str(&member_str);
+                        members_nums.push(num);
+                    }
+                    for i in members_nums {
+                        let link = format!("\tN{} -> N{}\n", members_num, i);
+                        members_stmts.push_str(&link);
+                    }
+                }
+
+                let mut mixins_exprs = String::new();
+                if !mixins.is_empty() {
+                    mixins_exprs.push_str(&self.mixins_node());
+                    let mixins_num = self.current_node;
+                    mixins_exprs.push_str(&format!("\tN{} -> N{}\n", next_node, mixins_num));
+
+                    for mixin in mixins {
+                        let (mixin_str, num) = self.visit_expr(mixin);
+                        mixins_exprs.push_str(&mixin_str);
+                        mixins_exprs.push_str(&format!("\tN{} -> N{}\n", mixins_num, num));
+                    }
+                }
+
+                format!("{base}{methods_stmts}{members_stmts}{mixins_exprs}")
+            }
+            Stmt::Mixin(name, methods) => {
+                let root = self.stmt_node("Mixin", Some(vec!["mixin"]));
+                let mixin_name = self.token_node(&str_type(name), Some(vec![name.get_lexeme()]));
+                let methods_node = self.methods_node();
+                let base = format!(
+                    "{}{}{}\tN{} -> {{N{}, N{}}}\n",
+                    root,
+                    mixin_name,
+                    methods_node,
+                    next_node,
+                    next_node + 1,
+                    next_node + 2
+                );
+
+                let mut methods_stmts = String::new();
+                let mut methods_nums = Vec::new();
+                for method in methods {
+                    let (method_str, num) = self.visit_stmt(method);
+                    methods_stmts.push_str(&method_str);
+                    methods_nums.push(num);
+                }
+                for i in methods_nums {
+                    let link = format!("\tN{} -> N{}\n", next_node + 2, i);
+                    methods_stmts.push_str(&link);
+                }
+
                 format!("{base}{methods_stmts}")
             }
+            Stmt::Enum(name, variants) => {
+                let root = self.stmt_node("Enum", Some(vec!["enum"]));
+                let enum_name = self.token_node(&str_type(name), Some(vec![name.get_lexeme()]));
+                let mut base = format!(
+                    "{}{}\tN{} -> N{}\n",
+                    root,
+                    enum_name,
+                    next_node,
+                    next_node + 1
+                );
+
+                for (variant, fields) in variants {
+                    let mut info = vec![variant.get_lexeme()];
+                    info.extend(fields.iter().map(|f| f.get_lexeme()));
+                    let variant_node = self.token_node("Variant", Some(info));
+                    base.push_str(&format!(
+                        "{}\tN{} -> N{}\n",
+                        variant_node, next_node, self.current_node
+                    ));
+                }
+
+                base
+            }
+            Stmt::Getter(name, body) => {
+                let root = self.stmt_node("Getter", None);
+                let getter_name = self.token_node(&str_type(name), Some(vec![name.get_lexeme()]));
+                let body_node = self.function_body_node();
+                let base = format!(
+                    "{}{}{}\tN{} -> {{N{}, N{}}}\n",
+                    root,
+                    getter_name,
+                    body_node,
+                    next_node,
+                    next_node + 1,
+                    next_node + 2
+                );
+
+                let mut getter_def = String::new();
+                let mut stmts_nums = Vec::new();
+                for statement in body {
+                    let (stmt_str, stmt_num) = self.visit_stmt(statement);
+                    getter_def.push_str(&stmt_str);
+                    stmts_nums.push(stmt_num);
+                }
+                for i in stmts_nums {
+                    let link = format!("\tN{} -> N{}\n", next_node + 2, i);
+                    getter_def.push_str(&link);
+                }
+
+                format!("{base}{getter_def}")
+            }
             Stmt::Expression(exp) => {
                 let root = self.stmt_node("Expression", Some(vec![";"]));
                 let (expression, expr_num) = self.visit_expr(exp);
                 format!("{}{}\tN{} -> N{}\n", root, expression, next_node, expr_num)
             }
-            Stmt::Function(name, parameters, body) => {
-                let root = self.stmt_node("Function", Some(vec!["fun"]));
+            Stmt::Function(name, parameters, return_type, body) => {
+                let root = match return_type {
+                    Some(ty) => self.stmt_node(
+                        "Function",
+                        Some(vec!["fun", &format!(": {}", ty.get_lexeme())]),
+                    ),
+                    None => self.stmt_node("Function", Some(vec!["fun"])),
+                };
                 let fun_name = self.token_node(&str_type(name), Some(vec![name.get_lexeme()]));
                 let params_node = self.parameters_node();
                 let body_node = self.function_body_node();
@@ -428,15 +597,26 @@ impl Visitor<(String, usize), (String, usize)> for AstVis {
 
                 let mut params = String::new();
                 for parameter in parameters {
-                    params.push_str(
-                        &self.token_node(&str_type(parameter), Some(vec![parameter.get_lexeme()])),
-                    );
+                    let name = parameter.get_name();
+                    let annotation = parameter.get_type().cloned();
+                    let label = match parameter {
+                        Param::Rest(_, _) => format!("...{}", typed_name(name, &annotation)),
+                        _ => typed_name(name, &annotation),
+                    };
+                    params.push_str(&self.token_node(&str_type(name), Some(vec![&label])));
                 }
                 let n = next_node + 4;
                 for i in n..n + parameters.len() {
                     let link = format!("\tN{} -> N{}\n", next_node + 2, i);
                     params.push_str(&link);
                 }
+                for (i, parameter) in parameters.iter().enumerate() {
+                    if let Param::Default(_, _, default) = parameter {
+                        let (default_str, default_num) = self.visit_expr(default);
+                        params.push_str(&default_str);
+                        params.push_str(&format!("\tN{} -> N{}\n", n + i, default_num));
+                    }
+                }
 
                 let mut func_def = String::new();
                 let mut stmts_nums = Vec::new();
@@ -474,7 +654,24 @@ impl Visitor<(String, usize), (String, usize)> for AstVis {
                     )
                 }
             }
-            Stmt::Print(exp) => {
+            Stmt::Match(_, value, cases) => {
+                let root = self.stmt_node("Match", Some(vec!["match"]));
+                let (val, val_num) = self.visit_expr(value);
+                let mut base = format!("{}{}\tN{} -> N{}\n", root, val, next_node, val_num);
+
+                for (pattern, body) in cases {
+                    let case_node = self.case_node(&pattern.to_string());
+                    let case_num = self.current_node;
+                    let (body_stmt, body_num) = self.visit_stmt(body);
+                    base.push_str(&format!(
+                        "{}{}\tN{} -> N{}\n\tN{} -> N{}\n",
+                        case_node, body_stmt, next_node, case_num, case_num, body_num
+                    ));
+                }
+
+                base
+            }
+            Stmt::Print(_, exp) => {
                 let root = self.stmt_node("Print", None);
                 let (expression, expr_num) = self.visit_expr(exp);
                 format!("{}{}\tN{} -> N{}\n", root, expression, next_node, expr_num)
@@ -484,9 +681,14 @@ impl Visitor<(String, usize), (String, usize)> for AstVis {
                 let (val, val_num) = self.visit_expr(value);
                 format!("{}{}\tN{} -> N{}\n", root, val, next_node, val_num)
             }
-            Stmt::Var(name, initializer) => {
-                let root = self.stmt_node("Var", Some(vec!["var", "="]));
-                let var = self.token_node(&str_type(name), Some(vec![name.get_lexeme()]));
+            Stmt::Const(name, annotation, initializer)
+            | Stmt::Var(name, annotation, initializer) => {
+                let root = match s {
+                    Stmt::Const(_, _, _) => self.stmt_node("Const", Some(vec!["const", "="])),
+                    _ => self.stmt_node("Var", Some(vec!["var", "="])),
+                };
+                let var =
+                    self.token_node(&str_type(name), Some(vec![&typed_name(name, annotation)]));
                 let (val, val_num) = self.visit_expr(initializer);
                 format!(
                     "{}{}{}\tN{} -> {{N{}, N{}}}\n",
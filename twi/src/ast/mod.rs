@@ -47,6 +47,14 @@ impl Visitor<String, String> for AstPrinter {
                 ];
                 self.parenthesize_with_transform(".", &parts)
             }
+            Expr::Index(list, _, index) => self.parenthesize("index", vec![list, index]),
+            Expr::List(_, elements) => {
+                let mut parts: Vec<PrintObj> = Vec::new();
+                for element in elements {
+                    parts.push(PrintObj::Exp(element.clone()));
+                }
+                self.parenthesize_with_transform("list", &parts)
+            }
             Expr::LiteralExpr(l) => format!("{l}"),
             Expr::Logical(l, op, r) => self.parenthesize(op.get_lexeme(), vec![l, r]),
             Expr::Unary(op, r) => self.parenthesize(op.get_lexeme(), vec![r]),
@@ -61,6 +69,7 @@ impl Visitor<String, String> for AstPrinter {
                 ];
                 self.parenthesize_with_transform("=", &parts)
             }
+            Expr::Spread(_, exp) => self.parenthesize("...", vec![exp]),
             Expr::Super(_, method) => {
                 self.parenthesize_with_transform("super", &vec![PrintObj::Tok(method.clone())])
             }
@@ -70,23 +79,57 @@ impl Visitor<String, String> for AstPrinter {
 
     fn visit_stmt(&mut self, s: &Stmt) -> String {
         match s {
-            Stmt::Print(exp) => self.parenthesize("print", vec![exp]),
+            Stmt::Print(_, exp) => self.parenthesize("print", vec![exp]),
+            Stmt::Match(_, value, cases) => {
+                let mut pretty_str = String::new();
+                pretty_str.push_str("(match ");
+                pretty_str.push_str(&self.visit_expr(value));
+
+                for (pattern, body) in cases {
+                    pretty_str.push_str(&format!(" (case {pattern} "));
+                    pretty_str.push_str(&self.visit_stmt(body));
+                    pretty_str.push(')');
+                }
+
+                pretty_str.push(')');
+                pretty_str
+            }
             Stmt::Expression(exp) => self.parenthesize(";", vec![exp]),
             Stmt::Return(_, value) => match value {
                 Expr::LiteralExpr(Literal::None) => "(return)".to_string(),
                 _ => self.parenthesize("return", vec![value]),
             },
-            Stmt::Var(name, initializer) => {
+            Stmt::Const(name, annotation, initializer) => {
+                let mut parts = vec![PrintObj::Tok(name.clone())];
+                if let Some(ty) = annotation {
+                    parts.push(PrintObj::Exp(Expr::LiteralExpr(Literal::String(
+                        ":".to_string(),
+                    ))));
+                    parts.push(PrintObj::Tok(ty.clone()));
+                }
+                parts.extend([
+                    PrintObj::Exp(Expr::LiteralExpr(Literal::String("=".to_string()))),
+                    PrintObj::Exp(initializer.clone()),
+                ]);
+                self.parenthesize_with_transform("const", &parts)
+            }
+            Stmt::Var(name, annotation, initializer) => {
+                let mut parts = vec![PrintObj::Tok(name.clone())];
+                if let Some(ty) = annotation {
+                    parts.push(PrintObj::Exp(Expr::LiteralExpr(Literal::String(
+                        ":".to_string(),
+                    ))));
+                    parts.push(PrintObj::Tok(ty.clone()));
+                }
+
                 if *initializer == Expr::LiteralExpr(Literal::None) {
-                    let parts = vec![PrintObj::Tok(name.clone())];
                     return self.parenthesize_with_transform("var", &parts);
                 }
 
-                let parts = vec![
-                    PrintObj::Tok(name.clone()),
+                parts.extend([
                     PrintObj::Exp(Expr::LiteralExpr(Literal::String("=".to_string()))),
                     PrintObj::Exp(initializer.clone()),
-                ];
+                ]);
                 self.parenthesize_with_transform("var", &parts)
             }
             Stmt::Block(stmts) => {
@@ -100,7 +143,7 @@ impl Visitor<String, String> for AstPrinter {
                 pretty_str.push(')');
                 pretty_str
             }
-            Stmt::Class(name, superclass, methods) => {
+            Stmt::Class(name, superclass, mixins, methods, members) => {
                 let mut pretty_str = String::new();
                 pretty_str.push_str("(class ");
                 pretty_str.push_str(name.get_lexeme());
@@ -110,6 +153,21 @@ impl Visitor<String, String> for AstPrinter {
                     pretty_str.push_str(&self.visit_expr(sup));
                 }
 
+                if !mixins.is_empty() {
+                    pretty_str.push_str(" (with");
+                    for mixin in mixins {
+                        pretty_str.push(' ');
+                        pretty_str.push_str(&self.visit_expr(mixin));
+                    }
+                    pretty_str.push(')');
+                }
+
+                for member in members {
+                    pretty_str.push_str(" (static ");
+                    pretty_str.push_str(&self.visit_stmt(member));
+                    pretty_str.push(')');
+                }
+
                 for method in methods {
                     pretty_str.push(' ');
                     pretty_str.push_str(&self.visit_stmt(method));
@@ -118,7 +176,44 @@ impl Visitor<String, String> for AstPrinter {
                 pretty_str.push(')');
                 pretty_str
             }
-            Stmt::Function(name, params, body) => {
+            Stmt::Enum(name, variants) => {
+                let mut pretty_str = String::new();
+                pretty_str.push_str("(enum ");
+                pretty_str.push_str(name.get_lexeme());
+
+                for (variant, fields) in variants {
+                    pretty_str.push(' ');
+                    if fields.is_empty() {
+                        pretty_str.push_str(variant.get_lexeme());
+                        continue;
+                    }
+
+                    pretty_str.push('(');
+                    pretty_str.push_str(variant.get_lexeme());
+                    for field in fields {
+                        pretty_str.push(' ');
+                        pretty_str.push_str(field.get_lexeme());
+                    }
+                    pretty_str.push(')');
+                }
+
+                pretty_str.push(')');
+                pretty_str
+            }
+            Stmt::Getter(name, body) => {
+                let mut pretty_str = String::new();
+                pretty_str.push_str("(getter ");
+                pretty_str.push_str(name.get_lexeme());
+                pretty_str.push(' ');
+
+                for stmt in body {
+                    pretty_str.push_str(&self.visit_stmt(stmt));
+                }
+
+                pretty_str.push(')');
+                pretty_str
+            }
+            Stmt::Function(name, params, return_type, body) => {
                 let mut pretty_str = String::new();
                 pretty_str.push_str("(fun ");
                 pretty_str.push_str(name.get_lexeme());
@@ -128,10 +223,25 @@ impl Visitor<String, String> for AstPrinter {
                     if i != 0 {
                         pretty_str.push(' ');
                     }
-                    pretty_str.push_str(param.get_lexeme());
+                    if let Param::Rest(_, _) = param {
+                        pretty_str.push_str("...");
+                    }
+                    pretty_str.push_str(param.get_name().get_lexeme());
+                    if let Some(ty) = param.get_type() {
+                        pretty_str.push(':');
+                        pretty_str.push_str(ty.get_lexeme());
+                    }
+                    if let Param::Default(_, _, default) = param {
+                        pretty_str.push('=');
+                        pretty_str.push_str(&self.visit_expr(default));
+                    }
                 }
 
                 pretty_str.push(')');
+                if let Some(ty) = return_type {
+                    pretty_str.push(':');
+                    pretty_str.push_str(ty.get_lexeme());
+                }
 
                 for stmt in body {
                     pretty_str.push_str(&self.visit_stmt(stmt));
@@ -140,6 +250,19 @@ impl Visitor<String, String> for AstPrinter {
                 pretty_str.push(')');
                 pretty_str
             }
+            Stmt::Mixin(name, methods) => {
+                let mut pretty_str = String::new();
+                pretty_str.push_str("(mixin ");
+                pretty_str.push_str(name.get_lexeme());
+
+                for method in methods {
+                    pretty_str.push(' ');
+                    pretty_str.push_str(&self.visit_stmt(method));
+                }
+
+                pretty_str.push(')');
+                pretty_str
+            }
             Stmt::If(condition, then_branch, else_branch) => {
                 let mut parts = vec![
                     PrintObj::Exp(condition.clone()),
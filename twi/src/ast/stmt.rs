@@ -1,15 +1,132 @@
 use crate::ast::Expr;
-use crate::lexer::token::Token;
+use crate::lexer::token::{Literal, Token};
+use std::fmt;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Stmt {
     Block(Vec<Stmt>),
-    Class(Token, Option<Expr>, Vec<Stmt>),
+    Class(Token, Option<Expr>, Vec<Expr>, Vec<Stmt>, Vec<Stmt>), // (name, superclass, mixins, methods, class members)
+    Const(Token, Option<Token>, Expr),                           // (name, type, initializer)
+    Enum(Token, Vec<(Token, Vec<Token>)>), // (name, variants with their payload fields)
     Expression(Expr),
-    Function(Token, Vec<Token>, Vec<Stmt>),
+    Function(Token, Vec<Param>, Option<Token>, Vec<Stmt>), // (name, params, return type, body)
+    Getter(Token, Vec<Stmt>),                              // (name, body)
     If(Expr, Box<Stmt>, Option<Box<Stmt>>),
-    Print(Expr),
+    Match(Token, Expr, Vec<(Pattern, Stmt)>), // (keyword, value, cases)
+    Mixin(Token, Vec<Stmt>),                  // (name, methods)
+    Print(Token, Expr),                       // (keyword, value)
     Return(Token, Expr),
-    Var(Token, Expr),       // (name, initializer)
-    While(Expr, Box<Stmt>), // (condition, body)
+    Var(Token, Option<Token>, Expr), // (name, type, initializer)
+    While(Expr, Box<Stmt>),          // (condition, body)
+}
+
+impl Stmt {
+    // First token of the statement, if any of its parts has one.
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            Stmt::Block(statements) => statements.iter().find_map(|s| s.token()),
+            Stmt::Class(name, ..)
+            | Stmt::Const(name, ..)
+            | Stmt::Enum(name, _)
+            | Stmt::Function(name, ..)
+            | Stmt::Getter(name, _)
+            | Stmt::Match(name, ..)
+            | Stmt::Mixin(name, _)
+            | Stmt::Print(name, _)
+            | Stmt::Return(name, _)
+            | Stmt::Var(name, ..) => Some(name),
+            Stmt::Expression(exp) => exp.token(),
+            Stmt::If(condition, body, _) | Stmt::While(condition, body) => {
+                condition.token().or_else(|| body.token())
+            }
+        }
+    }
+
+    // Line where the statement starts, if any of its parts has a token.
+    pub fn line(&self) -> Option<usize> {
+        self.token().map(Token::get_line)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Param {
+    Required(Token, Option<Token>),      // (name, type)
+    Default(Token, Option<Token>, Expr), // (name, type, default value)
+    Rest(Token, Option<Token>),
+}
+
+impl Param {
+    pub fn get_name(&self) -> &Token {
+        match self {
+            Param::Required(name, _) => name,
+            Param::Default(name, _, _) => name,
+            Param::Rest(name, _) => name,
+        }
+    }
+
+    pub fn get_type(&self) -> Option<&Token> {
+        match self {
+            Param::Required(_, ty) => ty.as_ref(),
+            Param::Default(_, ty, _) => ty.as_ref(),
+            Param::Rest(_, ty) => ty.as_ref(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    Alternatives(Vec<Pattern>),
+    Binding(Token),
+    Class(Token, Vec<(Token, Pattern)>), // (class name, fields with their patterns)
+    Literal(Token, Literal),
+    Variant(Token, Token, Vec<(Token, Pattern)>), // (enum name, variant name, payload fields)
+    Wildcard(Token),
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Alternatives(patterns) => {
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, " | ")?;
+                    }
+                    write!(f, "{pattern}")?;
+                }
+                Ok(())
+            }
+            Pattern::Binding(name) | Pattern::Wildcard(name) => write!(f, "{}", name.get_lexeme()),
+            Pattern::Class(name, fields) => {
+                write!(f, "{}(", name.get_lexeme())?;
+                write_fields(f, fields)?;
+                write!(f, ")")
+            }
+            Pattern::Variant(enumeration, variant, fields) => {
+                write!(f, "{}.{}", enumeration.get_lexeme(), variant.get_lexeme())?;
+                if !fields.is_empty() {
+                    write!(f, "(")?;
+                    write_fields(f, fields)?;
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Pattern::Literal(_, Literal::String(s)) => write!(f, "\"{s}\""),
+            Pattern::Literal(_, literal) => write!(f, "{literal}"),
+        }
+    }
+}
+
+fn write_fields(f: &mut fmt::Formatter<'_>, fields: &[(Token, Pattern)]) -> fmt::Result {
+    for (i, (field, pattern)) in fields.iter().enumerate() {
+        if i != 0 {
+            write!(f, ", ")?;
+        }
+        match pattern {
+            Pattern::Binding(b) if b.get_lexeme() == field.get_lexeme() => {
+                write!(f, "{}", field.get_lexeme())?
+            }
+            _ => write!(f, "{}: {pattern}", field.get_lexeme())?,
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,15 @@
+use std::io;
+use std::process;
+use twi::lsp::Server;
+
+fn main() {
+    let mut server = Server::new();
+    let stdin = io::stdin();
+    match server.run(&mut stdin.lock(), &mut io::stdout()) {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("Language server failed: {e}");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// Differential testing: every script is run by both interpreters, twi and bvm, which must
+// print the same values, exit with the same code and report errors on the same lines:
+//
+// DIFF test/numbers.lox
+//   exit code: twi 70, bvm 0
+//   error lines: twi 3 (Operand must be a number.), bvm none
+//   output line 2: twi '9223372036854775807', bvm '100000000000000000000000'
+//
+// Values that only look different are normalized first: numbers (twi writes integers through
+// `i64`) and functions (`Function: <fun f>.` in twi, `<fun f>` in bvm).
+
+use crate::tester::{self, Outcome};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Disagreements printed for a script, a script that fails early in one of the interpreters
+// would flood the report.
+const MAX_SHOWN: usize = 10;
+
+pub struct Interpreters {
+    pub twi: PathBuf,
+    pub bvm: PathBuf,
+}
+
+// Disagreements of the interpreters on the script, none if they agree.
+pub fn compare(twi: &Outcome, bvm: &Outcome) -> Vec<String> {
+    let mut disagreements = Vec::new();
+
+    if twi.code != bvm.code {
+        disagreements.push(format!(
+            "exit code: twi {}, bvm {}",
+            code(twi.code),
+            code(bvm.code)
+        ));
+    }
+
+    // Messages are worded differently, only the lines must be the same.
+    let twi_errors = twi.errors();
+    let bvm_errors = bvm.errors();
+    let lines =
+        |errors: &[(usize, String)]| errors.iter().map(|(line, _)| *line).collect::<Vec<_>>();
+    if lines(&twi_errors) != lines(&bvm_errors) {
+        disagreements.push(format!(
+            "error lines: twi {}, bvm {}",
+            errors(&twi_errors),
+            errors(&bvm_errors)
+        ));
+    }
+
+    let twi_output: Vec<String> = twi.stdout.lines().map(normalize).collect();
+    let bvm_output: Vec<String> = bvm.stdout.lines().map(normalize).collect();
+    let twi_lines: Vec<&str> = twi_output.iter().map(String::as_str).collect();
+    let bvm_lines: Vec<&str> = bvm_output.iter().map(String::as_str).collect();
+    for change in tester::diff_lines(&twi_lines, &bvm_lines) {
+        let disagreement = match change {
+            (Some(i), Some(j)) => {
+                format!(
+                    "output line {}: twi '{}', bvm '{}'",
+                    i + 1,
+                    twi_lines[i],
+                    bvm_lines[j]
+                )
+            }
+            (Some(i), None) => format!("output line {}: only twi '{}'", i + 1, twi_lines[i]),
+            (None, Some(j)) => format!("output line {}: only bvm '{}'", j + 1, bvm_lines[j]),
+            (None, None) => continue,
+        };
+        disagreements.push(disagreement);
+    }
+    disagreements
+}
+
+fn code(code: Option<i32>) -> String {
+    match code {
+        Some(code) => code.to_string(),
+        None => "none".to_string(),
+    }
+}
+
+fn errors(errors: &[(usize, String)]) -> String {
+    if errors.is_empty() {
+        return "none".to_string();
+    }
+    let errors: Vec<String> = errors
+        .iter()
+        .map(|(line, message)| format!("{line} ({message})"))
+        .collect();
+    errors.join(", ")
+}
+
+// A printed line in the same form for both interpreters.
+pub fn normalize(line: &str) -> String {
+    if let Some(function) = line
+        .strip_prefix("Function: ")
+        .and_then(|function| function.strip_suffix('.'))
+    {
+        // Natives are `<native fun 'clock'>` in twi.
+        return function.replace('\'', "");
+    }
+
+    // Numbers are runs of digits, dots and minus signs.
+    let is_number_char = |c: char| c.is_ascii_digit() || c == '.' || c == '-';
+    let mut normalized = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find(is_number_char) {
+        normalized.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_number_char(c)).unwrap_or(rest.len());
+        match rest[..end].parse::<f64>() {
+            // `-0` is `0` in twi.
+            Ok(0.0) => normalized.push('0'),
+            Ok(number) => normalized.push_str(&number.to_string()),
+            Err(_) => normalized.push_str(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+// Runs every script with both interpreters and prints disagreements, returns whether there
+// were none.
+pub fn run_all(interpreters: &Interpreters, paths: &[PathBuf]) -> bool {
+    let mut scripts = Vec::new();
+    for path in paths {
+        tester::collect_scripts(path, &mut scripts);
+    }
+
+    let mut different = 0;
+    for script in &scripts {
+        let disagreements = match compare_script(interpreters, script) {
+            Ok(disagreements) => disagreements,
+            Err(err) => vec![err.to_string()],
+        };
+        if disagreements.is_empty() {
+            println!("SAME {}", script.display());
+        } else {
+            println!("DIFF {}", script.display());
+            for disagreement in disagreements.iter().take(MAX_SHOWN) {
+                println!("  {disagreement}");
+            }
+            if disagreements.len() > MAX_SHOWN {
+                println!("  ... and {} more", disagreements.len() - MAX_SHOWN);
+            }
+            different += 1;
+        }
+    }
+
+    println!(
+        "\n{} scripts, {} same, {different} different",
+        scripts.len(),
+        scripts.len() - different
+    );
+    different == 0
+}
+
+fn compare_script(interpreters: &Interpreters, path: &Path) -> io::Result<Vec<String>> {
+    let twi = Outcome::run(&interpreters.twi, path)?;
+    let bvm = Outcome::run(&interpreters.bvm, path)?;
+    Ok(compare(&twi, &bvm))
+}
+
+#[cfg(test)]
+mod test_compare {
+    use super::{compare, normalize};
+    use crate::tester::Outcome;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("[1, -0, 2.5]"), "[1, 0, 2.5]");
+        assert_eq!(
+            normalize("Function: <native fun 'clock'>."),
+            "<native fun clock>"
+        );
+        assert_eq!(normalize("v-1.2.3 - 4"), "v-1.2.3 - 4");
+
+        let twi = Outcome {
+            stdout: "0\nFunction: <fun f>.\n".to_string(),
+            stderr: "error: Operand must be a number.\n --> test.lox:3:7\n".to_string(),
+            code: Some(70),
+        };
+        let bvm = Outcome {
+            stdout: "-0\n<fun f>\n".to_string(),
+            stderr: "error: Operand must be a number.\n --> test.lox:4:7\n".to_string(),
+            code: Some(70),
+        };
+        assert_eq!(
+            compare(&twi, &bvm),
+            ["error lines: twi 3 (Operand must be a number.), bvm 4 (Operand must be a number.)"]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+// Errors rendered the way rustc does it:
+//
+// error: Operands must be numbers.
+//  --> example.lox:3:9
+//   |
+// 3 | print a + "b";
+//   |         ^
+
+use crate::lexer::token::{Token, TokenType};
+use std::env;
+use std::io::{self, IsTerminal};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+
+// Colours are used only on a terminal and never with NO_COLOR set.
+pub fn is_colored() -> bool {
+    io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+pub struct Diagnostic {
+    label: String, // `error` or `warning[L001]`
+    message: String,
+    line: usize,
+    column: usize,
+    length: usize,
+    text: Option<String>, // expected source text, the snippet is dropped if it differs
+    notes: Vec<(&'static str, String)>, // (kind, text), e.g. ("help", "did you mean 'x'?")
+}
+
+impl Diagnostic {
+    pub fn new(label: &str, message: &str, line: usize, column: usize, length: usize) -> Self {
+        Diagnostic {
+            label: label.to_string(),
+            message: message.to_string(),
+            line,
+            column,
+            length,
+            text: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, kind: &'static str, text: &str) -> Self {
+        self.notes.push((kind, text.to_string()));
+        self
+    }
+
+    pub fn at(label: &str, message: &str, token: &Token) -> Self {
+        // Only the first line of a multiline string is underlined.
+        let lexeme = token.get_lexeme();
+        let first_line = lexeme.split('\n').next().unwrap_or_default();
+        let line = token.get_line() - lexeme.matches('\n').count();
+        let length = first_line.chars().count();
+
+        let mut diagnostic = Diagnostic::new(label, message, line, token.get_column(), length);
+        if *token.get_type() != TokenType::Eof {
+            diagnostic.text = Some(first_line.to_string());
+        }
+        diagnostic
+    }
+
+    pub fn render(&self, path: &str, source: &str, is_colored: bool) -> String {
+        let paint = |color: &str, s: &str| match is_colored {
+            true => format!("{color}{s}{RESET}"),
+            false => s.to_string(),
+        };
+        let label_color = match self.label.starts_with("warning") {
+            true => YELLOW,
+            false => RED,
+        };
+
+        let gutter = " ".repeat(self.line.to_string().len());
+        let mut out = format!(
+            "{}{}\n{gutter}{} {path}:{}:{}\n",
+            paint(label_color, &self.label),
+            paint(BOLD, &format!(": {}", self.message)),
+            paint(BLUE, "-->"),
+            self.line,
+            self.column + 1,
+        );
+
+        let snippet = source.lines().nth(self.line.wrapping_sub(1));
+        if let Some(snippet) = snippet.filter(|s| self.is_at(s)) {
+            out.push_str(&self.render_snippet(snippet, &gutter, &paint, label_color));
+        }
+        for (kind, text) in &self.notes {
+            out.push_str(&format!("{gutter} {} {kind}: {text}\n", paint(BLUE, "=")));
+        }
+        out.push('\n');
+        out
+    }
+
+    fn render_snippet(
+        &self,
+        snippet: &str,
+        gutter: &str,
+        paint: &impl Fn(&str, &str) -> String,
+        label_color: &str,
+    ) -> String {
+        // Tabs are kept so that the underline is aligned with the code.
+        let padding: String = snippet
+            .chars()
+            .take(self.column)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline = "^".repeat(self.length.max(1));
+
+        let mut out = paint(BLUE, &format!("{gutter} |\n{} |", self.line));
+        out.push_str(&format!(" {snippet}\n"));
+        out.push_str(&paint(BLUE, &format!("{gutter} |")));
+        out.push_str(&format!(" {padding}{}\n", paint(label_color, &underline)));
+        out
+    }
+
+    // Whether the source line has the expected text at the column.
+    fn is_at(&self, snippet: &str) -> bool {
+        match &self.text {
+            Some(text) => snippet
+                .chars()
+                .skip(self.column)
+                .collect::<String>()
+                .starts_with(text),
+            None => true,
+        }
+    }
+}
+
+// The most similar name if it is close enough to be a typo.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance > 0 && distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+// Levenshtein distance, a swap of two neighbours counts as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test_diagnostic {
+    use super::{suggest, Diagnostic};
+    use crate::lexer::token::{Literal, Token, TokenType};
+
+    #[test]
+    fn test_render() {
+        let source = "var a = 1;\n\tprint a + \"b\";\n";
+        let token = Token::new(TokenType::Plus, "+", Literal::None, 2).with_column(9);
+        let diagnostic = Diagnostic::at("error", "Operands must be numbers.", &token);
+        assert_eq!(
+            diagnostic.render("test.lox", source, false),
+            "error: Operands must be numbers.
+ --> test.lox:2:10
+  |
+2 | \tprint a + \"b\";
+  | \t        ^
+
+"
+        );
+
+        // The snippet is left out if the source doesn't match the token.
+        let diagnostic = Diagnostic::at("error", "Operands must be numbers.", &token);
+        assert_eq!(
+            diagnostic.render("test.lox", "print 1;", false),
+            "error: Operands must be numbers.\n --> test.lox:2:10\n\n"
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        let names = ["count", "counter", "print"];
+        assert_eq!(suggest("coutn", names), Some("count"));
+        assert_eq!(suggest("prnt", names), Some("print"));
+        assert_eq!(suggest("count", names), None);
+        assert_eq!(suggest("x", names), None);
+    }
+}
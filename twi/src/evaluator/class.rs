@@ -1,4 +1,5 @@
 use crate::evaluator::{Evaluator, Function, Instance, Object, RuntimeError};
+use crate::Token;
 use std::collections::HashMap;
 use std::fmt::Display;
 
@@ -7,6 +8,8 @@ pub struct Class {
     name: String,
     superclass: Box<Option<Object>>,
     methods: HashMap<String, Function>,
+    class_methods: HashMap<String, Function>,
+    fields: HashMap<String, Object>,
 }
 
 impl Class {
@@ -14,20 +17,54 @@ impl Class {
         name: String,
         superclass: Option<Object>,
         methods: HashMap<String, Function>,
+        class_methods: HashMap<String, Function>,
+        fields: HashMap<String, Object>,
     ) -> Self {
         Class {
             name,
             superclass: Box::new(superclass),
             methods,
+            class_methods,
+            fields,
         }
     }
 
-    pub fn arity(&self) -> usize {
+    // Static fields and methods, looked up through the superclass chain.
+    pub fn get(&self, name: &Token) -> Result<Object, RuntimeError> {
+        if let Some(value) = self.find_static(name.get_lexeme()) {
+            return Ok(value);
+        }
+
+        let msg = format!("Undefined property '{}'.", name.get_lexeme());
+        Err(RuntimeError::new(name, &msg))
+    }
+
+    pub fn set(&mut self, name: &Token, value: Object) {
+        self.fields.insert(name.get_lexeme().to_string(), value);
+    }
+
+    fn find_static(&self, name: &str) -> Option<Object> {
+        if let Some(value) = self.fields.get(name) {
+            return Some(value.clone());
+        }
+
+        if let Some(method) = self.class_methods.get(name) {
+            return Some(Object::Fun(method.clone()));
+        }
+
+        if let Some(Object::Cls(cls)) = &*self.superclass {
+            return cls.find_static(name);
+        }
+
+        None
+    }
+
+    pub fn arity(&self) -> (usize, Option<usize>) {
         let initializer = self.find_method("init");
         if let Some(init) = initializer {
             return init.arity();
         }
-        0
+        (0, Some(0))
     }
 
     pub fn call(
@@ -56,6 +93,17 @@ impl Class {
         None
     }
 
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        if self.name == other.name {
+            return true;
+        }
+
+        match &*self.superclass {
+            Some(Object::Cls(cls)) => cls.is_subclass_of(other),
+            _ => false,
+        }
+    }
+
     fn stringify(&self) -> String {
         self.name.clone()
     }
@@ -66,3 +114,131 @@ impl Display for Class {
         write!(f, "{}", self.stringify())
     }
 }
+
+#[cfg(test)]
+mod test_class {
+    use crate::evaluator::{environment::Environment, Evaluator};
+    use crate::resolver::Resolver;
+    use crate::{Lox, Parser, Scanner};
+
+    // Value of the global `result` after the program ran, or the message of its runtime error.
+    fn result_of(source: &str) -> String {
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        let mut scanner = Scanner::new(&mut interpreter, source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+        let statements = Parser::new(&mut interpreter, tokens).parse();
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve_optional_stmts(statements.clone());
+        let locals = resolver.locals();
+        assert!(!interpreter.had_error, "Static error in: {source}");
+
+        let mut evaluator = Evaluator::new(Environment::new(None));
+        evaluator.set_locals(locals);
+        for statement in statements.into_iter().flatten() {
+            if let Err(err) = evaluator.execute(&statement) {
+                return err.get_message();
+            }
+        }
+        evaluator.environment.values()["result"].to_string()
+    }
+
+    #[test]
+    fn test_static_members() {
+        let math = "class Math {
+  class pi = 3;
+  class square(n) { return n * n; }
+  class cube(n) { return n * Math.square(n); }
+}";
+        assert_eq!(
+            result_of(&format!("{math} var result = Math.cube(2);")),
+            "8"
+        );
+        assert_eq!(
+            result_of(&format!(
+                "{math} Math.pi = 4; var result = Math.square(Math.pi);"
+            )),
+            "16"
+        );
+        // Subclasses find the static members of their superclass.
+        assert_eq!(
+            result_of(&format!(
+                "{math} class More < Math {{}} var result = More.pi;"
+            )),
+            "3"
+        );
+        assert_eq!(
+            result_of("class A { class f() { return 1; } } var result = A().f();"),
+            "Undefined property 'f'."
+        );
+    }
+
+    #[test]
+    fn test_getters() {
+        let circle = "class Circle {
+  init(r) { this.r = r; }
+  area { return 3 * this.r * this.r; }
+  twice { return 2 * this.area; }
+}";
+        assert_eq!(
+            result_of(&format!("{circle} var result = Circle(2).area;")),
+            "12"
+        );
+        assert_eq!(
+            result_of(&format!("{circle} var result = Circle(1).twice;")),
+            "6"
+        );
+    }
+
+    #[test]
+    fn test_mixins() {
+        let animals = "class Animal {
+  name() { return \"animal\"; }
+  sound() { return \"...\"; }
+}
+mixin Loud {
+  sound() { return super.sound() + \"!\"; }
+  shout() { return this.name() + \" shouts\"; }
+}
+mixin Named {
+  name() { return \"named\"; }
+}
+class Dog < Animal with Loud, Named {
+  name() { return \"dog\"; }
+}
+class Cat < Animal with Named, Loud {}";
+        // The class wins over its mixins, mixins win over the superclass.
+        let cases = [
+            ("Dog().shout()", "dog shouts"),
+            ("Cat().shout()", "named shouts"),
+            ("Cat().sound()", "...!"),
+            ("Animal().sound()", "..."),
+        ];
+        for (call, expected) in cases {
+            assert_eq!(
+                result_of(&format!("{animals} var result = {call};")),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_mixin_conflicts() {
+        let mixins = "mixin A { f() { return 1; } } mixin B { f() { return 2; } }";
+        assert_eq!(
+            result_of(&format!("{mixins} class C with B, A {{}}")),
+            "Method 'f' is defined in both 'B' and 'A'."
+        );
+        // Unless the class defines the method itself.
+        assert_eq!(
+            result_of(&format!(
+                "{mixins} class C with A, B {{ f() {{ return 3; }} }} var result = C().f();"
+            )),
+            "3"
+        );
+        assert_eq!(
+            result_of("class A {} class B with A {}"),
+            "Can only use mixins."
+        );
+    }
+}
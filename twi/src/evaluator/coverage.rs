@@ -0,0 +1,367 @@
+// Line and branch coverage of a script, written in the lcov tracefile format and as a
+// short summary:
+//
+// Coverage of lib.lox:
+//   lines     10/12 (83.3%), missed: 4, 7-8
+//   branches  5/8 (62.5%), missed: 3 true, 9 false, 9 true
+//
+// A line is hit each time the execution comes to it from another line. Every condition of `if`,
+// `while` and `for`, and every `and`/`or`, is a branch with two ways: true and false.
+
+use super::{environment::Environment, function::Function, Evaluator, Hook};
+use crate::ast::expr::Expr;
+use crate::ast::stmt::{Param, Stmt};
+use crate::lexer::scanner::Scanner;
+use crate::parser::Parser;
+use crate::Lox;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+struct Branch {
+    line: usize,
+    counts: [u64; 2], // times the condition was true and false
+}
+
+pub struct Coverage {
+    path: String,
+    lines: BTreeMap<usize, u64>,
+    last_line: usize,      // of the previous statement in this call
+    callers: Vec<usize>,   // last lines of the calls below
+    branches: Vec<Branch>, // in the source order
+    // Branch of each condition and logical expression, a condition may be one too.
+    conditions: HashMap<Expr, usize>,
+    logicals: HashMap<Expr, usize>,
+}
+
+impl Coverage {
+    // Finds all lines and branches of the script, so the ones never run are reported too.
+    pub fn new(path: &str, source: &str) -> Self {
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        interpreter.set_quiet(true);
+        let mut scanner = Scanner::new(&mut interpreter, source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+        let statements = Parser::new(&mut interpreter, tokens).parse();
+
+        let mut coverage = Coverage {
+            path: path.to_string(),
+            lines: BTreeMap::new(),
+            last_line: 0,
+            callers: Vec::new(),
+            branches: Vec::new(),
+            conditions: HashMap::new(),
+            logicals: HashMap::new(),
+        };
+        for statement in statements.iter().flatten() {
+            coverage.add_stmt(statement);
+        }
+        coverage
+    }
+
+    fn add_stmt(&mut self, stmt: &Stmt) {
+        // A block has no code of its own.
+        if !matches!(stmt, Stmt::Block(_)) {
+            if let Some(line) = stmt.line() {
+                self.lines.entry(line).or_default();
+            }
+        }
+
+        match stmt {
+            Stmt::Block(statements) | Stmt::Getter(_, statements) | Stmt::Mixin(_, statements) => {
+                statements.iter().for_each(|s| self.add_stmt(s))
+            }
+            Stmt::Class(_, _, _, methods, members) => {
+                methods.iter().chain(members).for_each(|s| self.add_stmt(s))
+            }
+            Stmt::Function(_, params, _, body) => {
+                for param in params {
+                    if let Param::Default(_, _, value) = param {
+                        self.add_expr(value);
+                    }
+                }
+                body.iter().for_each(|s| self.add_stmt(s));
+            }
+            Stmt::Const(_, _, expr)
+            | Stmt::Expression(expr)
+            | Stmt::Print(_, expr)
+            | Stmt::Return(_, expr)
+            | Stmt::Var(_, _, expr) => self.add_expr(expr),
+            Stmt::Enum(..) => (),
+            Stmt::If(condition, then_branch, else_branch) => {
+                self.add_condition(condition);
+                self.add_expr(condition);
+                self.add_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.add_stmt(else_branch);
+                }
+            }
+            Stmt::Match(_, value, cases) => {
+                self.add_expr(value);
+                cases.iter().for_each(|(_, body)| self.add_stmt(body));
+            }
+            Stmt::While(condition, body) => {
+                self.add_condition(condition);
+                self.add_expr(condition);
+                self.add_stmt(body);
+            }
+        }
+    }
+
+    fn add_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Logical(left, op, right) => {
+                let id = self.add_branch(op.get_line());
+                self.logicals.insert(expr.clone(), id);
+                self.add_expr(left);
+                self.add_expr(right);
+            }
+            Expr::Binary(left, _, right)
+            | Expr::Index(left, _, right)
+            | Expr::Set(left, _, right) => {
+                self.add_expr(left);
+                self.add_expr(right);
+            }
+            Expr::Call(callee, _, arguments) => {
+                self.add_expr(callee);
+                arguments.iter().for_each(|a| self.add_expr(a));
+            }
+            Expr::List(_, elements) => elements.iter().for_each(|e| self.add_expr(e)),
+            Expr::Assign(_, expr)
+            | Expr::Get(expr, _)
+            | Expr::Grouping(expr)
+            | Expr::Unary(_, expr)
+            | Expr::Spread(_, expr) => self.add_expr(expr),
+            Expr::LiteralExpr(_) | Expr::Super(..) | Expr::This(_) | Expr::Variable(_) => (),
+        }
+    }
+
+    fn add_condition(&mut self, condition: &Expr) {
+        // A constant condition, e.g. of `for (;;)`, has no line and isn't a branch.
+        if let Some(line) = condition.line() {
+            let id = self.add_branch(line);
+            self.conditions.insert(condition.clone(), id);
+        }
+    }
+
+    fn add_branch(&mut self, line: usize) -> usize {
+        self.branches.push(Branch {
+            line,
+            counts: [0, 0],
+        });
+        self.branches.len() - 1
+    }
+
+    fn hit(&mut self, line: usize) {
+        if line != self.last_line {
+            *self.lines.entry(line).or_default() += 1;
+            self.last_line = line;
+        }
+    }
+
+    fn take(&mut self, id: Option<usize>, is_true: bool) {
+        if let Some(id) = id {
+            self.branches[id].counts[usize::from(!is_true)] += 1;
+        }
+    }
+
+    // Report in the lcov tracefile format, e.g. for `genhtml`. Branches of the same line
+    // are numbered as blocks in the source order.
+    pub fn lcov(&self) -> String {
+        let mut out = format!("TN:\nSF:{}\n", self.path);
+
+        let mut blocks: HashMap<usize, usize> = HashMap::new(); // line -> next block
+        for branch in &self.branches {
+            let block = blocks.entry(branch.line).or_default();
+            *block += 1;
+            let block = *block - 1;
+            for (way, count) in branch.counts.iter().enumerate() {
+                let taken = match branch.counts {
+                    [0, 0] => "-".to_string(),
+                    _ => count.to_string(),
+                };
+                let _ = writeln!(out, "BRDA:{},{block},{way},{taken}", branch.line);
+            }
+        }
+        let (branches_hit, branches_found) = self.branches_hit();
+        let _ = writeln!(out, "BRF:{branches_found}\nBRH:{branches_hit}");
+
+        for (line, count) in &self.lines {
+            let _ = writeln!(out, "DA:{line},{count}");
+        }
+        let lines_hit = self.lines.values().filter(|&&count| count > 0).count();
+        let _ = writeln!(out, "LF:{}\nLH:{lines_hit}", self.lines.len());
+
+        out.push_str("end_of_record\n");
+        out
+    }
+
+    pub fn summary(&self) -> String {
+        let missed: Vec<usize> = self
+            .lines
+            .iter()
+            .filter(|(_, &count)| count == 0)
+            .map(|(&line, _)| line)
+            .collect();
+        let lines_hit = self.lines.len() - missed.len();
+        let mut out = format!("Coverage of {}:\n", self.path);
+        let _ = write!(
+            out,
+            "  lines     {lines_hit}/{} ({})",
+            self.lines.len(),
+            percent(lines_hit, self.lines.len())
+        );
+        if !missed.is_empty() {
+            let _ = write!(out, ", missed: {}", ranges(&missed));
+        }
+
+        let (branches_hit, branches_found) = self.branches_hit();
+        let _ = write!(
+            out,
+            "\n  branches  {branches_hit}/{branches_found} ({})",
+            percent(branches_hit, branches_found)
+        );
+        let mut missed = Vec::new();
+        for branch in &self.branches {
+            for (way, name) in ["true", "false"].iter().enumerate() {
+                if branch.counts[way] == 0 {
+                    missed.push(format!("{} {name}", branch.line));
+                }
+            }
+        }
+        if !missed.is_empty() {
+            let _ = write!(out, ", missed: {}", missed.join(", "));
+        }
+        out.push('\n');
+        out
+    }
+
+    // Ways of the branches that were taken, and all of them.
+    fn branches_hit(&self) -> (usize, usize) {
+        let hit = self
+            .branches
+            .iter()
+            .flat_map(|branch| branch.counts)
+            .filter(|&count| count > 0)
+            .count();
+        (hit, self.branches.len() * 2)
+    }
+}
+
+impl Hook for Coverage {
+    fn before_stmt(&mut self, stmt: &Stmt, _: &Environment) {
+        if matches!(stmt, Stmt::Block(_)) {
+            return;
+        }
+        if let Some(line) = stmt.line() {
+            self.hit(line);
+        }
+    }
+
+    fn before_call(&mut self, _: &Function) {
+        self.callers.push(self.last_line);
+        self.last_line = 0;
+    }
+
+    fn after_call(&mut self, _: &Function) {
+        self.last_line = self.callers.pop().unwrap_or_default();
+    }
+
+    fn condition(&mut self, condition: &Expr, is_true: bool) {
+        // A loop comes back to the line of its condition after the body.
+        if let Some(line) = condition.line() {
+            self.hit(line);
+        }
+        self.take(self.conditions.get(condition).copied(), is_true);
+    }
+
+    fn logical(&mut self, expr: &Expr, is_true: bool) {
+        self.take(self.logicals.get(expr).copied(), is_true);
+    }
+}
+
+fn percent(hit: usize, found: usize) -> String {
+    match found {
+        0 => "100.0%".to_string(),
+        _ => format!("{:.1}%", hit as f64 * 100.0 / found as f64),
+    }
+}
+
+// Sorted line numbers, e.g. `4, 7-9`.
+fn ranges(lines: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{start}-{end}"),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test_coverage {
+    use super::Coverage;
+    use crate::evaluator::{environment::Environment, Evaluator};
+    use crate::Lox;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_lcov() {
+        let source = "fun sign(n) {
+  if (n > 0 and n < 100) return 1;
+  if (n < 0) {
+    return -1;
+  }
+  return 0;
+}
+print sign(5);
+print sign(0);";
+        let path = std::env::temp_dir().join("twi_test_coverage.lox");
+        fs::write(&path, source).unwrap();
+        let path = path.to_str().unwrap();
+
+        let coverage = Rc::new(RefCell::new(Coverage::new(path, source)));
+        let mut evaluator = Evaluator::new(Environment::new(None));
+        evaluator.set_hook(coverage.clone());
+        assert_eq!(Lox::new(evaluator).run_file_status(path), 0);
+
+        let coverage = coverage.borrow();
+        let lcov = coverage.lcov();
+        let records: Vec<&str> = lcov.lines().skip(2).collect();
+        assert_eq!(
+            records,
+            [
+                "BRDA:2,0,0,1",
+                "BRDA:2,0,1,1",
+                "BRDA:2,1,0,1",
+                "BRDA:2,1,1,1",
+                "BRDA:3,0,0,0",
+                "BRDA:3,0,1,1",
+                "BRF:6",
+                "BRH:5",
+                "DA:1,1",
+                "DA:2,2",
+                "DA:3,1",
+                "DA:4,0",
+                "DA:6,1",
+                "DA:8,1",
+                "DA:9,1",
+                "LF:7",
+                "LH:6",
+                "end_of_record",
+            ]
+        );
+        assert!(coverage.summary().ends_with(
+            "lines     6/7 (85.7%), missed: 4\n  branches  5/6 (83.3%), missed: 3 true\n"
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+// Step debugger on top of the evaluator hook, driven by commands, one per line:
+//
+// (lox) break 3
+// (lox) continue
+// fib.lox:3 in fib()
+//    3 |   if (n < 2) return n;
+// (lox) print n
+// n = 10
+//
+// It stops only at the first statement of a line.
+
+use super::{environment::Environment, function::Function, Hook, Object};
+use crate::ast::stmt::Stmt;
+use crate::lexer::token::{Literal, Token, TokenType};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process;
+
+const HELP: &str = "Commands:
+  break, b LINE       Stop at the line
+  delete, d LINE      Remove the breakpoint
+  continue, c         Run until the next breakpoint
+  step, s             Go to the next line, entering calls
+  next, n             Go to the next line of this function
+  finish, f           Run until this function returns
+  print, p NAME       Print a variable, e.g. `p point.x`
+  env, e              Print all variables by scope, innermost first
+  backtrace, bt       Print the call stack
+  quit, q             Stop the program
+End of input runs the program to the end.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Continue,
+    Step,
+    Next(usize, usize), // depth and line where the command was given
+    Finish(usize),
+}
+
+struct Frame {
+    name: String,
+    line: usize,
+}
+
+pub struct Debugger {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    path: String,
+    source: String,
+    mode: Mode,
+    breakpoints: Vec<usize>,
+    frames: Vec<Frame>,
+    last: Option<(usize, usize)>, // depth and line of the previous statement
+}
+
+impl Debugger {
+    // Stops at the first line of the program.
+    pub fn new(input: Box<dyn BufRead>, output: Box<dyn Write>, path: &str, source: &str) -> Self {
+        Debugger {
+            input,
+            output,
+            path: path.to_string(),
+            source: source.to_string(),
+            mode: Mode::Step,
+            breakpoints: Vec::new(),
+            frames: vec![Frame {
+                name: "script".to_string(),
+                line: 0,
+            }],
+            last: None,
+        }
+    }
+
+    pub fn stdio(path: &str, source: &str) -> Self {
+        let input = Box::new(BufReader::new(io::stdin()));
+        Self::new(input, Box::new(io::stdout()), path, source)
+    }
+
+    fn show_stop(&mut self, line: usize) -> io::Result<()> {
+        let name = &self.frames[self.frames.len() - 1].name;
+        writeln!(self.output, "{}:{line} in {name}", self.path)?;
+        if let Some(text) = self.source.lines().nth(line.wrapping_sub(1)) {
+            writeln!(self.output, "{line:>4} | {text}")?;
+        }
+        Ok(())
+    }
+
+    // Reads commands until one of them resumes the program.
+    fn prompt(&mut self, environment: &Environment, line: usize) -> io::Result<()> {
+        let depth = self.frames.len();
+        loop {
+            write!(self.output, "(lox) ")?;
+            self.output.flush()?;
+
+            let mut input = String::new();
+            if self.input.read_line(&mut input)? == 0 {
+                writeln!(self.output)?;
+                self.mode = Mode::Continue;
+                self.breakpoints.clear();
+                return Ok(());
+            }
+            let (command, argument) = match input.trim().split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (input.trim(), ""),
+            };
+
+            match command {
+                "" => (),
+                "break" | "b" => match argument.parse() {
+                    Ok(line) => {
+                        writeln!(self.output, "Breakpoint at {}:{line}", self.path)?;
+                        self.breakpoints.push(line);
+                    }
+                    Err(_) => writeln!(self.output, "Expect LINE.")?,
+                },
+                "delete" | "d" => match argument.parse::<usize>() {
+                    Ok(line) if self.breakpoints.contains(&line) => {
+                        self.breakpoints.retain(|&b| b != line)
+                    }
+                    Ok(line) => writeln!(self.output, "No breakpoint at {}:{line}", self.path)?,
+                    Err(_) => writeln!(self.output, "Expect LINE.")?,
+                },
+                "continue" | "c" => return self.resume(Mode::Continue),
+                "step" | "s" => return self.resume(Mode::Step),
+                "next" | "n" => return self.resume(Mode::Next(depth, line)),
+                "finish" | "f" => return self.resume(Mode::Finish(depth)),
+                "print" | "p" => match evaluate(environment, argument) {
+                    Ok(value) => writeln!(self.output, "{argument} = {value}")?,
+                    Err(message) => writeln!(self.output, "{message}")?,
+                },
+                "env" | "e" => self.print_environment(environment)?,
+                "backtrace" | "bt" => {
+                    for (i, frame) in self.frames.iter().rev().enumerate() {
+                        let Frame { name, line } = frame;
+                        writeln!(self.output, "#{i} {name} at {}:{line}", self.path)?;
+                    }
+                }
+                "help" | "h" => writeln!(self.output, "{HELP}")?,
+                "quit" | "q" => process::exit(0),
+                _ => writeln!(
+                    self.output,
+                    "Unknown command '{command}'. Type 'help' for a list of commands."
+                )?,
+            }
+        }
+    }
+
+    fn resume(&mut self, mode: Mode) -> io::Result<()> {
+        self.mode = mode;
+        Ok(())
+    }
+
+    fn print_environment(&mut self, environment: &Environment) -> io::Result<()> {
+        for (i, scope) in environment.scopes().into_iter().enumerate() {
+            let mut values: Vec<(String, Object)> = scope
+                .values()
+                .into_iter()
+                .filter(|(_, value)| !matches!(value, Object::Time(_) | Object::Len(_)))
+                .collect();
+            values.sort_by(|a, b| a.0.cmp(&b.0));
+            let values: Vec<String> = values
+                .iter()
+                .map(|(name, value)| format!("{name} = {value}"))
+                .collect();
+            writeln!(self.output, "#{i} {}", values.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Hook for Debugger {
+    fn before_stmt(&mut self, stmt: &Stmt, environment: &Environment) {
+        let Some(line) = stmt.line() else {
+            return;
+        };
+        let depth = self.frames.len();
+        self.frames[depth - 1].line = line;
+        if self.last.replace((depth, line)) == Some((depth, line)) {
+            return;
+        }
+
+        let is_stop = match self.mode {
+            Mode::Continue => false,
+            Mode::Step => true,
+            Mode::Next(d, l) => depth < d || (depth == d && line != l),
+            Mode::Finish(d) => depth < d,
+        };
+        if !is_stop && !self.breakpoints.contains(&line) {
+            return;
+        }
+
+        // Errors of writing to the output are not worth stopping the program.
+        let _ = self
+            .show_stop(line)
+            .and_then(|_| self.prompt(environment, line));
+    }
+
+    fn before_call(&mut self, function: &Function) {
+        self.frames.push(Frame {
+            name: format!("{}()", function.name().get_lexeme()),
+            line: function.name().get_line(),
+        });
+    }
+
+    fn after_call(&mut self, _: &Function) {
+        self.frames.pop();
+        // The rest of the caller's line doesn't count as a new stop.
+        let depth = self.frames.len();
+        self.last = Some((depth, self.frames[depth - 1].line));
+    }
+}
+
+// A variable followed by `.name` accesses of fields and enum variants.
+fn evaluate(environment: &Environment, expression: &str) -> Result<Object, String> {
+    let mut names = expression.split('.').map(str::trim);
+    let name = names.next().unwrap_or_default();
+    if name.is_empty() {
+        return Err("Expect variable name.".to_string());
+    }
+
+    let mut value = environment
+        .lookup(name)
+        .ok_or_else(|| format!("Undefined variable '{name}'."))?;
+    for field in names {
+        let token = Token::new(TokenType::Identifier, field, Literal::None, 0);
+        let property = match &value {
+            Object::Instance(instance) => instance.field(field),
+            Object::Enum(enumeration) => enumeration.get(&token).ok(),
+            Object::Variant(variant) => variant.get(&token).ok(),
+            _ => None,
+        };
+        value = property.ok_or_else(|| format!("Undefined property '{field}'."))?;
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test_debugger {
+    use super::Debugger;
+    use crate::evaluator::{environment::Environment, Evaluator};
+    use crate::{Lox, Parser, Scanner};
+    use std::cell::RefCell;
+    use std::io::{self, Cursor, Write};
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn debug(source: &str, commands: &str) -> String {
+        let output = Output::default();
+        let input = Box::new(Cursor::new(commands.to_string()));
+        let debugger = Debugger::new(input, Box::new(output.clone()), "test.lox", source);
+        let mut evaluator = Evaluator::new(Environment::new(None));
+        evaluator.set_hook(Rc::new(RefCell::new(debugger)));
+
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        let mut scanner = Scanner::new(&mut interpreter, source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+        for statement in Parser::new(&mut interpreter, tokens).parse() {
+            evaluator.execute(&statement.unwrap()).unwrap();
+        }
+
+        let text = String::from_utf8(output.0.take()).unwrap();
+        text.replace("(lox) ", "")
+    }
+
+    #[test]
+    fn test_session() {
+        let source = "fun add(a, b) {
+  var sum = a + b;
+  return sum;
+}
+{
+  var x = 1;
+  print add(x, 2);
+}
+print 4;";
+        let commands = "b 2\nc\ne\nbt\nn\np sum\nf\np x\n";
+        assert_eq!(
+            debug(source, commands),
+            "test.lox:1 in script
+   1 | fun add(a, b) {
+Breakpoint at test.lox:2
+test.lox:2 in add()
+   2 |   var sum = a + b;
+#0 a = 1, b = 2
+#1 x = 1
+#2 add = Function: <fun add>.
+#0 add() at test.lox:2
+#1 script at test.lox:7
+test.lox:3 in add()
+   3 |   return sum;
+sum = 3
+test.lox:9 in script
+   9 | print 4;
+Undefined variable 'x'.
+
+"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::evaluator::{Object, RuntimeError};
+use crate::Token;
+use std::fmt::Display;
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub struct Enum {
+    name: String,
+    variants: Vec<Rc<Variant>>,
+}
+
+impl Enum {
+    // Each variant is given with the names of its payload fields.
+    pub fn new(name: String, variants: Vec<(String, Vec<String>)>) -> Self {
+        let variants = variants
+            .into_iter()
+            .map(|(variant, fields)| Rc::new(Variant::new(name.clone(), variant, fields)))
+            .collect();
+        Enum { name, variants }
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Object, RuntimeError> {
+        let name_str = name.get_lexeme();
+        match self.variants.iter().find(|v| &*v.name == name_str) {
+            Some(variant) => Ok(Object::Variant(Rc::clone(variant))),
+            None => {
+                let msg = format!("Undefined variant '{}' in enum '{}'.", name_str, self.name);
+                Err(RuntimeError::new(name, &msg))
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    pub fn variant(&self, index: usize) -> Option<Object> {
+        self.variants
+            .get(index)
+            .map(|variant| Object::Variant(Rc::clone(variant)))
+    }
+}
+
+// An enum is equal only to itself.
+impl PartialEq for Enum {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Display for Enum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Variant {
+    enum_name: String,
+    name: Rc<str>, // shared by all values of the variant
+    fields: Vec<String>,
+    values: Vec<Object>,
+}
+
+impl Variant {
+    fn new(enum_name: String, name: String, fields: Vec<String>) -> Self {
+        Variant {
+            enum_name,
+            name: name.into(),
+            fields,
+            values: Vec::new(),
+        }
+    }
+
+    // A variant with payload that is not filled yet works as its constructor.
+    pub fn is_constructor(&self) -> bool {
+        !self.fields.is_empty() && self.values.is_empty()
+    }
+
+    // Whether both are values of one variant, whatever their payloads are.
+    pub fn is_same_variant(&self, other: &Variant) -> bool {
+        Rc::ptr_eq(&self.name, &other.name)
+    }
+
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        (self.fields.len(), Some(self.fields.len()))
+    }
+
+    pub fn call(&self, arguments: Vec<Object>) -> Object {
+        Object::Variant(Rc::new(Variant {
+            enum_name: self.enum_name.clone(),
+            name: Rc::clone(&self.name),
+            fields: self.fields.clone(),
+            values: arguments,
+        }))
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Object, RuntimeError> {
+        let position = self.fields.iter().position(|f| f == name.get_lexeme());
+        match position.and_then(|i| self.values.get(i)) {
+            Some(value) => Ok(value.clone()),
+            None => {
+                let msg = format!("Undefined payload field '{}'.", name.get_lexeme());
+                Err(RuntimeError::new(name, &msg))
+            }
+        }
+    }
+
+    fn stringify(&self) -> String {
+        let mut s = format!("{}.{}", self.enum_name, self.name);
+        if !self.values.is_empty() {
+            let values: Vec<String> = self.values.iter().map(|v| v.to_string()).collect();
+            s.push_str(&format!("({})", values.join(", ")));
+        }
+        s
+    }
+}
+
+// Values of the same variant are equal if their payloads are. Variants of different enums
+// with the same names are different.
+impl PartialEq for Variant {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.name, &other.name) && self.values == other.values
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.stringify())
+    }
+}
@@ -1,4 +1,5 @@
-use crate::evaluator::native::Clock;
+use crate::diagnostic::suggest;
+use crate::evaluator::native::{Clock, Len};
 use crate::evaluator::Object;
 use crate::evaluator::RuntimeError;
 use crate::lexer::token::Token;
@@ -15,6 +16,7 @@ impl Environment {
         let mut values = HashMap::new();
         // globals
         values.insert("clock".to_string(), Object::Time(Clock));
+        values.insert("len".to_string(), Object::Len(Len));
 
         // Very dirty "unique" id generation for each environment
         // let id = rand::random::<i128>().to_string();
@@ -31,6 +33,21 @@ impl Environment {
         self.enclosing.clone()
     }
 
+    // This environment and its ancestors, innermost first.
+    pub fn scopes(&self) -> Vec<&Environment> {
+        let mut scopes = vec![self];
+        while let Some(enclosing) = scopes[scopes.len() - 1].enclosing.as_deref() {
+            scopes.push(enclosing);
+        }
+        scopes
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        self.scopes()
+            .into_iter()
+            .find_map(|scope| scope.values.get(name).cloned())
+    }
+
     fn _ancestor_clone(&self, distance: usize) -> Environment {
         let mut env = self.clone();
         let msg = format!("Can't get clone of ancestor ({})!", distance);
@@ -65,11 +82,7 @@ impl Environment {
             return Ok(());
         }
 
-        let mut msg = String::new();
-        msg.push_str("Undefined variable '");
-        msg.push_str(name.get_lexeme());
-        msg.push_str("'.");
-        Err(RuntimeError::new(name, &msg))
+        Err(RuntimeError::new(name, &self.undefined(name)))
     }
 
     pub fn _assign_at(&mut self, distance: usize, name: Token, value: Object) {
@@ -108,11 +121,23 @@ impl Environment {
             return env.get(name);
         }
 
-        let mut msg = String::new();
-        msg.push_str("Undefined variable '");
-        msg.push_str(name.get_lexeme());
-        msg.push_str("'.");
-        Err(RuntimeError::new(&name, &msg))
+        Err(RuntimeError::new(&name, &self.undefined(&name)))
+    }
+
+    // Error message with the most similar defined name, if any.
+    fn undefined(&self, name: &Token) -> String {
+        let mut names: Vec<&str> = Vec::new();
+        let mut env = Some(self);
+        while let Some(environment) = env {
+            names.extend(environment.values.keys().map(|k| k.as_str()));
+            env = environment.enclosing.as_deref();
+        }
+
+        let msg = format!("Undefined variable '{}'.", name.get_lexeme());
+        match suggest(name.get_lexeme(), names) {
+            Some(similar) => format!("{msg} Did you mean '{similar}'?"),
+            None => msg,
+        }
     }
 
     pub fn _ref_global(&self) -> &Environment {
@@ -1,14 +1,21 @@
 use crate::evaluator::{Environment, Evaluator, Instance, Literal, Object, RuntimeError};
-use crate::{ast::stmt::Stmt, Token, TokenType};
+use crate::{
+    ast::stmt::{Param, Stmt},
+    Token, TokenType,
+};
+use std::cell::RefCell;
 use std::fmt;
+use std::mem;
+use std::rc::Rc;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Function {
     name: Token,
-    parameters: Vec<Token>,
+    parameters: Vec<Param>,
     body: Vec<Stmt>,
     closure: Environment,
     is_initializer: bool,
+    is_getter: bool,
 }
 
 impl Function {
@@ -19,36 +26,81 @@ impl Function {
         is_initializer: bool,
     ) -> Result<Function, RuntimeError> {
         match declaration {
-            Stmt::Function(name, parameters, body) => Ok(Function {
+            Stmt::Function(name, parameters, _, body) => Ok(Function {
                 name,
                 parameters,
                 body,
                 closure,
                 is_initializer,
+                is_getter: false,
+            }),
+            Stmt::Getter(name, body) => Ok(Function {
+                name,
+                parameters: Vec::new(),
+                body,
+                closure,
+                is_initializer,
+                is_getter: true,
             }),
             _ => Err(RuntimeError::new(
                 tok,
-                "Can create Function object only from Stmt::Function.",
+                "Can create Function object only from Stmt::Function or Stmt::Getter.",
             )),
         }
     }
 
-    pub fn arity(&self) -> usize {
-        self.parameters.len()
+    // Minimal and maximal (if any) number of arguments.
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        let mut min = 0;
+        let mut max = Some(0);
+        for param in &self.parameters {
+            match param {
+                Param::Required(_, _) => min += 1,
+                Param::Default(_, _, _) => (),
+                Param::Rest(_, _) => {
+                    max = None;
+                    break;
+                }
+            }
+            max = max.map(|m| m + 1);
+        }
+        (min, max)
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn is_getter(&self) -> bool {
+        self.is_getter
     }
 
     pub fn bind(&self, instance: &Instance) -> Result<Function, RuntimeError> {
         let mut environment = Environment::new(Some(Box::new(self.closure.clone())));
         environment.define("this".to_string(), Object::Instance(instance.clone()));
-        let declaration = Stmt::Function(
-            self.name.clone(),
-            self.parameters.clone(),
-            self.body.clone(),
-        );
+        let declaration = if self.is_getter {
+            Stmt::Getter(self.name.clone(), self.body.clone())
+        } else {
+            Stmt::Function(
+                self.name.clone(),
+                self.parameters.clone(),
+                None,
+                self.body.clone(),
+            )
+        };
         // println!("\n\n\n\n\n\n\n\n\n{:#?}\n\n\n\n\n\n\n\n\n", environment);
         Function::new(&self.name, declaration, environment, self.is_initializer)
     }
 
+    // Copy of a mixin method for the class that uses it, so `super` refers to its superclass.
+    pub fn with_super(&self, superclass: Object) -> Function {
+        let mut environment = Environment::new(Some(Box::new(self.closure.clone())));
+        environment.define("super".to_string(), superclass);
+        let mut function = self.clone();
+        function.closure = environment;
+        function
+    }
+
     pub fn call(
         &mut self,
         evaluator: &mut Evaluator,
@@ -64,14 +116,35 @@ impl Function {
         // println!("{:#?}", evaluator.locals);
         // crate::evaluator::environment::_print_envs(env.clone());
 
-        for i in 0..self.parameters.len() {
-            env.define(
-                self.parameters.get(i).unwrap().get_lexeme().to_string(),
-                arguments.get(i).unwrap().clone(),
-            )
+        let mut arguments = arguments.into_iter();
+        for param in &self.parameters {
+            let value = match param {
+                Param::Required(_, _) => arguments.next().unwrap(),
+                Param::Default(_, _, default) => match arguments.next() {
+                    Some(value) => value,
+                    None => {
+                        // Defaults see the parameters before them.
+                        let previous = mem::replace(&mut evaluator.environment, env);
+                        let value = evaluator.evaluate(default);
+                        env = mem::replace(&mut evaluator.environment, previous);
+                        value?
+                    }
+                },
+                Param::Rest(_, _) => {
+                    Object::List(Rc::new(RefCell::new(arguments.by_ref().collect())))
+                }
+            };
+            env.define(param.get_name().get_lexeme().to_string(), value);
         }
 
+        let hook = evaluator.hook.clone();
+        if let Some(hook) = &hook {
+            hook.borrow_mut().before_call(self);
+        }
         let (eval_res, closure) = evaluator.execute_block_fun(&self.body, env, depth);
+        if let Some(hook) = &hook {
+            hook.borrow_mut().after_call(self);
+        }
         self.closure = closure;
         match eval_res {
             Ok(_) => {
@@ -126,7 +199,7 @@ fn recover_env(current: Environment, closure: Environment) -> (Environment, usiz
     // TODO: consider case when only 'clock' (and possibly other built-ins) are in scopes
     // (they are considered the same, but they can be just temporarily empty)
     'over_closures: loop {
-        for (name, _) in clos.values().iter() {
+        for name in clos.values().keys() {
             if !curr_vals.contains_key(name) {
                 match curr.enclosing() {
                     Some(boxed_env) => {
@@ -1,4 +1,4 @@
-use crate::evaluator::{Class, Object, RuntimeError};
+use crate::evaluator::{Class, Function, Object, RuntimeError};
 use crate::Token;
 use std::collections::HashMap;
 use std::fmt::Display;
@@ -36,6 +36,21 @@ impl Instance {
         Err(RuntimeError::new(name, &msg))
     }
 
+    pub fn find_method(&self, name: &str) -> Result<Option<Function>, RuntimeError> {
+        match self.class.find_method(name) {
+            Some(method) => Ok(Some(method.bind(self)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_instance_of(&self, class: &Class) -> bool {
+        self.class.is_subclass_of(class)
+    }
+
+    pub fn field(&self, name: &str) -> Option<Object> {
+        self.fields.get(name).cloned()
+    }
+
     fn stringify(&self) -> String {
         format!("{} instance", self.class)
     }
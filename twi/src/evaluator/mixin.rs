@@ -0,0 +1,33 @@
+use crate::evaluator::Function;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mixin {
+    name: String,
+    methods: HashMap<String, Function>,
+}
+
+impl Mixin {
+    pub fn new(name: String, methods: HashMap<String, Function>) -> Self {
+        Mixin { name, methods }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn methods(&self) -> &HashMap<String, Function> {
+        &self.methods
+    }
+
+    fn stringify(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Display for Mixin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.stringify())
+    }
+}
@@ -1,21 +1,30 @@
 pub mod class;
+pub mod coverage;
+pub mod debugger;
+pub mod enums;
 pub mod environment;
 pub mod function;
 pub mod instance;
+pub mod mixin;
 pub mod native;
 
 use crate::ast::expr::Expr;
-use crate::ast::stmt::Stmt;
+use crate::ast::stmt::{Pattern, Stmt};
 use crate::lexer::token::{Literal, Token, TokenType};
 use crate::Visitor;
 use class::Class;
+use enums::{Enum, Variant};
 use environment::Environment;
 use function::Function;
 use instance::Instance;
-use native::Clock;
+use mixin::Mixin;
+use native::{Clock, Len};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::Display;
+use std::io::{self, Write};
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub struct RuntimeError {
@@ -73,11 +82,16 @@ impl RuntimeError {
 pub enum Object {
     Bool(bool),
     Cls(Class),
+    Enum(Rc<Enum>),
     Fun(Function),
     Instance(Instance),
+    Len(Len),
+    List(Rc<RefCell<Vec<Object>>>),
+    Mixin(Mixin),
     Time(Clock),
     Number(f64),
     String(String),
+    Variant(Rc<Variant>),
     None,
 }
 
@@ -86,8 +100,21 @@ impl Display for Object {
         match self {
             Object::Bool(b) => write!(f, "{b}"),
             Object::Cls(c) => write!(f, "{c}"),
+            Object::Enum(e) => write!(f, "{e}"),
             Object::Fun(fun) => write!(f, "Function: {}.", fun),
             Object::Instance(instance) => write!(f, "{instance}"),
+            Object::Len(l) => write!(f, "Function: {}.", l),
+            Object::List(list) => {
+                write!(f, "[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            }
+            Object::Mixin(mixin) => write!(f, "{mixin}"),
             Object::Time(c) => write!(f, "Function: {}.", c),
             Object::Number(n) => {
                 if n.fract() == 0.0 {
@@ -96,6 +123,7 @@ impl Display for Object {
                 write!(f, "{}", (*n))
             }
             Object::String(s) => write!(f, "{s}"),
+            Object::Variant(v) => write!(f, "{v}"),
             Object::None => write!(f, "nil"),
         }
     }
@@ -107,6 +135,8 @@ impl Object {
             Object::Cls(_) => Ok(()),
             Object::Fun(_) => Ok(()),
             Object::Time(_) => Ok(()),
+            Object::Len(_) => Ok(()),
+            Object::Variant(v) if v.is_constructor() => Ok(()),
             _ => Err(RuntimeError::new(
                 tok,
                 "Can only call functions and classes.",
@@ -114,33 +144,62 @@ impl Object {
         }
     }
 
-    fn arity(&self) -> usize {
+    fn arity(&self) -> (usize, Option<usize>) {
         match self {
             Object::Cls(c) => c.arity(),
             Object::Fun(f) => f.arity(),
             Object::Time(c) => c.arity(),
-            _ => panic!("arity() is defined only for Function, Class and clock()."),
+            Object::Len(l) => l.arity(),
+            Object::Variant(v) => v.arity(),
+            _ => panic!("arity() is defined only for Function, Class and native functions."),
         }
     }
 
     fn call(
         &mut self,
         evaluator: &mut Evaluator,
+        paren: &Token,
         arguments: Vec<Object>,
     ) -> Result<Object, RuntimeError> {
         match self {
             Object::Cls(c) => c.call(evaluator, arguments),
             Object::Fun(f) => f.call(evaluator, arguments),
             Object::Time(c) => c.call(evaluator, arguments),
-            _ => panic!("call() is defined only for Function, Class and clock()."),
+            Object::Len(l) => l.call(paren, arguments),
+            Object::Variant(v) => Ok(v.call(arguments)),
+            _ => panic!("call() is defined only for Function, Class and native functions."),
         }
     }
 }
 
+// "2", "1 to 3" or "at least 1".
+pub(crate) fn arity_to_string(min: usize, max: Option<usize>) -> String {
+    match max {
+        Some(max) if max == min => min.to_string(),
+        Some(max) => format!("{min} to {max}"),
+        None => format!("at least {min}"),
+    }
+}
+
+// Observer of the evaluation, e.g. a debugger.
+pub trait Hook {
+    // Before each statement, with the environment it runs in.
+    fn before_stmt(&mut self, stmt: &Stmt, environment: &Environment);
+    // Around each call of a Lox function (including methods, getters and initializers).
+    fn before_call(&mut self, function: &Function);
+    fn after_call(&mut self, function: &Function);
+    // After the condition of an `if` or a `while` is evaluated.
+    fn condition(&mut self, _condition: &Expr, _is_true: bool) {}
+    // After the left operand of `and`/`or` is evaluated, with the whole logical expression.
+    fn logical(&mut self, _expr: &Expr, _is_true: bool) {}
+}
+
 #[derive(Clone)]
 pub struct Evaluator {
     environment: Environment,
     _locals: HashMap<Expr, usize>,
+    hook: Option<Rc<RefCell<dyn Hook>>>,
+    output: Rc<RefCell<dyn Write>>, // where `print` writes to
 }
 
 impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluator {
@@ -154,12 +213,16 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
             },
             Expr::Logical(left, op, right) => {
                 let l = self.evaluate(left)?;
+                let is_true = self.is_truthy(&l);
+                if let Some(hook) = &self.hook {
+                    hook.borrow_mut().logical(e, is_true);
+                }
 
                 if *op.get_type() == TokenType::Or {
-                    if self.is_truthy(&l) {
+                    if is_true {
                         return Ok(l);
                     }
-                } else if !self.is_truthy(&l) {
+                } else if !is_true {
                     return Ok(l);
                 }
 
@@ -168,8 +231,19 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
             Expr::Get(object, name) => {
                 let obj = self.evaluate(object)?;
                 match obj {
-                    Object::Instance(instance) => Ok(instance.get(name)?),
-                    _ => Err(RuntimeError::new(name, "Only instances have properties.")),
+                    Object::Instance(instance) => match instance.get(name)? {
+                        Object::Fun(mut getter) if getter.is_getter() => {
+                            getter.call(self, Vec::new())
+                        }
+                        property => Ok(property),
+                    },
+                    Object::Cls(class) => class.get(name),
+                    Object::Enum(enumeration) => enumeration.get(name),
+                    Object::Variant(variant) => variant.get(name),
+                    _ => Err(RuntimeError::new(
+                        name,
+                        "Only instances and classes have properties.",
+                    )),
                 }
             }
             Expr::Set(object, name, value) => {
@@ -184,13 +258,31 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                         }
                         Ok(val)
                     }
-                    _ => Err(RuntimeError::new(name, "Only instances have fields.")),
+                    cls_obj @ Object::Cls(_) => {
+                        let val = self.evaluate(value)?;
+                        let obj_ref = self.environment._ref_mut_obj(cls_obj);
+                        match obj_ref {
+                            Object::Cls(class) => class.set(name, val.clone()),
+                            _ => unreachable!("Expected Object::Cls!"),
+                        }
+                        Ok(val)
+                    }
+                    _ => Err(RuntimeError::new(
+                        name,
+                        "Only instances and classes have fields.",
+                    )),
                 }
             }
             _exp @ Expr::Super(_, method) => {
                 let distance = self._locals.get(_exp).unwrap();
                 let superclass = self.environment._get_at(*distance, "super")?;
                 let object = self.environment._get_at(distance - 1, "this")?;
+                if superclass == Object::None {
+                    return Err(RuntimeError::new(
+                        method,
+                        "Can't use 'super' in a class with no superclass.",
+                    ));
+                }
                 if let Object::Cls(sup_cls) = superclass {
                     let opt_method = sup_cls.find_method(method.get_lexeme());
                     if opt_method.is_none() {
@@ -198,7 +290,10 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                         return Err(RuntimeError::new(method, &msg));
                     }
                     if let Object::Instance(obj) = object {
-                        let some_method = opt_method.unwrap().bind(&obj)?;
+                        let mut some_method = opt_method.unwrap().bind(&obj)?;
+                        if some_method.is_getter() {
+                            return some_method.call(self, Vec::new());
+                        }
                         return Ok(Object::Fun(some_method));
                     }
                 }
@@ -210,11 +305,52 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                 // self._lookup_var(keyword.clone(), _exp.clone())
             }
             Expr::Grouping(exp) => self.evaluate(exp),
+            Expr::Index(list, bracket, index) => {
+                let list = self.evaluate(list)?;
+                let index = self.evaluate(index)?;
+                match (list, index) {
+                    (Object::List(elements), Object::Number(i)) => {
+                        let elements = elements.borrow();
+                        if i.fract() != 0.0 || i < 0.0 || i as usize >= elements.len() {
+                            return Err(RuntimeError::new(bracket, "List index out of range."));
+                        }
+                        Ok(elements[i as usize].clone())
+                    }
+                    (Object::List(_), _) => {
+                        Err(RuntimeError::new(bracket, "List index must be a number."))
+                    }
+                    // Enums are indexed by the order of their variants.
+                    (Object::Enum(enumeration), Object::Number(i)) => {
+                        match enumeration.variant(i as usize) {
+                            Some(variant) if i.fract() == 0.0 && i >= 0.0 => Ok(variant),
+                            _ => Err(RuntimeError::new(bracket, "Enum index out of range.")),
+                        }
+                    }
+                    (Object::Enum(_), _) => {
+                        Err(RuntimeError::new(bracket, "Enum index must be a number."))
+                    }
+                    _ => Err(RuntimeError::new(
+                        bracket,
+                        "Can only index lists and enums.",
+                    )),
+                }
+            }
+            Expr::List(_, elements) => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Object::List(Rc::new(RefCell::new(values))))
+            }
+            Expr::Spread(_, _) => unreachable!("Spread is only allowed in call arguments!"),
             Expr::Unary(op, right) => {
                 let r = self.evaluate(right)?;
 
                 match op.get_type() {
-                    TokenType::Minus => Ok(Object::Number(-self.cast_num(op, r)?)),
+                    TokenType::Minus => match self.call_special(&r, "__neg__", Vec::new())? {
+                        Some(result) => Ok(result),
+                        None => Ok(Object::Number(-self.cast_num(op, r)?)),
+                    },
                     TokenType::Bang => Ok(Object::Bool(!self.is_truthy(&r))),
                     _ => Ok(Object::None),
                 }
@@ -232,6 +368,10 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                 let l = self.evaluate(left)?;
                 let r = self.evaluate(right)?;
 
+                if let Some(result) = self.call_operator(op, &l, &r)? {
+                    return Ok(result);
+                }
+
                 match op.get_type() {
                     TokenType::Greater => {
                         Ok(Object::Bool(self.cast_num(op, l)? > self.cast_num(op, r)?))
@@ -272,6 +412,16 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                             return Ok(Object::String(concatenated_str));
                         }
 
+                        // A string and an instance with `toString`.
+                        if self.is_str(&l) || self.is_str(&r) {
+                            let l_str = self.custom_string(&l)?;
+                            let r_str = self.custom_string(&r)?;
+                            if let (Some(mut concatenated_str), Some(r_str)) = (l_str, r_str) {
+                                concatenated_str.push_str(&r_str);
+                                return Ok(Object::String(concatenated_str));
+                            }
+                        }
+
                         Err(RuntimeError::new(
                             op,
                             "Operands must be two numbers or two strings.",
@@ -287,17 +437,25 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
 
                 let mut arguments: Vec<Object> = Vec::new();
                 for arg in args {
-                    arguments.push(self.evaluate(arg)?);
+                    match arg {
+                        Expr::Spread(ellipsis, list) => match self.evaluate(list)? {
+                            Object::List(elements) => {
+                                arguments.extend(elements.borrow().iter().cloned())
+                            }
+                            _ => return Err(RuntimeError::new(ellipsis, "Can only spread lists.")),
+                        },
+                        _ => arguments.push(self.evaluate(arg)?),
+                    }
                 }
 
                 callee.is_callable(tok)?;
 
                 let arg_len = arguments.len();
-                let arity = callee.arity();
-                if arg_len != arity {
+                let (min, max) = callee.arity();
+                if arg_len < min || max.is_some_and(|max| arg_len > max) {
                     let mut message = String::new();
                     message.push_str("Expected ");
-                    message.push_str(&arity.to_string());
+                    message.push_str(&arity_to_string(min, max));
                     message.push_str(" arguments but got ");
                     message.push_str(&arg_len.to_string());
                     message.push('.');
@@ -315,7 +473,7 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                 // self.environment = Environment::from_inner(previous);
                 // call_result
 
-                callee.call(self, arguments)
+                callee.call(self, tok, arguments)
             }
 
             Expr::Assign(name, value) => {
@@ -354,13 +512,13 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                 self.evaluate(exp)?;
                 Ok(())
             }
-            fun @ Stmt::Function(name, _, _) => {
+            fun @ Stmt::Function(name, _, _, _) => {
                 let function = Function::new(name, fun.clone(), self.environment.clone(), false)?;
                 self.environment
                     .define(name.get_lexeme().to_string(), Object::Fun(function));
                 Ok(())
             }
-            Stmt::Class(name, superclass, methods_stmts) => {
+            Stmt::Class(name, superclass, mixins, methods_stmts, members) => {
                 let mut super_class = None;
                 if let Some(sup_cls) = superclass {
                     super_class = Some(self.evaluate(sup_cls)?);
@@ -380,6 +538,30 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                 self.environment
                     .define(name.get_lexeme().to_string(), Object::None);
 
+                let mut class_methods: HashMap<String, Function> = HashMap::new();
+                let mut fields: HashMap<String, Object> = HashMap::new();
+                for member in members {
+                    match member {
+                        Stmt::Var(field_name, _, initializer) => {
+                            let mut value = Object::None;
+                            if *initializer != Expr::LiteralExpr(Literal::None) {
+                                value = self.evaluate(initializer)?;
+                            }
+                            fields.insert(field_name.get_lexeme().to_string(), value);
+                        }
+                        fun @ Stmt::Function(method_name, _, _, _) => {
+                            let function = Function::new(
+                                method_name,
+                                fun.clone(),
+                                self.environment.clone(),
+                                false,
+                            )?;
+                            class_methods.insert(method_name.get_lexeme().to_string(), function);
+                        }
+                        _ => unreachable!("A class member must be a Stmt::Var or Stmt::Function!"),
+                    }
+                }
+
                 if superclass.is_some() {
                     let old_env = self.environment.clone();
                     self.environment = Environment::new(Some(Box::new(old_env)));
@@ -387,32 +569,78 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                         .define("super".to_string(), super_class.clone().unwrap());
                 }
 
-                let mut methods: HashMap<String, Function> = HashMap::new();
+                let mut methods = self.mixin_methods(
+                    mixins,
+                    methods_stmts,
+                    super_class.clone().unwrap_or(Object::None),
+                )?;
                 for method in methods_stmts {
-                    if let fun @ Stmt::Function(method_name, _, _) = method {
-                        let is_initializer = method_name.get_lexeme() == "init";
-                        let function = Function::new(
-                            method_name,
-                            fun.clone(),
-                            self.environment.clone(),
-                            is_initializer,
-                        )?;
-                        methods.insert(method_name.get_lexeme().to_string(), function);
-                    } else {
-                        unreachable!("A method statement must be a Stmt::Function!");
+                    match method {
+                        fun @ Stmt::Function(method_name, _, _, _) => {
+                            let is_initializer = method_name.get_lexeme() == "init";
+                            let function = Function::new(
+                                method_name,
+                                fun.clone(),
+                                self.environment.clone(),
+                                is_initializer,
+                            )?;
+                            methods.insert(method_name.get_lexeme().to_string(), function);
+                        }
+                        getter @ Stmt::Getter(getter_name, _) => {
+                            let function = Function::new(
+                                getter_name,
+                                getter.clone(),
+                                self.environment.clone(),
+                                false,
+                            )?;
+                            methods.insert(getter_name.get_lexeme().to_string(), function);
+                        }
+                        _ => unreachable!("A method statement must be a Stmt::Function!"),
                     }
                 }
 
-                let class = Class::new(name.get_lexeme().to_string(), super_class, methods);
+                let class = Class::new(
+                    name.get_lexeme().to_string(),
+                    super_class,
+                    methods,
+                    class_methods,
+                    fields,
+                );
                 if superclass.is_some() {
                     self.environment = *self.environment.enclosing().unwrap();
                 }
                 self.environment.assign(name, Object::Cls(class))?;
                 Ok(())
             }
+            Stmt::Getter(_, _) => unreachable!("A getter can only be declared inside a class!"),
+            Stmt::Mixin(name, methods_stmts) => {
+                let mut methods: HashMap<String, Function> = HashMap::new();
+                for method in methods_stmts {
+                    let method_name = match method {
+                        Stmt::Function(method_name, _, _, _) | Stmt::Getter(method_name, _) => {
+                            method_name
+                        }
+                        _ => unreachable!("A method statement must be a Stmt::Function!"),
+                    };
+                    let function = Function::new(
+                        method_name,
+                        method.clone(),
+                        self.environment.clone(),
+                        method_name.get_lexeme() == "init",
+                    )?;
+                    methods.insert(method_name.get_lexeme().to_string(), function);
+                }
+
+                let mixin = Mixin::new(name.get_lexeme().to_string(), methods);
+                self.environment
+                    .define(name.get_lexeme().to_string(), Object::Mixin(mixin));
+                Ok(())
+            }
             Stmt::If(condition, then_branch, else_branch) => {
                 let cond = self.evaluate(condition)?;
-                if self.is_truthy(&cond) {
+                let is_true = self.is_truthy(&cond);
+                self.condition(condition, is_true);
+                if is_true {
                     self.execute(then_branch)?;
                     return Ok(());
                 }
@@ -421,9 +649,42 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                 }
                 Ok(())
             }
-            Stmt::Print(exp) => {
+            Stmt::Enum(name, variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|(variant, fields)| {
+                        let fields = fields.iter().map(|f| f.get_lexeme().to_string());
+                        (variant.get_lexeme().to_string(), fields.collect())
+                    })
+                    .collect();
+                let enumeration = Enum::new(name.get_lexeme().to_string(), variants);
+                self.environment.define(
+                    name.get_lexeme().to_string(),
+                    Object::Enum(Rc::new(enumeration)),
+                );
+                Ok(())
+            }
+            Stmt::Match(_, value, cases) => {
+                let value = self.evaluate(value)?;
+                for (pattern, body) in cases {
+                    let mut bindings = Vec::new();
+                    if self.match_pattern(pattern, &value, &mut bindings)? {
+                        let mut env = Environment::new(Some(Box::new(self.environment.clone())));
+                        for (name, bound) in bindings {
+                            env.define(name, bound);
+                        }
+                        return self.execute_block(&vec![body.clone()], env);
+                    }
+                }
+                Ok(())
+            }
+            Stmt::Print(_, exp) => {
                 let value = self.evaluate(exp)?;
-                println!("{value}");
+                let text = match self.custom_string(&value)? {
+                    Some(s) => s,
+                    None => value.to_string(),
+                };
+                writeln!(self.output.borrow_mut(), "{text}").expect("Couldn't write the output.");
                 Ok(())
             }
             Stmt::Return(keyword, value) => {
@@ -443,7 +704,13 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                 )?;
                 Ok(())
             }
-            Stmt::Var(name, initializer) => {
+            Stmt::Const(name, _, initializer) => {
+                let value = self.evaluate(initializer)?;
+                self.environment
+                    .define(name.get_lexeme().to_string(), value);
+                Ok(())
+            }
+            Stmt::Var(name, _, initializer) => {
                 let mut value = Object::None;
                 if *initializer != Expr::LiteralExpr(Literal::None) {
                     value = self.evaluate(initializer)?;
@@ -453,14 +720,15 @@ impl Visitor<Result<Object, RuntimeError>, Result<(), RuntimeError>> for Evaluat
                     .define(name.get_lexeme().to_string(), value);
                 Ok(())
             }
-            Stmt::While(condition, body) => {
-                let mut cond = self.evaluate(condition)?;
-                while self.is_truthy(&cond) {
-                    self.execute(body)?;
-                    cond = self.evaluate(condition)?;
+            Stmt::While(condition, body) => loop {
+                let cond = self.evaluate(condition)?;
+                let is_true = self.is_truthy(&cond);
+                self.condition(condition, is_true);
+                if !is_true {
+                    return Ok(());
                 }
-                Ok(())
-            }
+                self.execute(body)?;
+            },
         }
     }
 }
@@ -470,9 +738,19 @@ impl Evaluator {
         Evaluator {
             environment,
             _locals: HashMap::new(),
+            hook: None,
+            output: Rc::new(RefCell::new(io::stdout())),
         }
     }
 
+    pub fn set_hook(&mut self, hook: Rc<RefCell<dyn Hook>>) {
+        self.hook = Some(hook);
+    }
+
+    pub fn set_output(&mut self, output: Rc<RefCell<dyn Write>>) {
+        self.output = output;
+    }
+
     pub fn set_locals(&mut self, locals: HashMap<Expr, usize>) {
         self._locals = locals;
     }
@@ -482,9 +760,18 @@ impl Evaluator {
     }
 
     pub fn execute(&mut self, s: &Stmt) -> Result<(), RuntimeError> {
+        if let Some(hook) = &self.hook {
+            hook.borrow_mut().before_stmt(s, &self.environment);
+        }
         self.visit_stmt(s)
     }
 
+    fn condition(&self, condition: &Expr, is_true: bool) {
+        if let Some(hook) = &self.hook {
+            hook.borrow_mut().condition(condition, is_true);
+        }
+    }
+
     fn execute_block(
         &mut self,
         statements: &Vec<Stmt>,
@@ -546,6 +833,52 @@ impl Evaluator {
         (Ok(()), closure)
     }
 
+    // Methods a class gets from its mixins. Two mixins may only provide the same method
+    // if the class overrides it.
+    fn mixin_methods(
+        &mut self,
+        mixins: &[Expr],
+        methods_stmts: &[Stmt],
+        superclass: Object,
+    ) -> Result<HashMap<String, Function>, RuntimeError> {
+        let own_methods: Vec<&str> = methods_stmts
+            .iter()
+            .filter_map(|method| match method {
+                Stmt::Function(name, _, _, _) | Stmt::Getter(name, _) => Some(name.get_lexeme()),
+                _ => None,
+            })
+            .collect();
+
+        let mut methods: HashMap<String, Function> = HashMap::new();
+        let mut origins: HashMap<String, String> = HashMap::new();
+        for mixin_expr in mixins {
+            let mixin_name = match mixin_expr {
+                Expr::Variable(mixin_name) => mixin_name,
+                _ => unreachable!("A mixin must be an Expr::Variable!"),
+            };
+            let mixin = match self.evaluate(mixin_expr)? {
+                Object::Mixin(mixin) => mixin,
+                _ => return Err(RuntimeError::new(mixin_name, "Can only use mixins.")),
+            };
+
+            for (method_name, method) in mixin.methods() {
+                if let Some(origin) = origins.get(method_name) {
+                    if !own_methods.contains(&method_name.as_str()) {
+                        let msg = format!(
+                            "Method '{method_name}' is defined in both '{origin}' and '{}'.",
+                            mixin.name()
+                        );
+                        return Err(RuntimeError::new(mixin_name, &msg));
+                    }
+                }
+                origins.insert(method_name.clone(), mixin.name().to_string());
+                methods.insert(method_name.clone(), method.with_super(superclass.clone()));
+            }
+        }
+
+        Ok(methods)
+    }
+
     fn _lookup_var(&self, name: Token, exp: Expr) -> Result<Object, RuntimeError> {
         let distance = self._locals.get(&exp);
         if let Some(d) = distance {
@@ -556,6 +889,79 @@ impl Evaluator {
         self.environment._ref_global().get(name)
     }
 
+    // Dispatches a binary operator to the special method of the left operand, if it has one.
+    fn call_operator(
+        &mut self,
+        op: &Token,
+        l: &Object,
+        r: &Object,
+    ) -> Result<Option<Object>, RuntimeError> {
+        let method_name = match op.get_type() {
+            TokenType::Plus => "__add__",
+            TokenType::Minus => "__sub__",
+            TokenType::Star => "__mul__",
+            TokenType::Slash => "__div__",
+            TokenType::Greater => "__gt__",
+            TokenType::GreaterEqual => "__ge__",
+            TokenType::Less => "__lt__",
+            TokenType::LessEqual => "__le__",
+            TokenType::EqualEqual | TokenType::BangEqual => "__eq__",
+            _ => return Ok(None),
+        };
+
+        let result = match self.call_special(l, method_name, vec![r.clone()])? {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+
+        match op.get_type() {
+            TokenType::EqualEqual => Ok(Some(Object::Bool(self.is_truthy(&result)))),
+            TokenType::BangEqual => Ok(Some(Object::Bool(!self.is_truthy(&result)))),
+            _ => Ok(Some(result)),
+        }
+    }
+
+    // Calls a special method (`__add__`, `toString`, ...) if `obj` is an instance that defines it.
+    fn call_special(
+        &mut self,
+        obj: &Object,
+        name: &str,
+        arguments: Vec<Object>,
+    ) -> Result<Option<Object>, RuntimeError> {
+        let instance = match obj {
+            Object::Instance(instance) => instance,
+            _ => return Ok(None),
+        };
+
+        match instance.find_method(name)? {
+            Some(mut method) => {
+                let (min, max) = method.arity();
+                if arguments.len() < min || max.is_some_and(|max| arguments.len() > max) {
+                    let msg = format!(
+                        "Special method '{name}' must take {} arguments.",
+                        arguments.len()
+                    );
+                    return Err(RuntimeError::new(method.name(), &msg));
+                }
+                Ok(Some(method.call(self, arguments)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Text of a string or of an instance with `toString`.
+    fn custom_string(&mut self, obj: &Object) -> Result<Option<String>, RuntimeError> {
+        if let Object::String(s) = obj {
+            return Ok(Some(s.clone()));
+        }
+
+        match self.call_special(obj, "toString", Vec::new())? {
+            Some(Object::String(s)) => Ok(Some(s)),
+            Some(other) => Ok(Some(other.to_string())),
+            None => Ok(None),
+        }
+    }
+
     fn cast_num(&self, op: &Token, obj: Object) -> Result<f64, RuntimeError> {
         match obj {
             Object::Number(n) => Ok(n),
@@ -582,6 +988,87 @@ impl Evaluator {
         matches!(obj, Object::None)
     }
 
+    // Collects names bound by the pattern if the value matches it.
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &Object,
+        bindings: &mut Vec<(String, Object)>,
+    ) -> Result<bool, RuntimeError> {
+        match pattern {
+            Pattern::Alternatives(patterns) => {
+                for pattern in patterns {
+                    if self.match_pattern(pattern, value, bindings)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Pattern::Binding(name) => {
+                bindings.push((name.get_lexeme().to_string(), value.clone()));
+                Ok(true)
+            }
+            Pattern::Class(name, fields) => {
+                let class = match self.evaluate(&Expr::Variable(name.clone()))? {
+                    Object::Cls(class) => class,
+                    _ => {
+                        return Err(RuntimeError::new(
+                            name,
+                            "Can only match instances of classes.",
+                        ))
+                    }
+                };
+                let instance = match value {
+                    Object::Instance(instance) if instance.is_instance_of(&class) => instance,
+                    _ => return Ok(false),
+                };
+
+                for (field, field_pattern) in fields {
+                    match instance.field(field.get_lexeme()) {
+                        Some(field_value) => {
+                            if !self.match_pattern(field_pattern, &field_value, bindings)? {
+                                return Ok(false);
+                            }
+                        }
+                        None => return Ok(false),
+                    }
+                }
+                Ok(true)
+            }
+            Pattern::Literal(_, literal) => {
+                let literal = self.evaluate(&Expr::LiteralExpr(literal.clone()))?;
+                Ok(self.is_equal(literal, value.clone()))
+            }
+            Pattern::Variant(enum_name, variant_name, fields) => {
+                let expected = match self.evaluate(&Expr::Variable(enum_name.clone()))? {
+                    Object::Enum(enumeration) => match enumeration.get(variant_name)? {
+                        Object::Variant(variant) => variant,
+                        _ => unreachable!("Enums only have variants."),
+                    },
+                    _ => {
+                        return Err(RuntimeError::new(
+                            enum_name,
+                            "Can only match variants of enums.",
+                        ))
+                    }
+                };
+                let variant = match value {
+                    Object::Variant(variant) if variant.is_same_variant(&expected) => variant,
+                    _ => return Ok(false),
+                };
+
+                for (field, field_pattern) in fields {
+                    let field_value = variant.get(field)?;
+                    if !self.match_pattern(field_pattern, &field_value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Pattern::Wildcard(_) => Ok(true),
+        }
+    }
+
     fn is_truthy(&self, obj: &Object) -> bool {
         match obj {
             Object::None => false,
@@ -601,3 +1088,253 @@ impl Evaluator {
         l == r
     }
 }
+
+#[cfg(test)]
+mod test_evaluator {
+    use super::{environment::Environment, Evaluator};
+    use crate::resolver::Resolver;
+    use crate::{Lox, Parser, Scanner};
+
+    // Runs `setup` and evaluates `expression` after it, an error gives its message.
+    fn eval(setup: &str, expression: &str) -> Result<String, String> {
+        let source = format!("{setup}\nvar result = {expression};");
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        let mut scanner = Scanner::new(&mut interpreter, &source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+        let statements = Parser::new(&mut interpreter, tokens).parse();
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve_optional_stmts(statements.clone());
+        let locals = resolver.locals();
+        assert!(!interpreter.had_error, "Static error in: {source}");
+
+        let mut evaluator = Evaluator::new(Environment::new(None));
+        evaluator.set_locals(locals);
+        for statement in statements.into_iter().flatten() {
+            evaluator.execute(&statement).map_err(|e| e.get_message())?;
+        }
+        Ok(evaluator.environment.values()["result"].to_string())
+    }
+
+    #[test]
+    fn test_operators() {
+        let money = "class Money {
+  __add__(other) { return this.n + other.n; }
+  __sub__(other) { return this.n - other.n; }
+  __mul__(k) { return this.n * k; }
+  __div__(k) { return this.n / k; }
+  __neg__() { return -this.n; }
+  __lt__(other) { return this.n < other.n; }
+  __le__(other) { return this.n <= other.n; }
+  __gt__(other) { return this.n > other.n; }
+  __ge__(other) { return this.n >= other.n; }
+  __eq__(other) { return this.n == other.n; }
+  toString() { return this.currency; }
+}
+var a = Money(); a.n = 1; a.currency = \"EUR\";
+var b = Money(); b.n = 3;
+var c = Money(); c.n = 1;";
+        let cases = [
+            ("a + b", "4"),
+            ("a - b", "-2"),
+            ("a * 3", "3"),
+            ("a / 2", "0.5"),
+            ("-a", "-1"),
+            ("a < b", "true"),
+            ("a <= b", "true"),
+            ("a > b", "false"),
+            ("a >= b", "false"),
+            ("a == c", "true"),
+            ("a != c", "false"),
+            ("a == b", "false"),
+            ("\"in \" + a", "in EUR"),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(
+                eval(money, expression),
+                Ok(expected.to_string()),
+                "{expression}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_operator_fallbacks() {
+        // The special method of the left operand goes first, `toString` is the fallback of `+`.
+        let sum = "class Sum { __add__(other) { return other.n; } toString() { return \"sum\"; } }";
+        assert_eq!(
+            eval(sum, "Sum() + \"!\""),
+            Err("Only instances and classes have properties.".to_string())
+        );
+        let label = "class Label { toString() { return \"label\"; } }";
+        assert_eq!(eval(label, "Label() + \"!\""), Ok("label!".to_string()));
+
+        // Without a special method the operands are checked as usual.
+        let plain = "class P {}";
+        let number = Err("Operand must be a number.".to_string());
+        assert_eq!(eval(plain, "P() * 2"), number);
+        assert_eq!(eval(plain, "-P()"), number);
+        assert_eq!(
+            eval(plain, "P() + 1"),
+            Err("Operands must be two numbers or two strings.".to_string())
+        );
+        assert_eq!(
+            eval("class P { __add__() { return 1; } }", "P() + 1"),
+            Err("Special method '__add__' must take 1 arguments.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_default_and_rest_parameters() {
+        let greet = "fun greet(name, greeting = \"Hello\", mark = greeting + \"!\") {
+  return greeting + \", \" + name + mark;
+}
+fun count(first, ...rest) { return len(rest); }";
+        let cases = [
+            ("greet(\"Bob\")", "Hello, BobHello!"),
+            ("greet(\"Bob\", \"Hi\")", "Hi, BobHi!"),
+            ("greet(\"Bob\", \"Hi\", \".\")", "Hi, Bob."),
+            ("greet(...[\"Ann\", \"Hey\"])", "Hey, AnnHey!"),
+            ("count(1)", "0"),
+            ("count(1, 2, 3)", "2"),
+            ("count(...[1, 2])", "1"),
+        ];
+        for (call, expected) in cases {
+            assert_eq!(eval(greet, call), Ok(expected.to_string()), "{call}");
+        }
+
+        let arity = |call| eval(greet, call).unwrap_err();
+        assert_eq!(arity("greet()"), "Expected 1 to 3 arguments but got 0.");
+        assert_eq!(
+            arity("greet(1, 2, 3, 4)"),
+            "Expected 1 to 3 arguments but got 4."
+        );
+        assert_eq!(
+            arity("greet(...[1, 2, 3, 4])"),
+            "Expected 1 to 3 arguments but got 4."
+        );
+        assert_eq!(arity("count()"), "Expected at least 1 arguments but got 0.");
+        assert_eq!(arity("count(...1)"), "Can only spread lists.");
+    }
+
+    #[test]
+    fn test_constants() {
+        let setup = "const a = 1; fun f() { const b = a + 1; fun g() { return b; } return g; }";
+        assert_eq!(eval(setup, "f()()"), Ok("2".to_string()));
+    }
+
+    #[test]
+    fn test_match() {
+        let describe = "class Point {}
+class Point3 < Point {}
+fun point(x, y) { var p = Point(); p.x = x; p.y = y; return p; }
+fun describe(value) {
+  match (value) {
+    case 0 => return \"zero\";
+    case -1 | \"minus one\" => return \"negative\";
+    case Point(x: 0, y) => return \"on the y axis at \" + y;
+    case Point(x, y: _) => return \"point\";
+    case nil => return \"nothing\";
+    case other => return other;
+  }
+}";
+        let cases = [
+            ("describe(0)", "zero"),
+            ("describe(-1)", "negative"),
+            ("describe(\"minus one\")", "negative"),
+            ("describe(point(0, \"2\"))", "on the y axis at 2"),
+            ("describe(point(1, 2))", "point"),
+            ("describe(nil)", "nothing"),
+            ("describe(true)", "true"),
+            // Instances of subclasses match, instances without the field don't.
+            ("describe(Point3())", "Point3 instance"),
+        ];
+        for (call, expected) in cases {
+            assert_eq!(eval(describe, call), Ok(expected.to_string()), "{call}");
+        }
+
+        // Without a matching case nothing runs, and the bound names stay in the case.
+        let partial = "var result = \"none\"; var x = \"outer\";
+match (\"two\") { case 1 => result = \"one\"; case x => result = x; }";
+        assert_eq!(eval(partial, "result + x"), Ok("twoouter".to_string()));
+        assert_eq!(
+            eval("var A = 1; match (1) { case A(x) => print x; }", "nil"),
+            Err("Can only match instances of classes.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_enums() {
+        let enums = "enum Color { Red, Green }
+enum Paint { Red }
+enum Option { Some(value), None }";
+        let cases = [
+            ("Color.Red == Color.Red", "true"),
+            ("Color.Red == Color.Green", "false"),
+            ("Color.Red == Paint.Red", "false"),
+            // Values of one variant are equal if their payloads are.
+            ("Option.Some(1) == Option.Some(1)", "true"),
+            ("Option.Some(1) == Option.Some(2)", "false"),
+            ("Option.Some(1) == Option.None", "false"),
+            ("Option.Some == Option.Some(1)", "false"),
+            ("Option.Some(Color.Red)", "Option.Some(Color.Red)"),
+            ("Option.Some(2).value", "2"),
+            ("len(Color)", "2"),
+            ("Color[1]", "Color.Green"),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(
+                eval(enums, expression),
+                Ok(expected.to_string()),
+                "{expression}"
+            );
+        }
+        assert_eq!(
+            eval(enums, "Color.Blue"),
+            Err("Undefined variant 'Blue' in enum 'Color'.".to_string())
+        );
+        assert_eq!(
+            eval(enums, "Option.Some(1, 2)"),
+            Err("Expected 1 arguments but got 2.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_match_variants() {
+        let area = "enum Shape { Circle(radius), Rect(width, height), Empty }
+fun area(shape) {
+  match (shape) {
+    case Shape.Circle(radius) => return 3 * radius * radius;
+    case Shape.Rect(width: w, height: 2) => return 2 * w;
+    case Shape.Rect(width, height) => return width * height;
+    case Shape.Empty | nil => return 0;
+  }
+  return \"other\";
+}";
+        let cases = [
+            ("area(Shape.Circle(2))", "12"),
+            ("area(Shape.Rect(3, 2))", "6"),
+            ("area(Shape.Rect(3, 4))", "12"),
+            ("area(Shape.Empty)", "0"),
+            ("area(nil)", "0"),
+            ("area(1)", "other"),
+        ];
+        for (call, expected) in cases {
+            assert_eq!(eval(area, call), Ok(expected.to_string()), "{call}");
+        }
+
+        let error = |setup| eval(setup, "nil").unwrap_err();
+        assert_eq!(
+            error("var A = 1; match (2) { case A.B => print 1; }"),
+            "Can only match variants of enums."
+        );
+        assert_eq!(
+            error("enum A { B } match (A.B) { case A.C => print 1; }"),
+            "Undefined variant 'C' in enum 'A'."
+        );
+        assert_eq!(
+            error("enum A { B(x) } match (A.B(1)) { case A.B(y) => print y; }"),
+            "Undefined payload field 'y'."
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use super::{Evaluator, Object, RuntimeError};
+use crate::Token;
 use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -6,8 +7,8 @@ use std::time::{SystemTime, UNIX_EPOCH};
 pub struct Clock;
 
 impl Clock {
-    pub fn arity(&self) -> usize {
-        0
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        (0, Some(0))
     }
 
     pub fn call(&self, _: &mut Evaluator, _: Vec<Object>) -> Result<Object, RuntimeError> {
@@ -30,3 +31,34 @@ impl fmt::Display for Clock {
         write!(f, "{}", self.stringify())
     }
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Len;
+
+impl Len {
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        (1, Some(1))
+    }
+
+    pub fn call(&self, paren: &Token, arguments: Vec<Object>) -> Result<Object, RuntimeError> {
+        match &arguments[0] {
+            Object::List(list) => Ok(Object::Number(list.borrow().len() as f64)),
+            Object::String(s) => Ok(Object::Number(s.chars().count() as f64)),
+            Object::Enum(e) => Ok(Object::Number(e.len() as f64)),
+            _ => Err(RuntimeError::new(
+                paren,
+                "Can only get length of lists, strings and enums.",
+            )),
+        }
+    }
+
+    fn stringify(&self) -> String {
+        "<native fun 'len'>".to_string()
+    }
+}
+
+impl fmt::Display for Len {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.stringify())
+    }
+}
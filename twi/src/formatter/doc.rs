@@ -0,0 +1,129 @@
+// Layout of the formatted code in the style of Wadler's "prettier printer":
+// a group is printed on one line if it fits, otherwise its lines are broken.
+
+#[derive(Clone, Debug)]
+pub enum Doc {
+    Text(String),
+    Line,     // a space or a line break
+    SoftLine, // nothing or a line break
+    HardLine, // always a line break
+    Indent(Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+pub fn text(s: &str) -> Doc {
+    Doc::Text(s.to_string())
+}
+
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+pub fn indent(doc: Doc) -> Doc {
+    Doc::Indent(Box::new(doc))
+}
+
+pub fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+// Items separated with commas, broken one per line if they don't fit.
+pub fn list(open: Doc, items: Vec<Doc>, close: Doc) -> Doc {
+    if items.is_empty() {
+        return concat(vec![open, close]);
+    }
+
+    let mut body = vec![Doc::SoftLine];
+    for (i, item) in items.into_iter().enumerate() {
+        if i != 0 {
+            body.push(text(","));
+            body.push(Doc::Line);
+        }
+        body.push(item);
+    }
+    group(concat(vec![
+        open,
+        indent(concat(body)),
+        Doc::SoftLine,
+        close,
+    ]))
+}
+
+pub fn render(doc: &Doc, width: usize, indent_width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    let mut at_line_start = true;
+    let mut stack: Vec<(usize, Mode, &Doc)> = vec![(0, Mode::Break, doc)];
+
+    while let Some((level, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) => {
+                // Indentation is written lazily so that empty lines have no trailing spaces.
+                if at_line_start {
+                    out.push_str(&" ".repeat(level * indent_width));
+                    column = level * indent_width;
+                    at_line_start = false;
+                }
+                out.push_str(s);
+                column += s.chars().count();
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if let Doc::Line = doc {
+                    out.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.push('\n');
+                column = 0;
+                at_line_start = true;
+            }
+            Doc::Indent(doc) => stack.push((level + 1, mode, doc)),
+            Doc::Group(doc) => {
+                let start = if at_line_start {
+                    level * indent_width
+                } else {
+                    column
+                };
+                let mode = match fits(doc, width.saturating_sub(start)) {
+                    true => Mode::Flat,
+                    false => Mode::Break,
+                };
+                stack.push((level, mode, doc));
+            }
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((level, mode, doc));
+                }
+            }
+        }
+    }
+    out
+}
+
+// Whether the document printed flat fits into the given width.
+fn fits(doc: &Doc, width: usize) -> bool {
+    let mut remaining = width as isize;
+    let mut stack = vec![doc];
+    while let Some(doc) = stack.pop() {
+        match doc {
+            Doc::Text(s) => remaining -= s.chars().count() as isize,
+            Doc::Line => remaining -= 1,
+            Doc::SoftLine => (),
+            Doc::HardLine => return false,
+            Doc::Indent(doc) | Doc::Group(doc) => stack.push(doc),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev()),
+        }
+        if remaining < 0 {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,794 @@
+//! Prints Lox source in a canonical layout, keeping comments.
+//!
+//! The layout is built from the AST. Source tokens are followed alongside it,
+//! so that comments can be put back next to the code they were written at.
+
+use crate::ast::{
+    expr::Expr,
+    stmt::{Param, Pattern, Stmt},
+    AstPrinter,
+};
+use crate::evaluator::{environment::Environment, Evaluator};
+use crate::lexer::{
+    scanner::Scanner,
+    token::{Literal, Token, TokenType},
+};
+use crate::parser::Parser;
+use crate::{Lox, StaticError, Visitor};
+use doc::{concat, group, indent, list, render, text, Doc};
+use std::fmt::Display;
+
+mod doc;
+
+pub struct FormatOptions {
+    pub indent_width: usize,
+    pub line_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent_width: 4,
+            line_width: 80,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Syntax(Vec<StaticError>),
+    ChangedMeaning, // a bug in the formatter, the code is left as it is
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Syntax(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            FormatError::ChangedMeaning => {
+                write!(f, "Formatting would change the meaning of the program.")
+            }
+        }
+    }
+}
+
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, FormatError> {
+    let (statements, tokens, comments) = parse(source)?;
+    let mut formatter = Formatter::new(tokens, comments);
+    let doc = formatter.program(&statements);
+    let formatted = render(&doc, options.line_width, options.indent_width);
+
+    // The formatted code must give the same AST.
+    match parse(&formatted) {
+        Ok((formatted_statements, _, _)) if print(&statements) == print(&formatted_statements) => {
+            Ok(formatted)
+        }
+        _ => Err(FormatError::ChangedMeaning),
+    }
+}
+
+type Parsed = (Vec<Stmt>, Vec<Token>, Vec<Token>); // (statements, tokens, comments)
+
+fn parse(source: &str) -> Result<Parsed, FormatError> {
+    let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+    interpreter.set_quiet(true);
+
+    let mut scanner = Scanner::new(&mut interpreter, source);
+    scanner.scan_tokens();
+    let tokens = scanner.tokens().clone();
+    let comments = scanner.comments().clone();
+
+    let statements = Parser::new(&mut interpreter, tokens.clone()).parse();
+    let errors = interpreter.take_errors();
+    if !errors.is_empty() {
+        return Err(FormatError::Syntax(errors));
+    }
+    Ok((statements.into_iter().flatten().collect(), tokens, comments))
+}
+
+fn print(statements: &[Stmt]) -> Vec<String> {
+    let mut printer = AstPrinter;
+    statements.iter().map(|s| printer.visit_stmt(s)).collect()
+}
+
+fn position(token: &Token) -> (usize, usize) {
+    (token.get_line(), token.get_column())
+}
+
+struct Formatter {
+    tokens: Vec<Token>,
+    comments: Vec<Token>,
+    cursor: usize,       // next source token
+    next_comment: usize, // first comment that is not printed yet
+    pending: Vec<Token>, // comments from the middle of a statement, printed after it
+    last_line: usize,    // line of the last passed source token or comment
+}
+
+impl Formatter {
+    fn new(tokens: Vec<Token>, comments: Vec<Token>) -> Self {
+        Formatter {
+            tokens,
+            comments,
+            cursor: 0,
+            next_comment: 0,
+            pending: Vec::new(),
+            last_line: 0,
+        }
+    }
+
+    fn program(&mut self, statements: &[Stmt]) -> Doc {
+        let mut docs = Vec::new();
+        for (i, statement) in statements.iter().enumerate() {
+            if i != 0 {
+                docs.push(Doc::HardLine);
+            }
+            docs.extend(self.leading_comments(i == 0));
+            docs.push(self.stmt(statement));
+            docs.extend(self.trailing_comments());
+        }
+
+        let mut closing = self.closing_comments(statements.is_empty());
+        if statements.is_empty() && !closing.is_empty() {
+            closing.remove(0); // no line break before the first comment
+        }
+        docs.extend(closing);
+        docs.push(Doc::HardLine);
+        concat(docs)
+    }
+
+    // Source tokens.
+
+    fn peek_is(&self, lexeme: &str) -> bool {
+        self.tokens
+            .get(self.cursor)
+            .is_some_and(|t| t.get_lexeme() == lexeme && *t.get_type() != TokenType::Eof)
+    }
+
+    fn next_position(&self) -> (usize, usize) {
+        match self.tokens.get(self.cursor) {
+            Some(token) => position(token),
+            None => (usize::MAX, 0),
+        }
+    }
+
+    fn advance(&mut self) {
+        let next = self.next_position();
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if position(comment) > next {
+                break;
+            }
+            self.pending.push(comment.clone());
+            self.next_comment += 1;
+        }
+        self.last_line = next.0;
+        self.cursor += 1;
+    }
+
+    // Passes the source token if it is the next one.
+    fn consume(&mut self, lexeme: &str) {
+        // A trailing comma before a closing bracket is dropped.
+        let is_next_after_comma = self
+            .tokens
+            .get(self.cursor + 1)
+            .is_some_and(|t| t.get_lexeme() == lexeme);
+        if lexeme != "," && self.peek_is(",") && is_next_after_comma {
+            self.advance();
+        }
+
+        if self.peek_is(lexeme) {
+            self.advance();
+        }
+    }
+
+    fn tok(&mut self, lexeme: &str) -> Doc {
+        self.consume(lexeme);
+        text(lexeme)
+    }
+
+    fn name(&mut self, name: &Token) -> Doc {
+        self.tok(name.get_lexeme())
+    }
+
+    // Comments.
+
+    fn comment(&mut self) -> Option<Token> {
+        let comment = self.comments.get(self.next_comment)?;
+        if position(comment) > self.next_position() {
+            return None;
+        }
+        self.next_comment += 1;
+        Some(comment.clone())
+    }
+
+    fn comment_text(comment: &Token) -> Doc {
+        text(comment.get_lexeme().trim_end())
+    }
+
+    // Comments before the next statement, each on its own line. Blank lines between statements are kept.
+    fn leading_comments(&mut self, is_first: bool) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        let mut is_first = is_first;
+        while let Some(comment) = self.comment() {
+            if !is_first && comment.get_line() > self.last_line + 1 {
+                docs.push(Doc::HardLine);
+            }
+            docs.push(Self::comment_text(&comment));
+            docs.push(Doc::HardLine);
+            self.last_line = comment.get_line();
+            is_first = false;
+        }
+
+        if !is_first && self.next_position().0 > self.last_line + 1 {
+            docs.push(Doc::HardLine);
+        }
+        docs
+    }
+
+    // A comment on the same line after the statement and comments from inside of it.
+    fn trailing_comments(&mut self) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        let is_same_line = self
+            .comments
+            .get(self.next_comment)
+            .is_some_and(|c| c.get_line() == self.last_line);
+        if is_same_line {
+            if let Some(comment) = self.comment() {
+                docs.push(text(" "));
+                docs.push(Self::comment_text(&comment));
+            }
+        }
+
+        for comment in std::mem::take(&mut self.pending) {
+            docs.push(Doc::HardLine);
+            docs.push(Self::comment_text(&comment));
+        }
+        docs
+    }
+
+    // Comments before a closing brace or the end of the file.
+    fn closing_comments(&mut self, is_first: bool) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        let mut is_first = is_first;
+        while let Some(comment) = self.comment() {
+            docs.push(Doc::HardLine);
+            if !is_first && comment.get_line() > self.last_line + 1 {
+                docs.push(Doc::HardLine);
+            }
+            docs.push(Self::comment_text(&comment));
+            self.last_line = comment.get_line();
+            is_first = false;
+        }
+        docs
+    }
+
+    // `{`, items on their own lines, `}`.
+    fn braced<T>(&mut self, items: &[T], item: impl Fn(&mut Self, &T) -> Doc) -> Doc {
+        let open = self.tok("{");
+        let mut body = Vec::new();
+        for (i, it) in items.iter().enumerate() {
+            body.push(Doc::HardLine);
+            body.extend(self.leading_comments(i == 0));
+            body.push(item(self, it));
+            body.extend(self.trailing_comments());
+        }
+        body.extend(self.closing_comments(items.is_empty()));
+
+        if body.is_empty() {
+            return concat(vec![open, self.tok("}")]);
+        }
+        concat(vec![
+            open,
+            indent(concat(body)),
+            Doc::HardLine,
+            self.tok("}"),
+        ])
+    }
+
+    fn comma_separated<T>(&mut self, items: &[T], item: impl Fn(&mut Self, &T) -> Doc) -> Vec<Doc> {
+        let mut docs = Vec::new();
+        for (i, it) in items.iter().enumerate() {
+            if i != 0 {
+                self.consume(",");
+            }
+            docs.push(item(self, it));
+        }
+        docs
+    }
+
+    // Statements.
+
+    fn stmt(&mut self, s: &Stmt) -> Doc {
+        // `for` loops are turned into `while` loops by the parser.
+        if self.peek_is("for") {
+            if let Some(doc) = self.for_stmt(s) {
+                return doc;
+            }
+        }
+
+        match s {
+            Stmt::Block(statements) => self.block(statements),
+            Stmt::Class(name, superclass, mixins, methods, members) => {
+                let mut docs = vec![self.tok("class"), text(" "), self.name(name)];
+                if let Some(superclass) = superclass {
+                    docs.extend([text(" "), self.tok("<"), text(" "), self.expr(superclass)]);
+                }
+                if !mixins.is_empty() {
+                    docs.extend([text(" "), self.tok("with"), text(" ")]);
+                    let mixins = self.comma_separated(mixins, |f, mixin| f.expr(mixin));
+                    for (i, mixin) in mixins.into_iter().enumerate() {
+                        if i != 0 {
+                            docs.push(text(", "));
+                        }
+                        docs.push(mixin);
+                    }
+                }
+                docs.push(text(" "));
+
+                // Class members and methods are kept in their source order.
+                let mut body: Vec<(bool, &Stmt)> = members.iter().map(|m| (true, m)).collect();
+                body.extend(methods.iter().map(|m| (false, m)));
+                body.sort_by_key(|(_, member)| match member {
+                    Stmt::Function(name, _, _, _)
+                    | Stmt::Getter(name, _)
+                    | Stmt::Var(name, _, _) => position(name),
+                    _ => (0, 0),
+                });
+                docs.push(self.braced(&body, |f, (is_static, member)| {
+                    f.class_member(*is_static, member)
+                }));
+                concat(docs)
+            }
+            Stmt::Const(name, ty, initializer) => {
+                let keyword = self.tok("const");
+                self.variable(keyword, name, ty, initializer)
+            }
+            Stmt::Enum(name, variants) => {
+                let header = vec![self.tok("enum"), text(" "), self.name(name), text(" ")];
+                let open = self.tok("{");
+                let variants = self.comma_separated(variants, |f, (variant, fields)| {
+                    let name = f.name(variant);
+                    if fields.is_empty() {
+                        return name;
+                    }
+                    let open = f.tok("(");
+                    let fields = f.comma_separated(fields, |f, field| f.name(field));
+                    concat(vec![name, list(open, fields, f.tok(")"))])
+                });
+                let close = self.tok("}");
+                concat(
+                    header
+                        .into_iter()
+                        .chain([braced_list(open, variants, close)])
+                        .collect(),
+                )
+            }
+            Stmt::Expression(exp) => concat(vec![self.expr(exp), self.tok(";")]),
+            Stmt::Function(name, params, ret, body) => concat(vec![
+                self.tok("fun"),
+                text(" "),
+                self.function(name, params, ret, body),
+            ]),
+            Stmt::Getter(name, body) => concat(vec![self.name(name), text(" "), self.block(body)]),
+            Stmt::If(condition, then_branch, else_branch) => {
+                let mut docs = vec![
+                    self.tok("if"),
+                    text(" "),
+                    self.tok("("),
+                    self.expr(condition),
+                    self.tok(")"),
+                    self.body(then_branch),
+                ];
+                if let Some(else_branch) = else_branch {
+                    // Comments after the then branch stay with it, `else` goes to the next line.
+                    let comments = self.trailing_comments();
+                    let is_commented = !comments.is_empty();
+                    docs.extend(comments);
+                    docs.push(match **then_branch {
+                        Stmt::Block(_) if !is_commented => text(" "),
+                        _ => Doc::HardLine,
+                    });
+                    docs.push(self.tok("else"));
+                    docs.push(match **else_branch {
+                        Stmt::If(_, _, _) => concat(vec![text(" "), self.stmt(else_branch)]),
+                        _ => self.body(else_branch),
+                    });
+                }
+                concat(docs)
+            }
+            Stmt::Match(_, value, cases) => {
+                let header = vec![
+                    self.tok("match"),
+                    text(" "),
+                    self.tok("("),
+                    self.expr(value),
+                    self.tok(")"),
+                    text(" "),
+                ];
+                let cases = self.braced(cases, |f, (pattern, body)| {
+                    let case = vec![
+                        f.tok("case"),
+                        text(" "),
+                        f.pattern(pattern),
+                        text(" "),
+                        f.tok("=>"),
+                        text(" "),
+                        f.stmt(body),
+                    ];
+                    // Commas between cases are optional and left out.
+                    f.consume(",");
+                    concat(case)
+                });
+                concat(header.into_iter().chain([cases]).collect())
+            }
+            Stmt::Mixin(name, methods) => {
+                let header = vec![self.tok("mixin"), text(" "), self.name(name), text(" ")];
+                let body = self.braced(methods, |f, method| f.class_member(false, method));
+                concat(header.into_iter().chain([body]).collect())
+            }
+            Stmt::Print(_, exp) => concat(vec![
+                self.tok("print"),
+                text(" "),
+                self.expr(exp),
+                self.tok(";"),
+            ]),
+            Stmt::Return(_, value) => {
+                let mut docs = vec![self.tok("return")];
+                // `return;` and `return nil;` are the same statement.
+                if *value != Expr::LiteralExpr(Literal::None) || self.peek_is("nil") {
+                    docs.extend([text(" "), self.expr(value)]);
+                }
+                docs.push(self.tok(";"));
+                concat(docs)
+            }
+            Stmt::Var(name, ty, initializer) => {
+                let keyword = self.tok("var");
+                self.variable(keyword, name, ty, initializer)
+            }
+            Stmt::While(condition, body) => concat(vec![
+                self.tok("while"),
+                text(" "),
+                self.tok("("),
+                self.expr(condition),
+                self.tok(")"),
+                self.body(body),
+            ]),
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt]) -> Doc {
+        self.braced(statements, |f, statement| f.stmt(statement))
+    }
+
+    // Body of `if`, `while` and `for`: a block or a statement on the same line if it fits.
+    fn body(&mut self, statement: &Stmt) -> Doc {
+        match statement {
+            Stmt::Block(statements) => concat(vec![text(" "), self.block(statements)]),
+            _ => group(indent(concat(vec![Doc::Line, self.stmt(statement)]))),
+        }
+    }
+
+    fn variable(&mut self, keyword: Doc, name: &Token, ty: &Option<Token>, init: &Expr) -> Doc {
+        let mut docs = vec![keyword, text(" "), self.name(name), self.annotation(ty)];
+        // `var x;` and `var x = nil;` are the same declaration.
+        if *init != Expr::LiteralExpr(Literal::None) || self.peek_is("=") {
+            docs.extend([text(" "), self.tok("="), text(" "), self.expr(init)]);
+        }
+        docs.push(self.tok(";"));
+        concat(docs)
+    }
+
+    fn class_member(&mut self, is_static: bool, member: &Stmt) -> Doc {
+        let mut docs = Vec::new();
+        if is_static {
+            docs.extend([self.tok("class"), text(" ")]);
+        }
+
+        docs.push(match member {
+            Stmt::Function(name, params, ret, body) => self.function(name, params, ret, body),
+            Stmt::Var(name, _, initializer) => {
+                let mut field = vec![self.name(name)];
+                if *initializer != Expr::LiteralExpr(Literal::None) || self.peek_is("=") {
+                    field.extend([text(" "), self.tok("="), text(" "), self.expr(initializer)]);
+                }
+                field.push(self.tok(";"));
+                concat(field)
+            }
+            other => self.stmt(other),
+        });
+        concat(docs)
+    }
+
+    fn function(
+        &mut self,
+        name: &Token,
+        params: &[Param],
+        ret: &Option<Token>,
+        body: &[Stmt],
+    ) -> Doc {
+        let name = self.name(name);
+        let open = self.tok("(");
+        let params = self.comma_separated(params, |f, param| f.param(param));
+        let params = list(open, params, self.tok(")"));
+        let ret = self.annotation(ret);
+        concat(vec![name, params, ret, text(" "), self.block(body)])
+    }
+
+    fn param(&mut self, param: &Param) -> Doc {
+        match param {
+            Param::Required(name, ty) => concat(vec![self.name(name), self.annotation(ty)]),
+            Param::Default(name, ty, default) => concat(vec![
+                self.name(name),
+                self.annotation(ty),
+                text(" "),
+                self.tok("="),
+                text(" "),
+                self.expr(default),
+            ]),
+            Param::Rest(name, ty) => {
+                concat(vec![self.tok("..."), self.name(name), self.annotation(ty)])
+            }
+        }
+    }
+
+    fn annotation(&mut self, ty: &Option<Token>) -> Doc {
+        match ty {
+            Some(ty) => concat(vec![self.tok(":"), text(" "), self.name(ty)]),
+            None => concat(Vec::new()),
+        }
+    }
+
+    // Puts the parts of a desugared `for` loop back, `None` if it has an unexpected shape.
+    fn for_stmt(&mut self, s: &Stmt) -> Option<Doc> {
+        // Which clauses are there is seen from the source: `for (init; cond; incr)`.
+        let mut depth = 0;
+        let mut semicolons = Vec::new();
+        let mut close = None;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.cursor + 2) {
+            match token.get_type() {
+                TokenType::LeftParen | TokenType::LeftBracket => depth += 1,
+                TokenType::RightParen if depth == 0 => {
+                    close = Some(i);
+                    break;
+                }
+                TokenType::RightParen | TokenType::RightBracket => depth -= 1,
+                TokenType::Semicolon if depth == 0 => semicolons.push(i),
+                _ => (),
+            }
+        }
+        let (&[first, second], Some(close)) = (semicolons.as_slice(), close) else {
+            return None;
+        };
+        let has_initializer = first != self.cursor + 2;
+        let has_condition = second != first + 1;
+        let has_increment = close != second + 1;
+
+        let (initializer, while_stmt) = match s {
+            Stmt::Block(statements) if has_initializer && statements.len() == 2 => {
+                (Some(&statements[0]), &statements[1])
+            }
+            _ if has_initializer => return None,
+            _ => (None, s),
+        };
+        let Stmt::While(condition, body) = while_stmt else {
+            return None;
+        };
+        let (body, increment) = match &**body {
+            Stmt::Block(statements) if has_increment && statements.len() == 2 => {
+                match &statements[1] {
+                    Stmt::Expression(increment) => (&statements[0], Some(increment)),
+                    _ => return None,
+                }
+            }
+            _ if has_increment => return None,
+            body => (body, None),
+        };
+
+        let mut docs = vec![self.tok("for"), text(" "), self.tok("(")];
+        match initializer {
+            Some(initializer) => docs.push(self.stmt(initializer)),
+            None => docs.push(self.tok(";")),
+        }
+        if has_condition {
+            docs.extend([text(" "), self.expr(condition)]);
+        }
+        docs.push(self.tok(";"));
+        if let Some(increment) = increment {
+            docs.extend([text(" "), self.expr(increment)]);
+        }
+        docs.push(self.tok(")"));
+        docs.push(self.body(body));
+        Some(concat(docs))
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) -> Doc {
+        match pattern {
+            Pattern::Alternatives(patterns) => {
+                let mut docs = Vec::new();
+                for (i, pattern) in patterns.iter().enumerate() {
+                    if i != 0 {
+                        docs.extend([text(" "), self.tok("|"), text(" ")]);
+                    }
+                    docs.push(self.pattern(pattern));
+                }
+                concat(docs)
+            }
+            Pattern::Binding(name) | Pattern::Wildcard(name) => self.name(name),
+            Pattern::Class(name, fields) => {
+                let name = self.name(name);
+                concat(vec![name, self.field_patterns(fields)])
+            }
+            Pattern::Variant(enum_name, variant, fields) => {
+                let mut docs = vec![self.name(enum_name), self.tok("."), self.name(variant)];
+                if !fields.is_empty() {
+                    docs.push(self.field_patterns(fields));
+                }
+                concat(docs)
+            }
+            Pattern::Literal(token, Literal::Number(n)) if n.get() < 0.0 => {
+                concat(vec![self.tok("-"), self.name(token)])
+            }
+            Pattern::Literal(token, _) => self.name(token),
+        }
+    }
+
+    fn field_patterns(&mut self, fields: &[(Token, Pattern)]) -> Doc {
+        let open = self.tok("(");
+        let fields = self.comma_separated(fields, |f, (field, pattern)| match pattern {
+            // `Point(x)` is kept short.
+            Pattern::Binding(binding) if binding == field => f.name(field),
+            _ => concat(vec![
+                f.name(field),
+                f.tok(":"),
+                text(" "),
+                f.pattern(pattern),
+            ]),
+        });
+        list(open, fields, self.tok(")"))
+    }
+
+    // Expressions.
+
+    fn expr(&mut self, e: &Expr) -> Doc {
+        match e {
+            Expr::Assign(name, value) => concat(vec![
+                self.name(name),
+                text(" "),
+                self.tok("="),
+                text(" "),
+                self.expr(value),
+            ]),
+            Expr::Binary(l, op, r) | Expr::Logical(l, op, r) => {
+                let l = self.expr(l);
+                let op = self.name(op);
+                let r = self.expr(r);
+                group(concat(vec![
+                    l,
+                    text(" "),
+                    op,
+                    indent(concat(vec![Doc::Line, r])),
+                ]))
+            }
+            Expr::Call(callee, _, arguments) => {
+                let callee = self.expr(callee);
+                let open = self.tok("(");
+                let arguments = self.comma_separated(arguments, |f, argument| f.expr(argument));
+                concat(vec![callee, list(open, arguments, self.tok(")"))])
+            }
+            Expr::Get(object, name) => {
+                concat(vec![self.expr(object), self.tok("."), self.name(name)])
+            }
+            Expr::Grouping(exp) => concat(vec![self.tok("("), self.expr(exp), self.tok(")")]),
+            Expr::Index(list, _, index) => concat(vec![
+                self.expr(list),
+                self.tok("["),
+                self.expr(index),
+                self.tok("]"),
+            ]),
+            Expr::List(_, elements) => {
+                let open = self.tok("[");
+                let elements = self.comma_separated(elements, |f, element| f.expr(element));
+                list(open, elements, self.tok("]"))
+            }
+            Expr::LiteralExpr(literal) => self.literal(literal),
+            Expr::Unary(op, r) => concat(vec![self.name(op), self.expr(r)]),
+            Expr::Set(object, name, value) => concat(vec![
+                self.expr(object),
+                self.tok("."),
+                self.name(name),
+                text(" "),
+                self.tok("="),
+                text(" "),
+                self.expr(value),
+            ]),
+            Expr::Spread(_, exp) => concat(vec![self.tok("..."), self.expr(exp)]),
+            Expr::Super(_, method) => {
+                concat(vec![self.tok("super"), self.tok("."), self.name(method)])
+            }
+            Expr::This(_) => self.tok("this"),
+            Expr::Variable(name) => self.name(name),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) -> Doc {
+        match literal {
+            Literal::Bool(true) => self.tok("true"),
+            Literal::Bool(false) => self.tok("false"),
+            Literal::None => self.tok("nil"),
+            Literal::String(s) => self.tok(&format!("\"{s}\"")),
+            Literal::Number(n) => {
+                // Numbers are written as in the source, `1.50` stays `1.50`.
+                let token = self.tokens.get(self.cursor).cloned();
+                match token {
+                    Some(token) if token.get_literal() == literal => self.name(&token),
+                    _ => text(&n.to_string()),
+                }
+            }
+        }
+    }
+}
+
+// Like `list`, but with spaces inside the brackets when it fits on one line.
+fn braced_list(open: Doc, items: Vec<Doc>, close: Doc) -> Doc {
+    if items.is_empty() {
+        return concat(vec![open, close]);
+    }
+
+    let mut body = vec![Doc::Line];
+    for (i, item) in items.into_iter().enumerate() {
+        if i != 0 {
+            body.extend([text(","), Doc::Line]);
+        }
+        body.push(item);
+    }
+    group(concat(vec![open, indent(concat(body)), Doc::Line, close]))
+}
+
+#[cfg(test)]
+mod test_formatter {
+    use super::{format, FormatOptions};
+
+    fn run(source: &str) -> String {
+        let formatted = format(source, &FormatOptions::default()).unwrap();
+        // Formatting is idempotent.
+        assert_eq!(
+            format(&formatted, &FormatOptions::default()).unwrap(),
+            formatted
+        );
+        formatted
+    }
+
+    #[test]
+    fn test_layout() {
+        let source = "// sum\nfun add(a,b){return a+b;}   // add\n\n\nfor(var i=0;i<3;i=i+1) print add(i,1.50);";
+        assert_eq!(
+            run(source),
+            "// sum\nfun add(a, b) {\n    return a + b;\n} // add\n\nfor (var i = 0; i < 3; i = i + 1) print add(i, 1.50);\n",
+        );
+    }
+
+    #[test]
+    fn test_if_comments() {
+        let source = "if (true) print 1; // x\nelse print 2; // y\n";
+        assert_eq!(run(source), "if (true) print 1; // x\nelse print 2; // y\n");
+        let source = "if (a) { print 1; } // x\nelse { print 2; }";
+        assert_eq!(
+            run(source),
+            "if (a) {\n    print 1;\n} // x\nelse {\n    print 2;\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_line_width() {
+        let source = "print call(first, second, third);";
+        let options = FormatOptions {
+            indent_width: 2,
+            line_width: 20,
+        };
+        assert_eq!(
+            format(source, &options).unwrap(),
+            "print call(\n  first,\n  second,\n  third\n);\n",
+        );
+    }
+}
@@ -2,11 +2,15 @@ use crate::lexer::token::{Literal, Num, Token, TokenType, KEYWORDS};
 use crate::Lox;
 
 pub struct Scanner<'a> {
-    source: String,
+    // Indexed by characters, like the columns of the tokens.
+    source: Vec<char>,
     tokens: Vec<Token>,
+    comments: Vec<Token>,
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize,   // index of the first character of the current line
+    start_column: usize, // column of the current lexeme
 
     interpreter: &'a mut Lox,
 }
@@ -14,11 +18,14 @@ pub struct Scanner<'a> {
 impl Scanner<'_> {
     pub fn new<'a>(interpreter: &'a mut Lox, source: &str) -> Scanner<'a> {
         Scanner {
-            source: source.to_string(),
+            source: source.chars().collect(),
             tokens: vec![],
+            comments: vec![],
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_column: 0,
             interpreter,
         }
     }
@@ -27,15 +34,21 @@ impl Scanner<'_> {
         self.tokens.as_ref()
     }
 
+    pub fn comments(&self) -> &Vec<Token> {
+        self.comments.as_ref()
+    }
+
     pub fn scan_tokens(&mut self) {
         while !self.is_end() {
             // We are at the beginning of the next lexeme.
             self.start = self.current;
+            self.start_column = self.start - self.line_start;
             self.scan_token();
         }
 
+        let eof = Token::new(TokenType::Eof, "", Literal::None, self.line);
         self.tokens
-            .push(Token::new(TokenType::Eof, "", Literal::None, self.line));
+            .push(eof.with_column(self.current - self.line_start));
     }
 
     fn scan_token(&mut self) {
@@ -45,9 +58,20 @@ impl Scanner<'_> {
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
+            ':' => self.add_token(TokenType::Colon),
             ',' => self.add_token(TokenType::Comma),
-            '.' => self.add_token(TokenType::Dot),
+            '.' => {
+                if self.look_ahead() == '.' && self.look_ahead_next() == '.' {
+                    self.current += 2;
+                    self.add_token(TokenType::DotDotDot)
+                } else {
+                    self.add_token(TokenType::Dot)
+                }
+            }
             '-' => self.add_token(TokenType::Minus),
+            '|' => self.add_token(TokenType::Pipe),
             '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::Semicolon),
             '*' => self.add_token(TokenType::Star),
@@ -62,6 +86,8 @@ impl Scanner<'_> {
             '=' => {
                 if self.peek('=') {
                     self.add_token(TokenType::EqualEqual)
+                } else if self.peek('>') {
+                    self.add_token(TokenType::FatArrow)
                 } else {
                     self.add_token(TokenType::Equal)
                 }
@@ -87,13 +113,16 @@ impl Scanner<'_> {
                     while self.look_ahead() != '\n' && !self.is_end() {
                         self.advance();
                     }
+                    let text = self.text(self.start, self.current);
+                    let comment = Token::new(TokenType::Comment, &text, Literal::None, self.line);
+                    self.comments.push(comment.with_column(self.start_column));
                 } else {
                     self.add_token(TokenType::Slash)
                 }
             }
 
             ' ' | '\r' | '\t' => (),
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
 
             '"' => self.consume_string(),
 
@@ -103,13 +132,12 @@ impl Scanner<'_> {
 
             _ => self
                 .interpreter
-                .lex_error(self.line, "Unexpected character!"),
+                .lex_error(self.line, self.start_column, "Unexpected character!"),
         }
     }
 
     fn advance(&mut self) -> char {
-        let message = format!("Failed advancing character: {}!", self.current);
-        let c = self.source.chars().nth(self.current).expect(&message);
+        let c = self.source[self.current];
         self.current += 1;
         c
     }
@@ -118,12 +146,7 @@ impl Scanner<'_> {
         if self.is_end() {
             return false;
         }
-        let next_char = self
-            .source
-            .chars()
-            .nth(self.current)
-            .expect("Failed peeking next character!");
-        if next_char != expected {
+        if self.source[self.current] != expected {
             return false;
         }
 
@@ -131,25 +154,16 @@ impl Scanner<'_> {
         true
     }
 
-    // TODO: add helper fucntion to shorten str[idx] line
     fn look_ahead(&self) -> char {
-        if self.is_end() {
-            return '\0';
-        }
-        self.source
-            .chars()
-            .nth(self.current)
-            .expect("Failed looking ahead the character!")
+        self.source.get(self.current).copied().unwrap_or('\0')
     }
 
     fn look_ahead_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-        self.source
-            .chars()
-            .nth(self.current + 1)
-            .expect("Failed looking ahead the next character!")
+        self.source.get(self.current + 1).copied().unwrap_or('\0')
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        self.source[start..end].iter().collect()
     }
 
     fn add_token(&mut self, tok_type: TokenType) {
@@ -157,27 +171,29 @@ impl Scanner<'_> {
     }
 
     fn add_token_literal(&mut self, tok_type: TokenType, literal: Literal) {
-        let text: String = self
-            .source
-            .chars()
-            .skip(self.start)
-            .take(self.current - self.start)
-            .collect();
-        self.tokens
-            .push(Token::new(tok_type, &text, literal, self.line));
+        let text = self.text(self.start, self.current);
+        let token = Token::new(tok_type, &text, literal, self.line);
+        self.tokens.push(token.with_column(self.start_column));
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
     }
 
     fn consume_string(&mut self) {
         while self.look_ahead() != '"' && !self.is_end() {
-            if self.look_ahead() == '\n' {
-                self.line += 1
+            if self.advance() == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_end() {
+            // Reported at the opening quote.
+            let text = self.text(self.start, self.current);
+            let line = self.line - text.matches('\n').count();
             self.interpreter
-                .lex_error(self.line, "Unterminated string!");
+                .lex_error(line, self.start_column, "Unterminated string!");
             return;
         }
 
@@ -185,12 +201,7 @@ impl Scanner<'_> {
         self.advance();
 
         // Trim the surrounding quotes.
-        let value: String = self
-            .source
-            .chars()
-            .skip(self.start + 1)
-            .take(self.current - 2 - self.start)
-            .collect();
+        let value = self.text(self.start + 1, self.current - 1);
         self.add_token_literal(TokenType::String, Literal::String(value));
     }
 
@@ -209,12 +220,7 @@ impl Scanner<'_> {
             }
         }
 
-        let number: String = self
-            .source
-            .chars()
-            .skip(self.start)
-            .take(self.current - self.start)
-            .collect();
+        let number = self.text(self.start, self.current);
         let number: f64 = number.trim().parse().expect("Failed parsing number!");
         self.add_token_literal(TokenType::Number, Literal::Number(Num::new(number)))
     }
@@ -224,13 +230,7 @@ impl Scanner<'_> {
             self.advance();
         }
 
-        // TODO: wrap this str[beg:end] to a function
-        let text: String = self
-            .source
-            .chars()
-            .skip(self.start)
-            .take(self.current - self.start)
-            .collect();
+        let text = self.text(self.start, self.current);
         let tok_type = match KEYWORDS.get(&text) {
             Some(t) => t.clone(),
             None => TokenType::Identifier,
@@ -243,7 +243,7 @@ impl Scanner<'_> {
     }
 
     fn is_digit(&self, c: char) -> bool {
-        ('0'..='9').contains(&c)
+        c.is_ascii_digit()
     }
 
     fn is_alphanum(&self, c: char) -> bool {
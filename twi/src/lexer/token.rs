@@ -4,12 +4,17 @@ use std::hash::{Hash, Hasher};
 
 pub static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "and"    => TokenType::And,
+    "case"   => TokenType::Case,
     "class"  => TokenType::Class,
+    "const"  => TokenType::Const,
     "else"   => TokenType::Else,
+    "enum"   => TokenType::Enum,
     "false"  => TokenType::False,
     "for"    => TokenType::For,
     "fun"    => TokenType::Fun,
     "if"     => TokenType::If,
+    "match"  => TokenType::Match,
+    "mixin"  => TokenType::Mixin,
     "nil"    => TokenType::Nil,
     "or"     => TokenType::Or,
     "print"  => TokenType::Print,
@@ -19,6 +24,7 @@ pub static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "true"   => TokenType::True,
     "var"    => TokenType::Var,
     "while"  => TokenType::While,
+    "with"   => TokenType::With,
 };
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -28,23 +34,29 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
+    Pipe,
     Plus,
     Semicolon,
     Slash,
     Star,
 
-    // One or two character tokens.
+    // One, two or three character tokens.
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    DotDotDot,
 
     // Literals.
     Identifier,
@@ -53,12 +65,17 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Case,
     Class,
+    Const,
     Else,
+    Enum,
     False,
     Fun,
     For,
     If,
+    Match,
+    Mixin,
     Nil,
     Or,
     Print,
@@ -68,6 +85,10 @@ pub enum TokenType {
     True,
     Var,
     While,
+    With,
+
+    // Trivia, kept aside from the other tokens.
+    Comment,
 
     Eof,
 }
@@ -78,6 +99,7 @@ pub struct Token {
     lexeme: String,
     literal: Literal,
     line: usize,
+    column: usize, // position of the first character in its line, starting from 0
 }
 
 impl Token {
@@ -87,9 +109,15 @@ impl Token {
             lexeme: lexeme.to_string(),
             literal,
             line,
+            column: 0,
         }
     }
 
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = column;
+        self
+    }
+
     pub fn get_lexeme(&self) -> &str {
         &self.lexeme
     }
@@ -105,6 +133,10 @@ impl Token {
     pub fn get_line(&self) -> usize {
         self.line
     }
+
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
 }
 
 impl fmt::Display for Token {
@@ -1,28 +1,90 @@
+// `RuntimeError` doubles as the carrier for `return` values, so it is big.
+#![allow(clippy::result_large_err)]
+
 pub mod ast;
+pub mod compare;
+pub mod diagnostic;
 pub mod evaluator;
+pub mod formatter;
 pub mod lexer;
+pub mod lsp;
 pub mod parser;
 pub mod resolver;
+pub mod tester;
+pub mod typechecker;
 
-use ast::{graphviz::AstVis, AstPrinter};
+use ast::{graphviz::AstVis, stmt::Stmt, AstPrinter};
+use diagnostic::Diagnostic;
 use evaluator::{Evaluator, RuntimeError};
 use lexer::scanner::Scanner;
 use lexer::token::{Token, TokenType};
 use parser::Parser;
 use resolver::Resolver;
+use std::collections::HashSet;
+use std::fmt;
 use std::fs;
 use std::io::{self, Write};
 use std::process;
+use typechecker::TypeChecker;
 
 pub trait Visitor<T1, T2> {
     fn visit_expr(&mut self, e: &ast::expr::Expr) -> T1;
     fn visit_stmt(&mut self, s: &ast::stmt::Stmt) -> T2;
 }
 
+// An error found before evaluation: during scanning, parsing, resolving or type checking.
+#[derive(Clone, Debug)]
+pub struct StaticError {
+    pub line: usize,
+    pub column: usize,
+    pub token: Option<Token>, // `None` for scanner errors
+    pub message: String,
+    pub hint: Option<String>, // how to fix the error
+}
+
+impl StaticError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = match &self.token {
+            Some(token) => Diagnostic::at("error", &self.message, token),
+            None => Diagnostic::new("error", &self.message, self.line, self.column, 1),
+        };
+        match &self.hint {
+            Some(hint) => diagnostic.with_note("help", hint),
+            None => diagnostic,
+        }
+    }
+}
+
+impl fmt::Display for StaticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.line;
+        let message = &self.message;
+        match &self.token {
+            Some(token) if *token.get_type() == TokenType::Eof => {
+                write!(f, "[line {line}] Error at end: {message}")
+            }
+            Some(token) => write!(
+                f,
+                "[line {line}] Error at '{}': {message}",
+                token.get_lexeme()
+            ),
+            None => write!(f, "[line {line}] Error: {message}"),
+        }
+    }
+}
+
 pub struct Lox {
     had_error: bool,
     had_runtime_error: bool,
 
+    errors: Vec<StaticError>,
+    is_quiet: bool, // collect errors without printing them
+
+    path: String,   // shown in diagnostics
+    source: String, // code that is being run, for snippets in diagnostics
+
+    constants: HashSet<String>, // names of global constants
+
     evaluator: Evaluator,
 }
 
@@ -31,21 +93,51 @@ impl Lox {
         Lox {
             had_error: false,
             had_runtime_error: false,
+            errors: Vec::new(),
+            is_quiet: false,
+            path: "<repl>".to_string(),
+            source: String::new(),
+            constants: HashSet::new(),
             evaluator,
         }
     }
 
     pub fn run_file(&mut self, path: &str) {
+        match self.run_file_status(path) {
+            0 => (),
+            code => process::exit(code),
+        }
+    }
+
+    // Runs the file and returns the exit code, which indicates an error if there was one.
+    pub fn run_file_status(&mut self, path: &str) -> i32 {
         let contents = fs::read_to_string(path).expect("Couldn't read the given file!");
-        self.run(contents);
+        self.run_source(path, contents)
+    }
+
+    // Runs the code as if it was read from the file at `path`, returns the exit code.
+    pub fn run_source(&mut self, path: &str, source: String) -> i32 {
+        self.path = path.to_string();
+        self.run(source);
+        self.status()
+    }
+
+    // Scans, parses, resolves and type checks the code without running it, returns the exit
+    // code.
+    pub fn check_source(&mut self, path: &str, source: String) -> i32 {
+        self.path = path.to_string();
+        self.analyze(source);
+        self.status()
+    }
 
-        // Indicate an error in the exit code.
+    fn status(&self) -> i32 {
         if self.had_error {
-            process::exit(65)
+            return 65;
         }
         if self.had_runtime_error {
-            process::exit(70)
+            return 70;
         }
+        0
     }
 
     pub fn run_promt(&mut self) {
@@ -63,10 +155,28 @@ impl Lox {
             }
 
             self.had_error = false;
+            self.errors.clear();
         }
     }
 
     fn run(&mut self, source: String) {
+        let Some(statements) = self.analyze(source) else {
+            return;
+        };
+        for statement in statements {
+            match statement {
+                Some(s) => match self.evaluator.execute(&s) {
+                    Ok(_) => (),
+                    Err(err) => self.runtime_error(err),
+                },
+                None => eprintln!("Found None instead of Stmt while evaluation!"),
+            }
+        }
+    }
+
+    // Statements of the code, `None` if there was a static error.
+    fn analyze(&mut self, source: String) -> Option<Vec<Option<Stmt>>> {
+        self.source = source.clone();
         let mut scanner = Scanner::new(self, &source);
         scanner.scan_tokens();
 
@@ -76,7 +186,7 @@ impl Lox {
 
         // Stop if there was a syntax error.
         if self.had_error {
-            return;
+            return None;
         }
 
         let mut resolver = Resolver::new(self);
@@ -86,26 +196,23 @@ impl Lox {
 
         // Stop if there was a resolution error.
         if self.had_error {
-            return;
+            return None;
         }
 
-        for statement in statements {
-            match statement {
-                Some(s) => match self.evaluator.execute(&s) {
-                    Ok(_) => (),
-                    Err(err) => {
-                        self.runtime_error(err);
-                        // eprintln!("Failed expression evaluation!");
-                        eprintln!("Runtime error!");
-                    }
-                },
-                None => eprintln!("Found None instead of Stmt while evaluation!"),
-            }
+        let mut type_checker = TypeChecker::new(self);
+        type_checker.check_optional_stmts(&statements);
+
+        // Stop if there was a type error.
+        if self.had_error {
+            return None;
         }
+        Some(statements)
     }
 
     pub fn run_ast_print(&mut self, path: &str, is_graphviz: bool) {
         let contents = fs::read_to_string(path).expect("Couldn't read the given file!");
+        self.path = path.to_string();
+        self.source = contents.clone();
         let mut scanner = Scanner::new(self, &contents);
         scanner.scan_tokens();
 
@@ -135,6 +242,21 @@ impl Lox {
         }
     }
 
+    pub fn set_quiet(&mut self, is_quiet: bool) {
+        self.is_quiet = is_quiet;
+    }
+
+    // Errors reported so far, in the order they were found.
+    pub fn errors(&self) -> &[StaticError] {
+        &self.errors
+    }
+
+    // Returns the errors reported so far and resets the error state.
+    pub fn take_errors(&mut self) -> Vec<StaticError> {
+        self.had_error = false;
+        std::mem::take(&mut self.errors)
+    }
+
     fn _run_lex_print(&mut self, source: String) {
         let mut scanner = Scanner::new(self, &source);
         scanner.scan_tokens();
@@ -147,33 +269,53 @@ impl Lox {
     }
 
     // TODO: blend lex_error and error together
-    pub fn lex_error(&mut self, line: usize, msg: &str) {
-        self.report(line, "", msg);
+    pub fn lex_error(&mut self, line: usize, column: usize, msg: &str) {
+        self.report(StaticError {
+            line,
+            column,
+            token: None,
+            message: msg.to_string(),
+            hint: None,
+        });
     }
 
     pub fn runtime_error(&mut self, err: RuntimeError) {
-        let mut err_msg = err.get_message();
-        let line = err.get_token().get_line();
-        err_msg.push_str("\n[line ");
-        err_msg.push_str(&line.to_string());
-        err_msg.push(']');
-
-        eprintln!("{err_msg}");
+        if !self.is_quiet {
+            let diagnostic = Diagnostic::at("error", &err.get_message(), &err.get_token());
+            let rendered = diagnostic.render(&self.path, &self.source, diagnostic::is_colored());
+            eprint!("{rendered}");
+        }
 
         self.had_runtime_error = true;
     }
 
     pub fn error(&mut self, token: &Token, msg: &str) {
-        if *token.get_type() == TokenType::Eof {
-            self.report(token.get_line(), " at end", msg)
-        } else {
-            let err_type = format!(" at '{}'", token.get_lexeme());
-            self.report(token.get_line(), &err_type, msg)
-        }
+        self.report(StaticError {
+            line: token.get_line(),
+            column: token.get_column(),
+            token: Some(token.clone()),
+            message: msg.to_string(),
+            hint: None,
+        });
     }
 
-    fn report(&mut self, line: usize, err_type: &str, msg: &str) {
-        eprintln!("[line {line}] Error{err_type}: {msg}");
+    pub fn error_with_hint(&mut self, token: &Token, msg: &str, hint: &str) {
+        self.report(StaticError {
+            line: token.get_line(),
+            column: token.get_column(),
+            token: Some(token.clone()),
+            message: msg.to_string(),
+            hint: Some(hint.to_string()),
+        });
+    }
+
+    fn report(&mut self, error: StaticError) {
+        if !self.is_quiet {
+            let diagnostic = error.diagnostic();
+            let rendered = diagnostic.render(&self.path, &self.source, diagnostic::is_colored());
+            eprint!("{rendered}");
+        }
+        self.errors.push(error);
         self.had_error = true;
     }
 }
@@ -188,7 +330,7 @@ mod test_parser_basic {
         let evaluator = Evaluator::new(environment);
         let mut interpreter = Lox::new(evaluator);
 
-        let mut scanner = Scanner::new(&mut interpreter, &source);
+        let mut scanner = Scanner::new(&mut interpreter, source);
         scanner.scan_tokens();
 
         let tokens = scanner.tokens().clone();
@@ -196,14 +338,11 @@ mod test_parser_basic {
         let statements = parser.parse();
 
         let mut printer = AstPrinter;
-        for statement in statements {
-            match statement {
-                Some(s) => return format!("{}", printer.visit_stmt(&s)),
-                None => panic!("Failed while printing AST. (None Stmt)."),
-            }
+        match statements.into_iter().next() {
+            Some(Some(s)) => printer.visit_stmt(&s),
+            Some(None) => panic!("Failed while printing AST. (None Stmt)."),
+            None => panic!("Failed while printing AST."),
         }
-
-        panic!("Failed while printing AST.");
     }
 
     #[test]
@@ -0,0 +1,247 @@
+use crate::ast::stmt::Stmt;
+use crate::evaluator::{environment::Environment, Evaluator};
+use crate::lexer::{
+    scanner::Scanner,
+    token::{Token, TokenType},
+};
+use crate::parser::Parser;
+use crate::resolver::{
+    symbols::{Symbol, SymbolKind, SymbolTable},
+    Resolver,
+};
+use crate::typechecker::TypeChecker;
+use crate::{Lox, StaticError};
+
+// (line, column), both start from 0 as in LSP.
+pub type Position = (usize, usize);
+
+pub fn start(token: &Token) -> Position {
+    (token.get_line().saturating_sub(1), token.get_column())
+}
+
+pub fn end(token: &Token) -> Position {
+    let (line, column) = start(token);
+    (line, column + token.get_lexeme().chars().count())
+}
+
+fn is_member(symbol: &Symbol) -> bool {
+    symbol.get_container().is_some()
+}
+
+// Everything the language server knows about one document.
+pub struct Analysis {
+    tokens: Vec<Token>,
+    symbols: SymbolTable,
+    errors: Vec<StaticError>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        interpreter.set_quiet(true);
+
+        let mut scanner = Scanner::new(&mut interpreter, source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+
+        // Statements with syntax errors are skipped, the rest is still resolved.
+        let mut parser = Parser::new(&mut interpreter, tokens.clone());
+        let statements: Vec<Option<Stmt>> =
+            parser.parse().into_iter().filter(Option::is_some).collect();
+        let has_syntax_error = !interpreter.errors.is_empty();
+
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve_optional_stmts(statements.clone());
+        let symbols = resolver.symbols();
+
+        if !has_syntax_error {
+            TypeChecker::new(&mut interpreter).check_optional_stmts(&statements);
+        }
+
+        Analysis {
+            tokens,
+            symbols,
+            errors: interpreter.take_errors(),
+        }
+    }
+
+    pub fn errors(&self) -> &[StaticError] {
+        &self.errors
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        self.symbols.symbols()
+    }
+
+    fn token_index_at(&self, position: Position) -> Option<usize> {
+        self.tokens.iter().position(|token| {
+            *token.get_type() == TokenType::Identifier
+                && start(token) <= position
+                && position <= end(token)
+        })
+    }
+
+    // Symbols the name under the cursor may refer to.
+    fn symbols_at(&self, position: Position) -> Vec<usize> {
+        let Some(token) = self.token_index_at(position).map(|i| &self.tokens[i]) else {
+            return Vec::new();
+        };
+        let is_same = |other: &Token| start(other) == start(token);
+
+        let declared = self.symbols().iter().position(|s| is_same(s.get_name()));
+        let referenced = self
+            .symbols
+            .references()
+            .iter()
+            .find(|(usage, _)| is_same(usage))
+            .map(|(_, id)| *id);
+        if let Some(id) = declared.or(referenced) {
+            return vec![id];
+        }
+
+        // Properties can't be resolved statically, so every member with this name fits.
+        if self.symbols.properties().iter().any(is_same) {
+            return self.members_named(token.get_lexeme());
+        }
+        Vec::new()
+    }
+
+    fn members_named(&self, name: &str) -> Vec<usize> {
+        let symbols = self.symbols().iter().enumerate();
+        symbols
+            .filter(|(_, s)| is_member(s) && s.get_name().get_lexeme() == name)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    pub fn definition(&self, position: Position) -> Vec<&Token> {
+        let ids = self.symbols_at(position);
+        ids.into_iter()
+            .map(|id| self.symbols()[id].get_name())
+            .collect()
+    }
+
+    pub fn references(&self, position: Position, include_declaration: bool) -> Vec<&Token> {
+        let ids = self.symbols_at(position);
+        let mut usages: Vec<&Token> = Vec::new();
+
+        if ids.iter().any(|&id| is_member(&self.symbols()[id])) {
+            let name = self.symbols()[ids[0]].get_name().get_lexeme();
+            let properties = self.symbols.properties().iter();
+            usages.extend(properties.filter(|p| p.get_lexeme() == name));
+        } else {
+            let references = self.symbols.references().iter();
+            usages.extend(
+                references
+                    .filter(|(_, id)| ids.contains(id))
+                    .map(|(usage, _)| usage),
+            );
+        }
+
+        if include_declaration {
+            usages.extend(ids.iter().map(|&id| self.symbols()[id].get_name()));
+        }
+        usages.sort_by_key(|token| start(token));
+        usages
+    }
+
+    pub fn hover(&self, position: Position) -> Vec<&Symbol> {
+        let ids = self.symbols_at(position);
+        ids.into_iter().map(|id| &self.symbols()[id]).collect()
+    }
+
+    // Top level declarations together with their members.
+    pub fn document_symbols(&self) -> Vec<(&Symbol, Vec<&Symbol>)> {
+        let globals = self.symbols().iter().filter(|s| s.is_global());
+        globals
+            .map(|symbol| {
+                let name = symbol.get_name().get_lexeme();
+                let members = self
+                    .symbols()
+                    .iter()
+                    .filter(|m| m.get_container() == Some(name))
+                    .collect();
+                (symbol, members)
+            })
+            .collect()
+    }
+
+    // Whether a member name is being typed: right after a dot or in a name after it.
+    pub fn is_after_dot(&self, position: Position) -> bool {
+        let before = self
+            .tokens
+            .iter()
+            .rposition(|token| end(token) <= position && start(token) < position);
+        match before.map(|i| (i, self.tokens[i].get_type())) {
+            Some((_, TokenType::Dot)) => true,
+            Some((i, TokenType::Identifier)) => {
+                i > 0
+                    && *self.tokens[i - 1].get_type() == TokenType::Dot
+                    && end(&self.tokens[i]) == position
+            }
+            _ => false,
+        }
+    }
+
+    pub fn completions(&self, position: Position) -> Vec<&Symbol> {
+        let is_property = self.is_after_dot(position);
+        let mut completions: Vec<&Symbol> = Vec::new();
+        for symbol in self.symbols() {
+            let fits = match is_property {
+                true => is_member(symbol),
+                false => !is_member(symbol) && self.is_visible(symbol, position),
+            };
+            let name = symbol.get_name().get_lexeme();
+            if fits
+                && !completions
+                    .iter()
+                    .any(|s| s.get_name().get_lexeme() == name)
+            {
+                completions.push(symbol);
+            }
+        }
+        completions
+    }
+
+    // Locals are visible after their declaration until the end of the enclosing block.
+    fn is_visible(&self, symbol: &Symbol, position: Position) -> bool {
+        if symbol.is_global() {
+            return true;
+        }
+
+        let declared = start(symbol.get_name());
+        if position < declared {
+            return false;
+        }
+
+        // Parameters belong to the body that follows them.
+        let scope_end = match symbol.get_kind() {
+            SymbolKind::Parameter => self.block_after(declared),
+            _ => self.block_around(declared),
+        };
+        scope_end.is_none_or(|scope_end| position <= scope_end)
+    }
+
+    // End of the innermost block that contains the position.
+    fn block_around(&self, position: Position) -> Option<Position> {
+        let mut depth = 0;
+        let after = self.tokens.iter().skip_while(|t| start(t) < position);
+        for token in after {
+            match token.get_type() {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => return Some(start(token)),
+                TokenType::RightBrace => depth -= 1,
+                _ => (),
+            }
+        }
+        None
+    }
+
+    fn block_after(&self, position: Position) -> Option<Position> {
+        let brace = self
+            .tokens
+            .iter()
+            .find(|t| start(t) > position && *t.get_type() == TokenType::LeftBrace)?;
+        self.block_around(end(brace))
+    }
+}
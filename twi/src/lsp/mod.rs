@@ -0,0 +1,555 @@
+//! Language server for Lox that talks JSON-RPC over stdio.
+
+use crate::lexer::token::{Token, KEYWORDS};
+use crate::resolver::symbols::{Symbol, SymbolKind};
+use analysis::{end, start, Analysis, Position};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+pub mod analysis;
+
+// Native functions defined by the evaluator.
+const NATIVES: [&str; 2] = ["clock", "len"];
+
+// Error codes from the JSON-RPC specification.
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub struct Server {
+    documents: HashMap<String, String>, // uri -> text
+    is_shutdown: bool,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+            is_shutdown: false,
+        }
+    }
+
+    // Serves until the `exit` notification, returns the process exit code.
+    pub fn run(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> io::Result<i32> {
+        while let Some(message) = read_message(input)? {
+            if message["method"] == "exit" {
+                return Ok(if self.is_shutdown { 0 } else { 1 });
+            }
+
+            for reply in self.handle(&message) {
+                write_message(output, &reply)?;
+            }
+        }
+        Ok(1)
+    }
+
+    // Responses and notifications to send back for one message.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let id = &message["id"];
+
+        match method {
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                let text = document["text"].as_str().unwrap_or_default();
+                return self.update(document["uri"].as_str(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                // The whole text is sent on every change.
+                let changes = params["contentChanges"].as_array();
+                let text = changes.and_then(|c| c.last()).map(|c| &c["text"]);
+                let text = text.and_then(Value::as_str).unwrap_or_default();
+                return self.update(params["textDocument"]["uri"].as_str(), text.to_string());
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                return vec![diagnostics(uri, Vec::new())];
+            }
+            _ if id.is_null() => return Vec::new(), // other notifications are ignored
+            _ => (),
+        }
+
+        let result = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.is_shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition"
+            | "textDocument/references"
+            | "textDocument/hover"
+            | "textDocument/documentSymbol"
+            | "textDocument/completion" => self.query(method, params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{method}'."))),
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        vec![response]
+    }
+
+    fn update(&mut self, uri: Option<&str>, text: String) -> Vec<Value> {
+        let Some(uri) = uri else {
+            return Vec::new();
+        };
+
+        let analysis = Analysis::new(&text);
+        self.documents.insert(uri.to_string(), text);
+
+        let errors = analysis.errors().iter().map(|error| {
+            let (from, to) = match &error.token {
+                Some(token) => (start(token), end(token)),
+                None => {
+                    let from = (error.line - 1, error.column);
+                    (from, (from.0, from.1 + 1))
+                }
+            };
+            json!({
+                "range": range(from, to),
+                "severity": 1,
+                "source": "lox",
+                "message": error.message,
+            })
+        });
+        vec![diagnostics(uri, errors.collect())]
+    }
+
+    fn query(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(text) = self.documents.get(uri) else {
+            return Err((INVALID_PARAMS, format!("Unknown document '{uri}'.")));
+        };
+        let analysis = Analysis::new(text);
+
+        let position = &params["position"];
+        let position: Position = (
+            position["line"].as_u64().unwrap_or_default() as usize,
+            position["character"].as_u64().unwrap_or_default() as usize,
+        );
+
+        let result = match method {
+            "textDocument/definition" => {
+                let names = analysis.definition(position);
+                Value::from_iter(names.into_iter().map(|name| location(uri, name)))
+            }
+            "textDocument/references" => {
+                let include_declaration = params["context"]["includeDeclaration"] == true;
+                let usages = analysis.references(position, include_declaration);
+                Value::from_iter(usages.into_iter().map(|usage| location(uri, usage)))
+            }
+            "textDocument/hover" => match analysis.hover(position).as_slice() {
+                [] => Value::Null,
+                symbols => {
+                    let lines: Vec<String> = symbols.iter().map(|s| s.to_string()).collect();
+                    json!({
+                        "contents": {
+                            "kind": "markdown",
+                            "value": format!("```lox\n{}\n```", lines.join("\n")),
+                        },
+                    })
+                }
+            },
+            "textDocument/documentSymbol" => {
+                let symbols = analysis.document_symbols().into_iter();
+                Value::from_iter(symbols.map(|(symbol, members)| {
+                    let mut symbol = document_symbol(symbol);
+                    symbol["children"] = Value::from_iter(members.into_iter().map(document_symbol));
+                    symbol
+                }))
+            }
+            _ => {
+                let symbols = analysis.completions(position).into_iter();
+                let mut items: Vec<Value> = symbols.map(completion_item).collect();
+                if !analysis.is_after_dot(position) {
+                    let keywords = KEYWORDS.keys().map(|k| json!({ "label": k, "kind": 14 }));
+                    let natives = NATIVES.iter().map(|n| json!({ "label": n, "kind": 3 }));
+                    items.extend(keywords.chain(natives));
+                }
+                Value::from(items)
+            }
+        };
+        Ok(result)
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "hoverProvider": true,
+            "documentSymbolProvider": true,
+            "completionProvider": { "triggerCharacters": ["."] },
+        },
+        "serverInfo": { "name": "lox-lsp" },
+    })
+}
+
+fn diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn range(from: Position, to: Position) -> Value {
+    json!({
+        "start": { "line": from.0, "character": from.1 },
+        "end": { "line": to.0, "character": to.1 },
+    })
+}
+
+fn location(uri: &str, token: &Token) -> Value {
+    json!({ "uri": uri, "range": range(start(token), end(token)) })
+}
+
+fn document_symbol(symbol: &Symbol) -> Value {
+    let name = symbol.get_name();
+    // Numbers of `SymbolKind` from the LSP specification.
+    let kind = match symbol.get_kind() {
+        SymbolKind::Class => 5,
+        SymbolKind::Method => 6,
+        SymbolKind::Field => 8,
+        SymbolKind::Enum => 10,
+        SymbolKind::Mixin => 11,
+        SymbolKind::Function => 12,
+        SymbolKind::Parameter | SymbolKind::Variable => 13,
+        SymbolKind::Constant => 14,
+        SymbolKind::Variant => 22,
+    };
+    json!({
+        "name": name.get_lexeme(),
+        "detail": symbol.get_detail(),
+        "kind": kind,
+        "range": range(start(name), end(name)),
+        "selectionRange": range(start(name), end(name)),
+    })
+}
+
+fn completion_item(symbol: &Symbol) -> Value {
+    // Numbers of `CompletionItemKind` from the LSP specification.
+    let kind = match symbol.get_kind() {
+        SymbolKind::Method => 2,
+        SymbolKind::Function => 3,
+        SymbolKind::Field => 5,
+        SymbolKind::Parameter | SymbolKind::Variable => 6,
+        SymbolKind::Class => 7,
+        SymbolKind::Mixin => 8,
+        SymbolKind::Enum => 13,
+        SymbolKind::Variant => 20,
+        SymbolKind::Constant => 21,
+    };
+    json!({
+        "label": symbol.get_name().get_lexeme(),
+        "kind": kind,
+        "detail": symbol.to_string(),
+    })
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing Content-Length header.",
+        ));
+    };
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod test_server {
+    use super::Server;
+    use serde_json::{json, Value};
+    use std::io::Cursor;
+
+    // Sends the messages to the server as a client would and returns its replies.
+    fn run(messages: &[Value]) -> (i32, Vec<Value>) {
+        let mut input = String::new();
+        for message in messages {
+            let content = message.to_string();
+            input.push_str(&format!(
+                "Content-Length: {}\r\n\r\n{}",
+                content.len(),
+                content
+            ));
+        }
+
+        let mut output = Vec::new();
+        let code = Server::new()
+            .run(&mut Cursor::new(input), &mut output)
+            .unwrap();
+
+        let mut replies = Vec::new();
+        let mut output = String::from_utf8(output).unwrap();
+        while let Some((header, rest)) = output.split_once("\r\n\r\n") {
+            let length: usize = header["Content-Length: ".len()..].parse().unwrap();
+            replies.push(serde_json::from_str(&rest[..length]).unwrap());
+            output = rest[length..].to_string();
+        }
+        (code, replies)
+    }
+
+    fn open(uri: &str, text: &str) -> Value {
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen",
+                "params": { "textDocument": { "uri": uri, "text": text } } })
+    }
+
+    fn request(id: i64, method: &str, uri: &str, position: (i64, i64)) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": {
+            "textDocument": { "uri": uri },
+            "position": { "line": position.0, "character": position.1 },
+        } })
+    }
+
+    // Start positions of the locations in a reply.
+    fn starts(reply: &Value) -> Vec<(u64, u64)> {
+        let locations = reply["result"].as_array().unwrap();
+        locations
+            .iter()
+            .map(|l| &l["range"]["start"])
+            .map(|s| {
+                (
+                    s["line"].as_u64().unwrap(),
+                    s["character"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn labels(reply: &Value) -> Vec<&str> {
+        let items = reply["result"].as_array().unwrap();
+        items.iter().map(|i| i["label"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_session() {
+        let uri = "file:///main.lox";
+        let source = "fun add(a, b) { return a + b; }\nprint add(1, 2);\nprint \"a\" - 1;";
+        let position = |line, character| json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } });
+        let (code, replies) = run(&[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen",
+                    "params": { "textDocument": { "uri": uri, "text": source } } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/definition",
+                    "params": position(1, 7) }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/hover",
+                    "params": position(0, 23) }),
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]);
+
+        assert_eq!(code, 0);
+        assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+
+        let diagnostics = &replies[1]["params"]["diagnostics"];
+        assert_eq!(diagnostics[0]["message"], "Operands must be numbers.");
+        assert_eq!(
+            diagnostics[0]["range"]["start"],
+            json!({ "line": 2, "character": 10 })
+        );
+
+        let definition = &replies[2]["result"][0]["range"]["start"];
+        assert_eq!(*definition, json!({ "line": 0, "character": 4 }));
+
+        let hover = replies[3]["result"]["contents"]["value"].as_str().unwrap();
+        assert!(hover.contains("(parameter) a"));
+        assert_eq!(replies[4]["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_references() {
+        let uri = "file:///main.lox";
+        let source = "var count = 0;
+fun bump(step) {
+    count = count + step;
+    return count;
+}
+print bump(1) + count;
+class P { f() {} g() { this.f(); } }
+P().f();";
+        let with_declaration = |id, position: (i64, i64), include: bool| {
+            let mut message = request(id, "textDocument/references", uri, position);
+            message["params"]["context"] = json!({ "includeDeclaration": include });
+            message
+        };
+        let (_, replies) = run(&[
+            open(uri, source),
+            with_declaration(1, (0, 4), true),
+            with_declaration(2, (3, 12), false),
+            with_declaration(3, (1, 10), true),
+            with_declaration(4, (7, 4), false),
+            with_declaration(5, (6, 10), true),
+        ]);
+
+        assert_eq!(
+            starts(&replies[1]),
+            [(0, 4), (2, 4), (2, 12), (3, 11), (5, 16)]
+        );
+        assert_eq!(starts(&replies[2]), [(2, 4), (2, 12), (3, 11), (5, 16)]);
+        assert_eq!(starts(&replies[3]), [(1, 9), (2, 20)]);
+        // Properties are found by name, wherever they are used.
+        assert_eq!(starts(&replies[4]), [(6, 28), (7, 4)]);
+        assert_eq!(starts(&replies[5]), [(6, 10), (6, 28), (7, 4)]);
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let uri = "file:///main.lox";
+        let source = "class Point {
+    init(x) { this.x = x; }
+    norm() { return 0; }
+}
+fun f(a) { var b = a; return b; }
+enum Color { Red, Green }";
+        let (_, replies) = run(&[
+            open(uri, source),
+            request(1, "textDocument/documentSymbol", uri, (0, 0)),
+        ]);
+
+        // Each symbol as `name kind`.
+        let names = |symbols: &Value| -> Vec<String> {
+            let symbols = symbols.as_array().unwrap().iter();
+            symbols
+                .map(|s| format!("{} {}", s["name"].as_str().unwrap(), s["kind"]))
+                .collect()
+        };
+        let symbols = &replies[1]["result"];
+        assert_eq!(names(symbols), ["Point 5", "f 12", "Color 10"]);
+        // Members are the children of their class or enum, locals are left out.
+        assert_eq!(names(&symbols[0]["children"]), ["init 6", "norm 6"]);
+        assert_eq!(symbols[1]["children"], json!([]));
+        assert_eq!(names(&symbols[2]["children"]), ["Red 22", "Green 22"]);
+        assert_eq!(
+            symbols[0]["selectionRange"]["start"],
+            json!({ "line": 0, "character": 6 })
+        );
+    }
+
+    #[test]
+    fn test_completion() {
+        let uri = "file:///main.lox";
+        let source = "class P { f() {} }
+fun g(a) {
+    var b = P();
+    b.
+}";
+        let (_, replies) = run(&[
+            open(uri, source),
+            request(1, "textDocument/completion", uri, (3, 6)),
+            request(2, "textDocument/completion", uri, (3, 4)),
+        ]);
+
+        assert_eq!(labels(&replies[1]), ["f"]);
+        let labels = labels(&replies[2]);
+        for label in ["P", "g", "a", "b", "while", "clock", "len"] {
+            assert!(labels.contains(&label), "{label} is missing");
+        }
+        assert!(!labels.contains(&"f"));
+    }
+
+    #[test]
+    fn test_change_and_close() {
+        let uri = "file:///main.lox";
+        let change = |text: &str| {
+            json!({ "jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+                "textDocument": { "uri": uri },
+                "contentChanges": [{ "text": text }],
+            } })
+        };
+        let (_, replies) = run(&[
+            open(uri, "var a = 1;"),
+            change("var a = 1;\nprint -\"a\";"),
+            request(1, "textDocument/definition", uri, (1, 0)),
+            change("var a = 1;\nprint a;"),
+            request(2, "textDocument/definition", uri, (1, 6)),
+            json!({ "jsonrpc": "2.0", "method": "textDocument/didClose",
+                    "params": { "textDocument": { "uri": uri } } }),
+            request(3, "textDocument/definition", uri, (1, 6)),
+        ]);
+
+        assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+        let diagnostics = &replies[1]["params"]["diagnostics"];
+        assert_eq!(diagnostics[0]["message"], "Operand must be a number.");
+        assert_eq!(replies[2]["result"], json!([]));
+        assert_eq!(replies[3]["params"]["diagnostics"], json!([]));
+        assert_eq!(starts(&replies[4]), [(0, 4)]);
+        // Closing clears the diagnostics and forgets the document.
+        assert_eq!(replies[5]["params"]["uri"], uri);
+        assert_eq!(replies[5]["params"]["diagnostics"], json!([]));
+        assert_eq!(replies[6]["error"]["code"], super::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_errors() {
+        let uri = "file:///main.lox";
+        let (code, replies) = run(&[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "workspace/symbol", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "$/cancelRequest", "params": { "id": 1 } }),
+            request(2, "textDocument/hover", uri, (0, 0)),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]);
+
+        // Unknown notifications get no reply and exit without shutdown fails.
+        assert_eq!(code, 1);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0]["id"], 1);
+        assert_eq!(replies[0]["error"]["code"], super::METHOD_NOT_FOUND);
+        assert_eq!(
+            replies[0]["error"]["message"],
+            "Unknown method 'workspace/symbol'."
+        );
+        assert_eq!(replies[1]["id"], 2);
+        assert_eq!(replies[1]["error"]["code"], super::INVALID_PARAMS);
+        assert_eq!(
+            replies[1]["error"]["message"],
+            "Unknown document 'file:///main.lox'."
+        );
+    }
+}
@@ -1,20 +1,56 @@
+use std::cell::RefCell;
 use std::env;
+use std::fs;
+use std::path::PathBuf;
 use std::process;
-use twi::evaluator::{environment::Environment, Evaluator};
+use std::rc::Rc;
+use twi::compare::{self, Interpreters};
+use twi::diagnostic;
+use twi::evaluator::{coverage::Coverage, debugger::Debugger, environment::Environment, Evaluator};
+use twi::formatter::{self, FormatError, FormatOptions};
+use twi::resolver::lint::{self, Severity};
+use twi::tester;
 use twi::Lox;
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).is_some_and(|command| command == "fmt") {
+        run_fmt(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|command| command == "lint") {
+        run_lint(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|command| command == "test") {
+        run_test(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|command| command == "compare") {
+        run_compare(&args[2..]);
+        return;
+    }
+
     let environment = Environment::new(None);
-    let evaluator = Evaluator::new(environment);
+    let mut evaluator = Evaluator::new(environment);
+    if args.len() == 3 && args[2] == "-g" {
+        let source = fs::read_to_string(&args[1]).expect("Couldn't read the given file!");
+        let debugger = Debugger::stdio(&args[1], &source);
+        evaluator.set_hook(Rc::new(RefCell::new(debugger)));
+    }
+    if args.len() == 3 && args[2] == "--coverage" {
+        run_coverage(&args[1], evaluator);
+        return;
+    }
     let mut interpreter = Lox::new(evaluator);
 
-    let args: Vec<String> = env::args().collect();
     match args.len() {
         3 => match args[2].as_str() {
             "-p" => interpreter.run_ast_print(&args[1], false),
             "-v" => interpreter.run_ast_print(&args[1], true),
+            "-g" => interpreter.run_file(&args[1]),
             _ => {
-                eprintln!("Available commands: -v | -p");
+                eprintln!("Available commands: -v | -p | -g | --coverage");
                 process::exit(64);
             }
         },
@@ -26,3 +62,185 @@ fn main() {
         }
     }
 }
+
+// lox file --coverage
+fn run_coverage(path: &str, mut evaluator: Evaluator) {
+    let source = fs::read_to_string(path).expect("Couldn't read the given file!");
+    let coverage = Rc::new(RefCell::new(Coverage::new(path, &source)));
+    evaluator.set_hook(coverage.clone());
+    let code = Lox::new(evaluator).run_file_status(path);
+
+    // The report is written even if the script failed, it shows how far it got.
+    let coverage = coverage.borrow();
+    eprint!("{}", coverage.summary());
+    let lcov_path = format!("{path}.lcov");
+    match fs::write(&lcov_path, coverage.lcov()) {
+        Ok(()) => eprintln!("Coverage report is written to {lcov_path}"),
+        Err(err) => eprintln!("Couldn't write {lcov_path}: {err}"),
+    }
+    process::exit(code);
+}
+
+// lox fmt [--check] [--indent N] [--width N] files...
+fn run_fmt(args: &[String]) {
+    let usage = "Usage: lox fmt [--check] [--indent N] [--width N] files...";
+    let mut options = FormatOptions::default();
+    let mut is_check = false;
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => is_check = true,
+            "--indent" | "--width" => {
+                let Some(n) = args.next().and_then(|n| n.parse().ok()) else {
+                    eprintln!("{usage}");
+                    process::exit(64);
+                };
+                match arg.as_str() {
+                    "--indent" => options.indent_width = n,
+                    _ => options.line_width = n,
+                }
+            }
+            path => paths.push(path),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{usage}");
+        process::exit(64);
+    }
+
+    let is_colored = diagnostic::is_colored();
+    let mut is_failed = false;
+    for path in paths {
+        let source = fs::read_to_string(path).expect("Couldn't read the given file!");
+        match formatter::format(&source, &options) {
+            Ok(formatted) if formatted == source => (),
+            Ok(_) if is_check => {
+                println!("{path} is not formatted");
+                is_failed = true;
+            }
+            Ok(formatted) => fs::write(path, formatted).expect("Couldn't write the given file!"),
+            Err(FormatError::Syntax(errors)) => {
+                for error in errors {
+                    eprint!("{}", error.diagnostic().render(path, &source, is_colored));
+                }
+                is_failed = true;
+            }
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                is_failed = true;
+            }
+        }
+    }
+
+    if is_failed {
+        process::exit(1);
+    }
+}
+
+// lox lint files...
+fn run_lint(paths: &[String]) {
+    if paths.is_empty() {
+        eprintln!("Usage: lox lint files...");
+        process::exit(64);
+    }
+
+    // Only errors fail the check, warnings are just printed.
+    let is_colored = diagnostic::is_colored();
+    let mut is_failed = false;
+    for path in paths {
+        let source = fs::read_to_string(path).expect("Couldn't read the given file!");
+        match lint::lint(&source) {
+            Ok(lints) => {
+                for lint in lints {
+                    print!("{}", lint.diagnostic().render(path, &source, is_colored));
+                    is_failed |= lint.get_kind().severity() == Severity::Error;
+                }
+            }
+            Err(errors) => {
+                for error in errors {
+                    eprint!("{}", error.diagnostic().render(path, &source, is_colored));
+                }
+                is_failed = true;
+            }
+        }
+    }
+
+    if is_failed {
+        process::exit(1);
+    }
+}
+
+// lox test [--interpreter twi|bvm|PATH] paths...
+fn run_test(args: &[String]) {
+    let usage = "Usage: lox test [--interpreter twi|bvm|PATH] paths...";
+    let mut interpreter = "twi";
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--interpreter" => {
+                let Some(name) = args.next() else {
+                    eprintln!("{usage}");
+                    process::exit(64);
+                };
+                interpreter = name;
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{usage}");
+        process::exit(64);
+    }
+
+    // Both interpreters are built into the same directory.
+    let current = env::current_exe().expect("Couldn't find the interpreter!");
+    let interpreter = match interpreter {
+        "twi" => current,
+        "bvm" => current.with_file_name("bvm"),
+        path => PathBuf::from(path),
+    };
+    if !tester::run_all(&interpreter, &paths) {
+        process::exit(1);
+    }
+}
+
+// lox compare [--twi PATH] [--bvm PATH] paths...
+fn run_compare(args: &[String]) {
+    let usage = "Usage: lox compare [--twi PATH] [--bvm PATH] paths...";
+    // Both interpreters are built into the same directory.
+    let current = env::current_exe().expect("Couldn't find the interpreter!");
+    let mut interpreters = Interpreters {
+        bvm: current.with_file_name("bvm"),
+        twi: current,
+    };
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--twi" | "--bvm" => {
+                let Some(path) = args.next() else {
+                    eprintln!("{usage}");
+                    process::exit(64);
+                };
+                match arg.as_str() {
+                    "--twi" => interpreters.twi = PathBuf::from(path),
+                    _ => interpreters.bvm = PathBuf::from(path),
+                }
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{usage}");
+        process::exit(64);
+    }
+
+    if !compare::run_all(&interpreters, &paths) {
+        process::exit(1);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::ast::expr::Expr;
-use crate::ast::stmt::Stmt;
-use crate::lexer::token::{Literal, Token, TokenType};
+use crate::ast::stmt::{Param, Pattern, Stmt};
+use crate::diagnostic::suggest;
+use crate::lexer::token::{Literal, Num, Token, TokenType, KEYWORDS};
 use crate::Lox;
 use std::error::Error;
 use std::fmt::Display;
@@ -19,15 +20,17 @@ impl Display for ParseError {
 pub struct Parser<'a> {
     tokens: Vec<Token>,
     current: usize,
+    block_depth: usize, // number of blocks around the current token
 
     interpreter: &'a mut Lox,
 }
 
 impl Parser<'_> {
-    pub fn new(interpreter: &mut Lox, tokens: Vec<Token>) -> Parser {
+    pub fn new(interpreter: &mut Lox, tokens: Vec<Token>) -> Parser<'_> {
         Parser {
             tokens,
             current: 0,
+            block_depth: 0,
             interpreter,
         }
     }
@@ -69,6 +72,13 @@ impl Parser<'_> {
     }
 
     fn declaration(&mut self) -> Option<Stmt> {
+        if self.block_depth == 0 && self.check(&TokenType::RightBrace) {
+            let token = self.advance().clone();
+            self.error(&token, "Unmatched '}'.");
+            return None;
+        }
+        self.fix_misspelled_keyword();
+
         if self.match_tokens(&vec![TokenType::Class]) {
             match self.class_declaration() {
                 Ok(s) => return Some(s),
@@ -80,6 +90,26 @@ impl Parser<'_> {
             }
         }
 
+        if self.match_tokens(&vec![TokenType::Enum]) {
+            match self.enum_declaration() {
+                Ok(s) => return Some(s),
+                Err(_) => {
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+
+        if self.match_tokens(&vec![TokenType::Mixin]) {
+            match self.mixin_declaration() {
+                Ok(s) => return Some(s),
+                Err(_) => {
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+
         if self.match_tokens(&vec![TokenType::Fun]) {
             match self.function("function") {
                 Ok(s) => return Some(s),
@@ -91,6 +121,16 @@ impl Parser<'_> {
             }
         }
 
+        if self.match_tokens(&vec![TokenType::Const]) {
+            match self.const_declaration() {
+                Ok(s) => return Some(s),
+                Err(_) => {
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+
         if self.match_tokens(&vec![TokenType::Var]) {
             match self.var_declaration() {
                 Ok(s) => return Some(s),
@@ -121,6 +161,10 @@ impl Parser<'_> {
             return self.if_stmt();
         }
 
+        if self.match_tokens(&vec![TokenType::Match]) {
+            return self.match_stmt();
+        }
+
         if self.match_tokens(&vec![TokenType::Print]) {
             return self.print_stmt();
         }
@@ -200,12 +244,127 @@ impl Parser<'_> {
     }
 
     fn print_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
         let value = self.expression()?;
-        match self.consume(TokenType::Semicolon, "Expect ';' after value.") {
-            Ok(_) => (),
-            Err(err) => return Err(err),
-        };
-        Ok(Stmt::Print(value))
+        self.consume_semicolon("Expect ';' after value.")?;
+        Ok(Stmt::Print(keyword, value))
+    }
+
+    fn match_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'match'.")?;
+        let value = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after match value.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before match cases.")?;
+
+        let mut cases = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_end() {
+            self.consume(TokenType::Case, "Expect 'case' before pattern.")?;
+            let pattern = self.pattern()?;
+            self.consume(TokenType::FatArrow, "Expect '=>' after pattern.")?;
+            cases.push((pattern, self.statement()?));
+
+            // Cases may be separated with commas.
+            self.match_tokens(&vec![TokenType::Comma]);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after match cases.")?;
+        Ok(Stmt::Match(keyword, value, cases))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        let mut alternatives = vec![self.single_pattern()?];
+        while self.match_tokens(&vec![TokenType::Pipe]) {
+            alternatives.push(self.single_pattern()?);
+        }
+
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap());
+        }
+        Ok(Pattern::Alternatives(alternatives))
+    }
+
+    fn single_pattern(&mut self) -> Result<Pattern, ParseError> {
+        if self.match_tokens(&vec![TokenType::False]) {
+            return Ok(Pattern::Literal(
+                self.previous().clone(),
+                Literal::Bool(false),
+            ));
+        }
+        if self.match_tokens(&vec![TokenType::True]) {
+            return Ok(Pattern::Literal(
+                self.previous().clone(),
+                Literal::Bool(true),
+            ));
+        }
+        if self.match_tokens(&vec![TokenType::Nil]) {
+            return Ok(Pattern::Literal(self.previous().clone(), Literal::None));
+        }
+
+        if self.match_tokens(&vec![TokenType::Number, TokenType::String]) {
+            let token = self.previous().clone();
+            let literal = token.get_literal().clone();
+            return Ok(Pattern::Literal(token, literal));
+        }
+
+        if self.match_tokens(&vec![TokenType::Minus]) {
+            let token = self.consume(TokenType::Number, "Expect number after '-' in pattern.")?;
+            let literal = match token.get_literal() {
+                Literal::Number(n) => Literal::Number(Num::new(-n.get())),
+                _ => unreachable!("Number token must have a number literal!"),
+            };
+            return Ok(Pattern::Literal(token, literal));
+        }
+
+        if self.match_tokens(&vec![TokenType::Identifier]) {
+            let name = self.previous().clone();
+
+            if self.match_tokens(&vec![TokenType::Dot]) {
+                let variant =
+                    self.consume(TokenType::Identifier, "Expect variant name after '.'.")?;
+                let fields = if self.match_tokens(&vec![TokenType::LeftParen]) {
+                    self.field_patterns()?
+                } else {
+                    Vec::new()
+                };
+                return Ok(Pattern::Variant(name, variant, fields));
+            }
+
+            if self.match_tokens(&vec![TokenType::LeftParen]) {
+                return Ok(Pattern::Class(name, self.field_patterns()?));
+            }
+
+            if name.get_lexeme() == "_" {
+                return Ok(Pattern::Wildcard(name));
+            }
+            return Ok(Pattern::Binding(name));
+        }
+
+        let token = self.peek().clone();
+        Err(self.error(&token, "Expect pattern."))
+    }
+
+    // Patterns of fields after '(', like `x, y: 0)`.
+    fn field_patterns(&mut self) -> Result<Vec<(Token, Pattern)>, ParseError> {
+        let mut fields = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                let field = self.consume(TokenType::Identifier, "Expect field name.")?;
+                // `Point(x)` is a short form of `Point(x: x)`.
+                let pattern = if self.match_tokens(&vec![TokenType::Colon]) {
+                    self.pattern()?
+                } else {
+                    Pattern::Binding(field.clone())
+                };
+                fields.push((field, pattern));
+
+                if !self.match_tokens(&vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after field patterns.")?;
+        Ok(fields)
     }
 
     fn return_stmt(&mut self) -> Result<Stmt, ParseError> {
@@ -215,23 +374,41 @@ impl Parser<'_> {
             value = self.expression()?;
         }
 
-        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+        self.consume_semicolon("Expect ';' after return value.")?;
         Ok(Stmt::Return(keyword, value))
     }
 
+    fn const_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenType::Identifier, "Expect constant name.")?;
+        let annotation = self.type_annotation()?;
+        self.consume(TokenType::Equal, "Expect '=' after constant name.")?;
+        let initializer = self.expression()?;
+
+        self.consume_semicolon("Expect ';' after constant declaration.")?;
+        Ok(Stmt::Const(name, annotation, initializer))
+    }
+
     fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
         let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+        let annotation = self.type_annotation()?;
 
         let mut initializer = Expr::LiteralExpr(Literal::None);
         if self.match_tokens(&vec![TokenType::Equal]) {
             initializer = self.expression()?;
         }
 
-        self.consume(
-            TokenType::Semicolon,
-            "Expect ';' after variable declaration.",
-        )?;
-        Ok(Stmt::Var(name, initializer))
+        self.consume_semicolon("Expect ';' after variable declaration.")?;
+        Ok(Stmt::Var(name, annotation, initializer))
+    }
+
+    // Optional `: Type` after a name.
+    fn type_annotation(&mut self) -> Result<Option<Token>, ParseError> {
+        if self.match_tokens(&vec![TokenType::Colon]) {
+            return Ok(Some(
+                self.consume(TokenType::Identifier, "Expect type name after ':'.")?,
+            ));
+        }
+        Ok(None)
     }
 
     fn while_stmt(&mut self) -> Result<Stmt, ParseError> {
@@ -244,10 +421,7 @@ impl Parser<'_> {
 
     fn expression_stmt(&mut self) -> Result<Stmt, ParseError> {
         let exp = self.expression()?;
-        match self.consume(TokenType::Semicolon, "Expect ';' after value.") {
-            Ok(_) => (),
-            Err(err) => return Err(err),
-        };
+        self.consume_semicolon("Expect ';' after value.")?;
         Ok(Stmt::Expression(exp))
     }
 
@@ -260,16 +434,106 @@ impl Parser<'_> {
             superclass = Some(Expr::Variable(self.previous().clone()));
         }
 
+        let mut mixins = Vec::new();
+        if self.match_tokens(&vec![TokenType::With]) {
+            loop {
+                self.consume(TokenType::Identifier, "Expect mixin name.")?;
+                mixins.push(Expr::Variable(self.previous().clone()));
+                if !self.match_tokens(&vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
         self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
 
         let mut methods = Vec::new();
+        let mut members = Vec::new();
         while !self.check(&TokenType::RightBrace) && !self.is_end() {
-            methods.push(self.function("method")?);
+            if self.match_tokens(&vec![TokenType::Class]) {
+                members.push(self.class_member()?);
+            } else if self.check_next(&TokenType::LeftBrace) {
+                methods.push(self.getter()?);
+            } else {
+                methods.push(self.function("method")?);
+            }
         }
 
         self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
 
-        Ok(Stmt::Class(name, superclass, methods))
+        Ok(Stmt::Class(name, superclass, mixins, methods, members))
+    }
+
+    fn enum_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenType::Identifier, "Expect enum name.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before enum body.")?;
+
+        let mut variants = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_end() {
+            let variant = self.consume(TokenType::Identifier, "Expect variant name.")?;
+
+            let mut fields = Vec::new();
+            if self.match_tokens(&vec![TokenType::LeftParen]) {
+                loop {
+                    fields.push(self.consume(TokenType::Identifier, "Expect payload field name.")?);
+                    if !self.match_tokens(&vec![TokenType::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(TokenType::RightParen, "Expect ')' after payload fields.")?;
+            }
+            variants.push((variant, fields));
+
+            if !self.match_tokens(&vec![TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after enum body.")?;
+        Ok(Stmt::Enum(name, variants))
+    }
+
+    fn mixin_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenType::Identifier, "Expect mixin name.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before mixin body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_end() {
+            if self.check_next(&TokenType::LeftBrace) {
+                methods.push(self.getter()?);
+            } else {
+                methods.push(self.function("method")?);
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after mixin body.")?;
+
+        Ok(Stmt::Mixin(name, methods))
+    }
+
+    // Static method (`class name() {...}`) or class field (`class name = value;`).
+    fn class_member(&mut self) -> Result<Stmt, ParseError> {
+        if self.check_next(&TokenType::LeftParen) {
+            return self.function("method");
+        }
+
+        let name = self.consume(TokenType::Identifier, "Expect class member name.")?;
+
+        let mut initializer = Expr::LiteralExpr(Literal::None);
+        if self.match_tokens(&vec![TokenType::Equal]) {
+            initializer = self.expression()?;
+        }
+
+        self.consume_semicolon("Expect ';' after class field declaration.")?;
+        Ok(Stmt::Var(name, None, initializer))
+    }
+
+    // Method without a parameter list, e.g. `area { return this.w * this.h; }`.
+    fn getter(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenType::Identifier, "Expect getter name.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before getter body.")?;
+        let body = self.block_statements()?;
+        Ok(Stmt::Getter(name, body))
     }
 
     fn function(&mut self, kind: &str) -> Result<Stmt, ParseError> {
@@ -285,7 +549,7 @@ impl Parser<'_> {
         message.push_str(" name.");
         self.consume(TokenType::LeftParen, &message)?;
 
-        let mut parameters: Vec<Token> = Vec::new();
+        let mut parameters: Vec<Param> = Vec::new();
         if !self.check(&TokenType::RightParen) {
             loop {
                 if parameters.len() >= 255 {
@@ -293,7 +557,7 @@ impl Parser<'_> {
                     self.error(&tok, "Can't have more than 255 parameters.");
                 }
 
-                parameters.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+                parameters.push(self.parameter(parameters.last())?);
 
                 if !self.match_tokens(&vec![TokenType::Comma]) {
                     break;
@@ -302,6 +566,7 @@ impl Parser<'_> {
         }
 
         self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+        let return_type = self.type_annotation()?;
 
         message = String::new();
         message.push_str("Expect '{' before ");
@@ -310,7 +575,38 @@ impl Parser<'_> {
         self.consume(TokenType::LeftBrace, &message)?;
 
         let body = self.block_statements()?;
-        Ok(Stmt::Function(name, parameters, body))
+        Ok(Stmt::Function(name, parameters, return_type, body))
+    }
+
+    // Required parameters go first, then the ones with default values, then a single rest parameter.
+    fn parameter(&mut self, previous: Option<&Param>) -> Result<Param, ParseError> {
+        if let Some(Param::Rest(_, _)) = previous {
+            let tok = self.peek().clone();
+            return Err(self.error(&tok, "Rest parameter must be the last one."));
+        }
+
+        if self.match_tokens(&vec![TokenType::DotDotDot]) {
+            let name = self.consume(TokenType::Identifier, "Expect parameter name after '...'.")?;
+            let annotation = self.type_annotation()?;
+            return Ok(Param::Rest(name, annotation));
+        }
+
+        let name = self.consume(TokenType::Identifier, "Expect parameter name.")?;
+        let annotation = self.type_annotation()?;
+
+        if self.match_tokens(&vec![TokenType::Equal]) {
+            let default = self.expression()?;
+            return Ok(Param::Default(name, annotation, default));
+        }
+
+        if let Some(Param::Default(_, _, _)) = previous {
+            return Err(self.error(
+                &name,
+                "Parameter without default value can't follow one with it.",
+            ));
+        }
+
+        Ok(Param::Required(name, annotation))
     }
 
     fn block(&mut self) -> Result<Stmt, ParseError> {
@@ -318,15 +614,30 @@ impl Parser<'_> {
         Ok(Stmt::Block(statements))
     }
 
+    // Statements after `{` until the matching `}`.
     fn block_statements(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let open = self.previous().clone();
         let mut statements: Vec<Stmt> = Vec::new();
 
+        self.block_depth += 1;
         while !self.check(&TokenType::RightBrace) && !self.is_end() {
             if let Some(dec) = self.declaration() {
                 statements.push(dec);
             }
         }
+        self.block_depth -= 1;
 
+        if self.is_end() {
+            let token = self.peek().clone();
+            let hint = format!(
+                "the block opened at {}:{} is never closed",
+                open.get_line(),
+                open.get_column() + 1
+            );
+            self.interpreter
+                .error_with_hint(&token, "Expect '}' after block.", &hint);
+            return Err(ParseError);
+        }
         self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
         Ok(statements)
     }
@@ -435,6 +746,10 @@ impl Parser<'_> {
                 let name =
                     self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
                 exp = Expr::Get(Box::new(exp), name);
+            } else if self.match_tokens(&vec![TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+                exp = Expr::Index(Box::new(exp), bracket, Box::new(index));
             } else {
                 break;
             }
@@ -451,7 +766,12 @@ impl Parser<'_> {
                     let tok = self.peek().clone();
                     self.error(&tok, "Can't have more than 255 arguments.");
                 }
-                arguments.push(self.expression()?);
+                if self.match_tokens(&vec![TokenType::DotDotDot]) {
+                    let ellipsis = self.previous().clone();
+                    arguments.push(Expr::Spread(ellipsis, Box::new(self.expression()?)));
+                } else {
+                    arguments.push(self.expression()?);
+                }
                 if !self.match_tokens(&vec![TokenType::Comma]) {
                     break;
                 }
@@ -502,6 +822,21 @@ impl Parser<'_> {
             return Ok(Expr::Variable(self.previous().clone()));
         }
 
+        if self.match_tokens(&vec![TokenType::LeftBracket]) {
+            let mut elements = Vec::new();
+            if !self.check(&TokenType::RightBracket) {
+                loop {
+                    elements.push(self.expression()?);
+                    if !self.match_tokens(&vec![TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            let bracket =
+                self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+            return Ok(Expr::List(bracket, elements));
+        }
+
         if self.match_tokens(&vec![TokenType::LeftParen]) {
             let exp = self.expression()?;
             match self.consume(TokenType::RightParen, "Expect ')' after expression!") {
@@ -525,37 +860,94 @@ impl Parser<'_> {
         Err(self.error(&token, message))
     }
 
+    // A missing `;` at the end of a line is reported and treated as if it was there.
+    fn consume_semicolon(&mut self, message: &str) -> Result<Token, ParseError> {
+        if self.check(&TokenType::Semicolon) {
+            return Ok(self.advance().clone());
+        }
+
+        let previous = self.previous().clone();
+        let next = self.peek();
+        let is_line_end = next.get_line() > previous.get_line()
+            || matches!(next.get_type(), TokenType::RightBrace | TokenType::Eof);
+        if !is_line_end {
+            let token = next.clone();
+            return Err(self.error(&token, message));
+        }
+
+        let hint = format!("insert ';' after '{}'", previous.get_lexeme());
+        self.interpreter.error_with_hint(&previous, message, &hint);
+        Ok(previous)
+    }
+
+    // Two names in a row are never valid, so the first one is likely a misspelled
+    // keyword, e.g. `retrun x;`. It is reported and parsed as the keyword.
+    fn fix_misspelled_keyword(&mut self) {
+        let token = self.peek().clone();
+        let is_followed_by_value = [TokenType::Identifier, TokenType::Number, TokenType::String]
+            .iter()
+            .any(|tt| self.check_next(tt));
+        if *token.get_type() != TokenType::Identifier || !is_followed_by_value {
+            return;
+        }
+
+        let Some(keyword) = suggest(token.get_lexeme(), KEYWORDS.keys().copied()) else {
+            return;
+        };
+        let message = format!("Unknown keyword '{}'.", token.get_lexeme());
+        let hint = format!("did you mean '{keyword}'?");
+        self.interpreter.error_with_hint(&token, &message, &hint);
+
+        let tok_type = KEYWORDS[keyword].clone();
+        let fixed = Token::new(tok_type, keyword, Literal::None, token.get_line());
+        self.tokens[self.current] = fixed.with_column(token.get_column());
+    }
+
     fn error(&mut self, token: &Token, message: &str) -> ParseError {
         self.interpreter.error(token, message);
         ParseError
     }
 
+    // Skips tokens until the start of the next statement. Blocks inside the broken
+    // statement are skipped as a whole and the `}` of the enclosing block is kept.
     fn synchronize(&mut self) {
-        self.advance();
-
+        let mut depth: usize = 0; // of blocks inside the broken statement
+        let mut is_first = true;
         while !self.is_end() {
-            if *self.previous().get_type() == TokenType::Semicolon {
-                return;
-            }
-
-            let tok_types = vec![
-                TokenType::Class,
-                TokenType::Fun,
-                TokenType::Var,
-                TokenType::For,
-                TokenType::If,
-                TokenType::While,
-                TokenType::Print,
-                TokenType::Return,
-            ];
-            if tok_types.iter().any(|tt| tt == self.peek().get_type()) {
-                return;
+            match self.peek().get_type() {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 && self.block_depth > 0 => return,
+                TokenType::RightBrace => depth = depth.saturating_sub(1),
+                TokenType::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                _ if depth == 0 && !is_first && self.is_statement_start() => return,
+                _ => (),
             }
-
             self.advance();
+            is_first = false;
         }
     }
 
+    fn is_statement_start(&self) -> bool {
+        let tok_types = [
+            TokenType::Class,
+            TokenType::Enum,
+            TokenType::Mixin,
+            TokenType::Fun,
+            TokenType::Const,
+            TokenType::Var,
+            TokenType::For,
+            TokenType::If,
+            TokenType::Match,
+            TokenType::While,
+            TokenType::Print,
+            TokenType::Return,
+        ];
+        tok_types.iter().any(|tt| tt == self.peek().get_type())
+    }
+
     fn match_tokens(&mut self, tok_types: &Vec<TokenType>) -> bool {
         for tok_type in tok_types {
             if self.check(tok_type) {
@@ -583,6 +975,13 @@ impl Parser<'_> {
         self.previous()
     }
 
+    fn check_next(&self, tok_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => *token.get_type() == *tok_type,
+            None => false,
+        }
+    }
+
     fn is_end(&self) -> bool {
         *self.peek().get_type() == TokenType::Eof
     }
@@ -599,3 +998,84 @@ impl Parser<'_> {
             .expect("Failed peeking previous Token!")
     }
 }
+
+#[cfg(test)]
+mod test_parser {
+    use super::Parser;
+    use crate::evaluator::{environment::Environment, Evaluator};
+    use crate::{Lox, Scanner};
+
+    fn parses(source: &str) -> bool {
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        let mut scanner = Scanner::new(&mut interpreter, source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+        Parser::new(&mut interpreter, tokens).parse();
+        !interpreter.had_error
+    }
+
+    #[test]
+    fn test_parameters() {
+        assert!(parses("fun f(a, b = 1, ...rest) {} f(1, ...[2, 3]);"));
+        assert!(parses("fun f(a = 1, b = a + 1) {}"));
+        // Defaults go last, the rest parameter after them.
+        assert!(!parses("fun f(a = 1, b) {}"));
+        assert!(!parses("fun f(...rest, a) {}"));
+        assert!(!parses("fun f(...rest = []) {}"));
+        assert!(!parses("fun f(...) {}"));
+        assert!(!parses("fun f(a = ) {}"));
+    }
+}
+
+#[cfg(test)]
+mod test_recovery {
+    use crate::evaluator::{environment::Environment, Evaluator};
+    use crate::{Lox, Parser, Scanner};
+
+    // (line, message, hint) of each syntax error.
+    fn errors(source: &str) -> Vec<(usize, String, Option<String>)> {
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        interpreter.set_quiet(true);
+        let mut scanner = Scanner::new(&mut interpreter, source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+        Parser::new(&mut interpreter, tokens).parse();
+
+        let errors = interpreter.errors().iter();
+        errors
+            .map(|e| (e.line, e.message.clone(), e.hint.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_all_errors_reported() {
+        let source = "var a = 1
+retrun a;
+{ print (; print 2; }
+}
+{ print 3;";
+        let hint = |s: &str| Some(s.to_string());
+        assert_eq!(
+            errors(source),
+            [
+                (
+                    1,
+                    "Expect ';' after variable declaration.".to_string(),
+                    hint("insert ';' after '1'")
+                ),
+                (
+                    2,
+                    "Unknown keyword 'retrun'.".to_string(),
+                    hint("did you mean 'return'?")
+                ),
+                (3, "Expect expression.".to_string(), None),
+                (4, "Unmatched '}'.".to_string(), None),
+                (
+                    5,
+                    "Expect '}' after block.".to_string(),
+                    hint("the block opened at 5:1 is never closed")
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,405 @@
+use super::Resolver;
+use crate::ast::{expr::Expr, stmt::Param};
+use crate::diagnostic::Diagnostic;
+use crate::evaluator::{environment::Environment, Evaluator};
+use crate::lexer::{scanner::Scanner, token::Token};
+use crate::parser::Parser;
+use crate::{Lox, StaticError};
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LintKind {
+    UnusedVariable,
+    UnusedParameter,
+    Shadowing,
+    UnreachableCode,
+    SelfComparison,
+    SelfAssignment,
+    WrongArity,
+}
+
+impl LintKind {
+    // Codes never change, new lints get new ones.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LintKind::UnusedVariable => "L001",
+            LintKind::UnusedParameter => "L002",
+            LintKind::Shadowing => "L003",
+            LintKind::UnreachableCode => "L004",
+            LintKind::SelfComparison => "L005",
+            LintKind::SelfAssignment => "L006",
+            LintKind::WrongArity => "L007",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintKind::UnusedVariable => "unused-variable",
+            LintKind::UnusedParameter => "unused-parameter",
+            LintKind::Shadowing => "shadowing",
+            LintKind::UnreachableCode => "unreachable-code",
+            LintKind::SelfComparison => "self-comparison",
+            LintKind::SelfAssignment => "self-assignment",
+            LintKind::WrongArity => "wrong-arity",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintKind::WrongArity => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Lint {
+    kind: LintKind,
+    token: Token,
+    message: String,
+}
+
+impl Lint {
+    pub fn new(kind: LintKind, token: &Token, message: String) -> Self {
+        Lint {
+            kind,
+            token: token.clone(),
+            message,
+        }
+    }
+
+    pub fn get_kind(&self) -> LintKind {
+        self.kind
+    }
+
+    pub fn get_token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    // Labelled like `warning[L001]`.
+    pub fn diagnostic(&self) -> Diagnostic {
+        let severity = self.kind.severity().to_string().to_lowercase();
+        let label = format!("{severity}[{}]", self.kind.code());
+        let message = format!("{} ({})", self.message, self.kind.name());
+        Diagnostic::at(&label, &message, &self.token)
+    }
+}
+
+// `[line 3] Warning L001 (unused-variable) at 'x': Unused variable 'x'.`
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[line {}] {} {} ({}) at '{}': {}",
+            self.token.get_line(),
+            self.kind.severity(),
+            self.kind.code(),
+            self.kind.name(),
+            self.token.get_lexeme(),
+            self.message
+        )
+    }
+}
+
+// Minimal and maximal (if any) number of arguments.
+pub(super) fn arity(params: &[Param]) -> (usize, Option<usize>) {
+    let min = params
+        .iter()
+        .filter(|p| matches!(p, Param::Required(_, _)))
+        .count();
+    match params.last() {
+        Some(Param::Rest(_, _)) => (min, None),
+        _ => (min, Some(params.len())),
+    }
+}
+
+// Whether both expressions name the same variable or property, e.g. `a.b` and `(a.b)`.
+pub(super) fn is_same_place(left: &Expr, right: &Expr) -> bool {
+    match (left, right) {
+        (Expr::Grouping(left), right) | (right, Expr::Grouping(left)) => is_same_place(left, right),
+        (Expr::Variable(left), Expr::Variable(right)) => left.get_lexeme() == right.get_lexeme(),
+        (Expr::This(_), Expr::This(_)) => true,
+        (Expr::Get(left, left_name), Expr::Get(right, right_name)) => {
+            left_name.get_lexeme() == right_name.get_lexeme() && is_same_place(left, right)
+        }
+        _ => false,
+    }
+}
+
+// Lints silenced by a `// lint-ignore L001 shadowing` comment on the same line or
+// the line above, or by `// lint-ignore-file ...` anywhere. Without codes all lints are silenced.
+struct Ignore {
+    line: Option<usize>, // `None` for the whole file
+    lints: Vec<String>,
+}
+
+impl Ignore {
+    fn parse(comment: &Token) -> Option<Self> {
+        let text = comment.get_lexeme().trim_start_matches('/').trim();
+        let (line, rest) = if let Some(rest) = text.strip_prefix("lint-ignore-file") {
+            (None, rest)
+        } else {
+            (Some(comment.get_line()), text.strip_prefix("lint-ignore")?)
+        };
+        if !rest.is_empty() && !rest.starts_with([' ', '\t', ',']) {
+            return None;
+        }
+
+        let lints = rest
+            .split([' ', '\t', ','])
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+        Some(Ignore { line, lints })
+    }
+
+    fn silences(&self, lint: &Lint) -> bool {
+        let line = lint.get_token().get_line();
+        let is_on_line = self.line.is_none_or(|l| l == line || l + 1 == line);
+        let kind = lint.get_kind();
+        is_on_line
+            && (self.lints.is_empty()
+                || self
+                    .lints
+                    .iter()
+                    .any(|l| l == kind.code() || l == kind.name()))
+    }
+}
+
+// Lints of a program, or its errors if it can't be resolved.
+pub fn lint(source: &str) -> Result<Vec<Lint>, Vec<StaticError>> {
+    let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+    interpreter.set_quiet(true);
+
+    let mut scanner = Scanner::new(&mut interpreter, source);
+    scanner.scan_tokens();
+    let tokens = scanner.tokens().clone();
+    let ignores: Vec<Ignore> = scanner
+        .comments()
+        .iter()
+        .filter_map(Ignore::parse)
+        .collect();
+
+    let statements = Parser::new(&mut interpreter, tokens).parse();
+    if !interpreter.errors.is_empty() {
+        return Err(interpreter.take_errors());
+    }
+
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.resolve_optional_stmts(statements);
+    let lints = resolver.lints();
+    if !interpreter.errors.is_empty() {
+        return Err(interpreter.take_errors());
+    }
+
+    Ok(lints
+        .into_iter()
+        .filter(|lint| !ignores.iter().any(|ignore| ignore.silences(lint)))
+        .collect())
+}
+
+#[cfg(test)]
+mod test_lint {
+    use super::lint;
+
+    // Each lint as `code line lexeme`.
+    fn run(source: &str) -> Vec<String> {
+        let lints = lint(source).unwrap();
+        lints
+            .iter()
+            .map(|l| {
+                let token = l.get_token();
+                let code = l.get_kind().code();
+                format!("{code} {} {}", token.get_line(), token.get_lexeme())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lints() {
+        let source = "var a = 1;
+fun f(x, y) {
+    var a = 2;
+    a = a;
+    if (x == x) return;
+    return 1;
+    print a;
+}
+f(1);
+{ var unused; }";
+        assert_eq!(
+            run(source),
+            [
+                "L002 2 y",
+                "L003 3 a",
+                "L006 4 a",
+                "L005 5 ==",
+                "L004 7 print",
+                "L007 9 )",
+                "L001 10 unused"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_variable() {
+        let source = "var global;
+fun f() {
+    var unused;
+    const limit = 1;
+    var _ignored;
+    fun helper() {}
+    class Local {}
+    var used = 2;
+    print used;
+}";
+        assert_eq!(
+            run(source),
+            [
+                "L001 3 unused",
+                "L001 4 limit",
+                "L001 6 helper",
+                "L001 7 Local"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_parameter() {
+        let source = "fun f(a, b = 1, ...rest) { print a; }
+fun g(_a) {}
+class P { m(x) {} }";
+        assert_eq!(run(source), ["L002 1 b", "L002 1 rest", "L002 3 x"]);
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let source = "var a = 1;
+fun f(a) {
+    var b = a;
+    { var b = 2; print b; }
+    print b;
+}
+{ var c = 1; print c; }
+{ var c = 2; print c; }";
+        assert_eq!(run(source), ["L003 2 a", "L003 4 b"]);
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        let source = "fun f(x) {
+    if (x) { return 1; }
+    return 2;
+    print x;
+    x = 3;
+}
+fun g() {
+    return;
+    \"done\";
+}";
+        // At the first unreachable statement only, a literal has no token and falls back to `return`.
+        assert_eq!(run(source), ["L004 4 print", "L004 8 return"]);
+    }
+
+    #[test]
+    fn test_self_comparison() {
+        let source = "class P {
+    f(a, b) { return a == a or this.x < this.x or a != b or a.x >= a.y; }
+}";
+        assert_eq!(run(source), ["L005 2 ==", "L005 2 <"]);
+    }
+
+    #[test]
+    fn test_self_assignment() {
+        let source = "var a = 1;
+a = a;
+class P { f() { this.x = this.x; this.y = this.x; } }
+a = a + 0;";
+        assert_eq!(run(source), ["L006 2 a", "L006 3 x"]);
+    }
+
+    #[test]
+    fn test_wrong_arity() {
+        let source = "fun f(_a, _b = 1) {}
+fun g(_a, ..._rest) {}
+class P { init(_x) {} }
+f(1);
+f();
+f(1, 2, 3);
+g();
+g(1, 2, 3);
+P();
+var h = f;
+h();";
+        assert_eq!(
+            run(source),
+            ["L007 5 )", "L007 6 )", "L007 7 )", "L007 9 )"]
+        );
+    }
+
+    #[test]
+    fn test_ignore() {
+        let source = "// lint-ignore-file shadowing
+var a = 1;
+fun f(b) { // lint-ignore L002
+    var a;
+    var c; // lint-ignore
+    return a;
+}
+f(1, 2); // lint-ignore unused-variable";
+        assert_eq!(run(source), ["L007 8 )"]);
+    }
+
+    #[test]
+    fn test_ignore_line() {
+        // On the same line or the line above, by code or name, several at once.
+        let source = "fun f(x, y) {
+    // lint-ignore L001, unused-parameter
+    var unused;
+    var other; // lint-ignore unused-parameter
+    return x == x; // lint-ignore-me
+}
+// lint-ignore
+f(1);
+
+f(1);";
+        assert_eq!(
+            run(source),
+            ["L002 1 y", "L001 4 other", "L005 5 ==", "L007 10 )"]
+        );
+    }
+
+    #[test]
+    fn test_ignore_file() {
+        let source = "fun f(x) {
+    var unused;
+    print x == x;
+}
+f();
+// lint-ignore-file L001 self-comparison";
+        assert_eq!(run(source), ["L007 5 )"]);
+
+        let source = "fun f(x) { var unused; }
+// lint-ignore-file";
+        assert_eq!(run(source), Vec::<String>::new());
+    }
+}
@@ -1,26 +1,50 @@
-use crate::ast::{expr::Expr, stmt::Stmt};
-use crate::lexer::token::{Literal, Token};
+use crate::ast::{
+    expr::Expr,
+    stmt::{Param, Pattern, Stmt},
+};
+use crate::evaluator::arity_to_string;
+use crate::lexer::token::{Literal, Token, TokenType};
 use crate::{Lox, Visitor};
-use std::collections::HashMap;
+use lint::{Lint, LintKind};
+use std::collections::{HashMap, HashSet};
+use symbols::{Symbol, SymbolKind, SymbolTable};
+
+pub mod lint;
+pub mod symbols;
+
+type Arity = (usize, Option<usize>);
 
 #[derive(Clone, Copy, PartialEq)]
 enum FunctionType {
     None,
     Func,
+    Getter,
     Initializer,
     Method,
+    StaticMethod,
 }
 
 #[derive(Clone, Copy, PartialEq)]
 enum ClassType {
     None,
     Class,
+    Mixin,
+    Static,
     Subclass,
 }
 
 pub struct Resolver<'a> {
     scopes: Vec<HashMap<String, bool>>,
+    constants: Vec<HashSet<String>>, // constant names of each scope
     locals: HashMap<Expr, usize>,
+    symbol_ids: Vec<HashMap<String, usize>>, // symbols declared in each scope
+    global_ids: HashMap<String, usize>,
+    unresolved: Vec<Token>, // usages of globals that may be declared later
+    symbols: SymbolTable,
+    reads: HashSet<usize>,          // symbols whose values are used
+    arities: HashMap<usize, Arity>, // functions and classes with known arity
+    calls: Vec<(Token, Token, usize, Option<usize>)>, // (callee, paren, arguments, symbol)
+    lints: Vec<Lint>,
     current_function: FunctionType,
     current_class: ClassType,
 
@@ -28,10 +52,19 @@ pub struct Resolver<'a> {
 }
 
 impl Resolver<'_> {
-    pub fn new(interpreter: &mut Lox) -> Resolver {
+    pub fn new(interpreter: &mut Lox) -> Resolver<'_> {
         Resolver {
             scopes: Vec::new(),
+            constants: Vec::new(),
             locals: HashMap::new(),
+            symbol_ids: Vec::new(),
+            global_ids: HashMap::new(),
+            unresolved: Vec::new(),
+            symbols: SymbolTable::default(),
+            reads: HashSet::new(),
+            arities: HashMap::new(),
+            calls: Vec::new(),
+            lints: Vec::new(),
             interpreter,
             current_function: FunctionType::None,
             current_class: ClassType::None,
@@ -42,6 +75,37 @@ impl Resolver<'_> {
         self.locals.clone()
     }
 
+    // Declarations and their usages, globals used before declaration included.
+    pub fn symbols(&mut self) -> SymbolTable {
+        for usage in std::mem::take(&mut self.unresolved) {
+            if let Some(&id) = self.global_ids.get(usage.get_lexeme()) {
+                self.symbols.add_reference(&usage, id);
+            }
+        }
+        self.symbols.clone()
+    }
+
+    // Suspicious but valid code, sorted by position.
+    pub fn lints(&mut self) -> Vec<Lint> {
+        for (callee, paren, count, id) in std::mem::take(&mut self.calls) {
+            let id = id.or_else(|| self.global_ids.get(callee.get_lexeme()).copied());
+            let Some(&(min, max)) = id.and_then(|id| self.arities.get(&id)) else {
+                continue;
+            };
+            if count < min || max.is_some_and(|max| count > max) {
+                let msg = format!(
+                    "Expected {} arguments but got {count}.",
+                    arity_to_string(min, max)
+                );
+                self.lint(LintKind::WrongArity, &paren, msg);
+            }
+        }
+
+        let mut lints = self.lints.clone();
+        lints.sort_by_key(|l| (l.get_token().get_line(), l.get_token().get_column()));
+        lints
+    }
+
     pub fn resolve_optional_stmts(&mut self, statements: Vec<Option<Stmt>>) {
         for statement in statements {
             self.resolve_stmt(statement.unwrap());
@@ -49,6 +113,14 @@ impl Resolver<'_> {
     }
 
     fn resolve_stmts(&mut self, statements: Vec<Stmt>) {
+        // Reported at the statement right after `return`, or at `return` when that one has no token.
+        for pair in statements.windows(2) {
+            if let [Stmt::Return(keyword, _), next] = pair {
+                let msg = "Code after 'return' is never executed.".to_string();
+                let token = next.token().unwrap_or(keyword);
+                self.lint(LintKind::UnreachableCode, token, msg);
+            }
+        }
         for statement in statements {
             self.resolve_stmt(statement);
         }
@@ -63,16 +135,61 @@ impl Resolver<'_> {
     }
 
     fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new())
+        self.scopes.push(HashMap::new());
+        self.constants.push(HashSet::new());
+        self.symbol_ids.push(HashMap::new());
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop();
+        self.constants.pop();
+
+        // Names starting with `_` are unused on purpose.
+        let mut ids: Vec<usize> = self.symbol_ids.pop().unwrap().into_values().collect();
+        ids.sort();
+        for id in ids {
+            let symbol = &self.symbols.symbols()[id];
+            let name = symbol.get_name().clone();
+            if self.reads.contains(&id) || name.get_lexeme().starts_with('_') {
+                continue;
+            }
+            let kind = match symbol.get_kind() {
+                SymbolKind::Parameter => LintKind::UnusedParameter,
+                _ => LintKind::UnusedVariable,
+            };
+            let msg = format!("Unused {} '{}'.", symbol.get_kind(), name.get_lexeme());
+            self.lint(kind, &name, msg);
+        }
+    }
+
+    fn lint(&mut self, kind: LintKind, token: &Token, message: String) {
+        self.lints.push(Lint::new(kind, token, message));
     }
 
-    fn declare(&mut self, name: Token) {
+    fn declare(&mut self, symbol: Symbol) -> usize {
+        let name = symbol.get_name().clone();
+        let lexeme = name.get_lexeme();
+        let outer = self.scopes.len().saturating_sub(1);
+        if !self.scopes.is_empty()
+            && (self.scopes[..outer].iter().any(|s| s.contains_key(lexeme))
+                || self.global_ids.contains_key(lexeme))
+        {
+            let msg = format!("'{lexeme}' shadows a declaration from an outer scope.");
+            self.lint(LintKind::Shadowing, &name, msg);
+        }
+
+        let id = self.symbols.add(symbol, self.scopes.is_empty());
+        match self.symbol_ids.last_mut() {
+            Some(ids) => ids.insert(name.get_lexeme().to_string(), id),
+            None => self.global_ids.insert(name.get_lexeme().to_string(), id),
+        };
+
         if self.scopes.is_empty() {
-            return;
+            if self.interpreter.constants.contains(name.get_lexeme()) {
+                self.interpreter
+                    .error(&name, "Already a constant with this name.")
+            }
+            return id;
         }
 
         let scope = self.scopes.last_mut().unwrap();
@@ -83,6 +200,7 @@ impl Resolver<'_> {
         }
 
         scope.insert(name.get_lexeme().to_string(), false);
+        id
     }
 
     fn define(&mut self, name: Token) {
@@ -94,6 +212,63 @@ impl Resolver<'_> {
         scope.insert(name.get_lexeme().to_string(), true);
     }
 
+    fn declare_const(&mut self, symbol: Symbol) {
+        let name = symbol.get_name().clone();
+        self.declare(symbol);
+
+        let constants = match self.constants.last_mut() {
+            Some(constants) => constants,
+            None => &mut self.interpreter.constants,
+        };
+        constants.insert(name.get_lexeme().to_string());
+    }
+
+    fn check_assignment(&mut self, name: &Token) {
+        let lexeme = name.get_lexeme();
+        let is_const = match self.scopes.iter().rposition(|s| s.contains_key(lexeme)) {
+            Some(i) => self.constants[i].contains(lexeme),
+            None => self.interpreter.constants.contains(lexeme),
+        };
+
+        if is_const {
+            let msg = format!("Can't assign to constant '{lexeme}'.");
+            self.interpreter.error(name, &msg);
+        }
+    }
+
+    fn add_reference(&mut self, name: &Token) {
+        match self.local_id(name) {
+            Some(id) => self.symbols.add_reference(name, id),
+            None => self.unresolved.push(name.clone()),
+        }
+    }
+
+    fn local_id(&self, name: &Token) -> Option<usize> {
+        let lexeme = name.get_lexeme();
+        self.symbol_ids
+            .iter()
+            .rev()
+            .find_map(|ids| ids.get(lexeme))
+            .copied()
+    }
+
+    // Methods, fields and variants are found by name only.
+    fn declare_members(&mut self, container: &Token, members: &[Stmt], kind: SymbolKind) {
+        for member in members {
+            let symbol = match member {
+                Stmt::Function(name, params, ret, _) => {
+                    Symbol::new(name.clone(), kind).with_detail(signature(params, ret))
+                }
+                Stmt::Getter(name, _) => Symbol::new(name.clone(), kind),
+                Stmt::Var(name, ty, _) => {
+                    Symbol::new(name.clone(), SymbolKind::Field).with_detail(annotation(ty))
+                }
+                _ => continue,
+            };
+            self.symbols.add(symbol.with_container(container), false);
+        }
+    }
+
     fn resolve_local(&mut self, exp: &Expr, name: &Token) {
         for (depth, scope) in self.scopes.iter().rev().enumerate() {
             if scope.contains_key(name.get_lexeme()) {
@@ -103,26 +278,113 @@ impl Resolver<'_> {
         }
     }
 
-    fn resovle_function(&mut self, statement: &Stmt, fun_type: FunctionType) {
-        if let Stmt::Function(_, params, body) = statement {
-            let enclosing_fun = self.current_function;
-            self.current_function = fun_type;
+    fn resolve_methods(&mut self, methods: &[Stmt]) {
+        for method in methods {
+            let mut declaration = FunctionType::Method;
+            match method {
+                Stmt::Function(name, _, _, _) if name.get_lexeme() == "init" => {
+                    declaration = FunctionType::Initializer;
+                }
+                Stmt::Getter(name, _) => {
+                    if name.get_lexeme() == "init" {
+                        self.interpreter
+                            .error(name, "Initializer can't be a getter.");
+                    }
+                    declaration = FunctionType::Getter;
+                }
+                _ => (),
+            }
+            self.resovle_function(method, declaration);
+        }
+    }
 
-            self.begin_scope();
-            for param in params {
-                self.declare(param.clone());
-                self.define(param.clone());
+    fn resolve_pattern(&mut self, pattern: &Pattern, in_alternatives: bool) {
+        match pattern {
+            Pattern::Alternatives(patterns) => {
+                for pattern in patterns {
+                    self.resolve_pattern(pattern, true);
+                }
+            }
+            Pattern::Binding(name) => {
+                if in_alternatives {
+                    self.interpreter
+                        .error(name, "Can't bind names in alternative patterns.");
+                }
+                self.declare(Symbol::new(name.clone(), SymbolKind::Variable));
+                self.define(name.clone());
+            }
+            Pattern::Class(name, fields) => {
+                self.resolve_expr(Expr::Variable(name.clone()));
+                for (field, field_pattern) in fields {
+                    self.symbols.add_property(field);
+                    self.resolve_pattern(field_pattern, in_alternatives);
+                }
             }
-            self.resolve_stmts(body.clone());
-            self.end_scope();
+            Pattern::Variant(name, _, fields) => {
+                self.resolve_expr(Expr::Variable(name.clone()));
+                for (_, field_pattern) in fields {
+                    self.resolve_pattern(field_pattern, in_alternatives);
+                }
+            }
+            Pattern::Literal(_, _) | Pattern::Wildcard(_) => (),
+        }
+    }
 
-            self.current_function = enclosing_fun;
-        } else {
-            panic!("Used function resolver for inappropriate Stmt!")
+    fn resovle_function(&mut self, statement: &Stmt, fun_type: FunctionType) {
+        let (params, body) = match statement {
+            Stmt::Function(_, params, _, body) => (params.clone(), body),
+            Stmt::Getter(_, body) => (Vec::new(), body),
+            _ => panic!("Used function resolver for inappropriate Stmt!"),
+        };
+
+        let enclosing_fun = self.current_function;
+        self.current_function = fun_type;
+
+        self.begin_scope();
+        for param in params {
+            if let Param::Default(_, _, default) = &param {
+                self.resolve_expr(default.clone());
+            }
+            let symbol = Symbol::new(param.get_name().clone(), SymbolKind::Parameter);
+            self.declare(symbol.with_detail(annotation(&param.get_type().cloned())));
+            self.define(param.get_name().clone());
         }
+        self.resolve_stmts(body.clone());
+        self.end_scope();
+
+        self.current_function = enclosing_fun;
+    }
+}
+
+fn join_lexemes(tokens: &[Token]) -> String {
+    let lexemes: Vec<&str> = tokens.iter().map(|t| t.get_lexeme()).collect();
+    lexemes.join(", ")
+}
+
+fn annotation(ty: &Option<Token>) -> String {
+    match ty {
+        Some(ty) => format!(": {}", ty.get_lexeme()),
+        None => String::new(),
     }
 }
 
+// Parameter list and return type as they are written in the declaration.
+fn signature(params: &[Param], ret: &Option<Token>) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| {
+            let name = param.get_name().get_lexeme();
+            let ty = annotation(&param.get_type().cloned());
+            match param {
+                Param::Required(_, _) => format!("{name}{ty}"),
+                Param::Default(_, _, _) => format!("{name}{ty} = ..."),
+                Param::Rest(_, _) => format!("...{name}{ty}"),
+            }
+        })
+        .collect();
+    format!("({}){}", params.join(", "), annotation(ret))
+}
+
 impl Visitor<(), ()> for Resolver<'_> {
     fn visit_expr(&mut self, e: &Expr) {
         match e {
@@ -136,31 +398,80 @@ impl Visitor<(), ()> for Resolver<'_> {
                     }
                 }
 
+                if let Some(id) = self.local_id(name) {
+                    self.reads.insert(id);
+                }
+                self.add_reference(name);
                 self.resolve_local(exp, name)
             }
             exp @ Expr::Assign(name, value) => {
+                if lint::is_same_place(&Expr::Variable(name.clone()), value) {
+                    let msg = format!("Assigning '{}' to itself has no effect.", name.get_lexeme());
+                    self.lint(LintKind::SelfAssignment, name, msg);
+                }
                 self.resolve_expr(*value.clone());
+                self.check_assignment(name);
+                self.add_reference(name);
                 self.resolve_local(exp, name);
             }
-            Expr::Binary(l, _, r) => {
+            Expr::Binary(l, op, r) => {
+                let is_comparison = matches!(
+                    op.get_type(),
+                    TokenType::BangEqual
+                        | TokenType::EqualEqual
+                        | TokenType::Greater
+                        | TokenType::GreaterEqual
+                        | TokenType::Less
+                        | TokenType::LessEqual
+                );
+                if is_comparison && lint::is_same_place(l, r) {
+                    let msg = "Both sides of the comparison are the same.".to_string();
+                    self.lint(LintKind::SelfComparison, op, msg);
+                }
                 self.resolve_expr(*l.clone());
                 self.resolve_expr(*r.clone());
             }
-            Expr::Call(callee, _, arguments) => {
+            Expr::Call(callee, paren, arguments) => {
+                // The number of spread arguments is known only at runtime.
+                let has_spread = arguments.iter().any(|a| matches!(a, Expr::Spread(_, _)));
+                if let (Expr::Variable(name), false) = (&**callee, has_spread) {
+                    let id = self.local_id(name);
+                    let id = id.or_else(|| self.global_ids.get(name.get_lexeme()).copied());
+                    self.calls
+                        .push((name.clone(), paren.clone(), arguments.len(), id));
+                }
                 self.resolve_expr(*callee.clone());
                 for argument in arguments {
                     self.resolve_expr(argument.clone());
                 }
             }
-            Expr::Get(object, _) => self.resolve_expr(*object.clone()),
+            Expr::Get(object, name) => {
+                self.symbols.add_property(name);
+                self.resolve_expr(*object.clone());
+            }
             Expr::Grouping(exp) => self.resolve_expr(*exp.clone()),
+            Expr::Index(list, _, index) => {
+                self.resolve_expr(*list.clone());
+                self.resolve_expr(*index.clone());
+            }
+            Expr::List(_, elements) => {
+                for element in elements {
+                    self.resolve_expr(element.clone());
+                }
+            }
+            Expr::Spread(_, exp) => self.resolve_expr(*exp.clone()),
             Expr::LiteralExpr(_) => (),
             Expr::Logical(l, _, r) => {
                 self.resolve_expr(*l.clone());
                 self.resolve_expr(*r.clone());
             }
             Expr::Unary(_, r) => self.resolve_expr(*r.clone()),
-            Expr::Set(object, _, value) => {
+            Expr::Set(object, name, value) => {
+                if lint::is_same_place(&Expr::Get(object.clone(), name.clone()), value) {
+                    let msg = format!("Assigning '{}' to itself has no effect.", name.get_lexeme());
+                    self.lint(LintKind::SelfAssignment, name, msg);
+                }
+                self.symbols.add_property(name);
                 self.resolve_expr(*value.clone());
                 self.resolve_expr(*object.clone());
             }
@@ -168,7 +479,12 @@ impl Visitor<(), ()> for Resolver<'_> {
                 if self.current_class == ClassType::None {
                     self.interpreter
                         .error(keyword, "Can't use 'super' outside of a class.");
-                } else if self.current_class != ClassType::Subclass {
+                } else if self.current_class == ClassType::Static {
+                    self.interpreter
+                        .error(keyword, "Can't use 'super' in a static member.");
+                } else if self.current_class != ClassType::Subclass
+                    && self.current_class != ClassType::Mixin
+                {
                     self.interpreter
                         .error(keyword, "Can't use 'super' in a class with no superclass.");
                 }
@@ -179,6 +495,9 @@ impl Visitor<(), ()> for Resolver<'_> {
                 if self.current_class == ClassType::None {
                     self.interpreter
                         .error(keyword, "Can't use 'this' outside of a class.");
+                } else if self.current_class == ClassType::Static {
+                    self.interpreter
+                        .error(keyword, "Can't use 'this' in a static member.");
                 }
 
                 self.resolve_local(exp, keyword)
@@ -193,12 +512,47 @@ impl Visitor<(), ()> for Resolver<'_> {
                 self.resolve_stmts(statements.clone());
                 self.end_scope();
             }
-            Stmt::Class(name, superclass, methods) => {
+            Stmt::Class(name, superclass, mixins, methods, members) => {
                 let enclosing_class = self.current_class;
                 self.current_class = ClassType::Class;
 
-                self.declare(name.clone());
+                let detail = match superclass {
+                    Some(Expr::Variable(sup_cls)) => format!(" < {}", sup_cls.get_lexeme()),
+                    _ => String::new(),
+                };
+                let id =
+                    self.declare(Symbol::new(name.clone(), SymbolKind::Class).with_detail(detail));
+                let init = methods.iter().find_map(|method| match method {
+                    Stmt::Function(name, params, _, _) if name.get_lexeme() == "init" => {
+                        Some(lint::arity(params))
+                    }
+                    _ => None,
+                });
+                // Without an own initializer the arity is known only if nothing is inherited.
+                match init {
+                    Some(arity) => _ = self.arities.insert(id, arity),
+                    None if superclass.is_none() && mixins.is_empty() => {
+                        _ = self.arities.insert(id, (0, Some(0)))
+                    }
+                    None => (),
+                }
                 self.define(name.clone());
+                self.declare_members(name, members, SymbolKind::Function);
+                self.declare_members(name, methods, SymbolKind::Method);
+
+                // Static members see neither `this` nor `super`.
+                self.current_class = ClassType::Static;
+                for member in members {
+                    match member {
+                        Stmt::Var(_, _, initializer) => {
+                            if *initializer != Expr::LiteralExpr(Literal::None) {
+                                self.resolve_expr(initializer.clone());
+                            }
+                        }
+                        _ => self.resovle_function(member, FunctionType::StaticMethod),
+                    }
+                }
+                self.current_class = ClassType::Class;
 
                 if let Some(sup_cls) = superclass {
                     if let Expr::Variable(sup_cls_name) = sup_cls {
@@ -211,6 +565,16 @@ impl Visitor<(), ()> for Resolver<'_> {
                     self.resolve_expr(sup_cls.clone());
                 }
 
+                for mixin in mixins {
+                    if let Expr::Variable(mixin_name) = mixin {
+                        if name.get_lexeme() == mixin_name.get_lexeme() {
+                            self.interpreter
+                                .error(mixin_name, "A class can't use itself as a mixin.");
+                        }
+                    }
+                    self.resolve_expr(mixin.clone());
+                }
+
                 if superclass.is_some() {
                     self.begin_scope();
                     self.scopes
@@ -225,15 +589,7 @@ impl Visitor<(), ()> for Resolver<'_> {
                     .unwrap()
                     .insert("this".to_string(), true);
 
-                for method in methods {
-                    let mut declaration = FunctionType::Method;
-                    if let Stmt::Function(name, _, _) = method {
-                        if name.get_lexeme() == "init" {
-                            declaration = FunctionType::Initializer;
-                        }
-                    }
-                    self.resovle_function(method, declaration);
-                }
+                self.resolve_methods(methods);
 
                 self.end_scope();
                 if superclass.is_some() {
@@ -241,19 +597,78 @@ impl Visitor<(), ()> for Resolver<'_> {
                 }
                 self.current_class = enclosing_class;
             }
-            Stmt::Var(name, initializer) => {
-                self.declare(name.clone());
+            Stmt::Enum(name, variants) => {
+                self.declare(Symbol::new(name.clone(), SymbolKind::Enum));
+                self.define(name.clone());
+
+                for (i, (variant, fields)) in variants.iter().enumerate() {
+                    let detail = match fields.is_empty() {
+                        true => String::new(),
+                        false => format!("({})", join_lexemes(fields)),
+                    };
+                    let symbol = Symbol::new(variant.clone(), SymbolKind::Variant);
+                    self.symbols
+                        .add(symbol.with_container(name).with_detail(detail), false);
+
+                    if variants[..i]
+                        .iter()
+                        .any(|(other, _)| other.get_lexeme() == variant.get_lexeme())
+                    {
+                        self.interpreter
+                            .error(variant, "Already a variant with this name in this enum.");
+                    }
+                }
+            }
+            Stmt::Mixin(name, methods) => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Mixin;
+
+                self.declare(Symbol::new(name.clone(), SymbolKind::Mixin));
+                self.define(name.clone());
+                self.declare_members(name, methods, SymbolKind::Method);
+
+                // `super` is bound to the superclass of each class that uses the mixin.
+                self.begin_scope();
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert("super".to_string(), true);
+
+                self.begin_scope();
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert("this".to_string(), true);
+
+                self.resolve_methods(methods);
+
+                self.end_scope();
+                self.end_scope();
+                self.current_class = enclosing_class;
+            }
+            Stmt::Const(name, ty, initializer) => {
+                let symbol = Symbol::new(name.clone(), SymbolKind::Constant);
+                self.declare_const(symbol.with_detail(annotation(ty)));
+                self.resolve_expr(initializer.clone());
+                self.define(name.clone());
+            }
+            Stmt::Var(name, ty, initializer) => {
+                let symbol = Symbol::new(name.clone(), SymbolKind::Variable);
+                self.declare(symbol.with_detail(annotation(ty)));
                 if *initializer != Expr::LiteralExpr(Literal::None) {
                     self.resolve_expr(initializer.clone());
                 }
                 self.define(name.clone());
             }
-            st @ Stmt::Function(name, _, _) => {
-                self.declare(name.clone());
+            st @ Stmt::Function(name, params, ret, _) => {
+                let symbol = Symbol::new(name.clone(), SymbolKind::Function);
+                let id = self.declare(symbol.with_detail(signature(params, ret)));
+                self.arities.insert(id, lint::arity(params));
                 self.define(name.clone());
 
                 self.resovle_function(st, FunctionType::Func);
             }
+            st @ Stmt::Getter(_, _) => self.resovle_function(st, FunctionType::Getter),
             Stmt::Expression(exp) => self.resolve_expr(exp.clone()),
             Stmt::If(cond, then, els) => {
                 self.resolve_expr(cond.clone());
@@ -262,7 +677,17 @@ impl Visitor<(), ()> for Resolver<'_> {
                     self.resolve_stmt(*s.clone());
                 }
             }
-            Stmt::Print(exp) => self.resolve_expr(exp.clone()),
+            Stmt::Match(_, value, cases) => {
+                self.resolve_expr(value.clone());
+                for (pattern, body) in cases {
+                    // Names bound by a pattern are visible only in its case.
+                    self.begin_scope();
+                    self.resolve_pattern(pattern, false);
+                    self.resolve_stmt(body.clone());
+                    self.end_scope();
+                }
+            }
+            Stmt::Print(_, exp) => self.resolve_expr(exp.clone()),
             Stmt::Return(keyword, value) => {
                 if self.current_function == FunctionType::None {
                     self.interpreter
@@ -285,3 +710,74 @@ impl Visitor<(), ()> for Resolver<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod test_resolver {
+    use super::Resolver;
+    use crate::evaluator::{environment::Environment, Evaluator};
+    use crate::{Lox, Parser, Scanner};
+
+    fn resolves(source: &str) -> bool {
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        let mut scanner = Scanner::new(&mut interpreter, source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+        let statements = Parser::new(&mut interpreter, tokens).parse();
+        Resolver::new(&mut interpreter).resolve_optional_stmts(statements);
+        !interpreter.had_error
+    }
+
+    #[test]
+    fn test_static_members() {
+        assert!(resolves("class A { class f() { return A; } class x = 1; }"));
+        assert!(!resolves("class A { class f() { return this; } }"));
+        assert!(!resolves("class A { class x = this; }"));
+        assert!(!resolves(
+            "class A {} class B < A { class f() { return super.f(); } }"
+        ));
+    }
+
+    #[test]
+    fn test_getters() {
+        assert!(resolves("class A { size { return this.n; } }"));
+        assert!(!resolves("class A { init { return 1; } }"));
+    }
+
+    #[test]
+    fn test_mixins() {
+        assert!(resolves(
+            "mixin M { f() { return this; } } class C with M {}"
+        ));
+        assert!(!resolves("mixin M {} class C with C {}"));
+    }
+
+    #[test]
+    fn test_constants() {
+        assert!(resolves(
+            "const a = 1; { const b = a + 1; fun f() { return b; } }"
+        ));
+        assert!(!resolves("const a = 1; a = 2;"));
+        assert!(!resolves("{ const b = 1; fun f() { b = 2; } }"));
+        // A variable of the same name in an inner scope is not constant.
+        assert!(resolves("const a = 1; { var a = 2; a = 3; }"));
+    }
+
+    #[test]
+    fn test_match() {
+        assert!(resolves(
+            "match (1) { case x => print x; case _ => print 0; }"
+        ));
+        assert!(resolves(
+            "class P {} match (1) { case P(x, y: 2) => print x + y; }"
+        ));
+        assert!(!resolves("match (1) { case 1 | x => print x; }"));
+    }
+
+    #[test]
+    fn test_enums() {
+        assert!(resolves(
+            "enum A { B, C(x, y) } match (A.B) { case A.C(x) => print x; }"
+        ));
+        assert!(!resolves("enum A { B, B }"));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::lexer::token::Token;
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Class,
+    Constant,
+    Enum,
+    Field,
+    Function,
+    Method,
+    Mixin,
+    Parameter,
+    Variable,
+    Variant,
+}
+
+impl Display for SymbolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            SymbolKind::Class => "class",
+            SymbolKind::Constant => "constant",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Field => "field",
+            SymbolKind::Function => "function",
+            SymbolKind::Method => "method",
+            SymbolKind::Mixin => "mixin",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Variable => "variable",
+            SymbolKind::Variant => "variant",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+// A declaration found by the resolver.
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    name: Token,
+    kind: SymbolKind,
+    container: Option<String>, // class, mixin or enum the member belongs to
+    detail: String,            // parameters, type annotation or superclass
+    is_global: bool,
+}
+
+impl Symbol {
+    pub fn new(name: Token, kind: SymbolKind) -> Self {
+        Symbol {
+            name,
+            kind,
+            container: None,
+            detail: String::new(),
+            is_global: false,
+        }
+    }
+
+    pub fn with_container(mut self, container: &Token) -> Self {
+        self.container = Some(container.get_lexeme().to_string());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: String) -> Self {
+        self.detail = detail;
+        self
+    }
+
+    pub fn get_name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn get_kind(&self) -> SymbolKind {
+        self.kind
+    }
+
+    pub fn get_container(&self) -> Option<&str> {
+        self.container.as_deref()
+    }
+
+    pub fn get_detail(&self) -> &str {
+        &self.detail
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.is_global
+    }
+}
+
+// Like a hover text: `(method) Point.area` or `(function) add(a, b)`.
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}) ", self.kind)?;
+        if let Some(container) = &self.container {
+            write!(f, "{container}.")?;
+        }
+        write!(f, "{}{}", self.name.get_lexeme(), self.detail)
+    }
+}
+
+// Declarations of a program and the places where they are used.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    references: Vec<(Token, usize)>, // (usage, index of the symbol)
+    properties: Vec<Token>,          // names used after a dot or in class patterns
+}
+
+impl SymbolTable {
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn references(&self) -> &[(Token, usize)] {
+        &self.references
+    }
+
+    pub fn properties(&self) -> &[Token] {
+        &self.properties
+    }
+
+    pub(super) fn add(&mut self, mut symbol: Symbol, is_global: bool) -> usize {
+        symbol.is_global = is_global;
+        self.symbols.push(symbol);
+        self.symbols.len() - 1
+    }
+
+    pub(super) fn add_reference(&mut self, usage: &Token, symbol: usize) {
+        self.references.push((usage.clone(), symbol));
+    }
+
+    pub(super) fn add_property(&mut self, name: &Token) {
+        self.properties.push(name.clone());
+    }
+}
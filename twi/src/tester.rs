@@ -0,0 +1,291 @@
+// Runner of Lox scripts that check themselves with comments, in the format of the test
+// suite of Crafting Interpreters:
+//
+// print 1 + 2;  // expect: 3
+// print x;      // expect runtime error: Undefined variable 'x'.
+// var = 1;      // Error at '=': Expect variable name.
+// print "a" +   // [line 5] Error at end: Expect expression.
+//
+// A script is run by an interpreter binary (twi or bvm), and its output, errors and exit code
+// are compared with the expected ones. A script without such comments must run silently.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Expectations {
+    output: Vec<(usize, String)>, // (line, printed text)
+    errors: Vec<(usize, String)>, // (line, message) of syntax, resolution and type errors
+    runtime_error: Option<(usize, String)>,
+}
+
+impl Expectations {
+    pub fn parse(source: &str) -> Self {
+        let mut expectations = Expectations::default();
+        for (i, text) in source.lines().enumerate() {
+            let line = i + 1;
+            if let Some((_, value)) = text.split_once(EXPECT) {
+                expectations.output.push((line, value.to_string()));
+            } else if let Some((_, message)) = text.split_once(EXPECT_RUNTIME_ERROR) {
+                expectations.runtime_error = Some((line, message.to_string()));
+            } else if let Some(error) = parse_error(text, line) {
+                expectations.errors.push(error);
+            }
+        }
+        expectations
+    }
+
+    fn exit_code(&self) -> i32 {
+        match (self.errors.is_empty(), &self.runtime_error) {
+            (false, _) => 65,
+            (true, Some(_)) => 70,
+            (true, None) => 0,
+        }
+    }
+}
+
+// `// Error at 'x': message` or `// [line 3] Error: message`, as (line, message).
+fn parse_error(text: &str, line: usize) -> Option<(usize, String)> {
+    let (_, comment) = text.split_once("// ")?;
+    let (line, error) = match comment.strip_prefix("[line ") {
+        Some(rest) => {
+            let (number, error) = rest.split_once("] ")?;
+            (number.parse().ok()?, error)
+        }
+        None => (line, comment),
+    };
+
+    let rest = error.strip_prefix("Error")?;
+    let message = if let Some(rest) = rest.strip_prefix(" at '") {
+        rest.split_once("': ")?.1
+    } else if let Some(rest) = rest.strip_prefix(" at end: ") {
+        rest
+    } else {
+        rest.strip_prefix(": ")?
+    };
+    Some((line, message.to_string()))
+}
+
+// What a run of a script printed.
+pub struct Outcome {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>, // `None` if killed by a signal
+}
+
+impl Outcome {
+    pub fn run(interpreter: &Path, path: &Path) -> io::Result<Self> {
+        let output = Command::new(interpreter).arg(path).output()?;
+        Ok(Outcome {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            code: output.status.code(),
+        })
+    }
+
+    // Errors rendered as diagnostics, `error: message` followed by ` --> path:line:column`.
+    pub fn errors(&self) -> Vec<(usize, String)> {
+        let mut errors = Vec::new();
+        let mut lines = self.stderr.lines();
+        while let Some(text) = lines.next() {
+            let Some(message) = text.strip_prefix("error: ") else {
+                continue;
+            };
+            let line = lines
+                .next()
+                .and_then(|location| location.trim().strip_prefix("--> "))
+                .and_then(|location| location.rsplit(':').nth(1))
+                .and_then(|line| line.parse().ok())
+                .unwrap_or_default();
+            errors.push((line, message.to_string()));
+        }
+        errors
+    }
+}
+
+// Differences of the outcome from the expectations, one per line; none if the test passed.
+pub fn check(expectations: &Expectations, outcome: &Outcome) -> Vec<String> {
+    let mut failures = Vec::new();
+    let actual: Vec<&str> = outcome.stdout.lines().collect();
+    diff_output(&expectations.output, &actual, &mut failures);
+
+    let mut expected_errors = expectations.errors.clone();
+    expected_errors.extend(expectations.runtime_error.clone());
+    let actual_errors = outcome.errors();
+    for (line, message) in &expected_errors {
+        if !actual_errors.contains(&(*line, message.clone())) {
+            failures.push(format!("line {line}: expected error '{message}'"));
+        }
+    }
+    for (line, message) in &actual_errors {
+        if !expected_errors.contains(&(*line, message.clone())) {
+            failures.push(format!("line {line}: unexpected error '{message}'"));
+        }
+    }
+
+    let code = expectations.exit_code();
+    if outcome.code != Some(code) {
+        let actual = match outcome.code {
+            Some(code) => code.to_string(),
+            None => "none".to_string(),
+        };
+        failures.push(format!("expected exit code {code}, got {actual}"));
+        // A crash doesn't render its message as a diagnostic.
+        if actual_errors.is_empty() && !outcome.stderr.is_empty() {
+            failures.push(format!("stderr: {}", outcome.stderr.trim_end()));
+        }
+    }
+    failures
+}
+
+// Line diff of the printed values, a changed line is reported as one failure.
+fn diff_output(expected: &[(usize, String)], actual: &[&str], failures: &mut Vec<String>) {
+    let values: Vec<&str> = expected.iter().map(|(_, value)| value.as_str()).collect();
+    for change in diff_lines(&values, actual) {
+        let failure = match change {
+            (Some(i), Some(j)) => {
+                let (line, value) = &expected[i];
+                format!("line {line}: expected '{value}', got '{}'", actual[j])
+            }
+            (Some(i), None) => {
+                let (line, value) = &expected[i];
+                format!("line {line}: expected '{value}', got nothing")
+            }
+            (None, Some(j)) => format!("unexpected output '{}'", actual[j]),
+            (None, None) => continue,
+        };
+        failures.push(failure);
+    }
+}
+
+// Lines that differ between two texts, found by their longest common subsequence. Each change
+// is a pair of indices of a line replaced by another one, or of a line only one text has.
+pub fn diff_lines(left: &[&str], right: &[&str]) -> Vec<(Option<usize>, Option<usize>)> {
+    let (n, m) = (left.len(), right.len());
+    // Lengths of the common subsequences of the suffixes.
+    let mut lengths = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = match left[i] == right[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && left[i] == right[j] {
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            // Followed by a line only the right text has, it was replaced.
+            if j < m && lengths[i + 1][j] == lengths[i + 1][j + 1] {
+                changes.push((Some(i), Some(j)));
+                j += 1;
+            } else {
+                changes.push((Some(i), None));
+            }
+            i += 1;
+        } else {
+            changes.push((None, Some(j)));
+            j += 1;
+        }
+    }
+    changes
+}
+
+// Runs every script and prints failures, returns whether all of them passed.
+pub fn run_all(interpreter: &Path, paths: &[PathBuf]) -> bool {
+    let mut scripts = Vec::new();
+    for path in paths {
+        collect_scripts(path, &mut scripts);
+    }
+
+    let mut failed = 0;
+    for script in &scripts {
+        let failures = match test_script(interpreter, script) {
+            Ok(failures) => failures,
+            Err(err) => vec![err.to_string()],
+        };
+        if failures.is_empty() {
+            println!("PASS {}", script.display());
+        } else {
+            println!("FAIL {}", script.display());
+            for failure in failures {
+                println!("  {failure}");
+            }
+            failed += 1;
+        }
+    }
+
+    println!("\n{} passed, {failed} failed", scripts.len() - failed);
+    failed == 0
+}
+
+fn test_script(interpreter: &Path, path: &Path) -> io::Result<Vec<String>> {
+    let source = fs::read_to_string(path)?;
+    let outcome = Outcome::run(interpreter, path)?;
+    Ok(check(&Expectations::parse(&source), &outcome))
+}
+
+// `.lox` files of a directory and its subdirectories, sorted.
+pub fn collect_scripts(path: &Path, scripts: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        scripts.push(path.to_path_buf());
+        return;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir()
+            || entry
+                .extension()
+                .is_some_and(|extension| extension == "lox")
+        {
+            collect_scripts(&entry, scripts);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_tester {
+    use super::{check, Expectations, Outcome};
+
+    #[test]
+    fn test_check() {
+        let source = "print 1; // expect: 1
+print 2; // expect: 3
+var = 1; // Error at '=': Expect variable name.
+// [line 5] Error at end: Expect expression.
+print";
+        let expectations = Expectations::parse(source);
+        assert_eq!(expectations.errors.len(), 2);
+        assert_eq!(
+            expectations.errors[1],
+            (5, "Expect expression.".to_string())
+        );
+
+        let outcome = Outcome {
+            stdout: "1\n2\n".to_string(),
+            stderr: "error: Expect variable name.\n --> test.lox:3:5\n  |\n".to_string(),
+            code: Some(65),
+        };
+        assert_eq!(
+            check(&expectations, &outcome),
+            [
+                "line 2: expected '3', got '2'",
+                "line 5: expected error 'Expect expression.'"
+            ]
+        );
+    }
+}
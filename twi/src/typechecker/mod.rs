@@ -0,0 +1,731 @@
+use crate::ast::{
+    expr::Expr,
+    stmt::{Param, Pattern, Stmt},
+};
+use crate::evaluator::arity_to_string;
+use crate::lexer::token::{Literal, Token, TokenType};
+use crate::{Lox, Visitor};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::rc::Rc;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Any,
+    Bool,
+    Class(String),
+    Fun(Option<Rc<Signature>>), // `None` for a bare `Fun` annotation
+    Instance(String),
+    List,
+    Nil,
+    Num,
+    Str,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Signature {
+    params: Vec<Type>,
+    required: usize,
+    has_rest: bool,
+    ret: Type,
+    is_annotated: bool, // calls of unannotated functions are left to the runtime
+}
+
+impl Signature {
+    fn arity(&self) -> (usize, Option<usize>) {
+        let max = if self.has_rest {
+            None
+        } else {
+            Some(self.params.len())
+        };
+        (self.required, max)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Any => write!(f, "Any"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Class(name) => write!(f, "class {name}"),
+            Type::Fun(_) => write!(f, "Fun"),
+            Type::Instance(name) => write!(f, "{name}"),
+            Type::List => write!(f, "List"),
+            Type::Nil => write!(f, "Nil"),
+            Type::Num => write!(f, "Num"),
+            Type::Str => write!(f, "Str"),
+        }
+    }
+}
+
+impl Type {
+    // Values of these types may overload operators or are not known statically.
+    fn is_open(&self) -> bool {
+        matches!(self, Type::Any | Type::Instance(_))
+    }
+}
+
+// A name in scope with its type; only annotated names are checked on assignment.
+#[derive(Clone)]
+struct Binding {
+    ty: Type,
+    is_declared: bool,
+}
+
+pub struct TypeChecker<'a> {
+    scopes: Vec<HashMap<String, Binding>>,
+    globals: HashMap<String, Binding>,
+    classes: HashMap<String, Option<String>>, // class name -> superclass name
+    enums: HashSet<String>,
+    current_return: Option<Type>,
+    current_class: Option<String>,
+
+    interpreter: &'a mut Lox,
+}
+
+impl TypeChecker<'_> {
+    pub fn new(interpreter: &mut Lox) -> TypeChecker<'_> {
+        TypeChecker {
+            scopes: Vec::new(),
+            globals: HashMap::new(),
+            classes: HashMap::new(),
+            enums: HashSet::new(),
+            current_return: None,
+            current_class: None,
+            interpreter,
+        }
+    }
+
+    pub fn check_optional_stmts(&mut self, statements: &[Option<Stmt>]) {
+        let statements: Vec<Stmt> = statements.iter().flatten().cloned().collect();
+        // Classes can be used in annotations before their declaration.
+        self.collect_types(&statements);
+        self.check_stmts(&statements);
+    }
+
+    fn check_stmts(&mut self, statements: &[Stmt]) {
+        for (i, statement) in statements.iter().enumerate() {
+            match statement {
+                // An unannotated variable keeps the type of its value unless it's assigned
+                // later in its block, also from a function that may run at any time.
+                Stmt::Var(name, None, initializer) => {
+                    let value_ty = self.visit_expr(initializer);
+                    let rest = &statements[i + 1..];
+                    let is_assigned = rest.iter().any(|s| assigns_stmt(name.get_lexeme(), s));
+                    let ty = if is_assigned { Type::Any } else { value_ty };
+                    self.define(name, ty, false);
+                }
+                _ => self.visit_stmt(statement),
+            }
+        }
+    }
+
+    fn collect_types(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            match statement {
+                Stmt::Class(name, superclass, _, methods, members) => {
+                    let superclass = match superclass {
+                        Some(Expr::Variable(sup_cls)) => Some(sup_cls.get_lexeme().to_string()),
+                        _ => None,
+                    };
+                    self.classes
+                        .insert(name.get_lexeme().to_string(), superclass);
+                    self.collect_types(methods);
+                    self.collect_types(members);
+                }
+                Stmt::Enum(name, _) => {
+                    self.enums.insert(name.get_lexeme().to_string());
+                }
+                Stmt::Block(body)
+                | Stmt::Function(_, _, _, body)
+                | Stmt::Getter(_, body)
+                | Stmt::Mixin(_, body) => self.collect_types(body),
+                Stmt::If(_, then, els) => {
+                    self.collect_types(std::slice::from_ref(then));
+                    if let Some(els) = els {
+                        self.collect_types(std::slice::from_ref(els));
+                    }
+                }
+                Stmt::Match(_, _, cases) => {
+                    for (_, body) in cases {
+                        self.collect_types(std::slice::from_ref(body));
+                    }
+                }
+                Stmt::While(_, body) => self.collect_types(std::slice::from_ref(body)),
+                _ => (),
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn define(&mut self, name: &Token, ty: Type, is_declared: bool) {
+        let binding = Binding { ty, is_declared };
+        match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.get_lexeme().to_string(), binding),
+            None => self.globals.insert(name.get_lexeme().to_string(), binding),
+        };
+    }
+
+    fn lookup(&mut self, name: &Token) -> Option<&mut Binding> {
+        let lexeme = name.get_lexeme();
+        match self.scopes.iter().rposition(|s| s.contains_key(lexeme)) {
+            Some(i) => self.scopes[i].get_mut(lexeme),
+            None => self.globals.get_mut(lexeme),
+        }
+    }
+
+    // Turns an annotation into a type, unannotated names are `Any`.
+    fn annotation(&mut self, ty: &Option<Token>) -> Type {
+        let Some(token) = ty else {
+            return Type::Any;
+        };
+
+        match token.get_lexeme() {
+            "Any" => Type::Any,
+            "Bool" => Type::Bool,
+            "Fun" => Type::Fun(None),
+            "List" => Type::List,
+            "Nil" => Type::Nil,
+            "Num" => Type::Num,
+            "Str" => Type::Str,
+            name if self.classes.contains_key(name) => Type::Instance(name.to_string()),
+            name if self.enums.contains(name) => Type::Any,
+            name => {
+                let msg = format!("Unknown type '{name}'.");
+                self.interpreter.error(token, &msg);
+                Type::Any
+            }
+        }
+    }
+
+    fn is_subclass_of(&self, class: &str, superclass: &str) -> bool {
+        let mut current = Some(class.to_string());
+        while let Some(name) = current {
+            if name == superclass {
+                return true;
+            }
+            current = self.classes.get(&name).cloned().flatten();
+        }
+        false
+    }
+
+    // Gradual typing: `Any` fits everywhere and `nil` fits any annotated slot.
+    fn is_assignable(&self, expected: &Type, actual: &Type) -> bool {
+        match (expected, actual) {
+            (Type::Any, _) | (_, Type::Any) | (_, Type::Nil) => true,
+            (Type::Fun(None), Type::Fun(_) | Type::Class(_)) => true,
+            (Type::Fun(Some(_)), Type::Fun(_)) => true,
+            (Type::Instance(expected), Type::Instance(actual)) => {
+                self.is_subclass_of(actual, expected)
+            }
+            (expected, actual) => expected == actual,
+        }
+    }
+
+    fn expect(&mut self, token: &Token, expected: &Type, actual: &Type) {
+        if !self.is_assignable(expected, actual) {
+            let msg = format!("Type mismatch: expected {expected}, found {actual}.");
+            self.interpreter.error(token, &msg);
+        }
+    }
+
+    fn signature(&mut self, params: &[Param], ret: &Option<Token>) -> Signature {
+        let mut required = 0;
+        let mut has_rest = false;
+        let mut types = Vec::new();
+        let mut is_annotated = ret.is_some();
+        for param in params {
+            match param {
+                Param::Required(_, _) => required += 1,
+                Param::Default(_, _, _) => (),
+                Param::Rest(_, _) => has_rest = true,
+            }
+            let ty = param.get_type().cloned();
+            is_annotated |= ty.is_some();
+            types.push(self.annotation(&ty));
+        }
+        let ret = self.annotation(ret);
+
+        Signature {
+            params: types,
+            required,
+            has_rest,
+            ret,
+            is_annotated,
+        }
+    }
+
+    fn check_function(&mut self, params: &[Param], signature: &Signature, body: &[Stmt]) {
+        let enclosing_return = self.current_return.replace(signature.ret.clone());
+
+        self.begin_scope();
+        for (param, ty) in params.iter().zip(&signature.params) {
+            match param {
+                Param::Default(name, _, default) => {
+                    let default_ty = self.visit_expr(default);
+                    self.expect(name, ty, &default_ty);
+                    self.define(name, ty.clone(), true);
+                }
+                // Rest parameters collect the remaining arguments into a list.
+                Param::Rest(name, _) => self.define(name, Type::List, true),
+                Param::Required(name, _) => self.define(name, ty.clone(), true),
+            }
+        }
+        self.check_stmts(body);
+        self.end_scope();
+
+        self.current_return = enclosing_return;
+    }
+
+    fn check_methods(&mut self, methods: &[Stmt]) {
+        for method in methods {
+            match method {
+                Stmt::Function(_, params, ret, body) => {
+                    let signature = self.signature(params, ret);
+                    self.check_function(params, &signature, body);
+                }
+                Stmt::Getter(_, body) => {
+                    let signature = self.signature(&[], &None);
+                    self.check_function(&[], &signature, body);
+                }
+                Stmt::Var(_, _, initializer) => {
+                    self.visit_expr(initializer);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn check_call(&mut self, paren: &Token, signature: &Signature, arguments: &[Expr]) {
+        let arg_types: Vec<Type> = arguments.iter().map(|a| self.visit_expr(a)).collect();
+
+        // The number of spread arguments is known only at runtime.
+        let is_spread = arguments.iter().any(|a| matches!(a, Expr::Spread(_, _)));
+        if is_spread || !signature.is_annotated {
+            return;
+        }
+
+        let (min, max) = signature.arity();
+        if arg_types.len() < min || max.is_some_and(|max| arg_types.len() > max) {
+            let msg = format!(
+                "Expected {} arguments but got {}.",
+                arity_to_string(min, max),
+                arg_types.len()
+            );
+            self.interpreter.error(paren, &msg);
+            return;
+        }
+
+        for (i, actual) in arg_types.iter().enumerate() {
+            let expected = match signature.params.get(i) {
+                Some(_) if signature.has_rest && i >= signature.params.len() - 1 => &Type::Any,
+                Some(expected) => expected,
+                None => &Type::Any,
+            };
+            if !self.is_assignable(expected, actual) {
+                let msg = format!(
+                    "Type mismatch in argument {}: expected {expected}, found {actual}.",
+                    i + 1
+                );
+                self.interpreter.error(paren, &msg);
+            }
+        }
+    }
+
+    fn check_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Alternatives(patterns) => {
+                for pattern in patterns {
+                    self.check_pattern(pattern);
+                }
+            }
+            Pattern::Binding(name) => self.define(name, Type::Any, false),
+            Pattern::Class(_, fields) | Pattern::Variant(_, _, fields) => {
+                for (_, field_pattern) in fields {
+                    self.check_pattern(field_pattern);
+                }
+            }
+            Pattern::Literal(_, _) | Pattern::Wildcard(_) => (),
+        }
+    }
+}
+
+impl Visitor<Type, ()> for TypeChecker<'_> {
+    fn visit_expr(&mut self, e: &Expr) -> Type {
+        match e {
+            Expr::LiteralExpr(literal) => match literal {
+                Literal::Bool(_) => Type::Bool,
+                Literal::Number(_) => Type::Num,
+                Literal::String(_) => Type::Str,
+                Literal::None => Type::Nil,
+            },
+            Expr::Variable(name) => match self.lookup(name) {
+                Some(binding) => binding.ty.clone(),
+                None => Type::Any,
+            },
+            Expr::Assign(name, value) => {
+                let value_ty = self.visit_expr(value);
+                let Some(binding) = self.lookup(name).cloned() else {
+                    return value_ty;
+                };
+
+                if binding.is_declared {
+                    self.expect(name, &binding.ty, &value_ty);
+                } else if let Some(binding) = self.lookup(name) {
+                    // An unannotated name may now hold anything.
+                    binding.ty = Type::Any;
+                }
+                value_ty
+            }
+            Expr::Binary(left, op, right) => {
+                let l = self.visit_expr(left);
+                let r = self.visit_expr(right);
+
+                match op.get_type() {
+                    TokenType::BangEqual | TokenType::EqualEqual => Type::Bool,
+                    TokenType::Plus => match (&l, &r) {
+                        (Type::Num, Type::Num) => Type::Num,
+                        (Type::Str, Type::Str) => Type::Str,
+                        (Type::Str, other) | (other, Type::Str) if other.is_open() => Type::Str,
+                        _ if l.is_open() || r.is_open() => Type::Any,
+                        _ => {
+                            self.interpreter
+                                .error(op, "Operands must be two numbers or two strings.");
+                            Type::Any
+                        }
+                    },
+                    TokenType::Greater
+                    | TokenType::GreaterEqual
+                    | TokenType::Less
+                    | TokenType::LessEqual
+                    | TokenType::Minus
+                    | TokenType::Slash
+                    | TokenType::Star => {
+                        for operand in [&l, &r] {
+                            if *operand != Type::Num && !operand.is_open() {
+                                self.interpreter.error(op, "Operands must be numbers.");
+                                break;
+                            }
+                        }
+
+                        let is_comparison = !matches!(
+                            op.get_type(),
+                            TokenType::Minus | TokenType::Slash | TokenType::Star
+                        );
+                        match (l, r) {
+                            _ if is_comparison => Type::Bool,
+                            (Type::Num, Type::Num) => Type::Num,
+                            _ => Type::Any,
+                        }
+                    }
+                    _ => Type::Any,
+                }
+            }
+            Expr::Call(callee, paren, arguments) => match self.visit_expr(callee) {
+                Type::Fun(Some(signature)) => {
+                    self.check_call(paren, &signature, arguments);
+                    signature.ret.clone()
+                }
+                Type::Class(name) => {
+                    for argument in arguments {
+                        self.visit_expr(argument);
+                    }
+                    Type::Instance(name)
+                }
+                ty @ (Type::Bool | Type::List | Type::Nil | Type::Num | Type::Str) => {
+                    let msg = format!("Can only call functions and classes, found {ty}.");
+                    self.interpreter.error(paren, &msg);
+                    Type::Any
+                }
+                _ => {
+                    for argument in arguments {
+                        self.visit_expr(argument);
+                    }
+                    Type::Any
+                }
+            },
+            Expr::Get(object, _) => {
+                self.visit_expr(object);
+                Type::Any
+            }
+            Expr::Grouping(exp) => self.visit_expr(exp),
+            Expr::Index(list, _, index) => {
+                self.visit_expr(list);
+                self.visit_expr(index);
+                Type::Any
+            }
+            Expr::List(_, elements) => {
+                for element in elements {
+                    self.visit_expr(element);
+                }
+                Type::List
+            }
+            Expr::Logical(l, _, r) => {
+                let l = self.visit_expr(l);
+                let r = self.visit_expr(r);
+                if l == r {
+                    l
+                } else {
+                    Type::Any
+                }
+            }
+            Expr::Unary(op, right) => {
+                let r = self.visit_expr(right);
+                match op.get_type() {
+                    TokenType::Bang => Type::Bool,
+                    TokenType::Minus if r == Type::Num => Type::Num,
+                    TokenType::Minus if r.is_open() => Type::Any,
+                    TokenType::Minus => {
+                        self.interpreter.error(op, "Operand must be a number.");
+                        Type::Any
+                    }
+                    _ => Type::Any,
+                }
+            }
+            Expr::Set(object, _, value) => {
+                self.visit_expr(object);
+                self.visit_expr(value)
+            }
+            Expr::Spread(_, exp) => {
+                self.visit_expr(exp);
+                Type::Any
+            }
+            Expr::Super(_, _) => Type::Any,
+            Expr::This(_) => match &self.current_class {
+                Some(name) => Type::Instance(name.clone()),
+                None => Type::Any,
+            },
+        }
+    }
+
+    fn visit_stmt(&mut self, s: &Stmt) {
+        match s {
+            Stmt::Block(statements) => {
+                self.begin_scope();
+                self.check_stmts(statements);
+                self.end_scope();
+            }
+            Stmt::Class(name, superclass, mixins, methods, members) => {
+                self.define(name, Type::Class(name.get_lexeme().to_string()), false);
+
+                if let Some(sup_cls) = superclass {
+                    self.visit_expr(sup_cls);
+                }
+                for mixin in mixins {
+                    self.visit_expr(mixin);
+                }
+
+                let enclosing_class = self.current_class.take();
+                self.check_methods(members);
+                self.current_class = Some(name.get_lexeme().to_string());
+                self.check_methods(methods);
+                self.current_class = enclosing_class;
+            }
+            Stmt::Const(name, ty, initializer) | Stmt::Var(name, ty, initializer) => {
+                let value_ty = self.visit_expr(initializer);
+                if ty.is_none() {
+                    // Constants keep the type of their value.
+                    let ty = match s {
+                        Stmt::Const(_, _, _) => value_ty,
+                        _ => Type::Any,
+                    };
+                    self.define(name, ty, false);
+                    return;
+                }
+
+                let ty = self.annotation(ty);
+                self.expect(name, &ty, &value_ty);
+                self.define(name, ty, true);
+            }
+            Stmt::Enum(name, _) => self.define(name, Type::Any, false),
+            Stmt::Expression(exp) | Stmt::Print(_, exp) => {
+                self.visit_expr(exp);
+            }
+            Stmt::Function(name, params, ret, body) => {
+                let signature = Rc::new(self.signature(params, ret));
+                // Defined before the body so that recursive calls are checked too.
+                self.define(name, Type::Fun(Some(Rc::clone(&signature))), false);
+                self.check_function(params, &signature, body);
+            }
+            Stmt::Getter(_, _) => self.check_methods(std::slice::from_ref(s)),
+            Stmt::If(cond, then, els) => {
+                self.visit_expr(cond);
+                self.visit_stmt(then);
+                if let Some(els) = els {
+                    self.visit_stmt(els);
+                }
+            }
+            Stmt::Match(_, value, cases) => {
+                self.visit_expr(value);
+                for (pattern, body) in cases {
+                    self.begin_scope();
+                    self.check_pattern(pattern);
+                    self.visit_stmt(body);
+                    self.end_scope();
+                }
+            }
+            Stmt::Mixin(name, methods) => {
+                self.define(name, Type::Any, false);
+
+                let enclosing_class = self.current_class.take();
+                self.check_methods(methods);
+                self.current_class = enclosing_class;
+            }
+            Stmt::Return(keyword, value) => {
+                let value_ty = self.visit_expr(value);
+                if let Some(ret) = self.current_return.clone() {
+                    self.expect(keyword, &ret, &value_ty);
+                }
+            }
+            Stmt::While(cond, body) => {
+                self.visit_expr(cond);
+                self.visit_stmt(body);
+            }
+        }
+    }
+}
+
+// Whether the statement assigns the name anywhere, a shadowing name counts too.
+fn assigns_stmt(name: &str, s: &Stmt) -> bool {
+    let any_stmt = |statements: &[Stmt]| statements.iter().any(|s| assigns_stmt(name, s));
+    match s {
+        Stmt::Block(statements) | Stmt::Getter(_, statements) | Stmt::Mixin(_, statements) => {
+            any_stmt(statements)
+        }
+        Stmt::Class(_, superclass, mixins, methods, members) => {
+            superclass
+                .iter()
+                .chain(mixins)
+                .any(|e| assigns_expr(name, e))
+                || any_stmt(methods)
+                || any_stmt(members)
+        }
+        Stmt::Const(_, _, expr)
+        | Stmt::Expression(expr)
+        | Stmt::Print(_, expr)
+        | Stmt::Return(_, expr)
+        | Stmt::Var(_, _, expr) => assigns_expr(name, expr),
+        Stmt::Enum(..) => false,
+        Stmt::Function(_, params, _, body) => {
+            let is_default_assigned = params.iter().any(|p| match p {
+                Param::Default(_, _, default) => assigns_expr(name, default),
+                _ => false,
+            });
+            is_default_assigned || any_stmt(body)
+        }
+        Stmt::If(condition, then, els) => {
+            assigns_expr(name, condition)
+                || assigns_stmt(name, then)
+                || els.as_ref().is_some_and(|els| assigns_stmt(name, els))
+        }
+        Stmt::Match(_, value, cases) => {
+            assigns_expr(name, value) || cases.iter().any(|(_, body)| assigns_stmt(name, body))
+        }
+        Stmt::While(condition, body) => assigns_expr(name, condition) || assigns_stmt(name, body),
+    }
+}
+
+fn assigns_expr(name: &str, e: &Expr) -> bool {
+    match e {
+        Expr::Assign(target, value) => target.get_lexeme() == name || assigns_expr(name, value),
+        Expr::Binary(left, _, right)
+        | Expr::Index(left, _, right)
+        | Expr::Logical(left, _, right)
+        | Expr::Set(left, _, right) => assigns_expr(name, left) || assigns_expr(name, right),
+        Expr::Call(callee, _, arguments) => {
+            assigns_expr(name, callee) || arguments.iter().any(|a| assigns_expr(name, a))
+        }
+        Expr::List(_, elements) => elements.iter().any(|e| assigns_expr(name, e)),
+        Expr::Get(expr, _)
+        | Expr::Grouping(expr)
+        | Expr::Unary(_, expr)
+        | Expr::Spread(_, expr) => assigns_expr(name, expr),
+        Expr::LiteralExpr(_) | Expr::Super(..) | Expr::This(_) | Expr::Variable(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test_typechecker {
+    use super::TypeChecker;
+    use crate::evaluator::{environment::Environment, Evaluator};
+    use crate::{Lox, Parser, Scanner};
+
+    fn type_checks(source: &str) -> bool {
+        let mut interpreter = Lox::new(Evaluator::new(Environment::new(None)));
+        let mut scanner = Scanner::new(&mut interpreter, source);
+        scanner.scan_tokens();
+        let tokens = scanner.tokens().clone();
+        let statements = Parser::new(&mut interpreter, tokens).parse();
+        assert!(!interpreter.had_error, "Syntax error in: {source}");
+
+        TypeChecker::new(&mut interpreter).check_optional_stmts(&statements);
+        !interpreter.had_error
+    }
+
+    #[test]
+    fn test_annotated_mismatch() {
+        let greet = "fun greet(name: Str, times: Num = 1): Str { return name; }";
+        let class = "class A {} class B < A {}";
+        for source in [
+            "var count: Num = \"zero\";",
+            "fun greet(name: Str): Str { return 1; }",
+            &format!("{greet} greet(42);"),
+            &format!("{greet} greet(\"a\", 1, 2);"),
+            &format!("{class} var b: B = A();"),
+            "var x: Point = 1;",
+        ] {
+            assert!(!type_checks(source), "{source}");
+        }
+        assert!(type_checks(&format!(
+            "{greet} var s: Str = greet(\"a\", 2);"
+        )));
+        // Instances of subclasses fit the type of their superclass, `nil` fits any type.
+        assert!(type_checks(&format!(
+            "{class} var a: A = B(); var n: Num = nil;"
+        )));
+    }
+
+    #[test]
+    fn test_inference() {
+        // Types flow through annotated variables and constants.
+        for source in [
+            "var n: Num = 1; var s: Str = n;",
+            "const c = \"a\"; print c - 1;",
+            "fun f(): Bool { return true; } var m: Num = f();",
+            // An annotated variable keeps its type after assignments.
+            "var n: Num = 1; n = \"a\";",
+            // An unannotated variable that is never assigned keeps the type of its value.
+            "var s = \"x\"; print s + 1;",
+            "{ var n = 1; var t: Str = n; }",
+            "fun f(a: Num) {} var s = \"a\"; f(s);",
+        ] {
+            assert!(!type_checks(source), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_unannotated() {
+        // Unannotated names are `Any` and may change their type.
+        let source = "var a = 1; a = \"text\"; var s: Str = a; print a - 1;
+fun f(x) { return x; } var n: Num = f(\"a\");
+fun g(a, b) { return a; } g(1); g(1, 2, 3);";
+        assert!(type_checks(source));
+
+        // A variable assigned anywhere later in its block, even from a function, is `Any`.
+        for source in [
+            "var s = \"x\"; s = 1; print s + 1;",
+            "var s = \"x\"; fun f() { print s + 1; } fun g() { s = 1; }",
+            "for (var i = 0; i < 3; i = i + 1) { var n: Num = i; }",
+            "var x; while (x == nil) { x = 1; }",
+        ] {
+            assert!(type_checks(source), "{source}");
+        }
+    }
+}